@@ -24,6 +24,7 @@ import pyarrow
 import pyarrow.flight
 import pyarrow.csv as csv
 import datetime
+import ipaddress
 import pandas
 
 def list_flights(client, connection_args={}):
@@ -32,7 +33,9 @@ def list_flights(client, connection_args={}):
     for flight in client.list_flights():
         descriptor = flight.descriptor
         if descriptor.descriptor_type == pyarrow.flight.DescriptorType.PATH:
-            path_list.append(descriptor.path[0].decode())
+            # protocol tables other than Modbus are stored as (timestamp, name)
+            if len(descriptor.path) == 1:
+                path_list.append(descriptor.path[0].decode())
         elif descriptor.descriptor_type == pyarrow.flight.DescriptorType.CMD:
             print("Command:", descriptor.command)
         else:
@@ -54,21 +57,49 @@ def get_flight(args, client, connection_args={}):
             get_client = pyarrow.flight.FlightClient(location,
                                                      **connection_args)
             reader = get_client.do_get(endpoint.ticket)
-            df = reader.read_pandas()
+            df = read_pandas(reader)
             print(df)
 
 def select_path(path_list):
-    m = datetime.timedelta(hours=9, minutes=1)
-    now = datetime.datetime.now()
+    # flight paths are named after the capture time in UTC
+    m = datetime.timedelta(minutes=1)
+    now = datetime.datetime.now(datetime.timezone.utc)
     be_minutes = now - m
     minutes_path = []
     for path in path_list:
-        dt = datetime.datetime.strptime(path, '%Y-%m-%d-%H_%M_%S_%f')
+        dt = datetime.datetime.strptime(path, '%Y-%m-%d-%H_%M_%S_%f').replace(tzinfo=datetime.timezone.utc)
         if be_minutes < dt:
             print(path)
             minutes_path.append(path)
     return minutes_path
 
+def format_mac(value):
+    if value is None:
+        return None
+    return ':'.join('{:02x}'.format(b) for b in value)
+
+def format_ip(value):
+    if value is None:
+        return None
+    addr = ipaddress.IPv6Address(value)
+    return str(addr.ipv4_mapped or addr)
+
+# MAC/IP columns are FixedSizeBinary, tagged with an extension name in the field metadata
+EXTENSION_FORMATTERS = {
+    b'arrows.mac': format_mac,
+    b'arrows.ip': format_ip,
+}
+
+def read_pandas(reader):
+    table = reader.read_all()
+    # keep integer columns as integers when they contain nulls
+    df = table.to_pandas(integer_object_nulls=True)
+    for field in table.schema:
+        extension = (field.metadata or {}).get(b'ARROW:extension:name')
+        if extension in EXTENSION_FORMATTERS:
+            df[field.name] = df[field.name].map(EXTENSION_FORMATTERS[extension])
+    return df
+
 def get_table(client, minutes_path, connection_args={}):
     tables = []
     for path in minutes_path:
@@ -78,14 +109,20 @@ def get_table(client, minutes_path, connection_args={}):
             for location in endpoint.locations:
                 get_client = pyarrow.flight.FlightClient(location, **connection_args)
                 reader = get_client.do_get(endpoint.ticket)
-                df = reader.read_pandas()
+                df = read_pandas(reader)
                 tables.append(df)
     return pandas.concat(tables)
 
 def write(host_name, table):
+    # data.txt keeps the seconds / nanoseconds layout
+    nanos = table['Timestamp'].astype('int64')
+    table = table.drop(columns='Timestamp')
+    table.insert(0, 'DateTime', nanos // 1000000000)
+    table.insert(1, 'DateTimeSubsec', nanos % 1000000000)
     write_list = []
     for i in range(2, 14):
-        for row in table.iloc[:,[0,1,i]].itertuples():
+        # fields the function code doesn't define are null
+        for row in table.iloc[:,[0,1,i]].dropna().itertuples():
             write_list.append('{} {} {} {:0>9} {}\n'.format(host_name, table.columns[i], row[1], row[2], row[3]))
     for row in table[(table['Function'] == 1)|(table['Function'] == 2)].iloc[:,[0, 1, 14, 15, 16, 17]].itertuples():
         if row[8] == 502:
@@ -3,7 +3,9 @@ use libc;
 
 use log;
 mod packet_handler;
-use packet_handler::{PacketAttr, Action};
+use packet_handler::{PacketAttr, Action, Record, HandlerConfig, HandlerState};
+mod packet_buffer;
+use packet_buffer::{RecordBuffer, EnipPackets, S7Packets, Iec104Packets, BacnetPackets, MelsecPackets, FinsPackets, OpcuaPackets, GoosePackets, SvPackets, DcpPackets, RtPackets, MqttPackets, FlowPackets, ArpAssetPackets, ArpAlertPackets, TrafficPackets, DnsPackets, DhcpPackets, NeighborPackets, TlsPackets, ModbusSessionPackets, PtpPackets, SnmpPackets};
 
 #[cfg(target_os = "linux")]
 use std::os::unix::thread::JoinHandleExt;
@@ -12,6 +14,7 @@ use std::env;
 use std::io::{self, Write};
 //use std::net::IpAddr;
 //use std::net::{AddrParseError, IpAddr, Ipv4Addr};
+use std::net::IpAddr;
 use std::process;
 use std::thread;
 use std::sync::{Arc, Barrier};
@@ -19,7 +22,7 @@ use tokio::sync::mpsc;
 
 use pnet;
 use pnet::datalink;
-use pnet::datalink::{Channel, NetworkInterface, /*DataLinkSender,*/ DataLinkReceiver};
+use pnet::datalink::{Channel, MacAddr, NetworkInterface, /*DataLinkSender,*/ DataLinkReceiver};
 //use pnet::datalink::{Channel, MacAddr, NetworkInterface};
 
 use pnet::packet::ethernet::{/*EtherTypes,*/ EthernetPacket};
@@ -27,7 +30,7 @@ use pnet::packet::ethernet::{/*EtherTypes,*/ EthernetPacket};
 
 //use std::iter::FromIterator;
 use std::collections::{HashMap, VecDeque};
-use chrono::{Utc, DateTime, NaiveDateTime};
+use chrono::{Utc, DateTime};
 //use num_traits::cast::ToPrimitive;
 
 use tokio::time::{/*sleep,*/ interval_at, /*Duration,*/ Instant};
@@ -44,7 +47,7 @@ use arrow_flight::{
     utils::{flight_data_from_arrow_batch/*, flight_data_to_arrow_batch*/},
 };
 
-use datafusion::arrow::array::{/*Int64Array, UInt32Array, UInt16Array,*/ StringArray, PrimitiveArray};
+use datafusion::arrow::array::{/*Int64Array, UInt32Array, UInt16Array,*/ StringArray, PrimitiveArray, BooleanArray, TimestampNanosecondArray};
 use datafusion::arrow::datatypes::{DataType, Field, Schema};
 use datafusion::arrow::record_batch::RecordBatch;
 //use datafusion::arrow::util::pretty;
@@ -59,62 +62,80 @@ pub const MAX_SUB_LEN: usize = 64;
 
 // Packet record buffer for each interface
 struct IfPackets {
-    datetime: VecDeque<i64>,
-    datetime_subsec: VecDeque<u32>,
-    src_mac: VecDeque<String>,
-    dst_mac: VecDeque<String>,
-    src_addr: VecDeque<String>,
-    dst_addr: VecDeque<String>,
+    timestamp: VecDeque<i64>,
+    src_mac: VecDeque<MacAddr>,
+    dst_mac: VecDeque<MacAddr>,
+    src_addr: VecDeque<IpAddr>,
+    dst_addr: VecDeque<IpAddr>,
     src_port: VecDeque<u16>,
     dst_port: VecDeque<u16>,
     length: VecDeque<u32>,
-    transaction: VecDeque<u16>,
-    protocol: VecDeque<u16>,
-    len: VecDeque<u16>,
-    unit_id: VecDeque<u8>,
-    function: VecDeque<u8>,
-    ref_number: VecDeque<u16>,
-    data: VecDeque<u16>,
-    mult_count: VecDeque<u8>,
-    mult_data: VecDeque<String>
+    transaction: VecDeque<Option<u16>>,
+    protocol: VecDeque<Option<u16>>,
+    len: VecDeque<Option<u16>>,
+    unit_id: VecDeque<Option<u8>>,
+    function: VecDeque<Option<u8>>,
+    ref_number: VecDeque<Option<u16>>,
+    data: VecDeque<Option<u16>>,
+    mult_count: VecDeque<Option<u8>>,
+    mult_data: VecDeque<Option<String>>,
+    tcp_flags: VecDeque<u16>,
+    seq: VecDeque<u32>,
+    ack: VecDeque<u32>,
+    window: VecDeque<u16>,
+    payload_len: VecDeque<u32>,
+    retransmission: VecDeque<bool>,
+    zero_window: VecDeque<bool>,
+    rst_storm: VecDeque<bool>,
+    half_open: VecDeque<bool>,
 }
 
 impl IfPackets {
     pub fn new() -> Self {
         Self {
-            datetime: VecDeque::<i64>::new(),
-            datetime_subsec: VecDeque::<u32>::new(),
-            src_mac: VecDeque::<String>::new(),
-            dst_mac: VecDeque::<String>::new(),
-            src_addr: VecDeque::<String>::new(),
-            dst_addr: VecDeque::<String>::new(),
+            timestamp: VecDeque::<i64>::new(),
+            src_mac: VecDeque::<MacAddr>::new(),
+            dst_mac: VecDeque::<MacAddr>::new(),
+            src_addr: VecDeque::<IpAddr>::new(),
+            dst_addr: VecDeque::<IpAddr>::new(),
             src_port: VecDeque::<u16>::new(),
             dst_port: VecDeque::<u16>::new(),
             length: VecDeque::<u32>::new(),
-            transaction: VecDeque::<u16>::new(),
-            protocol: VecDeque::<u16>::new(),
-            len: VecDeque::<u16>::new(),
-            unit_id: VecDeque::<u8>::new(),
-            function: VecDeque::<u8>::new(),
-            ref_number: VecDeque::<u16>::new(),
-            data: VecDeque::<u16>::new(),
-            mult_count: VecDeque::<u8>::new(),
-            mult_data: VecDeque::<String>::new(),
+            transaction: VecDeque::<Option<u16>>::new(),
+            protocol: VecDeque::<Option<u16>>::new(),
+            len: VecDeque::<Option<u16>>::new(),
+            unit_id: VecDeque::<Option<u8>>::new(),
+            function: VecDeque::<Option<u8>>::new(),
+            ref_number: VecDeque::<Option<u16>>::new(),
+            data: VecDeque::<Option<u16>>::new(),
+            mult_count: VecDeque::<Option<u8>>::new(),
+            mult_data: VecDeque::<Option<String>>::new(),
+            tcp_flags: VecDeque::<u16>::new(),
+            seq: VecDeque::<u32>::new(),
+            ack: VecDeque::<u32>::new(),
+            window: VecDeque::<u16>::new(),
+            payload_len: VecDeque::<u32>::new(),
+            retransmission: VecDeque::<bool>::new(),
+            zero_window: VecDeque::<bool>::new(),
+            rst_storm: VecDeque::<bool>::new(),
+            half_open: VecDeque::<bool>::new(),
         }
     }
+}
+
+impl RecordBuffer for IfPackets {
+    type Attr = PacketAttr;
 
     fn push_back(&mut self, pa: PacketAttr, utc: &DateTime<Utc>) {
-        let ndt: NaiveDateTime = utc.naive_local();
-        self.datetime.push_back(ndt.timestamp());
-        self.datetime_subsec.push_back(ndt.timestamp_subsec_nanos());
-        self.src_mac.push_back(pa.src_mac.to_string());
-        self.dst_mac.push_back(pa.dst_mac.to_string());
-        self.src_addr.push_back(pa.src_addr.to_string());
-        self.dst_addr.push_back(pa.dst_addr.to_string());
+        self.timestamp.push_back(utc.timestamp_nanos());
+        self.src_mac.push_back(pa.src_mac);
+        self.dst_mac.push_back(pa.dst_mac);
+        self.src_addr.push_back(pa.src_addr);
+        self.dst_addr.push_back(pa.dst_addr);
         self.src_port.push_back(pa.src_port);
         self.dst_port.push_back(pa.dst_port);
         self.length.push_back(pa.length);
-        self.transaction.push_back(pa.protocol);
+        self.transaction.push_back(pa.transaction);
         self.protocol.push_back(pa.protocol);
         self.len.push_back(pa.len);
         self.unit_id.push_back(pa.unit_id);
@@ -123,11 +144,19 @@ impl IfPackets {
         self.data.push_back(pa.data);
         self.mult_count.push_back(pa.mult_count);
         self.mult_data.push_back(pa.mult_data.clone());
+        self.tcp_flags.push_back(pa.tcp_flags);
+        self.seq.push_back(pa.seq);
+        self.ack.push_back(pa.ack);
+        self.window.push_back(pa.window);
+        self.payload_len.push_back(pa.payload_len);
+        self.retransmission.push_back(pa.retransmission);
+        self.zero_window.push_back(pa.zero_window);
+        self.rst_storm.push_back(pa.rst_storm);
+        self.half_open.push_back(pa.half_open);
     }
 
     fn pop_front(&mut self) {
-        let datetime = self.datetime.pop_front().unwrap();
-        let datetime_subsec = self.datetime_subsec.pop_front().unwrap();
+        let timestamp = self.timestamp.pop_front().unwrap();
         let src_mac = self.src_mac.pop_front().unwrap();
         let dst_mac = self.dst_mac.pop_front().unwrap();
         let src_addr = self.src_addr.pop_front().unwrap();
@@ -144,11 +173,19 @@ impl IfPackets {
         let data = self.data.pop_front().unwrap();
         let mult_count = self.mult_count.pop_front().unwrap();
         let mult_data = self.mult_data.pop_front().unwrap();
+        let tcp_flags = self.tcp_flags.pop_front().unwrap();
+        let seq = self.seq.pop_front().unwrap();
+        let ack = self.ack.pop_front().unwrap();
+        let window = self.window.pop_front().unwrap();
+        let payload_len = self.payload_len.pop_front().unwrap();
+        let retransmission = self.retransmission.pop_front().unwrap();
+        let zero_window = self.zero_window.pop_front().unwrap();
+        let rst_storm = self.rst_storm.pop_front().unwrap();
+        let half_open = self.half_open.pop_front().unwrap();
     }
 
     fn clear(&mut self) {
-        self.datetime.clear();
-        self.datetime_subsec.clear();
+        self.timestamp.clear();
         self.src_mac.clear();
         self.dst_mac.clear();
         self.src_addr.clear();
@@ -165,33 +202,50 @@ impl IfPackets {
         self.data.clear();
         self.mult_count.clear();
         self.mult_data.clear();
+        self.tcp_flags.clear();
+        self.seq.clear();
+        self.ack.clear();
+        self.window.clear();
+        self.payload_len.clear();
+        self.retransmission.clear();
+        self.zero_window.clear();
+        self.rst_storm.clear();
+        self.half_open.clear();
     }
 
     fn len(&self) -> usize {
-        self.datetime.len()
+        self.timestamp.len()
     }
 
     fn get_schema(&self) -> Arc<Schema> {
         let schema = Arc::new(
             Schema::new(vec![
-                        Field::new("DateTime", DataType::Int64, false),         // 0
-                        Field::new("DateTimeSubsec", DataType::UInt32, false),  // 1
-                        Field::new("SrcMAC", DataType::Utf8, false),            // 2
-                        Field::new("DstMAC", DataType::Utf8, false),            // 3
-                        Field::new("SrcIP", DataType::Utf8, false),             // 4
-                        Field::new("DstIP", DataType::Utf8, false),             // 5
-                        Field::new("SrcPort", DataType::UInt16, false),         // 6
-                        Field::new("DstPort", DataType::UInt16, false),         // 7
-                        Field::new("TCPLen", DataType::UInt32, false),          // 8
-                        Field::new("Transaction", DataType::UInt16, false),     // 9
-                        Field::new("Protocol", DataType::UInt16, false),        // 10
-                        Field::new("Len", DataType::UInt16, false),             // 11
-                        Field::new("UnitID", DataType::UInt8, false),           // 12
-                        Field::new("Function", DataType::UInt8, false),         // 13
-                        Field::new("ReferenceNumber", DataType::UInt16, false), // 14
-                        Field::new("Data", DataType::UInt16, false),            // 15
-                        Field::new("MultCount", DataType::UInt8, false),        // 16
-                        Field::new("MultData", DataType::Utf8, false),          // 17
+                        Field::new("Timestamp", packet_buffer::timestamp_type(), false), // 0
+                        packet_buffer::mac_field("SrcMAC", false),              // 1
+                        packet_buffer::mac_field("DstMAC", false),              // 2
+                        packet_buffer::ip_field("SrcIP", false),                // 3
+                        packet_buffer::ip_field("DstIP", false),                // 4
+                        Field::new("SrcPort", DataType::UInt16, false),         // 5
+                        Field::new("DstPort", DataType::UInt16, false),         // 6
+                        Field::new("TCPLen", DataType::UInt32, false),          // 7
+                        Field::new("Transaction", DataType::UInt16, true),     // 8
+                        Field::new("Protocol", DataType::UInt16, true),        // 9
+                        Field::new("Len", DataType::UInt16, true),             // 10
+                        Field::new("UnitID", DataType::UInt8, true),           // 11
+                        Field::new("Function", DataType::UInt8, true),         // 12
+                        Field::new("ReferenceNumber", DataType::UInt16, true), // 13
+                        Field::new("Data", DataType::UInt16, true),            // 14
+                        Field::new("MultCount", DataType::UInt8, true),        // 15
+                        Field::new("MultData", DataType::Utf8, true),          // 16
+                        Field::new("TCPFlags", DataType::UInt16, false),        // 17
+                        Field::new("Seq", DataType::UInt32, false),             // 18
+                        Field::new("Ack", DataType::UInt32, false),             // 19
+                        Field::new("Window", DataType::UInt16, false),          // 20
+                        Field::new("PayloadLen", DataType::UInt32, false),      // 21
+                        Field::new("Retransmission", DataType::Boolean, false), // 22
+                        Field::new("ZeroWindow", DataType::Boolean, false),     // 23
+                        Field::new("RSTStorm", DataType::Boolean, false),       // 24
+                        Field::new("HalfOpen", DataType::Boolean, false),       // 25
             ]));
         schema
     }
@@ -200,24 +254,32 @@ impl IfPackets {
         let batch = RecordBatch::try_new(
             Arc::new(schema.clone()),
             vec![
-            Arc::new(PrimitiveArray::<arrow::datatypes::Int64Type>::from_iter_values(self.datetime.range(win_front..win_back).cloned())),
-            Arc::new(PrimitiveArray::<arrow::datatypes::UInt32Type>::from_iter_values(self.datetime_subsec.range(win_front..win_back).cloned())),
-            Arc::new(StringArray::from_iter_values(self.src_mac.range(win_front..win_back).cloned())),
-            Arc::new(StringArray::from_iter_values(self.dst_mac.range(win_front..win_back).cloned())),
-            Arc::new(StringArray::from_iter_values(self.src_addr.range(win_front..win_back).cloned())),
-            Arc::new(StringArray::from_iter_values(self.dst_addr.range(win_front..win_back).cloned())),
+            Arc::new(TimestampNanosecondArray::from_vec(self.timestamp.range(win_front..win_back).cloned().collect(), Some(packet_buffer::TIMEZONE.to_string()))),
+            packet_buffer::mac_column(self.src_mac.range(win_front..win_back).cloned().map(Some))?,
+            packet_buffer::mac_column(self.dst_mac.range(win_front..win_back).cloned().map(Some))?,
+            packet_buffer::ip_column(self.src_addr.range(win_front..win_back).cloned().map(Some))?,
+            packet_buffer::ip_column(self.dst_addr.range(win_front..win_back).cloned().map(Some))?,
             Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(self.src_port.range(win_front..win_back).cloned())),
             Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(self.dst_port.range(win_front..win_back).cloned())),
             Arc::new(PrimitiveArray::<arrow::datatypes::UInt32Type>::from_iter_values(self.length.range(win_front..win_back).cloned())),
-            Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(self.transaction.range(win_front..win_back).cloned())),
-            Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(self.protocol.range(win_front..win_back).cloned())),
-            Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(self.len.range(win_front..win_back).cloned())),
-            Arc::new(PrimitiveArray::<arrow::datatypes::UInt8Type>::from_iter_values(self.unit_id.range(win_front..win_back).cloned())),
-            Arc::new(PrimitiveArray::<arrow::datatypes::UInt8Type>::from_iter_values(self.function.range(win_front..win_back).cloned())),
-            Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(self.ref_number.range(win_front..win_back).cloned())),
-            Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(self.data.range(win_front..win_back).cloned())),
-            Arc::new(PrimitiveArray::<arrow::datatypes::UInt8Type>::from_iter_values(self.mult_count.range(win_front..win_back).cloned())),
-            Arc::new(StringArray::from_iter_values(self.mult_data.range(win_front..win_back).cloned())),
+            Arc::new(self.transaction.range(win_front..win_back).cloned().collect::<PrimitiveArray<arrow::datatypes::UInt16Type>>()),
+            Arc::new(self.protocol.range(win_front..win_back).cloned().collect::<PrimitiveArray<arrow::datatypes::UInt16Type>>()),
+            Arc::new(self.len.range(win_front..win_back).cloned().collect::<PrimitiveArray<arrow::datatypes::UInt16Type>>()),
+            Arc::new(self.unit_id.range(win_front..win_back).cloned().collect::<PrimitiveArray<arrow::datatypes::UInt8Type>>()),
+            Arc::new(self.function.range(win_front..win_back).cloned().collect::<PrimitiveArray<arrow::datatypes::UInt8Type>>()),
+            Arc::new(self.ref_number.range(win_front..win_back).cloned().collect::<PrimitiveArray<arrow::datatypes::UInt16Type>>()),
+            Arc::new(self.data.range(win_front..win_back).cloned().collect::<PrimitiveArray<arrow::datatypes::UInt16Type>>()),
+            Arc::new(self.mult_count.range(win_front..win_back).cloned().collect::<PrimitiveArray<arrow::datatypes::UInt8Type>>()),
+            Arc::new(self.mult_data.range(win_front..win_back).map(|v| v.as_deref()).collect::<StringArray>()),
+            Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(self.tcp_flags.range(win_front..win_back).cloned())),
+            Arc::new(PrimitiveArray::<arrow::datatypes::UInt32Type>::from_iter_values(self.seq.range(win_front..win_back).cloned())),
+            Arc::new(PrimitiveArray::<arrow::datatypes::UInt32Type>::from_iter_values(self.ack.range(win_front..win_back).cloned())),
+            Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(self.window.range(win_front..win_back).cloned())),
+            Arc::new(PrimitiveArray::<arrow::datatypes::UInt32Type>::from_iter_values(self.payload_len.range(win_front..win_back).cloned())),
+            Arc::new(self.retransmission.range(win_front..win_back).map(|v| Some(*v)).collect::<BooleanArray>()),
+            Arc::new(self.zero_window.range(win_front..win_back).map(|v| Some(*v)).collect::<BooleanArray>()),
+            Arc::new(self.rst_storm.range(win_front..win_back).map(|v| Some(*v)).collect::<BooleanArray>()),
+            Arc::new(self.half_open.range(win_front..win_back).map(|v| Some(*v)).collect::<BooleanArray>()),
             ])?;
         Ok(batch)
     }
@@ -227,8 +289,9 @@ fn packet_forwarding_thread(
     name: &str,
     iface: NetworkInterface,
     mut receiver: Box<dyn DataLinkReceiver>,
-    mut log_sender: mpsc::Sender<PacketAttr>,
-    b: Arc<Barrier>
+    mut log_sender: mpsc::Sender<(DateTime<Utc>, Record)>,
+    b: Arc<Barrier>,
+    config: HandlerConfig,
 ) -> io::Result<thread::JoinHandle<()>> {
     thread::Builder::new()
     .name(name.to_string())
@@ -242,27 +305,32 @@ fn packet_forwarding_thread(
         #[cfg(target_os = "macos")]
         log::debug!("Thread {} starts", thread_name);
 
+        let mut handler_state = HandlerState::new(config);
         loop {
             match receiver.next() {
                 Ok(packet) => {
+                    // capture time, handed to the trackers and sent along with the records of this frame
+                    let utc: DateTime<Utc> = Utc::now();
                     log::debug!("Ethernet@{:?}", thread_name);
                     log::debug!("packet bytes ---");
                     log::debug!("{:x?}", packet);
                     log::debug!("---");
                     log::debug!("len: {} @{:?}", packet.len(), thread_name);
-                    match packet_handler::handle_ethernet_frame(&iface, &EthernetPacket::new(packet).unwrap())
+                    match packet_handler::handle_ethernet_frame(&iface, &EthernetPacket::new(packet).unwrap(), utc, &mut handler_state)
                     {
-                        Some(Action::Log(packet_attr)) => {
-                            match log_sender.try_send(packet_attr) {
-                                Ok(_) => log::debug!(
-                                    "log_sender: send packet_attr successfully: @{:?}",
-                                    thread_name
-                                    ),
-                                Err(e) => log::debug!(
-                                    "log_sender: send packet_attr error: {} @{:?}",
-                                    e,
-                                    thread_name
-                                    ),
+                        Some(Action::Log(records)) => {
+                            for record in records {
+                                match log_sender.try_send((utc, record)) {
+                                    Ok(_) => log::debug!(
+                                        "log_sender: send record successfully: @{:?}",
+                                        thread_name
+                                        ),
+                                    Err(e) => log::debug!(
+                                        "log_sender: send record error: {} @{:?}",
+                                        e,
+                                        thread_name
+                                        ),
+                                }
                             }
                         },
                         Some(Action::Drop(message)) => log::warn!(
@@ -292,22 +360,107 @@ fn pthread_set_cpu(pthread: libc::pthread_t, cpu: usize) {
     }
 }
 
-async fn do_put_flight_data(client: &mut FlightServiceClient<tonic::transport::channel::Channel>, if_packets: &mut IfPackets, utc: &DateTime<Utc>, win_front: &usize, win_back: &usize) -> std::result::Result<(), Box<dyn std::error::Error>> {
-    let schema = if_packets.get_schema();
+async fn do_put_flight_data<B: RecordBuffer>(client: &mut FlightServiceClient<tonic::transport::channel::Channel>, packets: &B, name: Option<&str>, utc: &DateTime<Utc>, win_front: &usize, win_back: &usize) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let schema = packets.get_schema();
     let options = datafusion::arrow::ipc::writer::IpcWriteOptions::default();
     let mut flight_data_schema: FlightData = SchemaAsIpc::new(schema.as_ref(), &options).into();
+    // Modbus keeps the bare timestamp path, other tables append their name
+    let mut path = vec![format!("{}", utc.format("%Y-%m-%d-%H_%M_%S_%6f"))];
+    if let Some(name) = name {
+        path.push(name.to_string());
+    }
     flight_data_schema.flight_descriptor = Some(FlightDescriptor {
         r#type: flight_descriptor::DescriptorType::Path as i32,
         cmd: vec![],
-        path: vec![format!("{}", utc.format("%Y-%m-%d-%H_%M_%S_%6f"))],
+        path: path,
     });
     log::info!("{:?}", flight_data_schema);
-    let flight_data_batch = flight_data_from_arrow_batch(&if_packets.get_batch(&schema, &win_front, &win_back).unwrap(), &options);
+    let flight_data_batch = flight_data_from_arrow_batch(&packets.get_batch(&schema, &win_front, &win_back).unwrap(), &options);
     log::info!("{:?}", flight_data_batch);
     client.do_put(stream::iter(vec![flight_data_schema, flight_data_batch.1])).await?;
     Ok(())
 }
 
+// Record buffer with its own sliding window
+struct Table<B: RecordBuffer> {
+    name: Option<&'static str>,
+    packets: B,
+    // output interval counter
+    k: usize,
+    // time-based window index
+    win_fronts: Vec<usize>,
+    win_back: usize,
+}
+
+impl<B: RecordBuffer> Table<B> {
+    fn new(name: Option<&'static str>, packets: B, n: usize) -> Self {
+        Self {
+            name: name,
+            packets: packets,
+            k: 0,
+            win_fronts: vec![0; n],
+            win_back: 0,
+        }
+    }
+
+    async fn push_back(&mut self, client: &mut FlightServiceClient<tonic::transport::channel::Channel>, attr: B::Attr, utc: &DateTime<Utc>, window_type: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        self.packets.push_back(attr, utc);
+
+        if self.win_back < MAX_LEN { self.win_back += 1 }
+        // update time-based window index
+        // win_back is used as win_front..win_back in get_batch
+        // it means win_front <= X < win_back
+        match window_type {
+            "row" => {
+                // row-based
+                if self.packets.len() > MAX_LEN {
+                    do_put_flight_data(client, &self.packets, self.name, utc, &self.win_fronts[self.k], &self.win_back).await?;
+
+                    log::info!("do_put {:?} (row-based)", self.name);
+                    self.packets.clear();
+                    // reset time-based window index (currently not used in row-based mode)
+                    self.win_back = 0;
+                }
+            },
+            "time" => {
+                // time-based
+                // remove the oldest packet
+                if self.packets.len() > MAX_LEN {
+                    log::info!("pop a packet (time-based)");
+                    self.packets.pop_front();
+                    for i in 0..self.win_fronts.len() {
+                        if self.win_fronts[i] > 0 { self.win_fronts[i] -= 1 }
+                    }
+                }
+            },
+            _ => {
+            }
+        }
+        Ok(())
+    }
+
+    async fn output(&mut self, client: &mut FlightServiceClient<tonic::transport::channel::Channel>, utc: &DateTime<Utc>, window_type: &str) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        if self.win_fronts[self.k] != self.win_back {
+            match window_type {
+                "row" => {
+                },
+                "time" => {
+                    do_put_flight_data(client, &self.packets, self.name, utc, &self.win_fronts[self.k], &self.win_back).await?;
+                    log::info!("do_put {:?} (time-based)", self.name);
+
+                    // increment counter
+                    self.k = (self.k + 1) % self.win_fronts.len();
+                    // win_frontsの更新
+                    self.win_fronts[self.k] = self.win_back;
+                },
+                _ => {
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[tokio::main]
 pub async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
@@ -375,9 +528,11 @@ pub async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     };
 
     let barrier = Arc::new(Barrier::new(2));
+    let handler_config = HandlerConfig::from_env();
+    log::info!("{:?}", handler_config);
 
-    let (mut log_sender, mut log_receiver): (mpsc::Sender<PacketAttr>, mpsc::Receiver<PacketAttr>) = mpsc::channel(1024);
-    let handle1 = match packet_forwarding_thread("thread1", iface1, receiver1, /*sender2,*/ log_sender.clone(), barrier.clone()) {
+    let (mut log_sender, mut log_receiver): (mpsc::Sender<(DateTime<Utc>, Record)>, mpsc::Receiver<(DateTime<Utc>, Record)>) = mpsc::channel(1024);
+    let handle1 = match packet_forwarding_thread("thread1", iface1, receiver1, /*sender2,*/ log_sender.clone(), barrier.clone(), handler_config.clone()) {
         Ok(handle) => handle,
         Err(e) => panic!("Error creating thread1: {}", e),
     };
@@ -395,55 +550,65 @@ pub async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
             interval_at(start.clone(), tokio::time::Duration::from_secs(60 * 60 * 24))
         }
     );
-    // sliding window parameters
-    // output interval counter
-    let mut k = 0;
-    // time-based window index
-    let mut win_fronts: Vec<usize> = vec![0; n];
-    let mut win_back: usize = 0;
 
     // Create Flight client
     let mut client = FlightServiceClient::connect("http://localhost:5005").await?;
-    // Create packet buffer
-    let mut if_packets: IfPackets = IfPackets::new();
-    
+    // Create packet buffers (sliding window per table)
+    let mut if_packets = Table::new(None, IfPackets::new(), n);
+    let mut enip_packets = Table::new(Some("enip"), EnipPackets::new(), n);
+    let mut s7_packets = Table::new(Some("s7comm"), S7Packets::new(), n);
+    let mut iec104_packets = Table::new(Some("iec104"), Iec104Packets::new(), n);
+    let mut bacnet_packets = Table::new(Some("bacnet"), BacnetPackets::new(), n);
+    let mut melsec_packets = Table::new(Some("melsec"), MelsecPackets::new(), n);
+    let mut fins_packets = Table::new(Some("fins"), FinsPackets::new(), n);
+    let mut opcua_packets = Table::new(Some("opcua"), OpcuaPackets::new(), n);
+    let mut goose_packets = Table::new(Some("goose"), GoosePackets::new(), n);
+    let mut sv_packets = Table::new(Some("sv"), SvPackets::new(), n);
+    let mut dcp_packets = Table::new(Some("pn_dcp"), DcpPackets::new(), n);
+    let mut rt_packets = Table::new(Some("pn_rt"), RtPackets::new(), n);
+    let mut mqtt_packets = Table::new(Some("mqtt"), MqttPackets::new(), n);
+    let mut flow_records = Table::new(Some("flow"), FlowPackets::new(), n);
+    let mut arp_assets = Table::new(Some("arp_assets"), ArpAssetPackets::new(), n);
+    let mut arp_alerts = Table::new(Some("arp_alerts"), ArpAlertPackets::new(), n);
+    let mut traffic_records = Table::new(Some("traffic"), TrafficPackets::new(), n);
+    let mut dns_packets = Table::new(Some("dns"), DnsPackets::new(), n);
+    let mut dhcp_packets = Table::new(Some("dhcp"), DhcpPackets::new(), n);
+    let mut topology = Table::new(Some("topology"), NeighborPackets::new(), n);
+    let mut tls_packets = Table::new(Some("tls"), TlsPackets::new(), n);
+    let mut modbus_sessions = Table::new(Some("modbus_sessions"), ModbusSessionPackets::new(), n);
+    let mut ptp_packets = Table::new(Some("ptp"), PtpPackets::new(), n);
+    let mut snmp_packets = Table::new(Some("snmp"), SnmpPackets::new(), n);
+
     loop {
         tokio::select! {
-            Some(v) = log_receiver.recv() => {
-                let utc: DateTime<Utc> = Utc::now();
-                if_packets.push_back(v, &utc);
-
-                if win_back < MAX_LEN { win_back += 1 }
-                // update time-based window index
-                // win_back is used as win_front..win_back in get_batch
-                // it means win_front <= X < win_back
-                log::info!("add a packet");
-                match window_type {
-                    "row" => {
-                        // row-based
-                        if if_packets.len() > MAX_LEN {
-                            do_put_flight_data(&mut client, &mut if_packets, &utc, &win_fronts[k], &win_back).await?;
-
-                            log::info!("do_put IfPackets (row-based)");
-                            if_packets.clear();
-                            // reset time-based window index (currently not used in row-based mode)
-                            win_back = 0;
-                        }
-                    },
-                    "time" => {
-                        // time-based
-                        // remove the oldest packet
-                        if if_packets.len() > MAX_LEN {
-                            log::info!("pop a packet (time-based)");
-                            if_packets.pop_front();
-                            for i in 0..win_fronts.len() {
-                                if win_fronts[i] > 0 { win_fronts[i] -= 1 }
-                            }
-                        }
-                    },
-                    _ => {
-                    }
+            Some((utc, v)) = log_receiver.recv() => {
+                match v {
+                    Record::Modbus(v) => if_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Enip(v) => enip_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::S7(v) => s7_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Iec104(v) => iec104_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Bacnet(v) => bacnet_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Melsec(v) => melsec_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Fins(v) => fins_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Opcua(v) => opcua_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Goose(v) => goose_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Sv(v) => sv_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Dcp(v) => dcp_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Rt(v) => rt_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Mqtt(v) => mqtt_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Flow(v) => flow_records.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::ArpAsset(v) => arp_assets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::ArpAlert(v) => arp_alerts.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Traffic(v) => traffic_records.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Dns(v) => dns_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Dhcp(v) => dhcp_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Neighbor(v) => topology.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Tls(v) => tls_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::ModbusSession(v) => modbus_sessions.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Ptp(v) => ptp_packets.push_back(&mut client, v, &utc, window_type).await?,
+                    Record::Snmp(v) => snmp_packets.push_back(&mut client, v, &utc, window_type).await?,
                 }
+                log::info!("add a packet");
             },
             Some(v) = interval_stream.next() => {
                 // TODO: 経過時間が window_duration 以下の場合は，
@@ -451,23 +616,31 @@ pub async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
                 // output_interval ごとに出力する．
                 log::info!("{:?}", v);
 
-                if win_fronts[k] != win_back {
-                    match window_type {
-                        "row" => {
-                        },
-                        "time" => {
-                            // increment counter
-                            do_put_flight_data(&mut client, &mut if_packets, &utc, &win_fronts[k], &win_back).await?;
-                            log::info!("do_put IfPackets (time-based)");
-
-                            k = (k + 1) % n;
-                            // win_frontsの更新
-                            win_fronts[k] = win_back;
-                        },
-                        _ => {
-                        }
-                    }
-                }
+                let utc: DateTime<Utc> = Utc::now();
+                if_packets.output(&mut client, &utc, window_type).await?;
+                enip_packets.output(&mut client, &utc, window_type).await?;
+                s7_packets.output(&mut client, &utc, window_type).await?;
+                iec104_packets.output(&mut client, &utc, window_type).await?;
+                bacnet_packets.output(&mut client, &utc, window_type).await?;
+                melsec_packets.output(&mut client, &utc, window_type).await?;
+                fins_packets.output(&mut client, &utc, window_type).await?;
+                opcua_packets.output(&mut client, &utc, window_type).await?;
+                goose_packets.output(&mut client, &utc, window_type).await?;
+                sv_packets.output(&mut client, &utc, window_type).await?;
+                dcp_packets.output(&mut client, &utc, window_type).await?;
+                rt_packets.output(&mut client, &utc, window_type).await?;
+                mqtt_packets.output(&mut client, &utc, window_type).await?;
+                flow_records.output(&mut client, &utc, window_type).await?;
+                arp_assets.output(&mut client, &utc, window_type).await?;
+                arp_alerts.output(&mut client, &utc, window_type).await?;
+                traffic_records.output(&mut client, &utc, window_type).await?;
+                dns_packets.output(&mut client, &utc, window_type).await?;
+                dhcp_packets.output(&mut client, &utc, window_type).await?;
+                topology.output(&mut client, &utc, window_type).await?;
+                tls_packets.output(&mut client, &utc, window_type).await?;
+                modbus_sessions.output(&mut client, &utc, window_type).await?;
+                ptp_packets.output(&mut client, &utc, window_type).await?;
+                snmp_packets.output(&mut client, &utc, window_type).await?;
             },
         }
     }
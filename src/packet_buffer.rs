@@ -0,0 +1,305 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, FixedSizeBinaryBuilder, ListBuilder, PrimitiveArray, PrimitiveBuilder, StringBuilder, TimestampNanosecondArray};
+use datafusion::arrow::datatypes::{ArrowPrimitiveType, DataType, Field, Schema, TimeUnit};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use pnet::datalink::MacAddr;
+
+use crate::packet_handler::{Addresses, LinkAddresses};
+
+mod enip;
+pub use enip::EnipPackets;
+mod s7comm;
+pub use s7comm::S7Packets;
+mod iec104;
+pub use iec104::Iec104Packets;
+mod bacnet;
+pub use bacnet::BacnetPackets;
+mod melsec;
+pub use melsec::MelsecPackets;
+mod fins;
+pub use fins::FinsPackets;
+mod opcua;
+pub use opcua::OpcuaPackets;
+mod iec61850;
+pub use iec61850::{GoosePackets, SvPackets};
+mod profinet;
+pub use profinet::{DcpPackets, RtPackets};
+mod mqtt;
+pub use mqtt::MqttPackets;
+mod flow;
+pub use flow::FlowPackets;
+mod arp;
+pub use arp::{ArpAssetPackets, ArpAlertPackets};
+mod traffic;
+pub use traffic::TrafficPackets;
+mod dns;
+pub use dns::DnsPackets;
+mod dhcp;
+pub use dhcp::DhcpPackets;
+mod discovery;
+pub use discovery::NeighborPackets;
+mod tls;
+pub use tls::TlsPackets;
+mod modbus_security;
+pub use modbus_security::ModbusSessionPackets;
+mod ptp;
+pub use ptp::PtpPackets;
+mod snmp;
+pub use snmp::SnmpPackets;
+
+// Record buffer exported as one Arrow table
+pub trait RecordBuffer {
+    type Attr;
+
+    fn push_back(&mut self, attr: Self::Attr, utc: &DateTime<Utc>);
+    fn pop_front(&mut self);
+    fn clear(&mut self);
+    fn len(&self) -> usize;
+    fn get_schema(&self) -> Arc<Schema>;
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch>;
+}
+
+// capture time, the timezone is recorded in the schema so readers don't have to guess it
+pub const TIMEZONE: &str = "UTC";
+
+pub fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Nanosecond, Some(TIMEZONE.to_string()))
+}
+
+pub fn datetime_columns<'a>(utcs: impl Iterator<Item = &'a DateTime<Utc>> + Clone) -> Vec<ArrayRef> {
+    vec![
+        Arc::new(TimestampNanosecondArray::from_vec(utcs.map(|utc| utc.timestamp_nanos()).collect(), Some(TIMEZONE.to_string()))),
+    ]
+}
+
+// MACs and IPs are stored as raw bytes, the extension name tells clients how to render them
+pub const MAC_EXTENSION: &str = "arrows.mac";
+pub const IP_EXTENSION: &str = "arrows.ip";
+const MAC_LEN: i32 = 6;
+// IPv4 addresses are stored IPv4-mapped (::ffff:a.b.c.d)
+const IP_LEN: i32 = 16;
+
+fn extension_field(name: &str, data_type: DataType, nullable: bool, extension: &str) -> Field {
+    let mut field = Field::new(name, data_type, nullable);
+    let mut metadata = BTreeMap::new();
+    metadata.insert("ARROW:extension:name".to_string(), extension.to_string());
+    field.set_metadata(Some(metadata));
+    field
+}
+
+pub fn mac_field(name: &str, nullable: bool) -> Field {
+    extension_field(name, DataType::FixedSizeBinary(MAC_LEN), nullable, MAC_EXTENSION)
+}
+
+pub fn ip_field(name: &str, nullable: bool) -> Field {
+    extension_field(name, DataType::FixedSizeBinary(IP_LEN), nullable, IP_EXTENSION)
+}
+
+pub fn mac_bytes(mac: &MacAddr) -> [u8; 6] {
+    [mac.0, mac.1, mac.2, mac.3, mac.4, mac.5]
+}
+
+pub fn ip_bytes(addr: &IpAddr) -> [u8; 16] {
+    match addr {
+        IpAddr::V4(addr) => addr.to_ipv6_mapped().octets(),
+        IpAddr::V6(addr) => addr.octets(),
+    }
+}
+
+pub fn mac_column<I>(macs: I) -> arrow::error::Result<ArrayRef>
+where
+    I: Iterator<Item = Option<MacAddr>>,
+{
+    let mut builder = FixedSizeBinaryBuilder::new(0, MAC_LEN);
+    for mac in macs {
+        match mac {
+            Some(mac) => builder.append_value(&mac_bytes(&mac))?,
+            None => builder.append_null()?,
+        }
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+pub fn ip_column<I>(addrs: I) -> arrow::error::Result<ArrayRef>
+where
+    I: Iterator<Item = Option<IpAddr>>,
+{
+    let mut builder = FixedSizeBinaryBuilder::new(0, IP_LEN);
+    for addr in addrs {
+        match addr {
+            Some(addr) => builder.append_value(&ip_bytes(&addr))?,
+            None => builder.append_null()?,
+        }
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+pub fn address_fields() -> Vec<Field> {
+    vec![
+        mac_field("SrcMAC", false),
+        mac_field("DstMAC", false),
+        ip_field("SrcIP", false),
+        ip_field("DstIP", false),
+        Field::new("SrcPort", DataType::UInt16, false),
+        Field::new("DstPort", DataType::UInt16, false),
+        Field::new("Length", DataType::UInt32, false),
+    ]
+}
+
+pub fn address_columns<'a>(addresses: impl Iterator<Item = &'a Addresses> + Clone) -> arrow::error::Result<Vec<ArrayRef>> {
+    Ok(vec![
+        mac_column(addresses.clone().map(|a| Some(a.src_mac)))?,
+        mac_column(addresses.clone().map(|a| Some(a.dst_mac)))?,
+        ip_column(addresses.clone().map(|a| Some(a.src_addr)))?,
+        ip_column(addresses.clone().map(|a| Some(a.dst_addr)))?,
+        Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(addresses.clone().map(|a| a.src_port))),
+        Arc::new(PrimitiveArray::<arrow::datatypes::UInt16Type>::from_iter_values(addresses.clone().map(|a| a.dst_port))),
+        Arc::new(PrimitiveArray::<arrow::datatypes::UInt32Type>::from_iter_values(addresses.map(|a| a.length))),
+    ])
+}
+
+pub fn link_fields() -> Vec<Field> {
+    vec![
+        mac_field("SrcMAC", false),
+        mac_field("DstMAC", false),
+        Field::new("VLAN", DataType::UInt16, true),
+        Field::new("Length", DataType::UInt32, false),
+    ]
+}
+
+pub fn link_columns<'a>(links: impl Iterator<Item = &'a LinkAddresses> + Clone) -> arrow::error::Result<Vec<ArrayRef>> {
+    Ok(vec![
+        mac_column(links.clone().map(|l| Some(l.src_mac)))?,
+        mac_column(links.clone().map(|l| Some(l.dst_mac)))?,
+        Arc::new(links.clone().map(|l| l.vlan_id).collect::<PrimitiveArray<arrow::datatypes::UInt16Type>>()),
+        Arc::new(PrimitiveArray::<arrow::datatypes::UInt32Type>::from_iter_values(links.map(|l| l.length))),
+    ])
+}
+
+// List<T> column, one list per record
+pub fn list_field(name: &str, data_type: DataType) -> Field {
+    Field::new(name, DataType::List(Box::new(Field::new("item", data_type, true))), false)
+}
+
+pub fn list_column<'a, T, I>(lists: I) -> arrow::error::Result<ArrayRef>
+where
+    T: ArrowPrimitiveType,
+    I: Iterator<Item = &'a Vec<T::Native>>,
+{
+    let mut builder = ListBuilder::new(PrimitiveBuilder::<T>::new(0));
+    for list in lists {
+        for value in list {
+            builder.values().append_value(*value)?;
+        }
+        builder.append(true)?;
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+pub fn nullable_list_column<'a, T, I>(lists: I) -> arrow::error::Result<ArrayRef>
+where
+    T: ArrowPrimitiveType,
+    I: Iterator<Item = &'a Vec<Option<T::Native>>>,
+{
+    let mut builder = ListBuilder::new(PrimitiveBuilder::<T>::new(0));
+    for list in lists {
+        for value in list {
+            match value {
+                Some(value) => builder.values().append_value(*value)?,
+                None => builder.values().append_null()?,
+            }
+        }
+        builder.append(true)?;
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+pub fn string_list_column<'a, I>(lists: I) -> arrow::error::Result<ArrayRef>
+where
+    I: Iterator<Item = &'a Vec<String>>,
+{
+    let mut builder = ListBuilder::new(StringBuilder::new(0));
+    for list in lists {
+        for value in list {
+            builder.values().append_value(value)?;
+        }
+        builder.append(true)?;
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use datafusion::arrow::array::{Array, FixedSizeBinaryArray};
+    use crate::packet_handler::ArpAssetAttr;
+
+    #[test]
+    fn timestamp_is_the_capture_time() {
+        let first_seen = Utc.ymd(2021, 6, 1).and_hms(8, 0, 0);
+        let captured = Utc.ymd(2021, 6, 1).and_hms_nano(9, 30, 15, 123_456_789);
+        let mut buffer = ArpAssetPackets::new();
+        buffer.push_back(
+            ArpAssetAttr {
+                interface_name: "eth0".to_string(),
+                ip: IpAddr::from([192, 168, 0, 10]),
+                mac: MacAddr::new(0x00, 0x1d, 0x9c, 0x01, 0x02, 0x03),
+                vendor: None,
+                first_seen,
+                last_seen: first_seen,
+            },
+            &captured,
+        );
+        let schema = buffer.get_schema();
+        assert_eq!(schema.field(0).name(), "Timestamp");
+        assert_eq!(schema.field(0).data_type(), &DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".to_string())));
+        let batch = buffer.get_batch(&schema, &0, &1).unwrap();
+        let timestamps = batch.column(0).as_any().downcast_ref::<TimestampNanosecondArray>().unwrap();
+        assert_eq!(timestamps.value(0), captured.timestamp_nanos());
+        assert_eq!(timestamps.value(0), 1_622_539_815_123_456_789);
+    }
+
+    #[test]
+    fn ipv4_is_stored_mapped() {
+        let v4 = IpAddr::from([192, 168, 0, 10]);
+        assert_eq!(ip_bytes(&v4), [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 192, 168, 0, 10]);
+        let v6: IpAddr = "fe80::1".parse().unwrap();
+        assert_eq!(ip_bytes(&v6), [0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        let column = ip_column(vec![Some(v4), None, Some(v6)].into_iter()).unwrap();
+        assert_eq!(column.data_type(), &DataType::FixedSizeBinary(16));
+        let column = column.as_any().downcast_ref::<FixedSizeBinaryArray>().unwrap();
+        assert_eq!(column.value(0), &ip_bytes(&v4)[..]);
+        assert!(column.is_null(1));
+        assert_eq!(column.value(2), &ip_bytes(&v6)[..]);
+    }
+
+    #[test]
+    fn mac_is_stored_as_six_bytes() {
+        let mac = MacAddr::new(0x00, 0x1d, 0x9c, 0x01, 0x02, 0x03);
+        let column = mac_column(vec![Some(mac), None].into_iter()).unwrap();
+        assert_eq!(column.data_type(), &DataType::FixedSizeBinary(6));
+        let column = column.as_any().downcast_ref::<FixedSizeBinaryArray>().unwrap();
+        assert_eq!(column.value(0), &[0x00, 0x1d, 0x9c, 0x01, 0x02, 0x03]);
+        assert!(column.is_null(1));
+    }
+
+    #[test]
+    fn address_fields_carry_the_extension_name() {
+        let extension = |field: &Field| field.metadata().as_ref().and_then(|metadata| metadata.get("ARROW:extension:name").cloned());
+        let field = mac_field("MAC", false);
+        assert_eq!(field.data_type(), &DataType::FixedSizeBinary(6));
+        assert_eq!(extension(&field).as_deref(), Some("arrows.mac"));
+        let field = ip_field("IP", true);
+        assert_eq!(field.data_type(), &DataType::FixedSizeBinary(16));
+        assert!(field.is_nullable());
+        assert_eq!(extension(&field).as_deref(), Some("arrows.ip"));
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, StringArray, TimestampNanosecondArray, UInt32Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::{ArpAlertAttr, ArpAssetAttr};
+use super::{RecordBuffer, TIMEZONE, datetime_columns, ip_column, ip_field, link_columns, link_fields, mac_column, mac_field, timestamp_type};
+
+// Asset inventory buffer (one row per exported IP-MAC binding)
+pub struct ArpAssetPackets {
+    records: VecDeque<(DateTime<Utc>, ArpAssetAttr)>,
+}
+
+impl ArpAssetPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for ArpAssetPackets {
+    type Attr = ArpAssetAttr;
+
+    fn push_back(&mut self, attr: ArpAssetAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(vec![
+            ip_field("IP", false),
+            mac_field("MAC", false),
+            Field::new("Vendor", DataType::Utf8, true),
+            Field::new("FirstSeen", timestamp_type(), false),
+            Field::new("LastSeen", timestamp_type(), false),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(vec![
+            ip_column(records.clone().map(|(_, r)| Some(r.ip)))?,
+            mac_column(records.clone().map(|(_, r)| Some(r.mac)))?,
+            Arc::new(records.clone().map(|(_, r)| r.vendor).collect::<StringArray>()) as ArrayRef,
+            Arc::new(TimestampNanosecondArray::from_vec(records.clone().map(|(_, r)| r.first_seen.timestamp_nanos()).collect(), Some(TIMEZONE.to_string()))) as ArrayRef,
+            Arc::new(TimestampNanosecondArray::from_vec(records.clone().map(|(_, r)| r.last_seen.timestamp_nanos()).collect(), Some(TIMEZONE.to_string()))) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
+
+// ARP alert buffer
+pub struct ArpAlertPackets {
+    records: VecDeque<(DateTime<Utc>, ArpAlertAttr)>,
+}
+
+impl ArpAlertPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for ArpAlertPackets {
+    type Attr = ArpAlertAttr;
+
+    fn push_back(&mut self, attr: ArpAlertAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(link_fields());
+        fields.extend(vec![
+            Field::new("Alert", DataType::Utf8, false),
+            ip_field("IP", false),
+            mac_field("MAC", false),
+            mac_field("PreviousMAC", true),
+            Field::new("Count", DataType::UInt32, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(link_columns(records.clone().map(|(_, r)| &r.link))?);
+        columns.extend(vec![
+            Arc::new(StringArray::from_iter_values(records.clone().map(|(_, r)| r.alert))) as ArrayRef,
+            ip_column(records.clone().map(|(_, r)| Some(r.ip)))?,
+            mac_column(records.clone().map(|(_, r)| Some(r.mac)))?,
+            mac_column(records.clone().map(|(_, r)| r.previous_mac))?,
+            Arc::new(records.clone().map(|(_, r)| r.count).collect::<UInt32Array>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
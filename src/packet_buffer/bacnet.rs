@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, StringArray, UInt8Array, UInt16Array, UInt32Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::BacnetAttr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, timestamp_type};
+
+// BACnet/IP record buffer
+pub struct BacnetPackets {
+    records: VecDeque<(DateTime<Utc>, BacnetAttr)>,
+}
+
+impl BacnetPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for BacnetPackets {
+    type Attr = BacnetAttr;
+
+    fn push_back(&mut self, attr: BacnetAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("BVLCFunction", DataType::UInt8, false),
+            Field::new("BVLCLength", DataType::UInt16, false),
+            Field::new("ForwardedFrom", DataType::Utf8, true),
+            Field::new("NPDUControl", DataType::UInt8, true),
+            Field::new("DNET", DataType::UInt16, true),
+            Field::new("DADR", DataType::Utf8, true),
+            Field::new("SNET", DataType::UInt16, true),
+            Field::new("SADR", DataType::Utf8, true),
+            Field::new("HopCount", DataType::UInt8, true),
+            Field::new("NetworkMessage", DataType::UInt8, true),
+            Field::new("APDUType", DataType::UInt8, true),
+            Field::new("ServiceChoice", DataType::UInt8, true),
+            Field::new("InvokeID", DataType::UInt8, true),
+            Field::new("ObjectType", DataType::UInt16, true),
+            Field::new("ObjectInstance", DataType::UInt32, true),
+            Field::new("PropertyID", DataType::UInt32, true),
+            Field::new("ArrayIndex", DataType::UInt32, true),
+            Field::new("Priority", DataType::UInt8, true),
+            Field::new("MaxAPDU", DataType::UInt32, true),
+            Field::new("VendorID", DataType::UInt32, true),
+            Field::new("LowLimit", DataType::UInt32, true),
+            Field::new("HighLimit", DataType::UInt32, true),
+            Field::new("ErrorClass", DataType::UInt32, true),
+            Field::new("ErrorCode", DataType::UInt32, true),
+            Field::new("Reason", DataType::UInt8, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.bvlc_function))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.bvlc_length))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.forwarded_from.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.npdu_control).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.dnet).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.dadr.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.snet).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sadr.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.hop_count).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.network_message).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.apdu_type).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.service_choice).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.invoke_id).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.object_type).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.object_instance).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.property_id).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.array_index).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.priority).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.max_apdu).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.vendor_id).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.low_limit).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.high_limit).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.error_class).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.error_code).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.reason).collect::<UInt8Array>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, StringArray, UInt8Array, UInt32Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt8Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::DhcpAttr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, ip_column, ip_field, list_column, list_field, mac_column, mac_field, timestamp_type};
+
+// DHCPv4 record buffer
+pub struct DhcpPackets {
+    records: VecDeque<(DateTime<Utc>, DhcpAttr)>,
+}
+
+impl DhcpPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for DhcpPackets {
+    type Attr = DhcpAttr;
+
+    fn push_back(&mut self, attr: DhcpAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("Op", DataType::UInt8, false),
+            Field::new("XID", DataType::UInt32, false),
+            Field::new("MessageType", DataType::UInt8, true),
+            mac_field("ClientMAC", false),
+            ip_field("ClientIP", true),
+            ip_field("AssignedIP", true),
+            ip_field("RequestedIP", true),
+            ip_field("ServerID", true),
+            Field::new("LeaseTime", DataType::UInt32, true),
+            Field::new("Hostname", DataType::Utf8, true),
+            Field::new("VendorClass", DataType::Utf8, true),
+            list_field("ParameterRequestList", DataType::UInt8),
+            Field::new("Fingerprint", DataType::Utf8, true),
+            Field::new("Vendor", DataType::Utf8, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.op))) as ArrayRef,
+            Arc::new(UInt32Array::from_iter_values(records.clone().map(|(_, r)| r.xid))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.message_type).collect::<UInt8Array>()) as ArrayRef,
+            mac_column(records.clone().map(|(_, r)| Some(r.client_mac)))?,
+            ip_column(records.clone().map(|(_, r)| r.client_ip.map(IpAddr::V4)))?,
+            ip_column(records.clone().map(|(_, r)| r.assigned_ip.map(IpAddr::V4)))?,
+            ip_column(records.clone().map(|(_, r)| r.requested_ip.map(IpAddr::V4)))?,
+            ip_column(records.clone().map(|(_, r)| r.server_id.map(IpAddr::V4)))?,
+            Arc::new(records.clone().map(|(_, r)| r.lease_time).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.hostname.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.vendor_class.as_deref()).collect::<StringArray>()) as ArrayRef,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.parameter_request_list))?,
+            Arc::new(records.clone().map(|(_, r)| r.fingerprint.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.vendor).collect::<StringArray>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, StringArray, UInt16Array, UInt32Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::NeighborAttr;
+use super::{RecordBuffer, datetime_columns, link_columns, link_fields, timestamp_type};
+
+// Topology record buffer (one row per LLDP/CDP announcement)
+pub struct NeighborPackets {
+    records: VecDeque<(DateTime<Utc>, NeighborAttr)>,
+}
+
+impl NeighborPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for NeighborPackets {
+    type Attr = NeighborAttr;
+
+    fn push_back(&mut self, attr: NeighborAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(link_fields());
+        fields.extend(vec![
+            Field::new("Interface", DataType::Utf8, false),
+            Field::new("Protocol", DataType::Utf8, false),
+            Field::new("ChassisID", DataType::Utf8, true),
+            Field::new("PortID", DataType::Utf8, true),
+            Field::new("TTL", DataType::UInt16, true),
+            Field::new("PortDescription", DataType::Utf8, true),
+            Field::new("SystemName", DataType::Utf8, true),
+            Field::new("SystemDescription", DataType::Utf8, true),
+            Field::new("Platform", DataType::Utf8, true),
+            Field::new("Capabilities", DataType::UInt32, true),
+            Field::new("EnabledCapabilities", DataType::UInt32, true),
+            Field::new("ManagementAddress", DataType::Utf8, true),
+            Field::new("NativeVLAN", DataType::UInt16, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(link_columns(records.clone().map(|(_, r)| &r.link))?);
+        columns.extend(vec![
+            Arc::new(StringArray::from_iter_values(records.clone().map(|(_, r)| &r.link.interface_name))) as ArrayRef,
+            Arc::new(StringArray::from_iter_values(records.clone().map(|(_, r)| r.protocol))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.chassis_id.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.port_id.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.ttl).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.port_description.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.system_name.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.system_description.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.platform.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.capabilities).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.enabled_capabilities).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.management_address.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.native_vlan).collect::<UInt16Array>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, BooleanArray, Int64Array, UInt8Array, UInt16Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt16Type, UInt32Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::DnsAttr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, list_column, list_field, string_list_column, timestamp_type};
+
+// DNS record buffer (one row per message)
+pub struct DnsPackets {
+    records: VecDeque<(DateTime<Utc>, DnsAttr)>,
+}
+
+impl DnsPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for DnsPackets {
+    type Attr = DnsAttr;
+
+    fn push_back(&mut self, attr: DnsAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("TransactionID", DataType::UInt16, false),
+            Field::new("Flags", DataType::UInt16, false),
+            Field::new("Response", DataType::Boolean, false),
+            Field::new("Opcode", DataType::UInt8, false),
+            Field::new("Rcode", DataType::UInt8, false),
+            list_field("QueryName", DataType::Utf8),
+            list_field("QueryType", DataType::UInt16),
+            list_field("AnswerName", DataType::Utf8),
+            list_field("AnswerType", DataType::UInt16),
+            list_field("AnswerTTL", DataType::UInt32),
+            list_field("AnswerData", DataType::Utf8),
+            // nanoseconds since the matching query (responses only)
+            Field::new("Latency", DataType::Int64, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.transaction_id))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.flags))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| Some(r.response)).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.opcode))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.rcode))) as ArrayRef,
+            string_list_column(records.clone().map(|(_, r)| &r.query_names))?,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.query_types))?,
+            string_list_column(records.clone().map(|(_, r)| &r.answer_names))?,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.answer_types))?,
+            list_column::<UInt32Type, _>(records.clone().map(|(_, r)| &r.answer_ttls))?,
+            string_list_column(records.clone().map(|(_, r)| &r.answer_data))?,
+            Arc::new(records.clone().map(|(_, r)| r.latency).collect::<Int64Array>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
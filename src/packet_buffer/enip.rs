@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, StringArray, UInt8Array, UInt16Array, UInt32Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::EnipAttr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, timestamp_type};
+
+// EtherNet/IP (CIP) record buffer
+pub struct EnipPackets {
+    records: VecDeque<(DateTime<Utc>, EnipAttr)>,
+}
+
+impl EnipPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for EnipPackets {
+    type Attr = EnipAttr;
+
+    fn push_back(&mut self, attr: EnipAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("Command", DataType::UInt16, true),
+            Field::new("EncapLength", DataType::UInt16, true),
+            Field::new("SessionHandle", DataType::UInt32, true),
+            Field::new("EncapStatus", DataType::UInt32, true),
+            Field::new("ConnectionID", DataType::UInt32, true),
+            Field::new("EncapSequence", DataType::UInt32, true),
+            Field::new("CIPSequence", DataType::UInt16, true),
+            Field::new("CIPService", DataType::UInt8, true),
+            Field::new("CIPClass", DataType::UInt32, true),
+            Field::new("CIPInstance", DataType::UInt32, true),
+            Field::new("CIPAttribute", DataType::UInt32, true),
+            Field::new("CIPSymbol", DataType::Utf8, true),
+            Field::new("GeneralStatus", DataType::UInt8, true),
+            Field::new("ExtendedStatus", DataType::UInt16, true),
+            Field::new("VendorID", DataType::UInt16, true),
+            Field::new("DeviceType", DataType::UInt16, true),
+            Field::new("ProductCode", DataType::UInt16, true),
+            Field::new("SerialNumber", DataType::UInt32, true),
+            Field::new("ProductName", DataType::Utf8, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(records.clone().map(|(_, r)| r.command).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.length).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.session_handle).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.status).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.connection_id).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.encap_sequence).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.cip_sequence).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.cip_service).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.cip_class).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.cip_instance).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.cip_attribute).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.cip_symbol.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.general_status).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.extended_status).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.vendor_id).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.device_type).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.product_code).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.serial_number).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.product_name.as_deref()).collect::<StringArray>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, UInt8Array, UInt16Array, UInt32Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt8Type, UInt32Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::FinsAttr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, list_column, list_field, timestamp_type};
+
+// Omron FINS record buffer (one row per FINS or FINS/TCP frame)
+pub struct FinsPackets {
+    records: VecDeque<(DateTime<Utc>, FinsAttr)>,
+}
+
+impl FinsPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for FinsPackets {
+    type Attr = FinsAttr;
+
+    fn push_back(&mut self, attr: FinsAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("TCPCommand", DataType::UInt32, true),
+            Field::new("TCPError", DataType::UInt32, true),
+            Field::new("ClientNode", DataType::UInt32, true),
+            Field::new("ServerNode", DataType::UInt32, true),
+            Field::new("ICF", DataType::UInt8, true),
+            Field::new("GCT", DataType::UInt8, true),
+            Field::new("DNA", DataType::UInt8, true),
+            Field::new("DA1", DataType::UInt8, true),
+            Field::new("DA2", DataType::UInt8, true),
+            Field::new("SNA", DataType::UInt8, true),
+            Field::new("SA1", DataType::UInt8, true),
+            Field::new("SA2", DataType::UInt8, true),
+            Field::new("SID", DataType::UInt8, true),
+            Field::new("CommandCode", DataType::UInt16, true),
+            Field::new("EndCode", DataType::UInt16, true),
+            list_field("MemoryArea", DataType::UInt8),
+            list_field("MemoryAddress", DataType::UInt32),
+            Field::new("ItemCount", DataType::UInt16, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(records.clone().map(|(_, r)| r.tcp_command).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.tcp_error).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.client_node).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.server_node).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.icf).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.gct).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.dna).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.da1).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.da2).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sna).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sa1).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sa2).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sid).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.command_code).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.end_code).collect::<UInt16Array>()) as ArrayRef,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.memory_area))?,
+            list_column::<UInt32Type, _>(records.clone().map(|(_, r)| &r.memory_address))?,
+            Arc::new(records.clone().map(|(_, r)| r.item_count).collect::<UInt16Array>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
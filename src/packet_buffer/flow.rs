@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, StringArray, TimestampNanosecondArray, UInt8Array, UInt16Array, UInt64Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::FlowAttr;
+use super::{RecordBuffer, TIMEZONE, datetime_columns, ip_column, ip_field, timestamp_type};
+
+// Flow record buffer (one row per exported flow, IPFIX-like)
+pub struct FlowPackets {
+    records: VecDeque<(DateTime<Utc>, FlowAttr)>,
+}
+
+impl FlowPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for FlowPackets {
+    type Attr = FlowAttr;
+
+    fn push_back(&mut self, attr: FlowAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(vec![
+            Field::new("VLAN", DataType::UInt16, true),
+            Field::new("Protocol", DataType::UInt8, false),
+            ip_field("SrcIP", false),
+            Field::new("SrcPort", DataType::UInt16, false),
+            ip_field("DstIP", false),
+            Field::new("DstPort", DataType::UInt16, false),
+            Field::new("FirstSeen", timestamp_type(), false),
+            Field::new("LastSeen", timestamp_type(), false),
+            Field::new("PacketsFwd", DataType::UInt64, false),
+            Field::new("PacketsRev", DataType::UInt64, false),
+            Field::new("BytesFwd", DataType::UInt64, false),
+            Field::new("BytesRev", DataType::UInt64, false),
+            Field::new("TCPFlagsFwd", DataType::UInt16, false),
+            Field::new("TCPFlagsRev", DataType::UInt16, false),
+            Field::new("EndReason", DataType::Utf8, false),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(vec![
+            Arc::new(records.clone().map(|(_, r)| r.vlan_id).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.protocol))) as ArrayRef,
+            ip_column(records.clone().map(|(_, r)| Some(r.src_addr)))?,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.src_port))) as ArrayRef,
+            ip_column(records.clone().map(|(_, r)| Some(r.dst_addr)))?,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.dst_port))) as ArrayRef,
+            Arc::new(TimestampNanosecondArray::from_vec(records.clone().map(|(_, r)| r.first_seen.timestamp_nanos()).collect(), Some(TIMEZONE.to_string()))) as ArrayRef,
+            Arc::new(TimestampNanosecondArray::from_vec(records.clone().map(|(_, r)| r.last_seen.timestamp_nanos()).collect(), Some(TIMEZONE.to_string()))) as ArrayRef,
+            Arc::new(UInt64Array::from_iter_values(records.clone().map(|(_, r)| r.packets_fwd))) as ArrayRef,
+            Arc::new(UInt64Array::from_iter_values(records.clone().map(|(_, r)| r.packets_rev))) as ArrayRef,
+            Arc::new(UInt64Array::from_iter_values(records.clone().map(|(_, r)| r.bytes_fwd))) as ArrayRef,
+            Arc::new(UInt64Array::from_iter_values(records.clone().map(|(_, r)| r.bytes_rev))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.tcp_flags_fwd))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.tcp_flags_rev))) as ArrayRef,
+            Arc::new(StringArray::from_iter_values(records.clone().map(|(_, r)| r.end_reason))) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, BooleanArray, StringArray, UInt8Array, UInt16Array};
+use datafusion::arrow::datatypes::{DataType, Field, Float64Type, Schema, UInt8Type, UInt32Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::Iec104Attr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, list_column, list_field, nullable_list_column, timestamp_type};
+
+// IEC 60870-5-104 record buffer (one row per APDU)
+pub struct Iec104Packets {
+    records: VecDeque<(DateTime<Utc>, Iec104Attr)>,
+}
+
+impl Iec104Packets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for Iec104Packets {
+    type Attr = Iec104Attr;
+
+    fn push_back(&mut self, attr: Iec104Attr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("FrameFormat", DataType::Utf8, false),
+            Field::new("APDULength", DataType::UInt8, false),
+            Field::new("SendSeq", DataType::UInt16, true),
+            Field::new("RecvSeq", DataType::UInt16, true),
+            Field::new("UFunction", DataType::Utf8, true),
+            Field::new("TypeID", DataType::UInt8, true),
+            Field::new("SQ", DataType::Boolean, true),
+            Field::new("NumObjects", DataType::UInt8, true),
+            Field::new("COT", DataType::UInt8, true),
+            Field::new("Negative", DataType::Boolean, true),
+            Field::new("Test", DataType::Boolean, true),
+            Field::new("Originator", DataType::UInt8, true),
+            Field::new("CommonAddress", DataType::UInt16, true),
+            list_field("IOA", DataType::UInt32),
+            list_field("Value", DataType::Float64),
+            list_field("Quality", DataType::UInt8),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(StringArray::from_iter_values(records.clone().map(|(_, r)| r.frame_format))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.apdu_length))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.send_seq).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.recv_seq).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.u_function).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.type_id).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sq).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.num_objects).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.cot).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.negative).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.test).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.originator).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.common_address).collect::<UInt16Array>()) as ArrayRef,
+            list_column::<UInt32Type, _>(records.clone().map(|(_, r)| &r.ioa))?,
+            nullable_list_column::<Float64Type, _>(records.clone().map(|(_, r)| &r.value))?,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.quality))?,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, BooleanArray, StringArray, UInt8Array, UInt16Array, UInt32Array, UInt64Array};
+use datafusion::arrow::datatypes::{DataType, Field, Float64Type, Int32Type, Schema, UInt32Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::{GooseAttr, SvAttr};
+use super::{RecordBuffer, datetime_columns, link_columns, link_fields, list_column, list_field, nullable_list_column, timestamp_type};
+
+// IEC 61850 GOOSE record buffer
+pub struct GoosePackets {
+    records: VecDeque<(DateTime<Utc>, GooseAttr)>,
+}
+
+impl GoosePackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for GoosePackets {
+    type Attr = GooseAttr;
+
+    fn push_back(&mut self, attr: GooseAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(link_fields());
+        fields.extend(vec![
+            Field::new("APPID", DataType::UInt16, false),
+            Field::new("APDULength", DataType::UInt16, false),
+            Field::new("SimulationBit", DataType::Boolean, false),
+            Field::new("GocbRef", DataType::Utf8, true),
+            Field::new("TimeAllowedToLive", DataType::UInt32, true),
+            Field::new("DatSet", DataType::Utf8, true),
+            Field::new("GoID", DataType::Utf8, true),
+            Field::new("T", DataType::UInt64, true),
+            Field::new("StNum", DataType::UInt32, true),
+            Field::new("SqNum", DataType::UInt32, true),
+            Field::new("Simulation", DataType::Boolean, true),
+            Field::new("ConfRev", DataType::UInt32, true),
+            Field::new("NdsCom", DataType::Boolean, true),
+            Field::new("NumDatSetEntries", DataType::UInt32, true),
+            list_field("Value", DataType::Float64),
+            Field::new("Anomaly", DataType::Utf8, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(link_columns(records.clone().map(|(_, r)| &r.link))?);
+        columns.extend(vec![
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.appid))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.length))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| Some(r.simulation_bit)).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.gocb_ref.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.time_allowed_to_live).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.dat_set.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.go_id.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.t).collect::<UInt64Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.st_num).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sq_num).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.simulation).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.conf_rev).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.nds_com).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.num_dat_set_entries).collect::<UInt32Array>()) as ArrayRef,
+            nullable_list_column::<Float64Type, _>(records.clone().map(|(_, r)| &r.values))?,
+            Arc::new(records.clone().map(|(_, r)| r.anomaly).collect::<StringArray>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
+
+// IEC 61850-9-2 Sampled Values record buffer (one row per ASDU)
+pub struct SvPackets {
+    records: VecDeque<(DateTime<Utc>, SvAttr)>,
+}
+
+impl SvPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for SvPackets {
+    type Attr = SvAttr;
+
+    fn push_back(&mut self, attr: SvAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(link_fields());
+        fields.extend(vec![
+            Field::new("APPID", DataType::UInt16, false),
+            Field::new("APDULength", DataType::UInt16, false),
+            Field::new("SimulationBit", DataType::Boolean, false),
+            Field::new("NoASDU", DataType::UInt8, false),
+            Field::new("SvID", DataType::Utf8, true),
+            Field::new("DatSet", DataType::Utf8, true),
+            Field::new("SmpCnt", DataType::UInt16, true),
+            Field::new("ConfRev", DataType::UInt32, true),
+            Field::new("SmpSynch", DataType::UInt8, true),
+            Field::new("SmpRate", DataType::UInt16, true),
+            list_field("Value", DataType::Int32),
+            list_field("Quality", DataType::UInt32),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(link_columns(records.clone().map(|(_, r)| &r.link))?);
+        columns.extend(vec![
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.appid))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.length))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| Some(r.simulation_bit)).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.no_asdu))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sv_id.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.dat_set.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.smp_cnt).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.conf_rev).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.smp_synch).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.smp_rate).collect::<UInt16Array>()) as ArrayRef,
+            list_column::<Int32Type, _>(records.clone().map(|(_, r)| &r.values))?,
+            list_column::<UInt32Type, _>(records.clone().map(|(_, r)| &r.quality))?,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, UInt8Array, UInt16Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt16Type, UInt32Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::MelsecAttr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, list_column, list_field, timestamp_type};
+
+// MELSEC MC protocol record buffer (one row per 3E/4E frame)
+pub struct MelsecPackets {
+    records: VecDeque<(DateTime<Utc>, MelsecAttr)>,
+}
+
+impl MelsecPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for MelsecPackets {
+    type Attr = MelsecAttr;
+
+    fn push_back(&mut self, attr: MelsecAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("Subheader", DataType::UInt16, false),
+            Field::new("SerialNumber", DataType::UInt16, true),
+            Field::new("NetworkNumber", DataType::UInt8, false),
+            Field::new("StationNumber", DataType::UInt8, false),
+            Field::new("ModuleIO", DataType::UInt16, false),
+            Field::new("ModuleStation", DataType::UInt8, false),
+            Field::new("DataLength", DataType::UInt16, false),
+            Field::new("MonitoringTimer", DataType::UInt16, true),
+            Field::new("Command", DataType::UInt16, true),
+            Field::new("Subcommand", DataType::UInt16, true),
+            list_field("DeviceCode", DataType::UInt16),
+            list_field("DeviceNumber", DataType::UInt32),
+            Field::new("Points", DataType::UInt16, true),
+            Field::new("EndCode", DataType::UInt16, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.subheader))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.serial_number).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.network_number))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.station_number))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.module_io))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.module_station))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.data_length))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.monitoring_timer).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.command).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.subcommand).collect::<UInt16Array>()) as ArrayRef,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.device_code))?,
+            list_column::<UInt32Type, _>(records.clone().map(|(_, r)| &r.device_number))?,
+            Arc::new(records.clone().map(|(_, r)| r.points).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.end_code).collect::<UInt16Array>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
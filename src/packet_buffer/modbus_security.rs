@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, BooleanArray, StringArray, TimestampNanosecondArray, UInt16Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt8Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::ModbusSessionAttr;
+use super::{RecordBuffer, TIMEZONE, address_columns, address_fields, datetime_columns, list_column, list_field, timestamp_type};
+
+// Modbus/TCP Security session buffer (one row per client certificate or newly seen unit ID)
+pub struct ModbusSessionPackets {
+    records: VecDeque<(DateTime<Utc>, ModbusSessionAttr)>,
+}
+
+impl ModbusSessionPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for ModbusSessionPackets {
+    type Attr = ModbusSessionAttr;
+
+    fn push_back(&mut self, attr: ModbusSessionAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("Role", DataType::Utf8, true),
+            Field::new("ClientSubject", DataType::Utf8, true),
+            Field::new("ClientNotAfter", timestamp_type(), true),
+            Field::new("Expired", DataType::Boolean, false),
+            Field::new("ServerSubject", DataType::Utf8, true),
+            Field::new("ServerNotAfter", timestamp_type(), true),
+            Field::new("Version", DataType::UInt16, true),
+            Field::new("CipherSuite", DataType::UInt16, true),
+            list_field("UnitIDs", DataType::UInt8),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(records.clone().map(|(_, r)| r.role.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.client_subject.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(TimestampNanosecondArray::from_opt_vec(records.clone().map(|(_, r)| r.client_not_after).collect(), Some(TIMEZONE.to_string()))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| Some(r.expired)).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.server_subject.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(TimestampNanosecondArray::from_opt_vec(records.clone().map(|(_, r)| r.server_not_after).collect(), Some(TIMEZONE.to_string()))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.version).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.cipher_suite).collect::<UInt16Array>()) as ArrayRef,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.unit_ids))?,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, BooleanArray, StringArray, UInt8Array, UInt16Array, UInt32Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt8Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::MqttAttr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, list_column, list_field, string_list_column, timestamp_type};
+
+// MQTT record buffer (one row per control packet)
+pub struct MqttPackets {
+    records: VecDeque<(DateTime<Utc>, MqttAttr)>,
+}
+
+impl MqttPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for MqttPackets {
+    type Attr = MqttAttr;
+
+    fn push_back(&mut self, attr: MqttAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("PacketType", DataType::UInt8, false),
+            Field::new("Flags", DataType::UInt8, false),
+            Field::new("RemainingLength", DataType::UInt32, false),
+            Field::new("ProtocolName", DataType::Utf8, true),
+            Field::new("ProtocolLevel", DataType::UInt8, true),
+            Field::new("ConnectFlags", DataType::UInt8, true),
+            Field::new("KeepAlive", DataType::UInt16, true),
+            Field::new("ClientID", DataType::Utf8, true),
+            Field::new("HasUsername", DataType::Boolean, true),
+            Field::new("HasPassword", DataType::Boolean, true),
+            Field::new("ReturnCode", DataType::UInt8, true),
+            Field::new("PacketID", DataType::UInt16, true),
+            Field::new("Topic", DataType::Utf8, true),
+            Field::new("QoS", DataType::UInt8, true),
+            Field::new("Retain", DataType::Boolean, true),
+            Field::new("Dup", DataType::Boolean, true),
+            Field::new("PayloadSize", DataType::UInt32, true),
+            Field::new("PayloadPreview", DataType::Utf8, true),
+            list_field("TopicFilters", DataType::Utf8),
+            list_field("RequestedQoS", DataType::UInt8),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.packet_type))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.flags))) as ArrayRef,
+            Arc::new(UInt32Array::from_iter_values(records.clone().map(|(_, r)| r.remaining_length))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.protocol_name.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.protocol_level).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.connect_flags).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.keepalive).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.client_id.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.has_username).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.has_password).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.return_code).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.packet_id).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.topic.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.qos).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.retain).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.dup).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.payload_size).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.payload_preview.as_deref()).collect::<StringArray>()) as ArrayRef,
+            string_list_column(records.clone().map(|(_, r)| &r.topic_filters))?,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.requested_qos))?,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, StringArray, UInt32Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::OpcuaAttr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, timestamp_type};
+
+// OPC UA record buffer (one row per UA TCP message chunk)
+pub struct OpcuaPackets {
+    records: VecDeque<(DateTime<Utc>, OpcuaAttr)>,
+}
+
+impl OpcuaPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for OpcuaPackets {
+    type Attr = OpcuaAttr;
+
+    fn push_back(&mut self, attr: OpcuaAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("MessageType", DataType::Utf8, false),
+            Field::new("ChunkType", DataType::Utf8, false),
+            Field::new("MessageSize", DataType::UInt32, false),
+            Field::new("ProtocolVersion", DataType::UInt32, true),
+            Field::new("ReceiveBufferSize", DataType::UInt32, true),
+            Field::new("SendBufferSize", DataType::UInt32, true),
+            Field::new("MaxMessageSize", DataType::UInt32, true),
+            Field::new("MaxChunkCount", DataType::UInt32, true),
+            Field::new("EndpointUrl", DataType::Utf8, true),
+            Field::new("Error", DataType::UInt32, true),
+            Field::new("Reason", DataType::Utf8, true),
+            Field::new("SecureChannelId", DataType::UInt32, true),
+            Field::new("SecurityPolicyUri", DataType::Utf8, true),
+            Field::new("SenderCertificateSize", DataType::UInt32, true),
+            Field::new("TokenId", DataType::UInt32, true),
+            Field::new("SequenceNumber", DataType::UInt32, true),
+            Field::new("RequestId", DataType::UInt32, true),
+            Field::new("ServiceTypeId", DataType::UInt32, true),
+            Field::new("Service", DataType::Utf8, true),
+            Field::new("RequestHandle", DataType::UInt32, true),
+            Field::new("ServiceResult", DataType::UInt32, true),
+            Field::new("RequestType", DataType::UInt32, true),
+            Field::new("SecurityMode", DataType::UInt32, true),
+            Field::new("RevisedLifetime", DataType::UInt32, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(StringArray::from_iter_values(records.clone().map(|(_, r)| &r.message_type))) as ArrayRef,
+            Arc::new(StringArray::from_iter_values(records.clone().map(|(_, r)| &r.chunk_type))) as ArrayRef,
+            Arc::new(UInt32Array::from_iter_values(records.clone().map(|(_, r)| r.message_size))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.protocol_version).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.receive_buffer_size).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.send_buffer_size).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.max_message_size).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.max_chunk_count).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.endpoint_url.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.error).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.reason.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.secure_channel_id).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.security_policy_uri.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sender_certificate_size).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.token_id).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sequence_number).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.request_id).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.service_type_id).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.service).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.request_handle).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.service_result).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.request_type).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.security_mode).collect::<UInt32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.revised_lifetime).collect::<UInt32Array>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, StringArray, UInt8Array, UInt16Array, UInt32Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt16Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::{DcpAttr, RtAttr};
+use super::{RecordBuffer, datetime_columns, link_columns, link_fields, list_column, list_field, timestamp_type};
+
+// PROFINET DCP record buffer
+pub struct DcpPackets {
+    records: VecDeque<(DateTime<Utc>, DcpAttr)>,
+}
+
+impl DcpPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for DcpPackets {
+    type Attr = DcpAttr;
+
+    fn push_back(&mut self, attr: DcpAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(link_fields());
+        fields.extend(vec![
+            Field::new("FrameID", DataType::UInt16, false),
+            Field::new("ServiceID", DataType::UInt8, false),
+            Field::new("ServiceType", DataType::UInt8, false),
+            Field::new("Xid", DataType::UInt32, false),
+            Field::new("ResponseDelay", DataType::UInt16, false),
+            Field::new("DataLength", DataType::UInt16, false),
+            list_field("Options", DataType::UInt16),
+            Field::new("BlockQualifier", DataType::UInt16, true),
+            Field::new("NameOfStation", DataType::Utf8, true),
+            Field::new("TypeOfStation", DataType::Utf8, true),
+            Field::new("AliasName", DataType::Utf8, true),
+            Field::new("IPAddress", DataType::Utf8, true),
+            Field::new("SubnetMask", DataType::Utf8, true),
+            Field::new("Gateway", DataType::Utf8, true),
+            Field::new("VendorID", DataType::UInt16, true),
+            Field::new("DeviceID", DataType::UInt16, true),
+            Field::new("DeviceRole", DataType::UInt8, true),
+            Field::new("Control", DataType::UInt8, true),
+            Field::new("ResponseError", DataType::UInt8, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(link_columns(records.clone().map(|(_, r)| &r.link))?);
+        columns.extend(vec![
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.frame_id))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.service_id))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.service_type))) as ArrayRef,
+            Arc::new(UInt32Array::from_iter_values(records.clone().map(|(_, r)| r.xid))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.response_delay))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.data_length))) as ArrayRef,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.options))?,
+            Arc::new(records.clone().map(|(_, r)| r.block_qualifier).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.name_of_station.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.type_of_station.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.alias_name.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.ip_address.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.subnet_mask.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.gateway.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.vendor_id).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.device_id).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.device_role).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.control).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.response_error).collect::<UInt8Array>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
+
+// PROFINET RT cyclic frame record buffer
+pub struct RtPackets {
+    records: VecDeque<(DateTime<Utc>, RtAttr)>,
+}
+
+impl RtPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for RtPackets {
+    type Attr = RtAttr;
+
+    fn push_back(&mut self, attr: RtAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(link_fields());
+        fields.extend(vec![
+            Field::new("FrameID", DataType::UInt16, false),
+            Field::new("RTClass", DataType::Utf8, false),
+            Field::new("DataLength", DataType::UInt16, false),
+            Field::new("CycleCounter", DataType::UInt16, false),
+            Field::new("DataStatus", DataType::UInt8, false),
+            Field::new("TransferStatus", DataType::UInt8, false),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(link_columns(records.clone().map(|(_, r)| &r.link))?);
+        columns.extend(vec![
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.frame_id))) as ArrayRef,
+            Arc::new(StringArray::from_iter_values(records.clone().map(|(_, r)| r.rt_class))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.data_length))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.cycle_counter))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.data_status))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.transfer_status))) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, BooleanArray, Int8Array, Int16Array, Int64Array, StringArray, UInt8Array, UInt16Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::PtpAttr;
+use super::{RecordBuffer, datetime_columns, ip_column, ip_field, link_columns, link_fields, timestamp_type};
+
+// PTP record buffer (L2 and UDP transports)
+pub struct PtpPackets {
+    records: VecDeque<(DateTime<Utc>, PtpAttr)>,
+}
+
+impl PtpPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for PtpPackets {
+    type Attr = PtpAttr;
+
+    fn push_back(&mut self, attr: PtpAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(link_fields());
+        fields.extend(vec![
+            ip_field("SrcIP", true),
+            ip_field("DstIP", true),
+            Field::new("MessageType", DataType::UInt8, false),
+            Field::new("Version", DataType::UInt8, false),
+            Field::new("MessageLength", DataType::UInt16, false),
+            Field::new("Domain", DataType::UInt8, false),
+            Field::new("Flags", DataType::UInt16, false),
+            // nanoseconds * 2^16
+            Field::new("CorrectionField", DataType::Int64, false),
+            Field::new("ClockIdentity", DataType::Utf8, false),
+            Field::new("PortNumber", DataType::UInt16, false),
+            Field::new("SequenceID", DataType::UInt16, false),
+            Field::new("LogMessageInterval", DataType::Int8, false),
+            // PTP time in nanoseconds
+            Field::new("Timestamp", DataType::Int64, true),
+            Field::new("GrandmasterIdentity", DataType::Utf8, true),
+            Field::new("Priority1", DataType::UInt8, true),
+            Field::new("Priority2", DataType::UInt8, true),
+            Field::new("ClockClass", DataType::UInt8, true),
+            Field::new("ClockAccuracy", DataType::UInt8, true),
+            Field::new("StepsRemoved", DataType::UInt16, true),
+            Field::new("UTCOffset", DataType::Int16, true),
+            Field::new("TimeSource", DataType::UInt8, true),
+            Field::new("GrandmasterChanged", DataType::Boolean, false),
+            // nanoseconds between Sync messages and deviation from 2^LogMessageInterval
+            Field::new("SyncInterval", DataType::Int64, true),
+            Field::new("SyncJitter", DataType::Int64, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(link_columns(records.clone().map(|(_, r)| &r.link))?);
+        columns.extend(vec![
+            ip_column(records.clone().map(|(_, r)| r.src_addr))?,
+            ip_column(records.clone().map(|(_, r)| r.dst_addr))?,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.message_type))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.version))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.message_length))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.domain))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.flags))) as ArrayRef,
+            Arc::new(Int64Array::from_iter_values(records.clone().map(|(_, r)| r.correction))) as ArrayRef,
+            Arc::new(StringArray::from_iter_values(records.clone().map(|(_, r)| &r.clock_identity))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.port_number))) as ArrayRef,
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.sequence_id))) as ArrayRef,
+            Arc::new(Int8Array::from_iter_values(records.clone().map(|(_, r)| r.log_message_interval))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.timestamp).collect::<Int64Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.grandmaster_identity.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.priority1).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.priority2).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.clock_class).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.clock_accuracy).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.steps_removed).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.utc_offset).collect::<Int16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.time_source).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| Some(r.grandmaster_changed)).collect::<BooleanArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sync_interval).collect::<Int64Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.sync_jitter).collect::<Int64Array>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, StringArray, UInt8Array, UInt16Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt8Type, UInt16Type, UInt32Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::S7Attr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, list_column, list_field, timestamp_type};
+
+// S7comm / S7comm-plus record buffer
+pub struct S7Packets {
+    records: VecDeque<(DateTime<Utc>, S7Attr)>,
+}
+
+impl S7Packets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for S7Packets {
+    type Attr = S7Attr;
+
+    fn push_back(&mut self, attr: S7Attr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("COTPType", DataType::UInt8, false),
+            Field::new("SrcTSAP", DataType::UInt16, true),
+            Field::new("DstTSAP", DataType::UInt16, true),
+            Field::new("ProtocolID", DataType::UInt8, true),
+            Field::new("ROSCTR", DataType::UInt8, true),
+            Field::new("PDURef", DataType::UInt16, true),
+            Field::new("ErrorClass", DataType::UInt8, true),
+            Field::new("ErrorCode", DataType::UInt8, true),
+            Field::new("Function", DataType::UInt8, true),
+            Field::new("ItemCount", DataType::UInt8, true),
+            list_field("ItemTransportSize", DataType::UInt8),
+            list_field("ItemLength", DataType::UInt16),
+            list_field("ItemDBNumber", DataType::UInt16),
+            list_field("ItemArea", DataType::UInt8),
+            list_field("ItemAddress", DataType::UInt32),
+            list_field("ItemReturnCode", DataType::UInt8),
+            Field::new("MaxAmQCalling", DataType::UInt16, true),
+            Field::new("MaxAmQCalled", DataType::UInt16, true),
+            Field::new("PDULength", DataType::UInt16, true),
+            Field::new("Block", DataType::Utf8, true),
+            Field::new("PIService", DataType::Utf8, true),
+            Field::new("PlusVersion", DataType::UInt8, true),
+            Field::new("PlusOpcode", DataType::UInt8, true),
+            Field::new("PlusFunction", DataType::UInt16, true),
+            Field::new("PlusSequence", DataType::UInt16, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.cotp_pdu_type))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.src_tsap).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.dst_tsap).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.protocol_id).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.rosctr).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.pdu_ref).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.error_class).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.error_code).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.function).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.item_count).collect::<UInt8Array>()) as ArrayRef,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.item_transport_size))?,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.item_length))?,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.item_db_number))?,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.item_area))?,
+            list_column::<UInt32Type, _>(records.clone().map(|(_, r)| &r.item_address))?,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.item_return_code))?,
+            Arc::new(records.clone().map(|(_, r)| r.max_amq_calling).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.max_amq_called).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.pdu_length).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.block.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.pi_service.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.plus_version).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.plus_opcode).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.plus_function).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.plus_sequence).collect::<UInt16Array>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, BooleanArray, Int32Array, StringArray, UInt8Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt8Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::SnmpAttr;
+use super::{RecordBuffer, address_columns, address_fields, datetime_columns, list_column, list_field, string_list_column, timestamp_type};
+
+// SNMP v1/v2c record buffer
+pub struct SnmpPackets {
+    records: VecDeque<(DateTime<Utc>, SnmpAttr)>,
+}
+
+impl SnmpPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for SnmpPackets {
+    type Attr = SnmpAttr;
+
+    fn push_back(&mut self, attr: SnmpAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("Version", DataType::UInt8, false),
+            // HMAC-SHA256 of the community string
+            Field::new("CommunityHash", DataType::Utf8, false),
+            Field::new("PDUType", DataType::UInt8, false),
+            Field::new("RequestID", DataType::Int32, true),
+            Field::new("ErrorStatus", DataType::Int32, true),
+            Field::new("ErrorIndex", DataType::Int32, true),
+            Field::new("Enterprise", DataType::Utf8, true),
+            Field::new("AgentAddr", DataType::Utf8, true),
+            Field::new("GenericTrap", DataType::Int32, true),
+            Field::new("SpecificTrap", DataType::Int32, true),
+            list_field("VarBindOID", DataType::Utf8),
+            list_field("VarBindType", DataType::UInt8),
+            list_field("VarBindValue", DataType::Utf8),
+            Field::new("HighInterest", DataType::Boolean, false),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.version))) as ArrayRef,
+            Arc::new(StringArray::from_iter_values(records.clone().map(|(_, r)| &r.community_hash))) as ArrayRef,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.pdu_type))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.request_id).collect::<Int32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.error_status).collect::<Int32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.error_index).collect::<Int32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.enterprise.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.agent_addr.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.generic_trap).collect::<Int32Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.specific_trap).collect::<Int32Array>()) as ArrayRef,
+            string_list_column(records.clone().map(|(_, r)| &r.varbind_oids))?,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.varbind_types))?,
+            string_list_column(records.clone().map(|(_, r)| &r.varbind_values))?,
+            Arc::new(records.clone().map(|(_, r)| Some(r.high_interest)).collect::<BooleanArray>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, StringArray, TimestampNanosecondArray, UInt16Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt8Type, UInt16Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::TlsAttr;
+use super::{RecordBuffer, TIMEZONE, address_columns, address_fields, datetime_columns, list_column, list_field, string_list_column, timestamp_type};
+
+// TLS handshake record buffer (one row per handshake record)
+pub struct TlsPackets {
+    records: VecDeque<(DateTime<Utc>, TlsAttr)>,
+}
+
+impl TlsPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for TlsPackets {
+    type Attr = TlsAttr;
+
+    fn push_back(&mut self, attr: TlsAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(address_fields());
+        fields.extend(vec![
+            Field::new("RecordVersion", DataType::UInt16, false),
+            list_field("HandshakeType", DataType::UInt8),
+            Field::new("Version", DataType::UInt16, true),
+            list_field("SupportedVersions", DataType::UInt16),
+            list_field("CipherSuites", DataType::UInt16),
+            list_field("Extensions", DataType::UInt16),
+            list_field("SupportedGroups", DataType::UInt16),
+            list_field("ECPointFormats", DataType::UInt8),
+            list_field("SignatureAlgorithms", DataType::UInt16),
+            Field::new("SNI", DataType::Utf8, true),
+            list_field("ALPN", DataType::Utf8),
+            Field::new("CertSubject", DataType::Utf8, true),
+            Field::new("CertIssuer", DataType::Utf8, true),
+            Field::new("CertNotBefore", timestamp_type(), true),
+            Field::new("CertNotAfter", timestamp_type(), true),
+            Field::new("CertRole", DataType::Utf8, true),
+            Field::new("JA3", DataType::Utf8, true),
+            Field::new("JA3Hash", DataType::Utf8, true),
+            Field::new("JA4", DataType::Utf8, true),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(address_columns(records.clone().map(|(_, r)| &r.addresses))?);
+        columns.extend(vec![
+            Arc::new(UInt16Array::from_iter_values(records.clone().map(|(_, r)| r.record_version))) as ArrayRef,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.handshake_types))?,
+            Arc::new(records.clone().map(|(_, r)| r.version).collect::<UInt16Array>()) as ArrayRef,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.supported_versions))?,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.cipher_suites))?,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.extensions))?,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.supported_groups))?,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.ec_point_formats))?,
+            list_column::<UInt16Type, _>(records.clone().map(|(_, r)| &r.signature_algorithms))?,
+            Arc::new(records.clone().map(|(_, r)| r.sni.as_deref()).collect::<StringArray>()) as ArrayRef,
+            string_list_column(records.clone().map(|(_, r)| &r.alpn))?,
+            Arc::new(records.clone().map(|(_, r)| r.cert_subject.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.cert_issuer.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(TimestampNanosecondArray::from_opt_vec(records.clone().map(|(_, r)| r.cert_not_before).collect(), Some(TIMEZONE.to_string()))) as ArrayRef,
+            Arc::new(TimestampNanosecondArray::from_opt_vec(records.clone().map(|(_, r)| r.cert_not_after).collect(), Some(TIMEZONE.to_string()))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.cert_role.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.ja3.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.ja3_hash.as_deref()).collect::<StringArray>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.ja4.as_deref()).collect::<StringArray>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use datafusion::arrow::array::{ArrayRef, BooleanArray, UInt8Array, UInt16Array};
+use datafusion::arrow::datatypes::{DataType, Field, Schema, UInt8Type};
+use datafusion::arrow::record_batch::RecordBatch;
+
+use crate::packet_handler::TrafficAttr;
+use super::{RecordBuffer, datetime_columns, ip_column, ip_field, link_columns, link_fields, list_column, list_field, timestamp_type};
+
+// All-traffic record buffer (one row per IP packet)
+pub struct TrafficPackets {
+    records: VecDeque<(DateTime<Utc>, TrafficAttr)>,
+}
+
+impl TrafficPackets {
+    pub fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+}
+
+impl RecordBuffer for TrafficPackets {
+    type Attr = TrafficAttr;
+
+    fn push_back(&mut self, attr: TrafficAttr, utc: &DateTime<Utc>) {
+        self.records.push_back((utc.clone(), attr));
+    }
+
+    fn pop_front(&mut self) {
+        self.records.pop_front();
+    }
+
+    fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    fn get_schema(&self) -> Arc<Schema> {
+        let mut fields = vec![Field::new("Timestamp", timestamp_type(), false)];
+        fields.extend(link_fields());
+        fields.extend(vec![
+            ip_field("SrcIP", false),
+            ip_field("DstIP", false),
+            Field::new("Protocol", DataType::UInt8, false),
+            Field::new("SrcPort", DataType::UInt16, true),
+            Field::new("DstPort", DataType::UInt16, true),
+            Field::new("TCPFlags", DataType::UInt16, true),
+            Field::new("UDPLength", DataType::UInt16, true),
+            Field::new("ICMPType", DataType::UInt8, true),
+            Field::new("ICMPCode", DataType::UInt8, true),
+            Field::new("EchoID", DataType::UInt16, true),
+            Field::new("EchoSeq", DataType::UInt16, true),
+            list_field("IPv6ExtensionHeaders", DataType::UInt8),
+            Field::new("RoutingType0", DataType::Boolean, false),
+        ]);
+        Arc::new(Schema::new(fields))
+    }
+
+    fn get_batch(&self, schema: &Schema, win_front: &usize, win_back: &usize) -> datafusion::error::Result<RecordBatch> {
+        let records = self.records.range(win_front..win_back);
+        let mut columns = datetime_columns(records.clone().map(|(utc, _)| utc));
+        columns.extend(link_columns(records.clone().map(|(_, r)| &r.link))?);
+        columns.extend(vec![
+            ip_column(records.clone().map(|(_, r)| Some(r.src_addr)))?,
+            ip_column(records.clone().map(|(_, r)| Some(r.dst_addr)))?,
+            Arc::new(UInt8Array::from_iter_values(records.clone().map(|(_, r)| r.protocol))) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.src_port).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.dst_port).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.tcp_flags).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.udp_length).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.icmp_type).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.icmp_code).collect::<UInt8Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.echo_id).collect::<UInt16Array>()) as ArrayRef,
+            Arc::new(records.clone().map(|(_, r)| r.echo_seq).collect::<UInt16Array>()) as ArrayRef,
+            list_column::<UInt8Type, _>(records.clone().map(|(_, r)| &r.extension_headers))?,
+            Arc::new(records.clone().map(|(_, r)| Some(r.routing_type0)).collect::<BooleanArray>()) as ArrayRef,
+        ]);
+        let batch = RecordBatch::try_new(Arc::new(schema.clone()), columns)?;
+        Ok(batch)
+    }
+}
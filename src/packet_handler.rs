@@ -1,7 +1,10 @@
 use log;
 use std::net::IpAddr;
+use std::ops::RangeInclusive;
 //use std::net::{AddrParseError, IpAddr, Ipv4Addr};
 
+use chrono::{DateTime, Utc};
+
 use pnet;
 use pnet::datalink::{NetworkInterface, MacAddr};
 //use pnet::datalink::{Channel, MacAddr, NetworkInterface};
@@ -9,7 +12,7 @@ use pnet::datalink::{NetworkInterface, MacAddr};
 use pnet::packet::arp::ArpPacket;
 //use pnet::packet::arp::{ArpHardwareTypes, ArpOperations};
 //use pnet::packet::arp::{ArpPacket, MutableArpPacket};
-use pnet::packet::ethernet::{EtherTypes, EthernetPacket};
+use pnet::packet::ethernet::{EtherType, EtherTypes, EthernetPacket};
 use pnet::packet::icmp::{echo_reply, echo_request, IcmpPacket, IcmpTypes};
 use pnet::packet::icmpv6::Icmpv6Packet;
 use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
@@ -17,11 +20,59 @@ use pnet::packet::ipv4::Ipv4Packet;
 use pnet::packet::ipv6::Ipv6Packet;
 use pnet::packet::tcp::TcpPacket;
 use pnet::packet::udp::UdpPacket;
+use pnet::packet::vlan::VlanPacket;
 use pnet::packet::Packet;
 
 //use modbus::modbus_tcp::{ModbusTCPPacket, FunctionFieldValues};
 mod modbus_tcp;
 use modbus_tcp::*;
+// plaintext Modbus/TCP
+const MODBUS_PORT: u16 = 502;
+mod enip;
+pub use enip::EnipAttr;
+mod s7comm;
+pub use s7comm::S7Attr;
+mod iec104;
+pub use iec104::Iec104Attr;
+mod bacnet;
+pub use bacnet::BacnetAttr;
+mod melsec;
+pub use melsec::MelsecAttr;
+mod fins;
+pub use fins::FinsAttr;
+mod opcua;
+pub use opcua::OpcuaAttr;
+mod iec61850;
+pub use iec61850::{GooseAttr, SvAttr};
+mod profinet;
+pub use profinet::{DcpAttr, RtAttr};
+mod bounded_map;
+mod tcp_stream;
+mod mqtt;
+pub use mqtt::MqttAttr;
+mod flow;
+pub use flow::FlowAttr;
+mod tcp_state;
+mod arp;
+pub use arp::{ArpAssetAttr, ArpAlertAttr};
+mod traffic;
+pub use traffic::TrafficAttr;
+mod dns;
+pub use dns::DnsAttr;
+mod dhcp;
+pub use dhcp::DhcpAttr;
+mod discovery;
+pub use discovery::NeighborAttr;
+mod x509;
+mod tls;
+pub use tls::TlsAttr;
+mod modbus_security;
+pub use modbus_security::ModbusSessionAttr;
+mod ptp;
+pub use ptp::PtpAttr;
+mod snmp;
+pub use snmp::SnmpAttr;
+mod ipv6_ext;
 
 // Example Attributes (for logging)
 #[derive(Debug)]
@@ -34,15 +85,25 @@ pub struct PacketAttr {
     pub src_port: u16,
     pub dst_port: u16,
     pub length: u32,
-    pub transaction: u16,
-    pub protocol: u16,
-    pub len: u16,
-    pub unit_id: u8,
-    pub function: u8,
-    pub ref_number: u16,
-    pub data: u16,
-    pub mult_count: u8,
-    pub mult_data: String
+    // Modbus fields, None when the function code doesn't define them
+    pub transaction: Option<u16>,
+    pub protocol: Option<u16>,
+    pub len: Option<u16>,
+    pub unit_id: Option<u8>,
+    pub function: Option<u8>,
+    pub ref_number: Option<u16>,
+    pub data: Option<u16>,
+    pub mult_count: Option<u8>,
+    pub mult_data: Option<String>,
+    pub tcp_flags: u16,
+    pub seq: u32,
+    pub ack: u32,
+    pub window: u16,
+    pub payload_len: u32,
+    pub retransmission: bool,
+    pub zero_window: bool,
+    pub rst_storm: bool,
+    pub half_open: bool,
 }
 
 impl PacketAttr {
@@ -65,279 +126,309 @@ impl PacketAttr {
             src_port: src_port,
             dst_port: dst_port,
             length: length,
-            transaction: 0,
-            protocol: 0,
-            len: 0,
-            unit_id: 0,
-            function: 0,
-            ref_number: 0,
-            data: 0,
-            mult_count: 0,
-            mult_data: "".to_string()
+            transaction: None,
+            protocol: None,
+            len: None,
+            unit_id: None,
+            function: None,
+            ref_number: None,
+            data: None,
+            mult_count: None,
+            mult_data: None,
+            tcp_flags: 0,
+            seq: 0,
+            ack: 0,
+            window: 0,
+            payload_len: 0,
+            retransmission: false,
+            zero_window: false,
+            rst_storm: false,
+            half_open: false,
         }
     }
 
+    pub fn set_tcp(&mut self, tcp: &TcpPacket) {
+        self.tcp_flags = tcp.get_flags();
+        self.seq = tcp.get_sequence();
+        self.ack = tcp.get_acknowledgement();
+        self.window = tcp.get_window();
+        self.payload_len = tcp.payload().len() as u32;
+    }
+
+    // None when the PDU is shorter than its function code requires
     pub fn set_modbus(
         &mut self,
         modbus_tcp: &ModbusTCPPacket,
         payload: &[u8]
-    ){
+    ) -> Option<()> {
         match (self.src_port, self.dst_port) {
-            ( _ , 502 ) => { /* (送信元, 送信先) Request */
+            ( _ , MODBUS_PORT ) => { /* (送信元, 送信先) Request */
                 match modbus_tcp.get_function() {
                     FunctionFieldValues::ReadCoilStatus => {
-                        let m_packet = read_coil_status::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_bit_count();
+                        let m_packet = read_coil_status::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_bit_count());
                     }
                     FunctionFieldValues::ReadInputStatus => {
-                        let m_packet = read_input_status::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_bit_count();
+                        let m_packet = read_input_status::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_bit_count());
                     }
                     FunctionFieldValues::ReadHoldingRegister => {
-                        let m_packet = read_holding_register::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_bit_count();
+                        let m_packet = read_holding_register::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_bit_count());
                         
                     }
                     FunctionFieldValues::ReadInputRegister => {
-                        let m_packet = read_input_register::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_bit_count();
+                        let m_packet = read_input_register::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_bit_count());
                     }
                     FunctionFieldValues::ForceSingleCoil => {
-                        let m_packet = force_single_coil::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_data();
+                        let m_packet = force_single_coil::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_data());
                     }
                     FunctionFieldValues::PresetSingleRegister => {
-                        let m_packet = preset_single_register::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_data();
+                        let m_packet = preset_single_register::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_data());
                     }
                     FunctionFieldValues::Diagnostics => {
-                        let m_packet = diagnostics::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
+                        let m_packet = diagnostics::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
                     }
                     FunctionFieldValues::FetchCommunicationEventCounter  => {
-                        let m_packet = fetch_communication_event_counter::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
+                        let m_packet = fetch_communication_event_counter::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
                     }
                     FunctionFieldValues::FetchCommunicationEventCounterLog  => {
-                        let m_packet = fetch_communication_event_counter_log::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
+                        let m_packet = fetch_communication_event_counter_log::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
                     }
                     FunctionFieldValues::ForceMultipleCoils => {
-                        let m_packet = force_multiple_coils::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_register_count();
-                        self.mult_count = m_packet.get_byte_count();
+                        let m_packet = force_multiple_coils::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_register_count());
+                        self.mult_count = Some(m_packet.get_byte_count());
                         let datas = m_packet.get_data();
+                        let mut mult_data = String::new();
                         for data in datas{
-                            self.mult_data += &reverse_string(&format!("{:0>8b}", data));
+                            mult_data += &reverse_string(&format!("{:0>8b}", data));
                         }
+                        self.mult_data = Some(mult_data);
                     }
                     FunctionFieldValues::PresetMultipleRegisters => {
-                        let m_packet = preset_multiple_registers::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_register_count();
-                        self.mult_count = m_packet.get_byte_count();
+                        let m_packet = preset_multiple_registers::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_register_count());
+                        self.mult_count = Some(m_packet.get_byte_count());
                         let datas = m_packet.get_data();
+                        let mut mult_data = String::new();
                         for data in datas{
-                            self.mult_data += &reverse_string(&format!("{:0>8b}", data));
+                            mult_data += &reverse_string(&format!("{:0>8b}", data));
                         }
+                        self.mult_data = Some(mult_data);
                     }
                     FunctionFieldValues::ReportSlaveID  => {
-                        let m_packet = report_slave_id::request::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
+                        let m_packet = report_slave_id::request::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
                     }
                     _ => {
                         
                     }
                 }
             }
-            ( 502 , _ ) => { /* (送信元, 送信先) Reply */
+            ( MODBUS_PORT , _ ) => { /* (送信元, 送信先) Reply */
                 match modbus_tcp.get_function() {
                     FunctionFieldValues::ReadCoilStatus => {
-                        let m_packet = read_coil_status::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.mult_count = m_packet.get_byte_count();
+                        let m_packet = read_coil_status::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.mult_count = Some(m_packet.get_byte_count());
                         let datas = m_packet.get_data();
+                        let mut mult_data = String::new();
                         for data in datas{
-                            self.mult_data += &reverse_string(&format!("{:0>8b}", data));
+                            mult_data += &reverse_string(&format!("{:0>8b}", data));
                         }
+                        self.mult_data = Some(mult_data);
                     }
                     FunctionFieldValues::ReadInputStatus => {
-                        let m_packet = read_input_status::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.mult_count = m_packet.get_byte_count();
+                        let m_packet = read_input_status::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.mult_count = Some(m_packet.get_byte_count());
                         let datas = m_packet.get_data();
+                        let mut mult_data = String::new();
                         for data in datas{
-                            self.mult_data += &reverse_string(&format!("{:0>8b}", data));
+                            mult_data += &reverse_string(&format!("{:0>8b}", data));
                         }
+                        self.mult_data = Some(mult_data);
                     }
                     FunctionFieldValues::ReadHoldingRegister => {
-                        let m_packet = read_holding_register::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.mult_count = m_packet.get_byte_count();
+                        let m_packet = read_holding_register::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.mult_count = Some(m_packet.get_byte_count());
                         let datas = m_packet.get_data();
+                        let mut mult_data = String::new();
                         for data in datas{
-                            self.mult_data += &reverse_string(&format!("{:0>8b}", data));
+                            mult_data += &reverse_string(&format!("{:0>8b}", data));
                         }
+                        self.mult_data = Some(mult_data);
                     }
                     FunctionFieldValues::ReadInputRegister => {
-                        let m_packet = read_input_register::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.mult_count = m_packet.get_byte_count();
+                        let m_packet = read_input_register::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.mult_count = Some(m_packet.get_byte_count());
                         let datas = m_packet.get_data();
+                        let mut mult_data = String::new();
                         for data in datas{
-                            self.mult_data += &reverse_string(&format!("{:0>8b}", data));
+                            mult_data += &reverse_string(&format!("{:0>8b}", data));
                         }
+                        self.mult_data = Some(mult_data);
                     }
                     FunctionFieldValues::ForceSingleCoil => {
-                        let m_packet = force_single_coil::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_data();
+                        let m_packet = force_single_coil::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_data());
                     }
                     FunctionFieldValues::PresetSingleRegister => {
-                        let m_packet = preset_single_register::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_data();
+                        let m_packet = preset_single_register::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_data());
                     }
                     FunctionFieldValues::Diagnostics => {
-                        let m_packet = diagnostics::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
+                        let m_packet = diagnostics::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
                     }
                     FunctionFieldValues::FetchCommunicationEventCounter  => {
-                        let m_packet = fetch_communication_event_counter::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
+                        let m_packet = fetch_communication_event_counter::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
                     }
                     FunctionFieldValues::FetchCommunicationEventCounterLog  => {
-                        let m_packet = fetch_communication_event_counter_log::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
+                        let m_packet = fetch_communication_event_counter_log::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
                     }
                     FunctionFieldValues::ForceMultipleCoils => {
-                        let m_packet = force_multiple_coils::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_data();
+                        let m_packet = force_multiple_coils::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_data());
                     }
                     FunctionFieldValues::PresetMultipleRegisters => {
-                        let m_packet = preset_multiple_registers::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
-                        self.ref_number = m_packet.get_reference_number();
-                        self.data = m_packet.get_data();
+                        let m_packet = preset_multiple_registers::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
+                        self.ref_number = Some(m_packet.get_reference_number());
+                        self.data = Some(m_packet.get_data());
                     }
                     FunctionFieldValues::ReportSlaveID  => {
-                        let m_packet = report_slave_id::reply::ModbusPacket::new(payload).unwrap();
-                        self.transaction = m_packet.get_transaction();
-                        self.protocol = m_packet.get_protocol();
-                        self.len = m_packet.get_length();
-                        self.unit_id = m_packet.get_unit();
-                        self.function = m_packet.get_function();
+                        let m_packet = report_slave_id::reply::ModbusPacket::new(payload)?;
+                        self.transaction = Some(m_packet.get_transaction());
+                        self.protocol = Some(m_packet.get_protocol());
+                        self.len = Some(m_packet.get_length());
+                        self.unit_id = Some(m_packet.get_unit());
+                        self.function = Some(m_packet.get_function());
                     }
                     _ => {
                         
@@ -346,6 +437,7 @@ impl PacketAttr {
             }
             ( _ , _ ) => { /* ModbusTCP以外の通信 */ }
         }
+        Some(())
     }
 
     pub fn clone(&self) -> PacketAttr{
@@ -368,16 +460,299 @@ impl PacketAttr {
         cp.data = self.data.clone();
         cp.mult_count = self.mult_count.clone();
         cp.mult_data = self.mult_data.clone();
+        cp.tcp_flags = self.tcp_flags;
+        cp.seq = self.seq;
+        cp.ack = self.ack;
+        cp.window = self.window;
+        cp.payload_len = self.payload_len;
+        cp.retransmission = self.retransmission;
+        cp.zero_window = self.zero_window;
+        cp.rst_storm = self.rst_storm;
+        cp.half_open = self.half_open;
         cp
     }
 }
 
+// L2-L4 addressing carried by the protocol specific records
+#[derive(Debug, Clone)]
+pub struct Addresses {
+    pub interface_name: String,
+    pub src_mac: MacAddr,
+    pub dst_mac: MacAddr,
+    pub src_addr: IpAddr,
+    pub dst_addr: IpAddr,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub length: u32,
+}
+
+impl Addresses {
+    pub fn new(
+        interface_name: String,
+        source_mac: MacAddr,
+        destination_mac: MacAddr,
+        source: IpAddr,
+        destination: IpAddr,
+        src_port: u16,
+        dst_port: u16,
+        length: u32,
+    ) -> Self {
+        Self {
+            interface_name: interface_name,
+            src_mac: source_mac,
+            dst_mac: destination_mac,
+            src_addr: source,
+            dst_addr: destination,
+            src_port: src_port,
+            dst_port: dst_port,
+            length: length,
+        }
+    }
+}
+
+// L2 addressing carried by the records of non-IP protocols
+#[derive(Debug, Clone)]
+pub struct LinkAddresses {
+    pub interface_name: String,
+    pub src_mac: MacAddr,
+    pub dst_mac: MacAddr,
+    pub vlan_id: Option<u16>,
+    pub length: u32,
+}
+
+impl LinkAddresses {
+    pub fn new(
+        interface_name: String,
+        source_mac: MacAddr,
+        destination_mac: MacAddr,
+        vlan_id: Option<u16>,
+        length: u32,
+    ) -> Self {
+        Self {
+            interface_name: interface_name,
+            src_mac: source_mac,
+            dst_mac: destination_mac,
+            vlan_id: vlan_id,
+            length: length,
+        }
+    }
+}
+
+// Runtime options, read once from the environment at startup
+#[derive(Clone, Debug)]
+pub struct HandlerConfig {
+    pub log_all_traffic: bool,
+    pub melsec_ports: RangeInclusive<u16>,
+    pub snmp_community_key: snmp::CommunityKey,
+}
+
+impl Default for HandlerConfig {
+    fn default() -> Self {
+        Self {
+            log_all_traffic: false,
+            melsec_ports: melsec::MELSEC_PORT_MIN..=melsec::MELSEC_PORT_MAX,
+            snmp_community_key: snmp::CommunityKey::default(),
+        }
+    }
+}
+
+impl HandlerConfig {
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        config.log_all_traffic = env_flag(traffic::LOG_ALL_TRAFFIC_ENV);
+        config.snmp_community_key = snmp::CommunityKey::from_env();
+        if let Ok(value) = std::env::var(melsec::MELSEC_PORTS_ENV) {
+            match port_range(&value) {
+                Some(ports) => config.melsec_ports = ports,
+                None => log::warn!("{}: invalid port range {:?}, using {:?}", melsec::MELSEC_PORTS_ENV, value, config.melsec_ports),
+            }
+        }
+        config
+    }
+}
+
+// "5000-5010" or a single port
+fn port_range(value: &str) -> Option<RangeInclusive<u16>> {
+    let mut bounds = value.trim().splitn(2, '-');
+    let start = bounds.next()?.trim().parse::<u16>().ok()?;
+    let end = match bounds.next() {
+        Some(end) => end.trim().parse::<u16>().ok()?,
+        None => start,
+    };
+    if start > end {
+        return None;
+    }
+    Some(start..=end)
+}
+
+fn env_flag(name: &str) -> bool {
+    match std::env::var(name) {
+        Ok(value) => matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"),
+        Err(_) => false,
+    }
+}
+
+// State kept by the capture thread across frames
+#[derive(Default)]
+pub struct HandlerState {
+    config: HandlerConfig,
+    goose: iec61850::GooseTracker,
+    tcp_streams: tcp_stream::TcpReassembler,
+    mqtt: mqtt::MqttSessions,
+    flows: flow::FlowTracker,
+    tcp: tcp_state::TcpTracker,
+    arp: arp::ArpTracker,
+    dns: dns::DnsTransactions,
+    modbus_security: modbus_security::ModbusSecuritySessions,
+    ptp: ptp::PtpTracker,
+}
+
+impl HandlerState {
+    pub fn new(config: HandlerConfig) -> Self {
+        Self {
+            config: config,
+            ..Default::default()
+        }
+    }
+}
+
+// Decoded records (one Arrow table per variant)
+#[derive(Debug)]
+pub enum Record {
+    Modbus(PacketAttr),
+    Enip(EnipAttr),
+    S7(S7Attr),
+    Iec104(Iec104Attr),
+    Bacnet(BacnetAttr),
+    Melsec(MelsecAttr),
+    Fins(FinsAttr),
+    Opcua(OpcuaAttr),
+    Goose(GooseAttr),
+    Sv(SvAttr),
+    Dcp(DcpAttr),
+    Rt(RtAttr),
+    Mqtt(MqttAttr),
+    Flow(FlowAttr),
+    ArpAsset(ArpAssetAttr),
+    ArpAlert(ArpAlertAttr),
+    Traffic(TrafficAttr),
+    Dns(DnsAttr),
+    Dhcp(DhcpAttr),
+    Neighbor(NeighborAttr),
+    Tls(TlsAttr),
+    ModbusSession(ModbusSessionAttr),
+    Ptp(PtpAttr),
+    Snmp(SnmpAttr),
+}
+
 pub enum Action {
     Accept(String),
-    Log(PacketAttr),
+    Log(Vec<Record>),
     Drop(String),
 }
 
+// Adds records produced besides the packet's own (e.g. exported flows, traffic records)
+fn with_records(action: Option<Action>, mut records: Vec<Record>) -> Option<Action> {
+    if records.is_empty() {
+        return action;
+    }
+    match action {
+        Some(Action::Log(mut logged)) => {
+            logged.append(&mut records);
+            Some(Action::Log(logged))
+        }
+        _ => Some(Action::Log(records)),
+    }
+}
+
+// Ports to dispatch on, the server (listening) side first. Without a seen
+// handshake the lower port is taken as the server's, ephemeral ports are high.
+fn service_ports(src_port: u16, dst_port: u16, server_port: Option<u16>) -> Vec<u16> {
+    let first = server_port.unwrap_or_else(|| std::cmp::min(src_port, dst_port));
+    let second = if first == src_port { dst_port } else { src_port };
+    if first == second {
+        vec![first]
+    } else {
+        vec![first, second]
+    }
+}
+
+// MC protocol frames in one segment/datagram (one record per frame)
+fn melsec_records(addresses: &Addresses, mut payload: &[u8]) -> Vec<Record> {
+    let mut records = Vec::new();
+    loop {
+        let mut melsec_attr = MelsecAttr::new(addresses.clone());
+        match melsec_attr.set_frame(payload) {
+            Some(size) => {
+                records.push(Record::Melsec(melsec_attr));
+                payload = &payload[size..];
+            }
+            None => break,
+        }
+    }
+    records
+}
+
+// Bounds-checked field readers for the hand-parsed dissectors
+fn be_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn le_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn le_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// Fixed addressing for the dissector unit tests
+#[cfg(test)]
+fn test_addresses(src_port: u16, dst_port: u16) -> Addresses {
+    Addresses::new(
+        "eth0".to_string(),
+        MacAddr::new(0x00, 0x1d, 0x9c, 0x01, 0x02, 0x03),
+        MacAddr::new(0x00, 0x0e, 0x8c, 0x04, 0x05, 0x06),
+        IpAddr::from([192, 168, 0, 10]),
+        IpAddr::from([192, 168, 0, 1]),
+        src_port,
+        dst_port,
+        0,
+    )
+}
+
+// TCP segment (20-byte header) for the stream and state tracker unit tests
+#[cfg(test)]
+fn test_tcp_segment(src_port: u16, dst_port: u16, seq: u32, ack: u32, flags: u16, payload: &[u8]) -> Vec<u8> {
+    use pnet::packet::tcp::MutableTcpPacket;
+    let mut buffer = vec![0u8; 20 + payload.len()];
+    let mut tcp = MutableTcpPacket::new(&mut buffer).unwrap();
+    tcp.set_source(src_port);
+    tcp.set_destination(dst_port);
+    tcp.set_sequence(seq);
+    tcp.set_acknowledgement(ack);
+    tcp.set_data_offset(5);
+    tcp.set_flags(flags);
+    tcp.set_window(64240);
+    tcp.set_payload(payload);
+    buffer
+}
+
+#[cfg(test)]
+fn test_link() -> LinkAddresses {
+    LinkAddresses::new(
+        "eth0".to_string(),
+        MacAddr::new(0x00, 0x1d, 0x9c, 0x01, 0x02, 0x03),
+        MacAddr::new(0x01, 0x0c, 0xcd, 0x01, 0x00, 0x01),
+        None,
+        0,
+    )
+}
+
 fn reverse_string(input: &String) -> String {
     let mut reversed = String::new();
     let mut chars: Vec<char> = Vec::new();
@@ -404,6 +779,8 @@ fn handle_udp_packet(
     source: IpAddr,
     destination: IpAddr,
     packet: &[u8],
+    utc: DateTime<Utc>,
+    state: &mut HandlerState,
 ) -> Option<Action> {
     let udp = UdpPacket::new(packet);
 
@@ -418,18 +795,110 @@ fn handle_udp_packet(
             udp.get_length()
         );
         log::debug!("{}", message);
-        /*
-        let packet_attr = PacketAttr::new(
-            interface_name,
+        let addresses = Addresses::new(
+            interface_name.to_string(),
             source_mac,
             destination_mac,
             source,
             destination,
-            &udp.get_source(),
-            &udp.get_destination(),
-            &(packet.len() as u32)
-        );*/
-        //return Some(Action::Log(packet_attr));
+            udp.get_source(),
+            udp.get_destination(),
+            packet.len() as u32
+        );
+        // try the dissector of the server-side port first, a dissector that
+        // can't decode the payload falls through to the next one
+        for port in service_ports(udp.get_source(), udp.get_destination(), None) {
+            let records = match port {
+                enip::ENIP_IO_PORT => { /* Class 0/1 connected I/O */
+                    let mut records = Vec::new();
+                    let mut enip_attr = EnipAttr::new(addresses.clone());
+                    if enip_attr.set_io(udp.payload()) {
+                        records.push(Record::Enip(enip_attr));
+                    }
+                    records
+                }
+                enip::ENIP_PORT => { /* ListIdentity etc. */
+                    let mut records = Vec::new();
+                    if let Some(encap) = enip::EncapsulationPacket::new(udp.payload()) {
+                        let mut enip_attr = EnipAttr::new(addresses.clone());
+                        if enip_attr.set_enip(&encap) {
+                            records.push(Record::Enip(enip_attr));
+                        }
+                    }
+                    records
+                }
+                bacnet::BACNET_PORT => {
+                    let mut records = Vec::new();
+                    let mut bacnet_attr = BacnetAttr::new(addresses.clone());
+                    if bacnet_attr.set_bacnet(udp.payload()) {
+                        records.push(Record::Bacnet(bacnet_attr));
+                    }
+                    records
+                }
+                fins::FINS_PORT => {
+                    let mut records = Vec::new();
+                    let mut fins_attr = FinsAttr::new(addresses.clone());
+                    if fins_attr.set_fins(udp.payload()) {
+                        records.push(Record::Fins(fins_attr));
+                    }
+                    records
+                }
+                dns::DNS_PORT => {
+                    let mut records = Vec::new();
+                    let mut dns_attr = DnsAttr::new(addresses.clone());
+                    if dns_attr.set_dns(udp.payload(), &mut state.dns, utc) {
+                        records.push(Record::Dns(dns_attr));
+                    }
+                    records
+                }
+                ptp::PTP_EVENT_PORT | ptp::PTP_GENERAL_PORT => {
+                    let mut records = Vec::new();
+                    let link = LinkAddresses::new(
+                        interface_name.to_string(),
+                        source_mac,
+                        destination_mac,
+                        None,
+                        packet.len() as u32
+                    );
+                    let mut ptp_attr = PtpAttr::new(link, Some(source), Some(destination));
+                    if ptp_attr.set_ptp(udp.payload()) {
+                        state.ptp.check(&mut ptp_attr, utc);
+                        records.push(Record::Ptp(ptp_attr));
+                    }
+                    records
+                }
+                snmp::SNMP_PORT | snmp::SNMP_TRAP_PORT => {
+                    let mut records = Vec::new();
+                    let mut snmp_attr = SnmpAttr::new(addresses.clone());
+                    if snmp_attr.set_snmp(udp.payload(), &state.config.snmp_community_key) {
+                        if snmp_attr.high_interest {
+                            log::warn!(
+                                "[{}]: SNMP PDU 0x{:02x}: {} > {}",
+                                interface_name,
+                                snmp_attr.pdu_type,
+                                source,
+                                destination
+                            );
+                        }
+                        records.push(Record::Snmp(snmp_attr));
+                    }
+                    records
+                }
+                dhcp::DHCP_SERVER_PORT | dhcp::DHCP_CLIENT_PORT => {
+                    let mut records = Vec::new();
+                    let mut dhcp_attr = DhcpAttr::new(addresses.clone());
+                    if dhcp_attr.set_dhcp(udp.payload()) {
+                        records.push(Record::Dhcp(dhcp_attr));
+                    }
+                    records
+                }
+                port if state.config.melsec_ports.contains(&port) => melsec_records(&addresses, udp.payload()),
+                _ => Vec::new(),
+            };
+            if !records.is_empty() {
+                return Some(Action::Log(records));
+            }
+        }
         return Some(Action::Accept(message));
     } else {
         log::error!("[{}]: Malformed UDP Packet", interface_name);
@@ -521,6 +990,8 @@ fn handle_tcp_packet(
     source: IpAddr,
     destination: IpAddr,
     packet: &[u8],
+    utc: DateTime<Utc>,
+    state: &mut HandlerState,
 ) -> Option<Action> {
     let tcp = TcpPacket::new(packet);
     if let Some(tcp) = tcp {
@@ -534,7 +1005,160 @@ fn handle_tcp_packet(
             packet.len()
         );
         log::debug!("{}", message);
-        let modbus_tcp = ModbusTCPPacket::new(tcp.payload());
+        state.tcp.update(source, destination, &tcp, utc);
+        let addresses = Addresses::new(
+            interface_name.to_string(),
+            source_mac,
+            destination_mac,
+            source,
+            destination,
+            tcp.get_source(),
+            tcp.get_destination(),
+            packet.len() as u32
+        );
+        // try the dissector of the server-side port first, a dissector that
+        // can't decode the payload falls through to the next one
+        let server_port = state.tcp.server_port(source, destination, &tcp);
+        for port in service_ports(tcp.get_source(), tcp.get_destination(), server_port) {
+            let records = match port {
+                MODBUS_PORT => break, /* plaintext Modbus, decoded below */
+                enip::ENIP_PORT => {
+                    let mut records = Vec::new();
+                    if let Some(encap) = enip::EncapsulationPacket::new(tcp.payload()) {
+                        let mut enip_attr = EnipAttr::new(addresses.clone());
+                        if enip_attr.set_enip(&encap) {
+                            records.push(Record::Enip(enip_attr));
+                        }
+                    }
+                    records
+                }
+                s7comm::S7COMM_PORT => {
+                    let mut records = Vec::new();
+                    if let Some(tpkt) = s7comm::TpktPacket::new(tcp.payload()) {
+                        let mut s7_attr = S7Attr::new(addresses.clone());
+                        if s7_attr.set_s7(&tpkt) {
+                            records.push(Record::S7(s7_attr));
+                        }
+                    }
+                    records
+                }
+                iec104::IEC104_PORT => {
+                    /* a segment usually carries several APDUs */
+                    let mut records = Vec::new();
+                    let mut payload = tcp.payload();
+                    loop {
+                        let mut iec104_attr = Iec104Attr::new(addresses.clone());
+                        match iec104_attr.set_apdu(payload) {
+                            Some(size) => {
+                                records.push(Record::Iec104(iec104_attr));
+                                payload = &payload[size..];
+                            }
+                            None => break,
+                        }
+                    }
+                    records
+                }
+                fins::FINS_PORT => {
+                    let mut records = Vec::new();
+                    let mut payload = tcp.payload();
+                    loop {
+                        let mut fins_attr = FinsAttr::new(addresses.clone());
+                        match fins_attr.set_tcp(payload) {
+                            Some(size) => {
+                                records.push(Record::Fins(fins_attr));
+                                payload = &payload[size..];
+                            }
+                            None => break,
+                        }
+                    }
+                    records
+                }
+                mqtt::MQTT_PORT => {
+                    /* control packets may span segments */
+                    let mut records = Vec::new();
+                    let key = (source, tcp.get_source(), destination, tcp.get_destination());
+                    let mqtt_sessions = &mut state.mqtt;
+                    state.tcp_streams.reassemble(key, &tcp, |data| {
+                        let mut mqtt_attr = MqttAttr::new(addresses.clone());
+                        match mqtt_attr.set_mqtt(data, mqtt_sessions) {
+                            Ok(Some(size)) => {
+                                records.push(Record::Mqtt(mqtt_attr));
+                                Some(size)
+                            }
+                            Ok(None) => None,
+                            /* not a control packet here: resynchronise on the next byte */
+                            Err(()) => Some(1),
+                        }
+                    });
+                    records
+                }
+                dns::DNS_PORT => {
+                    /* length-prefixed messages may span segments */
+                    let mut records = Vec::new();
+                    let key = (source, tcp.get_source(), destination, tcp.get_destination());
+                    let transactions = &mut state.dns;
+                    state.tcp_streams.reassemble(key, &tcp, |data| {
+                        let mut dns_attr = DnsAttr::new(addresses.clone());
+                        let (size, decoded) = dns_attr.set_tcp(data, transactions, utc)?;
+                        if decoded {
+                            records.push(Record::Dns(dns_attr));
+                        }
+                        Some(size)
+                    });
+                    records
+                }
+                port if tls::TLS_PORTS.contains(&port) || port == modbus_security::MODBUS_SECURITY_PORT => {
+                    /* handshake records may span segments */
+                    let mut records = Vec::new();
+                    let key = (source, tcp.get_source(), destination, tcp.get_destination());
+                    let sessions = &mut state.modbus_security;
+                    state.tcp_streams.reassemble(key, &tcp, |data| {
+                        let mut tls_attr = TlsAttr::new(addresses.clone());
+                        match tls_attr.set_record(data) {
+                            Ok(Some((size, decoded))) => {
+                                if decoded {
+                                    let session = if port == modbus_security::MODBUS_SECURITY_PORT {
+                                        sessions.update(&tls_attr, utc)
+                                    } else {
+                                        None
+                                    };
+                                    records.push(Record::Tls(tls_attr));
+                                    if let Some(session_attr) = session {
+                                        records.push(Record::ModbusSession(session_attr));
+                                    }
+                                }
+                                Some(size)
+                            }
+                            Ok(None) => None,
+                            Err(()) => Some(data.len()),
+                        }
+                    });
+                    records
+                }
+                opcua::OPCUA_PORT => {
+                    let mut records = Vec::new();
+                    let mut payload = tcp.payload();
+                    loop {
+                        let mut opcua_attr = OpcuaAttr::new(addresses.clone());
+                        match opcua_attr.set_message(payload) {
+                            Some(size) => {
+                                records.push(Record::Opcua(opcua_attr));
+                                payload = &payload[size..];
+                            }
+                            None => break,
+                        }
+                    }
+                    records
+                }
+                port if state.config.melsec_ports.contains(&port) => melsec_records(&addresses, tcp.payload()),
+                _ => Vec::new(),
+            };
+            if !records.is_empty() {
+                return Some(Action::Log(records));
+            }
+        }
+        let is_modbus = tcp.get_source() == MODBUS_PORT || tcp.get_destination() == MODBUS_PORT;
+        let modbus_tcp = ModbusTCPPacket::new(tcp.payload()).filter(|_| is_modbus);
         if let Some(modbus_tcp) = modbus_tcp{
             let mut packet_attr = PacketAttr::new(
                 interface_name.to_string(),
@@ -546,8 +1170,20 @@ fn handle_tcp_packet(
                 tcp.get_destination().clone(),
                 (packet.len() as u32)
             );
-            packet_attr.set_modbus(&modbus_tcp, &tcp.payload());
-            return Some(Action::Log(packet_attr))
+            if packet_attr.set_modbus(&modbus_tcp, &tcp.payload()).is_some() {
+                packet_attr.set_tcp(&tcp);
+                state.tcp.take_anomalies(&mut packet_attr, utc);
+                /* role -> unit mapping for clients also seen on Modbus/TCP Security */
+                let session = match (packet_attr.dst_port, packet_attr.unit_id) {
+                    (MODBUS_PORT, Some(unit_id)) => state.modbus_security.observe_unit(source, destination, unit_id),
+                    _ => None,
+                };
+                let mut records = vec![Record::Modbus(packet_attr)];
+                if let Some(session_attr) = session {
+                    records.push(Record::ModbusSession(session_attr));
+                }
+                return Some(Action::Log(records))
+            }
         }
         return Some(Action::Accept(message));
     } else {
@@ -560,17 +1196,41 @@ fn handle_transport_protocol(
     interface_name: &str,
     source_mac: MacAddr,
     destination_mac: MacAddr,
+    vlan_id: Option<u16>,
     source: IpAddr,
     destination: IpAddr,
     protocol: IpNextHeaderProtocol,
     packet: &[u8],
+    extensions: Option<&ipv6_ext::ExtensionHeaders>,
+    utc: DateTime<Utc>,
+    state: &mut HandlerState,
 ) -> Option<Action> {
-    match protocol {
+    let mut records: Vec<Record> = state.flows
+        .update(interface_name, vlan_id, source, destination, protocol, packet, utc)
+        .into_iter()
+        .map(Record::Flow)
+        .collect();
+    if state.config.log_all_traffic {
+        let link = LinkAddresses::new(
+            interface_name.to_string(),
+            source_mac,
+            destination_mac,
+            vlan_id,
+            packet.len() as u32
+        );
+        let mut traffic_attr = TrafficAttr::new(link, source, destination, protocol);
+        traffic_attr.set_transport(protocol, packet);
+        if let Some(extensions) = extensions {
+            traffic_attr.set_extensions(extensions);
+        }
+        records.push(Record::Traffic(traffic_attr));
+    }
+    let action = match protocol {
         IpNextHeaderProtocols::Udp => {
-            handle_udp_packet(interface_name, source_mac, destination_mac, source, destination, packet)
+            handle_udp_packet(interface_name, source_mac, destination_mac, source, destination, packet, utc, state)
         }
         IpNextHeaderProtocols::Tcp => {
-            handle_tcp_packet(interface_name, source_mac, destination_mac, source, destination, packet)
+            handle_tcp_packet(interface_name, source_mac, destination_mac, source, destination, packet, utc, state)
         }
         IpNextHeaderProtocols::Icmp => {
             handle_icmp_packet(interface_name, source, destination, packet)
@@ -591,22 +1251,34 @@ fn handle_transport_protocol(
                 protocol,
                 packet.len()
             );
-            return None;
+            None
         }
-    }
+    };
+    with_records(action, records)
 }
 
-fn handle_ipv4_packet(interface_name: &str, ethernet: &EthernetPacket) -> Option<Action> {
-    let header = Ipv4Packet::new(ethernet.payload());
+fn handle_ipv4_packet(
+    interface_name: &str,
+    ethernet: &EthernetPacket,
+    vlan_id: Option<u16>,
+    packet: &[u8],
+    utc: DateTime<Utc>,
+    state: &mut HandlerState,
+) -> Option<Action> {
+    let header = Ipv4Packet::new(packet);
     if let Some(header) = header {
         handle_transport_protocol(
             interface_name,
             ethernet.get_source(),
             ethernet.get_destination(),
+            vlan_id,
             IpAddr::V4(header.get_source()),
             IpAddr::V4(header.get_destination()),
             header.get_next_level_protocol(),
             header.payload(),
+            None,
+            utc,
+            state,
         )
     } else {
         log::error!("[{}]: Malformed IPv4 Packet", interface_name);
@@ -614,17 +1286,49 @@ fn handle_ipv4_packet(interface_name: &str, ethernet: &EthernetPacket) -> Option
     }
 }
 
-fn handle_ipv6_packet(interface_name: &str, ethernet: &EthernetPacket) -> Option<Action> {
-    let header = Ipv6Packet::new(ethernet.payload());
+fn handle_ipv6_packet(
+    interface_name: &str,
+    ethernet: &EthernetPacket,
+    vlan_id: Option<u16>,
+    packet: &[u8],
+    utc: DateTime<Utc>,
+    state: &mut HandlerState,
+) -> Option<Action> {
+    let header = Ipv6Packet::new(packet);
     if let Some(header) = header {
+        let (protocol, payload, extensions) = ipv6_ext::walk(header.get_next_header(), header.payload());
+        if extensions.routing_type0 {
+            log::warn!(
+                "[{}]: IPv6 routing header type 0: {} > {}",
+                interface_name,
+                header.get_source(),
+                header.get_destination()
+            );
+        }
+        if extensions.later_fragment {
+            let message = format!(
+                "[{}]: IPv6 fragment: {} > {}; protocol: {:?} length: {}",
+                interface_name,
+                header.get_source(),
+                header.get_destination(),
+                protocol,
+                payload.len()
+            );
+            log::debug!("{}", message);
+            return Some(Action::Accept(message));
+        }
         handle_transport_protocol(
             interface_name,
             ethernet.get_source(),
             ethernet.get_destination(),
+            vlan_id,
             IpAddr::V6(header.get_source()),
             IpAddr::V6(header.get_destination()),
-            header.get_next_header(),
-            header.payload(),
+            protocol,
+            payload,
+            Some(&extensions),
+            utc,
+            state,
         )
     } else {
         log::error!("[{}]: Malformed IPv6 Packet", interface_name);
@@ -632,20 +1336,274 @@ fn handle_ipv6_packet(interface_name: &str, ethernet: &EthernetPacket) -> Option
     }
 }
 
-fn handle_arp_packet(interface_name: &str, ethernet: &EthernetPacket) -> Option<Action> {
-    let header = ArpPacket::new(ethernet.payload());
+// 802.1Q tagged frames (GOOSE/SV and PROFINET RT are usually sent with a priority tag)
+fn handle_vlan_packet(
+    interface_name: &str,
+    ethernet: &EthernetPacket,
+    utc: DateTime<Utc>,
+    state: &mut HandlerState,
+) -> Option<Action> {
+    let header = VlanPacket::new(ethernet.payload());
+    if let Some(header) = header {
+        match header.get_ethertype() {
+            EtherTypes::Ipv4 => handle_ipv4_packet(
+                interface_name,
+                ethernet,
+                Some(header.get_vlan_identifier()),
+                header.payload(),
+                utc,
+                state,
+            ),
+            EtherTypes::Ipv6 => handle_ipv6_packet(
+                interface_name,
+                ethernet,
+                Some(header.get_vlan_identifier()),
+                header.payload(),
+                utc,
+                state,
+            ),
+            EtherTypes::Arp => handle_arp_packet(
+                interface_name,
+                ethernet.get_source(),
+                ethernet.get_destination(),
+                Some(header.get_vlan_identifier()),
+                header.payload(),
+                utc,
+                state,
+            ),
+            iec61850::GOOSE_ETHERTYPE | iec61850::SV_ETHERTYPE => handle_iec61850_packet(
+                interface_name,
+                ethernet.get_source(),
+                ethernet.get_destination(),
+                Some(header.get_vlan_identifier()),
+                header.get_ethertype(),
+                header.payload(),
+                state,
+            ),
+            profinet::PROFINET_ETHERTYPE => handle_profinet_packet(
+                interface_name,
+                ethernet.get_source(),
+                ethernet.get_destination(),
+                Some(header.get_vlan_identifier()),
+                header.payload(),
+            ),
+            discovery::LLDP_ETHERTYPE => handle_discovery_packet(
+                interface_name,
+                ethernet.get_source(),
+                ethernet.get_destination(),
+                Some(header.get_vlan_identifier()),
+                header.get_ethertype(),
+                header.payload(),
+            ),
+            ptp::PTP_ETHERTYPE => handle_ptp_packet(
+                interface_name,
+                ethernet.get_source(),
+                ethernet.get_destination(),
+                Some(header.get_vlan_identifier()),
+                header.payload(),
+                utc,
+                state,
+            ),
+            _ => {
+                log::error!(
+                    "[{}]: Unknown VLAN packet: {} > {}; vlan: {} ethertype: {:?} length: {}",
+                    interface_name,
+                    ethernet.get_source(),
+                    ethernet.get_destination(),
+                    header.get_vlan_identifier(),
+                    header.get_ethertype(),
+                    ethernet.packet().len()
+                );
+                return None;
+            }
+        }
+    } else {
+        log::error!("[{}]: Malformed VLAN Packet", interface_name);
+        return None;
+    }
+}
+
+fn handle_iec61850_packet(
+    interface_name: &str,
+    source_mac: MacAddr,
+    destination_mac: MacAddr,
+    vlan_id: Option<u16>,
+    ethertype: EtherType,
+    packet: &[u8],
+    state: &mut HandlerState,
+) -> Option<Action> {
+    let message = format!(
+        "[{}]: IEC 61850 {} packet: {} > {}; length: {}",
+        interface_name,
+        if ethertype == iec61850::GOOSE_ETHERTYPE { "GOOSE" } else { "SV" },
+        source_mac,
+        destination_mac,
+        packet.len()
+    );
+    log::debug!("{}", message);
+    let link = LinkAddresses::new(
+        interface_name.to_string(),
+        source_mac,
+        destination_mac,
+        vlan_id,
+        packet.len() as u32
+    );
+    if ethertype == iec61850::GOOSE_ETHERTYPE {
+        let mut goose_attr = GooseAttr::new(link);
+        if goose_attr.set_goose(packet) {
+            state.goose.check(&mut goose_attr);
+            return Some(Action::Log(vec![Record::Goose(goose_attr)]));
+        }
+    } else {
+        let records: Vec<Record> = iec61850::sv_records(&link, packet).into_iter().map(Record::Sv).collect();
+        if !records.is_empty() {
+            return Some(Action::Log(records));
+        }
+    }
+    return Some(Action::Accept(message));
+}
+
+fn handle_profinet_packet(
+    interface_name: &str,
+    source_mac: MacAddr,
+    destination_mac: MacAddr,
+    vlan_id: Option<u16>,
+    packet: &[u8],
+) -> Option<Action> {
+    let message = format!(
+        "[{}]: PROFINET packet: {} > {}; length: {}",
+        interface_name,
+        source_mac,
+        destination_mac,
+        packet.len()
+    );
+    log::debug!("{}", message);
+    let link = LinkAddresses::new(
+        interface_name.to_string(),
+        source_mac,
+        destination_mac,
+        vlan_id,
+        packet.len() as u32
+    );
+    let mut dcp_attr = DcpAttr::new(link.clone());
+    if dcp_attr.set_dcp(packet) {
+        return Some(Action::Log(vec![Record::Dcp(dcp_attr)]));
+    }
+    let mut rt_attr = RtAttr::new(link);
+    if rt_attr.set_rt(packet) {
+        return Some(Action::Log(vec![Record::Rt(rt_attr)]));
+    }
+    return Some(Action::Accept(message));
+}
+
+fn handle_ptp_packet(
+    interface_name: &str,
+    source_mac: MacAddr,
+    destination_mac: MacAddr,
+    vlan_id: Option<u16>,
+    packet: &[u8],
+    utc: DateTime<Utc>,
+    state: &mut HandlerState,
+) -> Option<Action> {
+    let message = format!(
+        "[{}]: PTP packet: {} > {}; length: {}",
+        interface_name,
+        source_mac,
+        destination_mac,
+        packet.len()
+    );
+    log::debug!("{}", message);
+    let link = LinkAddresses::new(
+        interface_name.to_string(),
+        source_mac,
+        destination_mac,
+        vlan_id,
+        packet.len() as u32
+    );
+    let mut ptp_attr = PtpAttr::new(link, None, None);
+    if ptp_attr.set_ptp(packet) {
+        state.ptp.check(&mut ptp_attr, utc);
+        return Some(Action::Log(vec![Record::Ptp(ptp_attr)]));
+    }
+    return Some(Action::Accept(message));
+}
+
+fn handle_discovery_packet(
+    interface_name: &str,
+    source_mac: MacAddr,
+    destination_mac: MacAddr,
+    vlan_id: Option<u16>,
+    ethertype: EtherType,
+    packet: &[u8],
+) -> Option<Action> {
+    let protocol = if ethertype == discovery::LLDP_ETHERTYPE { "LLDP" } else { "CDP" };
+    let message = format!(
+        "[{}]: {} packet: {} > {}; length: {}",
+        interface_name,
+        if ethertype == discovery::LLDP_ETHERTYPE { "LLDP" } else { "802.3 LLC" },
+        source_mac,
+        destination_mac,
+        packet.len()
+    );
+    log::debug!("{}", message);
+    let link = LinkAddresses::new(
+        interface_name.to_string(),
+        source_mac,
+        destination_mac,
+        vlan_id,
+        packet.len() as u32
+    );
+    let mut neighbor_attr = NeighborAttr::new(link, protocol);
+    let decoded = if ethertype == discovery::LLDP_ETHERTYPE {
+        neighbor_attr.set_lldp(packet)
+    } else {
+        neighbor_attr.set_cdp(packet)
+    };
+    if decoded {
+        return Some(Action::Log(vec![Record::Neighbor(neighbor_attr)]));
+    }
+    return Some(Action::Accept(message));
+}
+
+fn handle_arp_packet(
+    interface_name: &str,
+    source_mac: MacAddr,
+    destination_mac: MacAddr,
+    vlan_id: Option<u16>,
+    packet: &[u8],
+    utc: DateTime<Utc>,
+    state: &mut HandlerState,
+) -> Option<Action> {
+    let header = ArpPacket::new(packet);
     if let Some(header) = header {
         let message = format!(
             "[{}]: ARP packet: {}({}) > {}({}); operation: {:?}",
             interface_name,
-            ethernet.get_source(),
+            source_mac,
             header.get_sender_proto_addr(),
-            ethernet.get_destination(),
+            destination_mac,
             header.get_target_proto_addr(),
             header.get_operation()
         );
         log::debug!("{}", message);
-        return Some(Action::Accept(message));
+        let link = LinkAddresses::new(
+            interface_name.to_string(),
+            source_mac,
+            destination_mac,
+            vlan_id,
+            packet.len() as u32
+        );
+        let records: Vec<Record> = state.arp.update(&link, &header, utc)
+            .into_iter()
+            .map(|record| match record {
+                arp::ArpRecord::Asset(asset_attr) => Record::ArpAsset(asset_attr),
+                arp::ArpRecord::Alert(alert_attr) => {
+                    log::warn!("[{}]: ARP {}: {} is-at {}", interface_name, alert_attr.alert, alert_attr.ip, alert_attr.mac);
+                    Record::ArpAlert(alert_attr)
+                }
+            })
+            .collect();
+        return with_records(Some(Action::Accept(message)), records);
     } else {
         log::error!("[{}]: Malformed ARP Packet", interface_name);
         return None;
@@ -655,12 +1613,65 @@ fn handle_arp_packet(interface_name: &str, ethernet: &EthernetPacket) -> Option<
 pub fn handle_ethernet_frame(
     interface: &NetworkInterface,
     ethernet: &EthernetPacket,
+    utc: DateTime<Utc>,
+    state: &mut HandlerState,
 ) -> Option<Action> {
     let interface_name = &interface.name[..];
     match ethernet.get_ethertype() {
-        EtherTypes::Ipv4 => handle_ipv4_packet(interface_name, ethernet),
-        EtherTypes::Ipv6 => handle_ipv6_packet(interface_name, ethernet),
-        EtherTypes::Arp => handle_arp_packet(interface_name, ethernet),
+        EtherTypes::Ipv4 => handle_ipv4_packet(interface_name, ethernet, None, ethernet.payload(), utc, state),
+        EtherTypes::Ipv6 => handle_ipv6_packet(interface_name, ethernet, None, ethernet.payload(), utc, state),
+        EtherTypes::Arp => handle_arp_packet(
+            interface_name,
+            ethernet.get_source(),
+            ethernet.get_destination(),
+            None,
+            ethernet.payload(),
+            utc,
+            state,
+        ),
+        EtherTypes::Vlan => handle_vlan_packet(interface_name, ethernet, utc, state),
+        iec61850::GOOSE_ETHERTYPE | iec61850::SV_ETHERTYPE => handle_iec61850_packet(
+            interface_name,
+            ethernet.get_source(),
+            ethernet.get_destination(),
+            None,
+            ethernet.get_ethertype(),
+            ethernet.payload(),
+            state,
+        ),
+        profinet::PROFINET_ETHERTYPE => handle_profinet_packet(
+            interface_name,
+            ethernet.get_source(),
+            ethernet.get_destination(),
+            None,
+            ethernet.payload(),
+        ),
+        discovery::LLDP_ETHERTYPE => handle_discovery_packet(
+            interface_name,
+            ethernet.get_source(),
+            ethernet.get_destination(),
+            None,
+            ethernet.get_ethertype(),
+            ethernet.payload(),
+        ),
+        ptp::PTP_ETHERTYPE => handle_ptp_packet(
+            interface_name,
+            ethernet.get_source(),
+            ethernet.get_destination(),
+            None,
+            ethernet.payload(),
+            utc,
+            state,
+        ),
+        /* 802.3 length field: LLC frames (CDP over SNAP) */
+        EtherType(length) if length <= 1500 => handle_discovery_packet(
+            interface_name,
+            ethernet.get_source(),
+            ethernet.get_destination(),
+            None,
+            ethernet.get_ethertype(),
+            &ethernet.payload()[..(length as usize).min(ethernet.payload().len())],
+        ),
         _ => {
             log::error!(
                 "[{}]: Unknown packet: {} > {}; ethertype: {:?} length: {}",
@@ -674,3 +1685,135 @@ pub fn handle_ethernet_frame(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn service_ports_prefers_server_side() {
+        assert_eq!(service_ports(50000, 502, None), vec![502, 50000]);
+        assert_eq!(service_ports(502, 50000, None), vec![502, 50000]);
+        assert_eq!(service_ports(502, 44818, Some(44818)), vec![44818, 502]);
+        assert_eq!(service_ports(9600, 9600, None), vec![9600]);
+    }
+
+    #[test]
+    fn port_range_parsing() {
+        assert_eq!(port_range("5000-5010"), Some(5000..=5010));
+        assert_eq!(port_range(" 5000 - 5010 "), Some(5000..=5010));
+        assert_eq!(port_range("5007"), Some(5007..=5007));
+        assert_eq!(port_range("5010-5000"), None);
+        assert_eq!(port_range("5000-"), None);
+        assert_eq!(port_range("70000"), None);
+        assert_eq!(port_range(""), None);
+    }
+
+    fn modbus_attr(src_port: u16, dst_port: u16, payload: &[u8]) -> (Option<()>, PacketAttr) {
+        let addresses = test_addresses(src_port, dst_port);
+        let mut attr = PacketAttr::new(
+            addresses.interface_name,
+            addresses.src_mac,
+            addresses.dst_mac,
+            addresses.src_addr,
+            addresses.dst_addr,
+            src_port,
+            dst_port,
+            60,
+        );
+        let modbus_tcp = ModbusTCPPacket::new(payload).unwrap();
+        (attr.set_modbus(&modbus_tcp, payload), attr)
+    }
+
+    #[test]
+    fn modbus_request_fields() {
+        /* Read Holding Registers: unit 17, 3 registers from 107 */
+        let payload = [0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x11, 0x03, 0x00, 0x6B, 0x00, 0x03];
+        let (result, attr) = modbus_attr(50123, MODBUS_PORT, &payload);
+        assert_eq!(result, Some(()));
+        assert_eq!(attr.transaction, Some(1));
+        assert_eq!(attr.protocol, Some(0));
+        assert_eq!(attr.len, Some(6));
+        assert_eq!(attr.unit_id, Some(0x11));
+        assert_eq!(attr.function, Some(3));
+        assert_eq!(attr.ref_number, Some(107));
+        assert_eq!(attr.data, Some(3));
+        assert_eq!(attr.mult_count, None);
+        assert_eq!(attr.mult_data, None);
+    }
+
+    #[test]
+    fn modbus_reply_fields() {
+        let payload = [0x00, 0x01, 0x00, 0x00, 0x00, 0x09, 0x11, 0x03, 0x06, 0x02, 0x2B, 0x00, 0x00, 0x00, 0x64];
+        let (result, attr) = modbus_attr(MODBUS_PORT, 50123, &payload);
+        assert_eq!(result, Some(()));
+        assert_eq!(attr.transaction, Some(1));
+        assert_eq!(attr.function, Some(3));
+        assert_eq!(attr.ref_number, None);
+        assert_eq!(attr.data, None);
+        assert_eq!(attr.mult_count, Some(6));
+        /* bits of each byte, least significant first */
+        assert_eq!(
+            attr.mult_data.as_deref(),
+            Some("010000001101010000000000000000000000000000100110")
+        );
+    }
+
+    #[test]
+    fn modbus_malformed_pdus() {
+        /* request cut after the function code */
+        let (result, attr) = modbus_attr(50123, MODBUS_PORT, &[0x00, 0x02, 0x00, 0x00, 0x00, 0x06, 0x11, 0x03]);
+        assert_eq!(result, None);
+        assert_eq!(attr.transaction, None);
+        assert_eq!(attr.function, None);
+        /* byte count past the end of the reply */
+        let (result, attr) = modbus_attr(MODBUS_PORT, 50123, &[0x00, 0x02, 0x00, 0x00, 0x00, 0x05, 0x11, 0x03, 0x06, 0x00, 0x01]);
+        assert_eq!(result, Some(()));
+        assert_eq!(attr.mult_count, Some(6));
+        assert_eq!(attr.mult_data.as_deref(), Some("0000000010000000"));
+        /* function code without a layout (Read Device Identification) */
+        let (result, attr) = modbus_attr(50123, MODBUS_PORT, &[0x00, 0x03, 0x00, 0x00, 0x00, 0x05, 0x11, 0x2B, 0x0E, 0x01, 0x00]);
+        assert_eq!(result, Some(()));
+        assert_eq!(attr.transaction, None);
+        assert_eq!(attr.function, None);
+    }
+
+    fn tcp_action(src_port: u16, dst_port: u16, payload: &[u8]) -> Option<Action> {
+        let addresses = test_addresses(src_port, dst_port);
+        let segment = test_tcp_segment(src_port, dst_port, 1000, 0, pnet::packet::tcp::TcpFlags::ACK, payload);
+        handle_tcp_packet(
+            &addresses.interface_name,
+            addresses.src_mac,
+            addresses.dst_mac,
+            addresses.src_addr,
+            addresses.dst_addr,
+            &segment,
+            Utc::now(),
+            &mut HandlerState::default(),
+        )
+    }
+
+    #[test]
+    fn modbus_records_only_on_the_modbus_port() {
+        let request = [0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x11, 0x03, 0x00, 0x6B, 0x00, 0x03];
+        let records = match tcp_action(50123, MODBUS_PORT, &request) {
+            Some(Action::Log(records)) => records,
+            _ => panic!("expected a Modbus record"),
+        };
+        assert!(matches!(records[..], [Record::Modbus(_)]));
+        /* Modbus-like bytes on another port, and a PDU cut short */
+        assert!(matches!(tcp_action(50123, 8080, &request), Some(Action::Accept(_))));
+        assert!(matches!(tcp_action(50123, MODBUS_PORT, &request[..8]), Some(Action::Accept(_))));
+    }
+
+    #[test]
+    fn byte_readers_stop_at_the_end() {
+        let data = [0x12, 0x34, 0x56, 0x78];
+        assert_eq!(be_u16(&data, 0), Some(0x1234));
+        assert_eq!(le_u16(&data, 2), Some(0x7856));
+        assert_eq!(be_u32(&data, 0), Some(0x12345678));
+        assert_eq!(le_u32(&data, 0), Some(0x78563412));
+        assert_eq!(be_u16(&data, 3), None);
+        assert_eq!(be_u32(&data, 1), None);
+    }
+}
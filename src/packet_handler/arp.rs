@@ -0,0 +1,298 @@
+//! ARP-based asset inventory and spoofing alerts
+//!
+//! Every ARP sender (IP, MAC) pair is kept as a binding. Binding rows are
+//! exported when a binding is created or changes, and the whole table every
+//! INVENTORY_INTERVAL. Alerts:
+//! - BindingChange: an IP moved to another MAC
+//! - DuplicateIP: another MAC claims an IP whose binding was seen within DUPLICATE_WINDOW
+//! - GratuitousFlood: more than GRATUITOUS_FLOOD_COUNT gratuitous ARPs from one MAC within a second
+//!
+//! At most MAX_BINDINGS bindings are kept; the least recently seen ones make
+//! room for new addresses.
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use chrono::{DateTime, Duration, Utc};
+
+use pnet::datalink::MacAddr;
+use pnet::packet::arp::{ArpOperations, ArpPacket};
+
+use super::bounded_map::BoundedMap;
+use super::LinkAddresses;
+
+const INVENTORY_INTERVAL_SECS: i64 = 60;
+const DUPLICATE_WINDOW_SECS: i64 = 10;
+const GRATUITOUS_FLOOD_COUNT: u32 = 5;
+const MAX_BINDINGS: usize = 65536;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod AlertValues {
+    pub const BindingChange: &str = "BindingChange";
+    pub const DuplicateIP: &str = "DuplicateIP";
+    pub const GratuitousFlood: &str = "GratuitousFlood";
+}
+
+// Embedded OUI subset (industrial vendors and common infrastructure)
+const OUI_TABLE: &[([u8; 3], &str)] = &[
+    ([0x00, 0x00, 0x0A], "Omron"),
+    ([0x00, 0x00, 0x0C], "Cisco"),
+    ([0x00, 0x00, 0x54], "Schneider Electric (Modicon)"),
+    ([0x00, 0x00, 0x64], "Yokogawa"),
+    ([0x00, 0x00, 0xBC], "Rockwell Automation"),
+    ([0x00, 0x01, 0x05], "Beckhoff"),
+    ([0x00, 0x02, 0xA2], "Hilscher"),
+    ([0x00, 0x0C, 0x29], "VMware"),
+    ([0x00, 0x0E, 0x8C], "Siemens"),
+    ([0x00, 0x1B, 0x1B], "Siemens"),
+    ([0x00, 0x1D, 0x9C], "Rockwell Automation"),
+    ([0x00, 0x30, 0xDE], "WAGO"),
+    ([0x00, 0x40, 0x84], "Honeywell"),
+    ([0x00, 0x50, 0x56], "VMware"),
+    ([0x00, 0x80, 0x63], "Hirschmann"),
+    ([0x00, 0x80, 0xF4], "Schneider Electric (Telemecanique)"),
+    ([0x00, 0x90, 0xE8], "Moxa"),
+    ([0x00, 0xA0, 0x45], "Phoenix Contact"),
+    ([0x00, 0xD0, 0xC9], "Advantech"),
+    ([0x08, 0x00, 0x06], "Siemens"),
+    ([0x28, 0x63, 0x36], "Siemens"),
+    ([0x5C, 0x88, 0x16], "Rockwell Automation"),
+    ([0xB8, 0x27, 0xEB], "Raspberry Pi"),
+    ([0xDC, 0xA6, 0x32], "Raspberry Pi"),
+];
+
+pub fn vendor(mac: &MacAddr) -> Option<&'static str> {
+    let oui = [mac.0, mac.1, mac.2];
+    if let Some((_, vendor)) = OUI_TABLE.iter().find(|(prefix, _)| *prefix == oui) {
+        return Some(vendor);
+    }
+    if mac.0 & 0x02 != 0 {
+        return Some("Locally administered");
+    }
+    None
+}
+
+#[derive(Debug, Clone)]
+pub struct ArpAssetAttr {
+    pub interface_name: String,
+    pub ip: IpAddr,
+    pub mac: MacAddr,
+    pub vendor: Option<&'static str>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct ArpAlertAttr {
+    pub link: LinkAddresses,
+    pub alert: &'static str,
+    pub ip: IpAddr,
+    pub mac: MacAddr,
+    pub previous_mac: Option<MacAddr>,
+    pub count: Option<u32>,
+}
+
+pub enum ArpRecord {
+    Asset(ArpAssetAttr),
+    Alert(ArpAlertAttr),
+}
+
+struct GratuitousCounter {
+    window_start: DateTime<Utc>,
+    count: u32,
+}
+
+#[derive(Default)]
+pub struct ArpTracker {
+    bindings: BoundedMap<Ipv4Addr, ArpAssetAttr, MAX_BINDINGS>,
+    gratuitous: BoundedMap<MacAddr, GratuitousCounter, MAX_BINDINGS>,
+    last_inventory: Option<DateTime<Utc>>,
+}
+
+impl ArpTracker {
+    pub fn update(&mut self, link: &LinkAddresses, arp: &ArpPacket, utc: DateTime<Utc>) -> Vec<ArpRecord> {
+        let mut records = Vec::new();
+        let sender_ip = arp.get_sender_proto_addr();
+        let sender_mac = arp.get_sender_hw_addr();
+
+        /* gratuitous: announcement of the sender's own address */
+        let gratuitous = sender_ip == arp.get_target_proto_addr()
+            || (arp.get_operation() == ArpOperations::Reply && link.dst_mac == MacAddr::broadcast());
+        if gratuitous {
+            let counter = self.gratuitous.get_or_insert_with(sender_mac, || GratuitousCounter {
+                window_start: utc,
+                count: 0,
+            });
+            if utc - counter.window_start >= Duration::seconds(1) {
+                counter.window_start = utc;
+                counter.count = 0;
+            }
+            counter.count += 1;
+            /* once per window */
+            if counter.count == GRATUITOUS_FLOOD_COUNT + 1 {
+                records.push(ArpRecord::Alert(ArpAlertAttr {
+                    link: link.clone(),
+                    alert: AlertValues::GratuitousFlood,
+                    ip: IpAddr::V4(sender_ip),
+                    mac: sender_mac,
+                    previous_mac: None,
+                    count: Some(counter.count),
+                }));
+            }
+        }
+
+        /* probes (sender 0.0.0.0) do not claim an address */
+        if !sender_ip.is_unspecified() {
+            match self.bindings.get_mut(&sender_ip) {
+                Some(binding) if binding.mac == sender_mac => {
+                    binding.last_seen = utc;
+                }
+                Some(binding) => {
+                    let alert = if utc - binding.last_seen < Duration::seconds(DUPLICATE_WINDOW_SECS) {
+                        AlertValues::DuplicateIP
+                    } else {
+                        AlertValues::BindingChange
+                    };
+                    records.push(ArpRecord::Alert(ArpAlertAttr {
+                        link: link.clone(),
+                        alert: alert,
+                        ip: IpAddr::V4(sender_ip),
+                        mac: sender_mac,
+                        previous_mac: Some(binding.mac),
+                        count: None,
+                    }));
+                    binding.mac = sender_mac;
+                    binding.vendor = vendor(&sender_mac);
+                    binding.first_seen = utc;
+                    binding.last_seen = utc;
+                    records.push(ArpRecord::Asset(binding.clone()));
+                }
+                None => {
+                    let binding = ArpAssetAttr {
+                        interface_name: link.interface_name.clone(),
+                        ip: IpAddr::V4(sender_ip),
+                        mac: sender_mac,
+                        vendor: vendor(&sender_mac),
+                        first_seen: utc,
+                        last_seen: utc,
+                    };
+                    records.push(ArpRecord::Asset(binding.clone()));
+                    self.bindings.insert(sender_ip, binding);
+                }
+            }
+        }
+
+        /* periodic snapshot of the whole inventory */
+        let due = self.last_inventory.map_or(true, |last| utc - last >= Duration::seconds(INVENTORY_INTERVAL_SECS));
+        if due {
+            self.last_inventory = Some(utc);
+            records.extend(self.bindings.values().cloned().map(ArpRecord::Asset));
+        }
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_link;
+    use pnet::packet::arp::{ArpHardwareTypes, ArpOperation, MutableArpPacket};
+    use pnet::packet::ethernet::EtherTypes;
+
+    const PLC: MacAddr = MacAddr(0x00, 0x0e, 0x8c, 0x01, 0x02, 0x03);
+    const ROGUE: MacAddr = MacAddr(0x02, 0x00, 0x00, 0xaa, 0xbb, 0xcc);
+
+    fn arp(operation: ArpOperation, mac: MacAddr, ip: [u8; 4], target_ip: [u8; 4]) -> Vec<u8> {
+        let mut buffer = vec![0u8; 28];
+        let mut arp = MutableArpPacket::new(&mut buffer).unwrap();
+        arp.set_hardware_type(ArpHardwareTypes::Ethernet);
+        arp.set_protocol_type(EtherTypes::Ipv4);
+        arp.set_hw_addr_len(6);
+        arp.set_proto_addr_len(4);
+        arp.set_operation(operation);
+        arp.set_sender_hw_addr(mac);
+        arp.set_sender_proto_addr(ip.into());
+        arp.set_target_hw_addr(MacAddr::zero());
+        arp.set_target_proto_addr(target_ip.into());
+        buffer
+    }
+
+    fn update(tracker: &mut ArpTracker, mac: MacAddr, ip: [u8; 4], target_ip: [u8; 4]) -> Vec<ArpRecord> {
+        let data = arp(ArpOperations::Request, mac, ip, target_ip);
+        tracker.update(&test_link(), &ArpPacket::new(&data).unwrap(), Utc::now())
+    }
+
+    fn alerts(records: &[ArpRecord]) -> Vec<(&'static str, Option<MacAddr>, Option<u32>)> {
+        records.iter().filter_map(|record| match record {
+            ArpRecord::Alert(alert) => Some((alert.alert, alert.previous_mac, alert.count)),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn vendor_lookup() {
+        assert_eq!(vendor(&PLC), Some("Siemens"));
+        assert_eq!(vendor(&ROGUE), Some("Locally administered"));
+        assert_eq!(vendor(&MacAddr(0x00, 0x11, 0x22, 0x33, 0x44, 0x55)), None);
+    }
+
+    #[test]
+    fn new_binding_is_exported() {
+        let mut tracker = ArpTracker::default();
+        let records = update(&mut tracker, PLC, [192, 168, 0, 1], [192, 168, 0, 10]);
+        assert!(alerts(&records).is_empty());
+        match records.first() {
+            Some(ArpRecord::Asset(asset)) => {
+                assert_eq!(asset.ip, IpAddr::from([192, 168, 0, 1]));
+                assert_eq!(asset.mac, PLC);
+                assert_eq!(asset.vendor, Some("Siemens"));
+            }
+            _ => panic!("expected an asset record"),
+        }
+        /* known binding: nothing until the next inventory */
+        assert!(update(&mut tracker, PLC, [192, 168, 0, 1], [192, 168, 0, 10]).is_empty());
+        /* probes claim no address */
+        assert!(update(&mut tracker, ROGUE, [0, 0, 0, 0], [192, 168, 0, 1]).is_empty());
+        assert_eq!(tracker.bindings.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_ip_and_binding_change() {
+        let mut tracker = ArpTracker::default();
+        update(&mut tracker, PLC, [192, 168, 0, 1], [192, 168, 0, 10]);
+        let records = update(&mut tracker, ROGUE, [192, 168, 0, 1], [192, 168, 0, 10]);
+        assert_eq!(alerts(&records), vec![(AlertValues::DuplicateIP, Some(PLC), None)]);
+        let binding = tracker.bindings.get_mut(&Ipv4Addr::new(192, 168, 0, 1)).unwrap();
+        binding.last_seen = binding.last_seen - Duration::seconds(DUPLICATE_WINDOW_SECS);
+        let records = update(&mut tracker, PLC, [192, 168, 0, 1], [192, 168, 0, 10]);
+        assert_eq!(alerts(&records), vec![(AlertValues::BindingChange, Some(ROGUE), None)]);
+    }
+
+    #[test]
+    fn gratuitous_flood_alerts_once() {
+        let mut tracker = ArpTracker::default();
+        let mut flood = Vec::new();
+        for _ in 0..GRATUITOUS_FLOOD_COUNT + 3 {
+            flood.extend(alerts(&update(&mut tracker, ROGUE, [192, 168, 0, 1], [192, 168, 0, 1])));
+        }
+        assert_eq!(flood, vec![(AlertValues::GratuitousFlood, None, Some(GRATUITOUS_FLOOD_COUNT + 1))]);
+    }
+
+    #[test]
+    fn full_inventory_keeps_recent_bindings() {
+        let mut tracker = ArpTracker::default();
+        update(&mut tracker, PLC, [192, 168, 0, 1], [192, 168, 0, 10]);
+        for host in 0..MAX_BINDINGS as u32 {
+            let ip = (10u32 << 24 | host).to_be_bytes();
+            update(&mut tracker, ROGUE, ip, [192, 168, 0, 1]);
+            if host % 4096 == 0 {
+                update(&mut tracker, PLC, [192, 168, 0, 1], [192, 168, 0, 10]);
+            }
+        }
+        assert!(tracker.bindings.len() < MAX_BINDINGS);
+        assert!(tracker.bindings.get(&Ipv4Addr::new(10, 0, 0, 0)).is_none());
+        /* still known: no new asset record */
+        assert!(update(&mut tracker, PLC, [192, 168, 0, 1], [192, 168, 0, 10]).is_empty());
+    }
+}
@@ -0,0 +1,527 @@
+//! BACnet/IP (Annex J)
+//!
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  BVLC Type    | BVLC Function |          BVLC Length          |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |    Version    |    Control    | DNET, DLEN, DADR, SNET, SLEN,
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+  SADR, Hop Count (optional) ...
+//! |  APDU Type/Flags | ... Invoke ID, Service Choice, Service parameters
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+use std::net::Ipv4Addr;
+
+use super::{Addresses, be_u16};
+
+// 0xBAC0
+pub const BACNET_PORT: u16 = 47808;
+
+const BVLC_TYPE: u8 = 0x81;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod BvlcValues {
+    pub const Result: u8 = 0x00;
+    pub const WriteBroadcastDistributionTable: u8 = 0x01;
+    pub const ReadBroadcastDistributionTable: u8 = 0x02;
+    pub const ReadBroadcastDistributionTableAck: u8 = 0x03;
+    pub const ForwardedNpdu: u8 = 0x04;
+    pub const RegisterForeignDevice: u8 = 0x05;
+    pub const ReadForeignDeviceTable: u8 = 0x06;
+    pub const ReadForeignDeviceTableAck: u8 = 0x07;
+    pub const DeleteForeignDeviceTableEntry: u8 = 0x08;
+    pub const DistributeBroadcastToNetwork: u8 = 0x09;
+    pub const OriginalUnicastNpdu: u8 = 0x0A;
+    pub const OriginalBroadcastNpdu: u8 = 0x0B;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod ApduValues {
+    pub const ConfirmedRequest: u8 = 0;
+    pub const UnconfirmedRequest: u8 = 1;
+    pub const SimpleAck: u8 = 2;
+    pub const ComplexAck: u8 = 3;
+    pub const SegmentAck: u8 = 4;
+    pub const Error: u8 = 5;
+    pub const Reject: u8 = 6;
+    pub const Abort: u8 = 7;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod ServiceValues {
+    // confirmed
+    pub const SubscribeCov: u8 = 5;
+    pub const ReadProperty: u8 = 12;
+    pub const ReadPropertyMultiple: u8 = 14;
+    pub const WriteProperty: u8 = 15;
+    pub const WritePropertyMultiple: u8 = 16;
+    pub const DeviceCommunicationControl: u8 = 17;
+    pub const ReinitializeDevice: u8 = 20;
+    // unconfirmed
+    pub const IAm: u8 = 0;
+    pub const IHave: u8 = 1;
+    pub const WhoHas: u8 = 7;
+    pub const WhoIs: u8 = 8;
+}
+
+#[derive(Debug)]
+pub struct BacnetAttr {
+    pub addresses: Addresses,
+    // BVLC
+    pub bvlc_function: u8,
+    pub bvlc_length: u16,
+    pub forwarded_from: Option<String>,
+    // NPDU
+    pub npdu_control: Option<u8>,
+    pub dnet: Option<u16>,
+    pub dadr: Option<String>,
+    pub snet: Option<u16>,
+    pub sadr: Option<String>,
+    pub hop_count: Option<u8>,
+    pub network_message: Option<u8>,
+    // APDU
+    pub apdu_type: Option<u8>,
+    pub service_choice: Option<u8>,
+    pub invoke_id: Option<u8>,
+    // ReadProperty/WriteProperty/I-Am object and property
+    pub object_type: Option<u16>,
+    pub object_instance: Option<u32>,
+    pub property_id: Option<u32>,
+    pub array_index: Option<u32>,
+    pub priority: Option<u8>,
+    // I-Am
+    pub max_apdu: Option<u32>,
+    pub vendor_id: Option<u32>,
+    // Who-Is range
+    pub low_limit: Option<u32>,
+    pub high_limit: Option<u32>,
+    // Error (class/code), Reject and Abort (reason)
+    pub error_class: Option<u32>,
+    pub error_code: Option<u32>,
+    pub reason: Option<u8>,
+}
+
+// Tag header: (tag number, context specific, length/value/type, header size, value size)
+struct Tag {
+    number: u8,
+    context: bool,
+    lvt: u8,
+    header: usize,
+    length: usize,
+}
+
+impl Tag {
+    fn is_opening(&self) -> bool {
+        self.context && self.lvt == 6
+    }
+
+    fn is_closing(&self) -> bool {
+        self.context && self.lvt == 7
+    }
+}
+
+fn tag(data: &[u8], offset: usize) -> Option<Tag> {
+    let first = *data.get(offset)?;
+    let mut header = 1;
+    let mut number = first >> 4;
+    if number == 0x0F {
+        number = *data.get(offset + header)?;
+        header += 1;
+    }
+    let context = first & 0x08 != 0;
+    let lvt = first & 0x07;
+    let length = if context && (lvt == 6 || lvt == 7) {
+        0
+    } else if !context && number == 1 {
+        /* application boolean carries its value in LVT */
+        0
+    } else if lvt == 5 {
+        let extended = *data.get(offset + header)?;
+        header += 1;
+        match extended {
+            254 => {
+                let length = be_u16(data, offset + header)? as usize;
+                header += 2;
+                length
+            }
+            255 => return None,
+            _ => extended as usize,
+        }
+    } else {
+        lvt as usize
+    };
+    Some(Tag { number: number, context: context, lvt: lvt, header: header, length: length })
+}
+
+fn unsigned(data: &[u8]) -> u32 {
+    data.iter().take(4).fold(0, |value, b| value << 8 | *b as u32)
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(":")
+}
+
+impl BacnetAttr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            bvlc_function: 0,
+            bvlc_length: 0,
+            forwarded_from: None,
+            npdu_control: None,
+            dnet: None,
+            dadr: None,
+            snet: None,
+            sadr: None,
+            hop_count: None,
+            network_message: None,
+            apdu_type: None,
+            service_choice: None,
+            invoke_id: None,
+            object_type: None,
+            object_instance: None,
+            property_id: None,
+            array_index: None,
+            priority: None,
+            max_apdu: None,
+            vendor_id: None,
+            low_limit: None,
+            high_limit: None,
+            error_class: None,
+            error_code: None,
+            reason: None,
+        }
+    }
+
+    // returns false when the datagram is not BACnet/IP
+    pub fn set_bacnet(&mut self, payload: &[u8]) -> bool {
+        if payload.len() < 4 || payload[0] != BVLC_TYPE {
+            return false;
+        }
+        self.bvlc_function = payload[1];
+        self.bvlc_length = be_u16(payload, 2).unwrap_or(0);
+        let npdu = match self.bvlc_function {
+            BvlcValues::ForwardedNpdu => {
+                /* B/IP address of the originating device (IPv4 + port) */
+                if let Some(origin) = payload.get(4..10) {
+                    self.forwarded_from = Some(format!(
+                        "{}:{}",
+                        Ipv4Addr::new(origin[0], origin[1], origin[2], origin[3]),
+                        be_u16(origin, 4).unwrap_or(0)
+                    ));
+                }
+                payload.get(10..)
+            }
+            BvlcValues::OriginalUnicastNpdu
+            | BvlcValues::OriginalBroadcastNpdu
+            | BvlcValues::DistributeBroadcastToNetwork => payload.get(4..),
+            _ => None,
+        };
+        if let Some(npdu) = npdu {
+            self.set_npdu(npdu);
+        }
+        true
+    }
+
+    fn set_npdu(&mut self, npdu: &[u8]) {
+        /* Version (1) | Control */
+        if npdu.len() < 2 || npdu[0] != 0x01 {
+            return;
+        }
+        let control = npdu[1];
+        self.npdu_control = Some(control);
+        let mut offset = 2;
+        if control & 0x20 != 0 {
+            self.dnet = be_u16(npdu, offset);
+            let dlen = match npdu.get(offset + 2) {
+                Some(dlen) => *dlen as usize,
+                None => return,
+            };
+            self.dadr = npdu.get(offset + 3..offset + 3 + dlen).map(hex);
+            offset += 3 + dlen;
+        }
+        if control & 0x08 != 0 {
+            self.snet = be_u16(npdu, offset);
+            let slen = match npdu.get(offset + 2) {
+                Some(slen) => *slen as usize,
+                None => return,
+            };
+            self.sadr = npdu.get(offset + 3..offset + 3 + slen).map(hex);
+            offset += 3 + slen;
+        }
+        if control & 0x20 != 0 {
+            self.hop_count = npdu.get(offset).cloned();
+            offset += 1;
+        }
+        if control & 0x80 != 0 {
+            /* network layer message, no APDU */
+            self.network_message = npdu.get(offset).cloned();
+            return;
+        }
+        if let Some(apdu) = npdu.get(offset..) {
+            self.set_apdu(apdu);
+        }
+    }
+
+    fn set_apdu(&mut self, apdu: &[u8]) {
+        let first = match apdu.get(0) {
+            Some(first) => *first,
+            None => return,
+        };
+        let apdu_type = first >> 4;
+        self.apdu_type = Some(apdu_type);
+        let segmented = first & 0x08 != 0;
+        match apdu_type {
+            ApduValues::ConfirmedRequest => {
+                /* Flags | Max Segments/APDU | Invoke ID | [Sequence, Window] | Service */
+                self.invoke_id = apdu.get(2).cloned();
+                let offset = if segmented { 5 } else { 3 };
+                self.service_choice = apdu.get(offset).cloned();
+                if !segmented {
+                    self.set_service(&apdu[(offset + 1).min(apdu.len())..]);
+                }
+            }
+            ApduValues::UnconfirmedRequest => {
+                self.service_choice = apdu.get(1).cloned();
+                self.set_service(&apdu[2.min(apdu.len())..]);
+            }
+            ApduValues::SimpleAck => {
+                self.invoke_id = apdu.get(1).cloned();
+                self.service_choice = apdu.get(2).cloned();
+            }
+            ApduValues::ComplexAck => {
+                /* Flags | Invoke ID | [Sequence, Window] | Service */
+                self.invoke_id = apdu.get(1).cloned();
+                let offset = if segmented { 4 } else { 2 };
+                self.service_choice = apdu.get(offset).cloned();
+                if !segmented {
+                    self.set_service(&apdu[(offset + 1).min(apdu.len())..]);
+                }
+            }
+            ApduValues::SegmentAck => {
+                self.invoke_id = apdu.get(1).cloned();
+            }
+            ApduValues::Error => {
+                /* Invoke ID | Service | Error Class | Error Code */
+                self.invoke_id = apdu.get(1).cloned();
+                self.service_choice = apdu.get(2).cloned();
+                let mut offset = 3;
+                if let Some(class) = tag(apdu, offset) {
+                    let value = apdu.get(offset + class.header..offset + class.header + class.length);
+                    self.error_class = value.map(unsigned);
+                    offset += class.header + class.length;
+                    if let Some(code) = tag(apdu, offset) {
+                        let value = apdu.get(offset + code.header..offset + code.header + code.length);
+                        self.error_code = value.map(unsigned);
+                    }
+                }
+            }
+            ApduValues::Reject | ApduValues::Abort => {
+                self.invoke_id = apdu.get(1).cloned();
+                self.reason = apdu.get(2).cloned();
+            }
+            _ => {}
+        }
+    }
+
+    fn set_service(&mut self, data: &[u8]) {
+        let confirmed = self.apdu_type != Some(ApduValues::UnconfirmedRequest);
+        let mut offset = 0;
+        while let Some(tag) = tag(data, offset) {
+            if tag.is_opening() || tag.is_closing() {
+                /* property value (WriteProperty) is not decoded */
+                offset += tag.header;
+                let mut depth = if tag.is_opening() { 1 } else { 0 };
+                while depth > 0 {
+                    let inner = match self::tag(data, offset) {
+                        Some(inner) => inner,
+                        None => return,
+                    };
+                    if inner.is_opening() {
+                        depth += 1;
+                    } else if inner.is_closing() {
+                        depth -= 1;
+                    }
+                    offset += inner.header + inner.length;
+                }
+                continue;
+            }
+            let value = match data.get(offset + tag.header..offset + tag.header + tag.length) {
+                Some(value) => value,
+                None => return,
+            };
+            match (confirmed, self.service_choice, tag.context, tag.number) {
+                /* ReadProperty / WriteProperty request and ReadProperty-ACK */
+                (true, Some(ServiceValues::ReadProperty), true, 0)
+                | (true, Some(ServiceValues::WriteProperty), true, 0)
+                | (true, Some(ServiceValues::ReadPropertyMultiple), true, 0)
+                | (true, Some(ServiceValues::WritePropertyMultiple), true, 0) => {
+                    self.set_object_identifier(value);
+                }
+                (true, Some(ServiceValues::ReadProperty), true, 1)
+                | (true, Some(ServiceValues::WriteProperty), true, 1) => {
+                    self.property_id = Some(unsigned(value));
+                }
+                (true, Some(ServiceValues::ReadProperty), true, 2)
+                | (true, Some(ServiceValues::WriteProperty), true, 2) => {
+                    self.array_index = Some(unsigned(value));
+                }
+                (true, Some(ServiceValues::WriteProperty), true, 4) => {
+                    self.priority = Some(unsigned(value) as u8);
+                }
+                /* I-Am: Object ID | Max APDU | Segmentation | Vendor ID */
+                (false, Some(ServiceValues::IAm), false, 12) => {
+                    self.set_object_identifier(value);
+                }
+                (false, Some(ServiceValues::IAm), false, 2) => {
+                    if self.max_apdu.is_none() {
+                        self.max_apdu = Some(unsigned(value));
+                    } else {
+                        self.vendor_id = Some(unsigned(value));
+                    }
+                }
+                /* Who-Is: [Low Limit] [High Limit] */
+                (false, Some(ServiceValues::WhoIs), true, 0) => {
+                    self.low_limit = Some(unsigned(value));
+                }
+                (false, Some(ServiceValues::WhoIs), true, 1) => {
+                    self.high_limit = Some(unsigned(value));
+                }
+                _ => {}
+            }
+            /* only the first object of ReadPropertyMultiple/WritePropertyMultiple */
+            if self.object_type.is_some() && self.property_id.is_none()
+                && (self.service_choice == Some(ServiceValues::ReadPropertyMultiple)
+                    || self.service_choice == Some(ServiceValues::WritePropertyMultiple)) {
+                return;
+            }
+            offset += tag.header + tag.length;
+        }
+    }
+
+    fn set_object_identifier(&mut self, value: &[u8]) {
+        /* object type (10 bits) | instance number (22 bits) */
+        if value.len() == 4 {
+            let identifier = unsigned(value);
+            self.object_type = Some((identifier >> 22) as u16);
+            self.object_instance = Some(identifier & 0x3FFFFF);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+
+    fn parse(payload: &[u8]) -> (bool, BacnetAttr) {
+        let mut attr = BacnetAttr::new(test_addresses(BACNET_PORT, BACNET_PORT));
+        (attr.set_bacnet(payload), attr)
+    }
+
+    #[test]
+    fn who_is_with_limits() {
+        let (ok, attr) = parse(&[0x81, 0x0b, 0x00, 0x0c, 0x01, 0x00, 0x10, 0x08, 0x09, 0x00, 0x19, 0x64]);
+        assert!(ok);
+        assert_eq!(attr.bvlc_function, BvlcValues::OriginalBroadcastNpdu);
+        assert_eq!(attr.bvlc_length, 12);
+        assert_eq!(attr.apdu_type, Some(ApduValues::UnconfirmedRequest));
+        assert_eq!(attr.service_choice, Some(ServiceValues::WhoIs));
+        assert_eq!(attr.low_limit, Some(0));
+        assert_eq!(attr.high_limit, Some(100));
+    }
+
+    #[test]
+    fn i_am() {
+        let payload = [
+            0x81, 0x0b, 0x00, 0x14, 0x01, 0x00, 0x10, 0x00,
+            0xc4, 0x02, 0x00, 0x00, 0x0a, 0x22, 0x05, 0xc4, 0x91, 0x00, 0x21, 0x0f,
+        ];
+        let (_, attr) = parse(&payload);
+        assert_eq!(attr.service_choice, Some(ServiceValues::IAm));
+        assert_eq!(attr.object_type, Some(8));
+        assert_eq!(attr.object_instance, Some(10));
+        assert_eq!(attr.max_apdu, Some(1476));
+        assert_eq!(attr.vendor_id, Some(15));
+    }
+
+    #[test]
+    fn read_property_request() {
+        let payload = [
+            0x81, 0x0a, 0x00, 0x11, 0x01, 0x04,
+            0x00, 0x05, 0x01, 0x0c, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x19, 0x55,
+        ];
+        let (_, attr) = parse(&payload);
+        assert_eq!(attr.npdu_control, Some(0x04));
+        assert_eq!(attr.apdu_type, Some(ApduValues::ConfirmedRequest));
+        assert_eq!(attr.invoke_id, Some(1));
+        assert_eq!(attr.service_choice, Some(ServiceValues::ReadProperty));
+        assert_eq!(attr.object_type, Some(0));
+        assert_eq!(attr.object_instance, Some(1));
+        assert_eq!(attr.property_id, Some(85));
+        assert_eq!(attr.array_index, None);
+    }
+
+    #[test]
+    fn write_property_skips_the_value() {
+        let payload = [
+            0x81, 0x0a, 0x00, 0x18, 0x01, 0x04,
+            0x00, 0x05, 0x02, 0x0f, 0x0c, 0x00, 0x80, 0x00, 0x05, 0x19, 0x55,
+            0x3e, 0x44, 0x42, 0xc8, 0x00, 0x00, 0x3f, 0x49, 0x10,
+        ];
+        let (_, attr) = parse(&payload);
+        assert_eq!(attr.service_choice, Some(ServiceValues::WriteProperty));
+        assert_eq!(attr.object_type, Some(2));
+        assert_eq!(attr.object_instance, Some(5));
+        assert_eq!(attr.property_id, Some(85));
+        assert_eq!(attr.priority, Some(16));
+    }
+
+    #[test]
+    fn error_pdu() {
+        let (_, attr) = parse(&[0x81, 0x0a, 0x00, 0x0d, 0x01, 0x00, 0x50, 0x03, 0x0c, 0x91, 0x02, 0x91, 0x20]);
+        assert_eq!(attr.apdu_type, Some(ApduValues::Error));
+        assert_eq!(attr.invoke_id, Some(3));
+        assert_eq!(attr.service_choice, Some(ServiceValues::ReadProperty));
+        assert_eq!(attr.error_class, Some(2));
+        assert_eq!(attr.error_code, Some(32));
+    }
+
+    #[test]
+    fn forwarded_and_routed_npdu() {
+        let payload = [
+            0x81, 0x04, 0x00, 0x12, 0xc0, 0xa8, 0x00, 0x05, 0xba, 0xc0,
+            0x01, 0x20, 0xff, 0xff, 0x00, 0xff, 0x10, 0x08,
+        ];
+        let (_, attr) = parse(&payload);
+        assert_eq!(attr.forwarded_from.as_deref(), Some("192.168.0.5:47808"));
+        assert_eq!(attr.dnet, Some(0xffff));
+        assert_eq!(attr.dadr.as_deref(), Some(""));
+        assert_eq!(attr.hop_count, Some(255));
+        assert_eq!(attr.service_choice, Some(ServiceValues::WhoIs));
+        let (_, attr) = parse(&[0x81, 0x0b, 0x00, 0x09, 0x01, 0x80, 0x01, 0x00, 0x01]);
+        assert_eq!(attr.network_message, Some(0x01));
+        assert_eq!(attr.apdu_type, None);
+    }
+
+    #[test]
+    fn malformed_datagrams() {
+        assert!(!parse(&[0x82, 0x0b, 0x00, 0x08, 0x01, 0x00, 0x10, 0x08]).0);
+        assert!(!parse(&[0x81, 0x0b, 0x00]).0);
+        /* wrong NPDU version, truncated object identifier, reserved length, unterminated opening tag */
+        let (ok, attr) = parse(&[0x81, 0x0b, 0x00, 0x08, 0x02, 0x00, 0x10, 0x08]);
+        assert!(ok);
+        assert_eq!(attr.npdu_control, None);
+        let (_, attr) = parse(&[0x81, 0x0a, 0x00, 0x0d, 0x01, 0x04, 0x00, 0x05, 0x01, 0x0c, 0x0c, 0x00, 0x00]);
+        assert_eq!(attr.service_choice, Some(ServiceValues::ReadProperty));
+        assert_eq!(attr.object_type, None);
+        let (_, attr) = parse(&[0x81, 0x0a, 0x00, 0x0c, 0x01, 0x04, 0x00, 0x05, 0x01, 0x0c, 0x1d, 0xff]);
+        assert_eq!(attr.property_id, None);
+        let (_, attr) = parse(&[0x81, 0x0a, 0x00, 0x0d, 0x01, 0x04, 0x00, 0x05, 0x01, 0x0f, 0x3e, 0x44, 0x42]);
+        assert_eq!(attr.priority, None);
+    }
+}
@@ -0,0 +1,143 @@
+//! Hash map holding at most CAPACITY entries for the per-connection trackers
+//!
+//! Each entry remembers when it was last inserted or updated. Inserting a new
+//! key into a full map evicts the least recently used entries down to the
+//! low-water mark (7/8 of CAPACITY), so live state is never dropped wholesale
+//! and the eviction scan runs once per CAPACITY / 8 new keys.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+pub struct BoundedMap<K, V, const CAPACITY: usize> {
+    entries: HashMap<K, (u64, V)>,
+    // use counter; larger is more recent
+    clock: u64,
+}
+
+impl<K, V, const CAPACITY: usize> Default for BoundedMap<K, V, CAPACITY> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+}
+
+impl<K: Eq + Hash, V, const CAPACITY: usize> BoundedMap<K, V, CAPACITY> {
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    // Lookup without marking the entry as used
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let used = self.tick();
+        self.entries.get_mut(key).map(|(last_used, value)| {
+            *last_used = used;
+            value
+        })
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if !self.entries.contains_key(&key) {
+            self.make_room();
+        }
+        let used = self.tick();
+        self.entries.insert(key, (used, value)).map(|(_, value)| value)
+    }
+
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, default: F) -> &mut V {
+        if !self.entries.contains_key(&key) {
+            self.make_room();
+        }
+        let used = self.tick();
+        let (last_used, value) = self.entries.entry(key).or_insert_with(|| (used, default()));
+        *last_used = used;
+        value
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|(_, value)| value)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.values().map(|(_, value)| value)
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    // Evicts the least recently used entries when the map is full
+    fn make_room(&mut self) {
+        if self.entries.len() < CAPACITY {
+            return;
+        }
+        let low_water = CAPACITY - CAPACITY / 8;
+        let excess = self.entries.len() + 1 - low_water;
+        let mut uses: Vec<u64> = self.entries.values().map(|(last_used, _)| *last_used).collect();
+        let (_, cutoff, _) = uses.select_nth_unstable(excess - 1);
+        let cutoff = *cutoff;
+        self.entries.retain(|_, (last_used, _)| *last_used > cutoff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_recently_used_are_evicted() {
+        let mut map: BoundedMap<u32, u32, 16> = BoundedMap::default();
+        for key in 0..16 {
+            map.insert(key, key);
+        }
+        /* refreshed entries survive the eviction */
+        *map.get_mut(&0).unwrap() += 100;
+        *map.get_or_insert_with(1, || 0) += 100;
+        assert_eq!(map.len(), 16);
+        map.insert(16, 16);
+        assert_eq!(map.len(), 14);
+        assert_eq!(map.get(&0), Some(&100));
+        assert_eq!(map.get(&1), Some(&101));
+        for key in 2..5 {
+            assert!(!map.contains_key(&key), "{}", key);
+        }
+        assert!(map.contains_key(&5));
+        assert_eq!(map.get(&16), Some(&16));
+    }
+
+    #[test]
+    fn updates_do_not_evict() {
+        let mut map: BoundedMap<u32, u32, 4> = BoundedMap::default();
+        for key in 0..4 {
+            map.insert(key, key);
+        }
+        map.insert(3, 30);
+        *map.get_or_insert_with(2, || 0) += 1;
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.remove(&3), Some(30));
+        map.insert(4, 4);
+        assert_eq!(map.len(), 4);
+        /* full again: the oldest entry makes room */
+        map.insert(5, 5);
+        assert_eq!(map.len(), 4);
+        assert!(!map.contains_key(&0));
+        assert_eq!(map.values().sum::<u32>(), 1 + 3 + 4 + 5);
+    }
+}
@@ -0,0 +1,236 @@
+//! DHCPv4 (UDP 67/68)
+//!
+//! +----+-------+------+------+-----+------+-------+--------+--------+--------+--------+--------+-------+------+--------+---------
+//! | Op | HType | HLen | Hops | XID | Secs | Flags | ciaddr | yiaddr | siaddr | giaddr | chaddr | sname | file | Cookie | Options
+//! +----+-------+------+------+-----+------+-------+--------+--------+--------+--------+--------+-------+------+--------+---------
+//! | 1  |   1   |  1   |  1   |  4  |  2   |   2   |   4    |   4    |   4    |   4    |   16   |  64   | 128  |   4    | ...
+
+use std::net::Ipv4Addr;
+
+use pnet::datalink::MacAddr;
+
+use super::{Addresses, be_u32};
+use super::arp;
+
+pub const DHCP_SERVER_PORT: u16 = 67;
+pub const DHCP_CLIENT_PORT: u16 = 68;
+
+const MAGIC_COOKIE: u32 = 0x63825363;
+const OPTIONS_OFFSET: usize = 240;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod MessageTypeValues {
+    pub const Discover: u8 = 1;
+    pub const Offer: u8 = 2;
+    pub const Request: u8 = 3;
+    pub const Decline: u8 = 4;
+    pub const Ack: u8 = 5;
+    pub const Nak: u8 = 6;
+    pub const Release: u8 = 7;
+    pub const Inform: u8 = 8;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod OptionValues {
+    pub const Pad: u8 = 0;
+    pub const HostName: u8 = 12;
+    pub const RequestedIP: u8 = 50;
+    pub const LeaseTime: u8 = 51;
+    pub const MessageType: u8 = 53;
+    pub const ServerID: u8 = 54;
+    pub const ParameterRequestList: u8 = 55;
+    pub const VendorClass: u8 = 60;
+    pub const End: u8 = 255;
+}
+
+#[derive(Debug)]
+pub struct DhcpAttr {
+    pub addresses: Addresses,
+    pub op: u8,
+    pub xid: u32,
+    pub message_type: Option<u8>,
+    pub client_mac: MacAddr,
+    pub client_ip: Option<Ipv4Addr>,
+    pub assigned_ip: Option<Ipv4Addr>,
+    pub requested_ip: Option<Ipv4Addr>,
+    pub server_id: Option<Ipv4Addr>,
+    pub lease_time: Option<u32>,
+    pub hostname: Option<String>,
+    pub vendor_class: Option<String>,
+    pub parameter_request_list: Vec<u8>,
+    // fingerprinting: option 55 as "1,3,6,15" (Fingerbank format) and the client MAC's OUI vendor
+    pub fingerprint: Option<String>,
+    pub vendor: Option<&'static str>,
+}
+
+fn ipv4(data: &[u8], offset: usize) -> Option<Ipv4Addr> {
+    be_u32(data, offset).map(Ipv4Addr::from)
+}
+
+// 0.0.0.0 means "not set" in the fixed header
+fn assigned(data: &[u8], offset: usize) -> Option<Ipv4Addr> {
+    ipv4(data, offset).filter(|ip| !ip.is_unspecified())
+}
+
+impl DhcpAttr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            op: 0,
+            xid: 0,
+            message_type: None,
+            client_mac: MacAddr::zero(),
+            client_ip: None,
+            assigned_ip: None,
+            requested_ip: None,
+            server_id: None,
+            lease_time: None,
+            hostname: None,
+            vendor_class: None,
+            parameter_request_list: Vec::new(),
+            fingerprint: None,
+            vendor: None,
+        }
+    }
+
+    pub fn set_dhcp(&mut self, data: &[u8]) -> bool {
+        if be_u32(data, 236) != Some(MAGIC_COOKIE) {
+            return false;
+        }
+        /* Ethernet hardware addresses only */
+        if data[1] != 1 || data[2] != 6 {
+            return false;
+        }
+        self.op = data[0];
+        self.xid = be_u32(data, 4).unwrap_or(0);
+        self.client_ip = assigned(data, 12);
+        self.assigned_ip = assigned(data, 16);
+        self.client_mac = MacAddr::new(data[28], data[29], data[30], data[31], data[32], data[33]);
+        self.vendor = arp::vendor(&self.client_mac);
+        self.set_options(&data[OPTIONS_OFFSET..]);
+        if !self.parameter_request_list.is_empty() {
+            self.fingerprint = Some(
+                self.parameter_request_list.iter().map(|code| code.to_string()).collect::<Vec<String>>().join(","),
+            );
+        }
+        true
+    }
+
+    fn set_options(&mut self, options: &[u8]) {
+        /* Code | Length | Value */
+        let mut offset = 0;
+        while offset < options.len() {
+            let code = options[offset];
+            match code {
+                OptionValues::Pad => {
+                    offset += 1;
+                    continue;
+                }
+                OptionValues::End => break,
+                _ => {}
+            }
+            let length = match options.get(offset + 1) {
+                Some(length) => *length as usize,
+                None => break,
+            };
+            let value = match options.get(offset + 2..offset + 2 + length) {
+                Some(value) => value,
+                None => break,
+            };
+            match code {
+                OptionValues::MessageType => self.message_type = value.get(0).copied(),
+                OptionValues::RequestedIP => self.requested_ip = ipv4(value, 0),
+                OptionValues::ServerID => self.server_id = ipv4(value, 0),
+                OptionValues::LeaseTime => self.lease_time = be_u32(value, 0),
+                OptionValues::HostName => self.hostname = Some(String::from_utf8_lossy(value).into_owned()),
+                OptionValues::VendorClass => self.vendor_class = Some(String::from_utf8_lossy(value).into_owned()),
+                OptionValues::ParameterRequestList => self.parameter_request_list = value.to_vec(),
+                _ => {}
+            }
+            offset += 2 + length;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+
+    // fixed header for a client with a Siemens MAC
+    fn message(op: u8, yiaddr: [u8; 4], options: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; OPTIONS_OFFSET];
+        data[0..4].copy_from_slice(&[op, 1, 6, 0]);
+        data[4..8].copy_from_slice(&0x3903f326u32.to_be_bytes());
+        data[16..20].copy_from_slice(&yiaddr);
+        data[28..34].copy_from_slice(&[0x00, 0x1b, 0x1b, 0x11, 0x22, 0x33]);
+        data[236..240].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        data.extend_from_slice(options);
+        data
+    }
+
+    fn parse(data: &[u8]) -> (bool, DhcpAttr) {
+        let mut attr = DhcpAttr::new(test_addresses(DHCP_CLIENT_PORT, DHCP_SERVER_PORT));
+        (attr.set_dhcp(data), attr)
+    }
+
+    #[test]
+    fn discover_fingerprint() {
+        let mut options = vec![53, 1, MessageTypeValues::Discover, 0, 0];
+        options.extend_from_slice(&[12, 6]);
+        options.extend_from_slice(b"plc-01");
+        options.extend_from_slice(&[60, 8]);
+        options.extend_from_slice(b"SIMATIC\0");
+        options.extend_from_slice(&[55, 4, 1, 3, 6, 15, 50, 4, 192, 168, 0, 50, 255, 0, 0]);
+        let (ok, attr) = parse(&message(1, [0; 4], &options));
+        assert!(ok);
+        assert_eq!(attr.op, 1);
+        assert_eq!(attr.xid, 0x3903f326);
+        assert_eq!(attr.message_type, Some(MessageTypeValues::Discover));
+        assert_eq!(attr.client_mac, MacAddr::new(0x00, 0x1b, 0x1b, 0x11, 0x22, 0x33));
+        assert_eq!(attr.vendor, Some("Siemens"));
+        assert_eq!(attr.client_ip, None);
+        assert_eq!(attr.assigned_ip, None);
+        assert_eq!(attr.hostname.as_deref(), Some("plc-01"));
+        assert_eq!(attr.vendor_class.as_deref(), Some("SIMATIC\0"));
+        assert_eq!(attr.parameter_request_list, vec![1, 3, 6, 15]);
+        assert_eq!(attr.fingerprint.as_deref(), Some("1,3,6,15"));
+        assert_eq!(attr.requested_ip, Some(Ipv4Addr::new(192, 168, 0, 50)));
+    }
+
+    #[test]
+    fn ack_lease() {
+        let options = [53, 1, MessageTypeValues::Ack, 54, 4, 192, 168, 0, 1, 51, 4, 0x00, 0x01, 0x51, 0x80, 255];
+        let (ok, attr) = parse(&message(2, [192, 168, 0, 50], &options));
+        assert!(ok);
+        assert_eq!(attr.message_type, Some(MessageTypeValues::Ack));
+        assert_eq!(attr.assigned_ip, Some(Ipv4Addr::new(192, 168, 0, 50)));
+        assert_eq!(attr.server_id, Some(Ipv4Addr::new(192, 168, 0, 1)));
+        assert_eq!(attr.lease_time, Some(86400));
+        assert_eq!(attr.fingerprint, None);
+    }
+
+    #[test]
+    fn malformed_messages() {
+        /* too short for the cookie, wrong cookie, non-Ethernet hardware */
+        assert!(!parse(&message(1, [0; 4], &[])[..239]).0);
+        let mut data = message(1, [0; 4], &[53, 1, 1, 255]);
+        data[239] = 0x00;
+        assert!(!parse(&data).0);
+        let mut data = message(1, [0; 4], &[53, 1, 1, 255]);
+        data[1] = 6;
+        assert!(!parse(&data).0);
+        /* option running past the end, no End option */
+        let (ok, attr) = parse(&message(1, [0; 4], &[53, 1, MessageTypeValues::Request, 12, 20, b'p', b'l']));
+        assert!(ok);
+        assert_eq!(attr.message_type, Some(MessageTypeValues::Request));
+        assert_eq!(attr.hostname, None);
+        let (ok, attr) = parse(&message(1, [0; 4], &[53]));
+        assert!(ok);
+        assert_eq!(attr.message_type, None);
+    }
+}
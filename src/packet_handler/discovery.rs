@@ -0,0 +1,356 @@
+//! Neighbor discovery: LLDP (ethertype 0x88CC) and CDP (802.3 LLC/SNAP)
+//!
+//! LLDP TLV
+//! +----------+------------+-------+
+//! | Type (7) | Length (9) | Value |
+//! +----------+------------+-------+
+//!
+//! CDP
+//! +------------------+------------------+---------+-----+----------+------
+//! | LLC (AA AA 03)   | SNAP (00000C 2000)| Version | TTL | Checksum | TLVs
+//! +------------------+------------------+---------+-----+----------+------
+//! TLV: Type (2) | Length (2, including the header) | Value
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use pnet::datalink::MacAddr;
+use pnet::packet::ethernet::EtherType;
+
+use super::{LinkAddresses, be_u16, be_u32};
+
+pub const LLDP_ETHERTYPE: EtherType = EtherType(0x88CC);
+
+// LLC DSAP/SSAP/Control + SNAP OUI (Cisco) + PID
+const CDP_SNAP_HEADER: [u8; 8] = [0xAA, 0xAA, 0x03, 0x00, 0x00, 0x0C, 0x20, 0x00];
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod LldpTlvValues {
+    pub const End: u8 = 0;
+    pub const ChassisID: u8 = 1;
+    pub const PortID: u8 = 2;
+    pub const TTL: u8 = 3;
+    pub const PortDescription: u8 = 4;
+    pub const SystemName: u8 = 5;
+    pub const SystemDescription: u8 = 6;
+    pub const SystemCapabilities: u8 = 7;
+    pub const ManagementAddress: u8 = 8;
+    pub const OrganizationSpecific: u8 = 127;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod CdpTlvValues {
+    pub const DeviceID: u16 = 0x0001;
+    pub const Addresses: u16 = 0x0002;
+    pub const PortID: u16 = 0x0003;
+    pub const Capabilities: u16 = 0x0004;
+    pub const SoftwareVersion: u16 = 0x0005;
+    pub const Platform: u16 = 0x0006;
+    pub const NativeVLAN: u16 = 0x000A;
+    pub const ManagementAddresses: u16 = 0x0016;
+}
+
+#[derive(Debug)]
+pub struct NeighborAttr {
+    pub link: LinkAddresses,
+    pub protocol: &'static str,
+    pub chassis_id: Option<String>,
+    pub port_id: Option<String>,
+    pub ttl: Option<u16>,
+    pub port_description: Option<String>,
+    pub system_name: Option<String>,
+    pub system_description: Option<String>,
+    pub platform: Option<String>,
+    // bit meanings differ between LLDP and CDP
+    pub capabilities: Option<u32>,
+    pub enabled_capabilities: Option<u32>,
+    pub management_address: Option<String>,
+    pub native_vlan: Option<u16>,
+}
+
+fn text(value: &[u8]) -> String {
+    String::from_utf8_lossy(value).trim_end_matches('\0').to_string()
+}
+
+fn mac(value: &[u8]) -> Option<String> {
+    if value.len() != 6 {
+        return None;
+    }
+    Some(MacAddr::new(value[0], value[1], value[2], value[3], value[4], value[5]).to_string())
+}
+
+// IANA address family (LLDP) or address length (CDP) decides the format
+fn ip(value: &[u8]) -> Option<String> {
+    match value.len() {
+        4 => Some(Ipv4Addr::new(value[0], value[1], value[2], value[3]).to_string()),
+        16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(value);
+            Some(Ipv6Addr::from(octets).to_string())
+        }
+        _ => None,
+    }
+}
+
+// LLDP Chassis ID / Port ID: Subtype | ID
+fn lldp_id(value: &[u8], mac_subtype: u8, address_subtype: u8) -> Option<String> {
+    let (subtype, id) = value.split_first()?;
+    if *subtype == mac_subtype {
+        mac(id)
+    } else if *subtype == address_subtype {
+        /* Address Family | Address */
+        ip(id.get(1..)?)
+    } else {
+        Some(text(id))
+    }
+}
+
+impl NeighborAttr {
+    pub fn new(link: LinkAddresses, protocol: &'static str) -> Self {
+        Self {
+            link: link,
+            protocol: protocol,
+            chassis_id: None,
+            port_id: None,
+            ttl: None,
+            port_description: None,
+            system_name: None,
+            system_description: None,
+            platform: None,
+            capabilities: None,
+            enabled_capabilities: None,
+            management_address: None,
+            native_vlan: None,
+        }
+    }
+
+    pub fn set_lldp(&mut self, data: &[u8]) -> bool {
+        let mut offset = 0;
+        while let Some(header) = be_u16(data, offset) {
+            let tlv_type = (header >> 9) as u8;
+            let length = (header & 0x01FF) as usize;
+            let value = match data.get(offset + 2..offset + 2 + length) {
+                Some(value) => value,
+                None => break,
+            };
+            match tlv_type {
+                LldpTlvValues::End => break,
+                LldpTlvValues::ChassisID => self.chassis_id = lldp_id(value, 4, 5),
+                LldpTlvValues::PortID => self.port_id = lldp_id(value, 3, 4),
+                LldpTlvValues::TTL => self.ttl = be_u16(value, 0),
+                LldpTlvValues::PortDescription => self.port_description = Some(text(value)),
+                LldpTlvValues::SystemName => self.system_name = Some(text(value)),
+                LldpTlvValues::SystemDescription => self.system_description = Some(text(value)),
+                LldpTlvValues::SystemCapabilities => {
+                    self.capabilities = be_u16(value, 0).map(u32::from);
+                    self.enabled_capabilities = be_u16(value, 2).map(u32::from);
+                }
+                LldpTlvValues::ManagementAddress => {
+                    /* String Length | Subtype | Address | ... (first address only) */
+                    if self.management_address.is_none() {
+                        if let Some(size) = value.get(0) {
+                            self.management_address = value.get(2..1 + *size as usize).and_then(ip);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            offset += 2 + length;
+        }
+        /* Chassis ID, Port ID and TTL are mandatory */
+        self.chassis_id.is_some() && self.port_id.is_some()
+    }
+
+    // data: 802.3 payload starting at the LLC header
+    pub fn set_cdp(&mut self, data: &[u8]) -> bool {
+        if data.get(0..8) != Some(&CDP_SNAP_HEADER[..]) || data.len() < 12 {
+            return false;
+        }
+        self.ttl = Some(data[9] as u16);
+        let mut offset = 12;
+        while let (Some(tlv_type), Some(length)) = (be_u16(data, offset), be_u16(data, offset + 2)) {
+            let length = length as usize;
+            if length < 4 {
+                break;
+            }
+            let value = match data.get(offset + 4..offset + length) {
+                Some(value) => value,
+                None => break,
+            };
+            match tlv_type {
+                CdpTlvValues::DeviceID => self.chassis_id = Some(text(value)),
+                CdpTlvValues::PortID => self.port_id = Some(text(value)),
+                CdpTlvValues::Capabilities => {
+                    self.capabilities = be_u32(value, 0);
+                    self.enabled_capabilities = self.capabilities;
+                }
+                CdpTlvValues::SoftwareVersion => self.system_description = Some(text(value)),
+                CdpTlvValues::Platform => self.platform = Some(text(value)),
+                CdpTlvValues::NativeVLAN => self.native_vlan = be_u16(value, 0),
+                CdpTlvValues::Addresses => {
+                    if self.management_address.is_none() {
+                        self.management_address = Self::cdp_address(value);
+                    }
+                }
+                CdpTlvValues::ManagementAddresses => {
+                    /* preferred over the interface addresses */
+                    if let Some(address) = Self::cdp_address(value) {
+                        self.management_address = Some(address);
+                    }
+                }
+                _ => {}
+            }
+            offset += length;
+        }
+        self.chassis_id.is_some()
+    }
+
+    // Number of Addresses | (Protocol Type | Protocol Length | Protocol | Address Length | Address) ...
+    fn cdp_address(value: &[u8]) -> Option<String> {
+        if be_u32(value, 0)? == 0 {
+            return None;
+        }
+        let protocol_length = *value.get(5)? as usize;
+        let offset = 6 + protocol_length;
+        let address_length = be_u16(value, offset)? as usize;
+        ip(value.get(offset + 2..offset + 2 + address_length)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_link;
+
+    fn lldp_tlv(tlv_type: u8, value: &[u8]) -> Vec<u8> {
+        let header = (tlv_type as u16) << 9 | value.len() as u16;
+        let mut tlv = header.to_be_bytes().to_vec();
+        tlv.extend_from_slice(value);
+        tlv
+    }
+
+    fn cdp_tlv(tlv_type: u16, value: &[u8]) -> Vec<u8> {
+        let mut tlv = tlv_type.to_be_bytes().to_vec();
+        tlv.extend_from_slice(&(value.len() as u16 + 4).to_be_bytes());
+        tlv.extend_from_slice(value);
+        tlv
+    }
+
+    // switch port advertisement as sent by an industrial managed switch
+    fn lldp_frame() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(lldp_tlv(LldpTlvValues::ChassisID, &[4, 0x00, 0x0e, 0x8c, 0xaa, 0xbb, 0xcc]));
+        data.extend(lldp_tlv(LldpTlvValues::PortID, b"\x05port-003"));
+        data.extend(lldp_tlv(LldpTlvValues::TTL, &[0x00, 0x78]));
+        data.extend(lldp_tlv(LldpTlvValues::PortDescription, b"Port 3"));
+        data.extend(lldp_tlv(LldpTlvValues::SystemName, b"scalance-x204\0"));
+        data.extend(lldp_tlv(LldpTlvValues::SystemDescription, b"SCALANCE X204-2"));
+        data.extend(lldp_tlv(LldpTlvValues::SystemCapabilities, &[0x00, 0x14, 0x00, 0x04]));
+        data.extend(lldp_tlv(LldpTlvValues::ManagementAddress, &[5, 1, 192, 168, 0, 2, 2, 0, 0, 0, 1, 0]));
+        data.extend(lldp_tlv(LldpTlvValues::ManagementAddress, &[5, 1, 10, 0, 0, 2, 2, 0, 0, 0, 2, 0]));
+        data.extend(lldp_tlv(LldpTlvValues::End, &[]));
+        data
+    }
+
+    fn cdp_frame() -> Vec<u8> {
+        let mut data = CDP_SNAP_HEADER.to_vec();
+        data.extend_from_slice(&[0x02, 0xb4, 0x12, 0x34]);
+        data.extend(cdp_tlv(CdpTlvValues::DeviceID, b"core-sw1"));
+        /* one IPv4 address: NLPID 0xCC */
+        let address = [0, 0, 0, 1, 0x01, 0x01, 0xcc, 0x00, 0x04, 10, 0, 0, 1];
+        data.extend(cdp_tlv(CdpTlvValues::Addresses, &address));
+        data.extend(cdp_tlv(CdpTlvValues::PortID, b"GigabitEthernet0/1"));
+        data.extend(cdp_tlv(CdpTlvValues::Capabilities, &[0x00, 0x00, 0x00, 0x28]));
+        data.extend(cdp_tlv(CdpTlvValues::SoftwareVersion, b"Cisco IOS Software"));
+        data.extend(cdp_tlv(CdpTlvValues::Platform, b"cisco WS-C2960"));
+        data.extend(cdp_tlv(CdpTlvValues::NativeVLAN, &[0x00, 0x01]));
+        let management = [0, 0, 0, 1, 0x01, 0x01, 0xcc, 0x00, 0x04, 192, 168, 100, 1];
+        data.extend(cdp_tlv(CdpTlvValues::ManagementAddresses, &management));
+        data
+    }
+
+    #[test]
+    fn lldp_tlvs() {
+        let mut attr = NeighborAttr::new(test_link(), "lldp");
+        assert!(attr.set_lldp(&lldp_frame()));
+        assert_eq!(attr.chassis_id.as_deref(), Some("00:0e:8c:aa:bb:cc"));
+        assert_eq!(attr.port_id.as_deref(), Some("port-003"));
+        assert_eq!(attr.ttl, Some(120));
+        assert_eq!(attr.port_description.as_deref(), Some("Port 3"));
+        assert_eq!(attr.system_name.as_deref(), Some("scalance-x204"));
+        assert_eq!(attr.system_description.as_deref(), Some("SCALANCE X204-2"));
+        assert_eq!(attr.capabilities, Some(0x14));
+        assert_eq!(attr.enabled_capabilities, Some(0x04));
+        /* only the first management address is kept */
+        assert_eq!(attr.management_address.as_deref(), Some("192.168.0.2"));
+    }
+
+    #[test]
+    fn lldp_network_address_id() {
+        let mut data = lldp_tlv(LldpTlvValues::ChassisID, &[5, 1, 192, 168, 0, 2]);
+        data.extend(lldp_tlv(LldpTlvValues::PortID, &[3, 0x00, 0x0e, 0x8c, 0xaa, 0xbb, 0xcd]));
+        let mut attr = NeighborAttr::new(test_link(), "lldp");
+        assert!(attr.set_lldp(&data));
+        assert_eq!(attr.chassis_id.as_deref(), Some("192.168.0.2"));
+        assert_eq!(attr.port_id.as_deref(), Some("00:0e:8c:aa:bb:cd"));
+    }
+
+    #[test]
+    fn lldp_truncated() {
+        let data = lldp_frame();
+        /* TLV length running past the frame stops the walk */
+        let mut attr = NeighborAttr::new(test_link(), "lldp");
+        assert!(attr.set_lldp(&data[..30]));
+        assert_eq!(attr.ttl, Some(120));
+        assert_eq!(attr.port_description, None);
+        /* Port ID missing */
+        let mut attr = NeighborAttr::new(test_link(), "lldp");
+        assert!(!attr.set_lldp(&data[..12]));
+        assert!(!NeighborAttr::new(test_link(), "lldp").set_lldp(&[0x02]));
+        /* malformed MAC-subtype chassis ID */
+        let mut attr = NeighborAttr::new(test_link(), "lldp");
+        let mut data = lldp_tlv(LldpTlvValues::ChassisID, &[4, 0x00, 0x0e]);
+        data.extend(lldp_tlv(LldpTlvValues::PortID, b"\x05p1"));
+        assert!(!attr.set_lldp(&data));
+    }
+
+    #[test]
+    fn cdp_tlvs() {
+        let mut attr = NeighborAttr::new(test_link(), "cdp");
+        assert!(attr.set_cdp(&cdp_frame()));
+        assert_eq!(attr.ttl, Some(180));
+        assert_eq!(attr.chassis_id.as_deref(), Some("core-sw1"));
+        assert_eq!(attr.port_id.as_deref(), Some("GigabitEthernet0/1"));
+        assert_eq!(attr.capabilities, Some(0x28));
+        assert_eq!(attr.enabled_capabilities, Some(0x28));
+        assert_eq!(attr.system_description.as_deref(), Some("Cisco IOS Software"));
+        assert_eq!(attr.platform.as_deref(), Some("cisco WS-C2960"));
+        assert_eq!(attr.native_vlan, Some(1));
+        /* management address wins over the interface address */
+        assert_eq!(attr.management_address.as_deref(), Some("192.168.100.1"));
+    }
+
+    #[test]
+    fn cdp_malformed() {
+        let data = cdp_frame();
+        let mut attr = NeighborAttr::new(test_link(), "cdp");
+        assert!(!attr.set_cdp(&data[..11]));
+        let mut other = data.clone();
+        other[5] = 0x0d;
+        assert!(!NeighborAttr::new(test_link(), "cdp").set_cdp(&other));
+        /* zero TLV length must not loop */
+        let mut zero = data[..12].to_vec();
+        zero.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, b'x']);
+        assert!(!NeighborAttr::new(test_link(), "cdp").set_cdp(&zero));
+        /* Device ID cut short */
+        let mut attr = NeighborAttr::new(test_link(), "cdp");
+        assert!(!attr.set_cdp(&data[..18]));
+        assert_eq!(attr.ttl, Some(180));
+        /* address count of zero yields nothing */
+        assert_eq!(NeighborAttr::cdp_address(&[0, 0, 0, 0, 1, 1, 0xcc, 0, 4, 10, 0, 0, 1]), None);
+        assert_eq!(NeighborAttr::cdp_address(&[0, 0, 0, 1, 1, 1, 0xcc, 0, 4, 10, 0]), None);
+    }
+}
@@ -0,0 +1,393 @@
+//! DNS (UDP/TCP 53)
+//!
+//! +----------------+----------------+
+//! | Transaction ID |     Flags      |
+//! +----------------+----------------+
+//! |   Questions    |  Answer RRs    |
+//! +----------------+----------------+
+//! | Authority RRs  | Additional RRs |
+//! +----------------+----------------+
+//! | Questions (Name | Type | Class) ...
+//! | Answers (Name | Type | Class | TTL | RDLength | RData) ...
+//!
+//! Over TCP every message is prefixed with a 2-byte length.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use chrono::{DateTime, Utc};
+
+use super::bounded_map::BoundedMap;
+use super::{Addresses, be_u16, be_u32};
+
+pub const DNS_PORT: u16 = 53;
+
+const MAX_PENDING: usize = 4096;
+// compression pointers followed per name
+const MAX_POINTERS: usize = 16;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod TypeValues {
+    pub const A: u16 = 1;
+    pub const NS: u16 = 2;
+    pub const CNAME: u16 = 5;
+    pub const SOA: u16 = 6;
+    pub const PTR: u16 = 12;
+    pub const MX: u16 = 15;
+    pub const TXT: u16 = 16;
+    pub const AAAA: u16 = 28;
+    pub const SRV: u16 = 33;
+    pub const ANY: u16 = 255;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod RcodeValues {
+    pub const NoError: u8 = 0;
+    pub const FormErr: u8 = 1;
+    pub const ServFail: u8 = 2;
+    pub const NXDomain: u8 = 3;
+    pub const NotImp: u8 = 4;
+    pub const Refused: u8 = 5;
+}
+
+// (client address, client port, server address, transaction ID)
+type QueryKey = (IpAddr, u16, IpAddr, u16);
+
+// Outstanding queries, matched to their response for latency; unanswered
+// queries give way to newer ones once MAX_PENDING are outstanding
+#[derive(Default)]
+pub struct DnsTransactions {
+    pending: BoundedMap<QueryKey, DateTime<Utc>, MAX_PENDING>,
+}
+
+impl DnsTransactions {
+    fn query(&mut self, addresses: &Addresses, transaction_id: u16, utc: DateTime<Utc>) {
+        let key = (addresses.src_addr, addresses.src_port, addresses.dst_addr, transaction_id);
+        self.pending.insert(key, utc);
+    }
+
+    // nanoseconds since the matching query
+    fn response(&mut self, addresses: &Addresses, transaction_id: u16, utc: DateTime<Utc>) -> Option<i64> {
+        let key = (addresses.dst_addr, addresses.dst_port, addresses.src_addr, transaction_id);
+        let sent = self.pending.remove(&key)?;
+        (utc - sent).num_nanoseconds()
+    }
+}
+
+#[derive(Debug)]
+pub struct DnsAttr {
+    pub addresses: Addresses,
+    pub transaction_id: u16,
+    pub flags: u16,
+    pub response: bool,
+    pub opcode: u8,
+    pub rcode: u8,
+    pub query_names: Vec<String>,
+    pub query_types: Vec<u16>,
+    pub answer_names: Vec<String>,
+    pub answer_types: Vec<u16>,
+    pub answer_ttls: Vec<u32>,
+    pub answer_data: Vec<String>,
+    pub latency: Option<i64>,
+}
+
+// Reads a (possibly compressed) name; returns it with the offset past it
+fn name(message: &[u8], offset: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut position = offset;
+    let mut end = None;
+    let mut pointers = 0;
+    loop {
+        let length = *message.get(position)? as usize;
+        match length & 0xC0 {
+            0x00 => {
+                if length == 0 {
+                    position += 1;
+                    break;
+                }
+                let label = message.get(position + 1..position + 1 + length)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                position += 1 + length;
+            }
+            0xC0 => {
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return None;
+                }
+                if end.is_none() {
+                    end = Some(position + 2);
+                }
+                position = (be_u16(message, position)? & 0x3FFF) as usize;
+            }
+            _ => return None,
+        }
+    }
+    Some((labels.join("."), end.unwrap_or(position)))
+}
+
+fn rdata(message: &[u8], offset: usize, rr_type: u16, length: usize) -> Option<String> {
+    let data = message.get(offset..offset + length)?;
+    let text = match rr_type {
+        TypeValues::A if length == 4 => Ipv4Addr::new(data[0], data[1], data[2], data[3]).to_string(),
+        TypeValues::AAAA if length == 16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(data);
+            Ipv6Addr::from(octets).to_string()
+        }
+        TypeValues::NS | TypeValues::CNAME | TypeValues::PTR => name(message, offset)?.0,
+        TypeValues::MX => format!("{} {}", be_u16(message, offset)?, name(message, offset + 2)?.0),
+        TypeValues::TXT => {
+            /* one or more <length><string> */
+            let mut strings = Vec::new();
+            let mut position = 0;
+            while position < data.len() {
+                let size = data[position] as usize;
+                strings.push(String::from_utf8_lossy(data.get(position + 1..position + 1 + size)?).into_owned());
+                position += 1 + size;
+            }
+            strings.join(" ")
+        }
+        _ => data.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(""),
+    };
+    Some(text)
+}
+
+impl DnsAttr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            transaction_id: 0,
+            flags: 0,
+            response: false,
+            opcode: 0,
+            rcode: 0,
+            query_names: Vec::new(),
+            query_types: Vec::new(),
+            answer_names: Vec::new(),
+            answer_types: Vec::new(),
+            answer_ttls: Vec::new(),
+            answer_data: Vec::new(),
+            latency: None,
+        }
+    }
+
+    pub fn set_dns(&mut self, message: &[u8], transactions: &mut DnsTransactions, utc: DateTime<Utc>) -> bool {
+        if message.len() < 12 {
+            return false;
+        }
+        self.transaction_id = be_u16(message, 0).unwrap_or(0);
+        self.flags = be_u16(message, 2).unwrap_or(0);
+        self.response = self.flags & 0x8000 != 0;
+        self.opcode = ((self.flags >> 11) & 0x0F) as u8;
+        self.rcode = (self.flags & 0x000F) as u8;
+        let questions = be_u16(message, 4).unwrap_or(0);
+        let answers = be_u16(message, 6).unwrap_or(0);
+        /* queries always carry a question */
+        if questions == 0 && !self.response {
+            return false;
+        }
+        let mut offset = 12;
+        for _ in 0..questions {
+            let (query_name, end) = match name(message, offset) {
+                Some(name) => name,
+                None => return false,
+            };
+            self.query_names.push(query_name);
+            self.query_types.push(match be_u16(message, end) {
+                Some(query_type) => query_type,
+                None => return false,
+            });
+            offset = end + 4;
+        }
+        for _ in 0..answers {
+            /* Name | Type | Class | TTL | RDLength | RData */
+            let (answer_name, end) = match name(message, offset) {
+                Some(name) => name,
+                None => break,
+            };
+            let (rr_type, ttl, length) = match (be_u16(message, end), be_u32(message, end + 4), be_u16(message, end + 8)) {
+                (Some(rr_type), Some(ttl), Some(length)) => (rr_type, ttl, length as usize),
+                _ => break,
+            };
+            let data = match rdata(message, end + 10, rr_type, length) {
+                Some(data) => data,
+                None => break,
+            };
+            self.answer_names.push(answer_name);
+            self.answer_types.push(rr_type);
+            self.answer_ttls.push(ttl);
+            self.answer_data.push(data);
+            offset = end + 10 + length;
+        }
+        if self.response {
+            self.latency = transactions.response(&self.addresses, self.transaction_id, utc);
+        } else {
+            transactions.query(&self.addresses, self.transaction_id, utc);
+        }
+        true
+    }
+
+    // DNS over TCP: returns the consumed size and whether it decoded,
+    // or None when the message is incomplete
+    pub fn set_tcp(&mut self, data: &[u8], transactions: &mut DnsTransactions, utc: DateTime<Utc>) -> Option<(usize, bool)> {
+        let length = be_u16(data, 0)? as usize;
+        let message = data.get(2..2 + length)?;
+        Some((2 + length, self.set_dns(message, transactions, utc)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::packet_handler::test_addresses;
+
+    const QUESTION: &[u8] = b"\x05plc01\x07example\x03com\x00\x00\x01\x00\x01";
+
+    fn header(id: u16, flags: u16, questions: u16, answers: u16) -> Vec<u8> {
+        let mut message = Vec::new();
+        for value in [id, flags, questions, answers, 0, 0].iter() {
+            message.extend_from_slice(&value.to_be_bytes());
+        }
+        message
+    }
+
+    // Name (pointer to the question) | Type | Class IN | TTL | RData
+    fn answer(rr_type: u16, ttl: u32, data: &[u8]) -> Vec<u8> {
+        let mut record = vec![0xc0, 0x0c];
+        record.extend_from_slice(&rr_type.to_be_bytes());
+        record.extend_from_slice(&[0x00, 0x01]);
+        record.extend_from_slice(&ttl.to_be_bytes());
+        record.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        record.extend_from_slice(data);
+        record
+    }
+
+    fn query() -> Vec<u8> {
+        let mut message = header(0x1a2b, 0x0100, 1, 0);
+        message.extend_from_slice(QUESTION);
+        message
+    }
+
+    fn from_client() -> Addresses {
+        test_addresses(50000, DNS_PORT)
+    }
+
+    fn from_server() -> Addresses {
+        let mut addresses = test_addresses(DNS_PORT, 50000);
+        std::mem::swap(&mut addresses.src_addr, &mut addresses.dst_addr);
+        addresses
+    }
+
+    #[test]
+    fn query_and_response_latency() {
+        let mut transactions = DnsTransactions::default();
+        let sent = Utc::now();
+        let mut attr = DnsAttr::new(from_client());
+        assert!(attr.set_dns(&query(), &mut transactions, sent));
+        assert!(!attr.response);
+        assert_eq!(attr.transaction_id, 0x1a2b);
+        assert_eq!(attr.query_names, vec!["plc01.example.com".to_string()]);
+        assert_eq!(attr.query_types, vec![TypeValues::A]);
+        assert_eq!(attr.latency, None);
+
+        let mut response = header(0x1a2b, 0x8180, 1, 2);
+        response.extend_from_slice(QUESTION);
+        /* CNAME "scada" + pointer to "example.com" at offset 18 */
+        response.extend(answer(TypeValues::CNAME, 300, b"\x05scada\xc0\x12"));
+        response.extend(answer(TypeValues::A, 60, &[10, 0, 0, 5]));
+        let mut attr = DnsAttr::new(from_server());
+        assert!(attr.set_dns(&response, &mut transactions, sent + Duration::milliseconds(3)));
+        assert!(attr.response);
+        assert_eq!(attr.rcode, RcodeValues::NoError);
+        assert_eq!(attr.answer_types, vec![TypeValues::CNAME, TypeValues::A]);
+        assert_eq!(attr.answer_ttls, vec![300, 60]);
+        assert_eq!(attr.answer_data, vec!["scada.example.com".to_string(), "10.0.0.5".to_string()]);
+        assert_eq!(attr.latency, Some(3_000_000));
+        /* the query is matched once */
+        let mut attr = DnsAttr::new(from_server());
+        assert!(attr.set_dns(&response, &mut transactions, Utc::now()));
+        assert_eq!(attr.latency, None);
+    }
+
+    #[test]
+    fn oldest_queries_give_way() {
+        let mut transactions = DnsTransactions::default();
+        let addresses = from_client();
+        for transaction_id in 0..=MAX_PENDING as u16 {
+            transactions.query(&addresses, transaction_id, Utc::now());
+        }
+        assert!(transactions.pending.len() < MAX_PENDING);
+        let addresses = from_server();
+        assert_eq!(transactions.response(&addresses, 0, Utc::now()), None);
+        assert!(transactions.response(&addresses, MAX_PENDING as u16, Utc::now()).is_some());
+    }
+
+    #[test]
+    fn record_data() {
+        let mut response = header(1, 0x8180, 1, 4);
+        response.extend_from_slice(QUESTION);
+        response.extend(answer(TypeValues::AAAA, 60, &[0xfd, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x05]));
+        response.extend(answer(TypeValues::MX, 60, b"\x00\x0a\x04mail\xc0\x12"));
+        response.extend(answer(TypeValues::TXT, 60, b"\x05v=spf\x04-all"));
+        response.extend(answer(TypeValues::SRV, 60, &[0x00, 0x01]));
+        let mut attr = DnsAttr::new(from_server());
+        assert!(attr.set_dns(&response, &mut DnsTransactions::default(), Utc::now()));
+        assert_eq!(attr.answer_data, vec![
+            "fd00::5".to_string(),
+            "10 mail.example.com".to_string(),
+            "v=spf -all".to_string(),
+            "0001".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn nxdomain_without_answers() {
+        let mut response = header(7, 0x8183, 1, 0);
+        response.extend_from_slice(QUESTION);
+        let mut attr = DnsAttr::new(from_server());
+        assert!(attr.set_dns(&response, &mut DnsTransactions::default(), Utc::now()));
+        assert_eq!(attr.rcode, RcodeValues::NXDomain);
+        assert!(attr.answer_names.is_empty());
+    }
+
+    #[test]
+    fn malformed_messages() {
+        let mut transactions = DnsTransactions::default();
+        /* short header, query without a question, truncated question, pointer loop, reserved label type */
+        assert!(!DnsAttr::new(from_client()).set_dns(&[0x1a, 0x2b, 0x01, 0x00], &mut transactions, Utc::now()));
+        assert!(!DnsAttr::new(from_client()).set_dns(&header(1, 0x0100, 0, 0), &mut transactions, Utc::now()));
+        let message = query();
+        assert!(!DnsAttr::new(from_client()).set_dns(&message[..message.len() - 5], &mut transactions, Utc::now()));
+        let mut message = header(1, 0x0100, 1, 0);
+        message.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01]);
+        assert!(!DnsAttr::new(from_client()).set_dns(&message, &mut transactions, Utc::now()));
+        let mut message = header(1, 0x0100, 1, 0);
+        message.extend_from_slice(&[0x40, 0x00, 0x00, 0x01, 0x00, 0x01]);
+        assert!(!DnsAttr::new(from_client()).set_dns(&message, &mut transactions, Utc::now()));
+        /* answer RData past the end: the answers decoded so far are kept */
+        let mut response = header(1, 0x8180, 1, 2);
+        response.extend_from_slice(QUESTION);
+        response.extend(answer(TypeValues::A, 60, &[10, 0, 0, 5]));
+        response.extend(answer(TypeValues::A, 60, &[10, 0, 0, 6]));
+        response.truncate(response.len() - 2);
+        let mut attr = DnsAttr::new(from_server());
+        assert!(attr.set_dns(&response, &mut transactions, Utc::now()));
+        assert_eq!(attr.answer_data, vec!["10.0.0.5".to_string()]);
+    }
+
+    #[test]
+    fn tcp_length_prefix() {
+        let message = query();
+        let mut data = (message.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(&message);
+        let mut transactions = DnsTransactions::default();
+        assert_eq!(DnsAttr::new(from_client()).set_tcp(&data, &mut transactions, Utc::now()), Some((data.len(), true)));
+        assert_eq!(DnsAttr::new(from_client()).set_tcp(&data[..data.len() - 1], &mut transactions, Utc::now()), None);
+        assert_eq!(DnsAttr::new(from_client()).set_tcp(&[0x00], &mut transactions, Utc::now()), None);
+        assert_eq!(DnsAttr::new(from_client()).set_tcp(&[0x00, 0x02, 0xff, 0xff], &mut transactions, Utc::now()), Some((4, false)));
+    }
+}
@@ -0,0 +1,522 @@
+//! EtherNet/IP encapsulation and CIP
+//!
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |        Command (LE)           |          Length (LE)          |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                     Session Handle (LE)                       |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                         Status (LE)                           |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                    Sender Context (8 bytes)                   |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                         Options (LE)                          |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |   Command specific data ...
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+use pnet_macros::packet;
+use pnet_macros_support::types::*;
+use pnet::packet::Packet;
+
+use super::{Addresses, le_u16, le_u32};
+
+// explicit messaging (TCP) and ListIdentity (UDP)
+pub const ENIP_PORT: u16 = 44818;
+// implicit (connected) I/O
+pub const ENIP_IO_PORT: u16 = 2222;
+
+#[packet]
+pub struct Encapsulation {
+    pub command: u16le,
+    pub length: u16le,
+    pub session_handle: u32le,
+    pub status: u32le,
+    #[length = "8"]
+    pub sender_context: Vec<u8>,
+    pub options: u32le,
+    #[payload]
+    pub payload: Vec<u8>,
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod CommandValues {
+    pub const Nop: u16 = 0x0000;
+    pub const ListServices: u16 = 0x0004;
+    pub const ListIdentity: u16 = 0x0063;
+    pub const ListInterfaces: u16 = 0x0064;
+    pub const RegisterSession: u16 = 0x0065;
+    pub const UnRegisterSession: u16 = 0x0066;
+    pub const SendRRData: u16 = 0x006F;
+    pub const SendUnitData: u16 = 0x0070;
+}
+
+// Common Packet Format item type IDs
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod ItemValues {
+    pub const NullAddress: u16 = 0x0000;
+    pub const ListIdentity: u16 = 0x000C;
+    pub const ConnectedAddress: u16 = 0x00A1;
+    pub const ConnectedData: u16 = 0x00B1;
+    pub const UnconnectedData: u16 = 0x00B2;
+    pub const SequencedAddress: u16 = 0x8002;
+}
+
+// CIP services used while decoding the request path
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod ServiceValues {
+    pub const GetAttributesAll: u8 = 0x01;
+    pub const SetAttributesAll: u8 = 0x02;
+    pub const GetAttributeSingle: u8 = 0x0E;
+    pub const SetAttributeSingle: u8 = 0x10;
+    pub const ReadTag: u8 = 0x4C;
+    pub const WriteTag: u8 = 0x4D;
+    pub const ForwardClose: u8 = 0x4E;
+    pub const UnconnectedSend: u8 = 0x52;
+    pub const ForwardOpen: u8 = 0x54;
+    pub const LargeForwardOpen: u8 = 0x5B;
+    // set on every reply
+    pub const Reply: u8 = 0x80;
+}
+
+// Connection Manager object class
+const CONNECTION_MANAGER: u32 = 0x06;
+
+#[derive(Debug)]
+pub struct EnipAttr {
+    pub addresses: Addresses,
+    // encapsulation header (absent on UDP I/O)
+    pub command: Option<u16>,
+    pub length: Option<u16>,
+    pub session_handle: Option<u32>,
+    pub status: Option<u32>,
+    // connected messaging / I/O
+    pub connection_id: Option<u32>,
+    pub encap_sequence: Option<u32>,
+    pub cip_sequence: Option<u16>,
+    // CIP message (service has bit 7 set on replies)
+    pub cip_service: Option<u8>,
+    pub cip_class: Option<u32>,
+    pub cip_instance: Option<u32>,
+    pub cip_attribute: Option<u32>,
+    pub cip_symbol: Option<String>,
+    pub general_status: Option<u8>,
+    pub extended_status: Option<u16>,
+    // ListIdentity reply
+    pub vendor_id: Option<u16>,
+    pub device_type: Option<u16>,
+    pub product_code: Option<u16>,
+    pub serial_number: Option<u32>,
+    pub product_name: Option<String>,
+}
+
+struct CpfItem<'a> {
+    type_id: u16,
+    data: &'a [u8],
+}
+
+// Item Count followed by (Type ID, Length, Data) items
+fn parse_cpf(data: &[u8]) -> Vec<CpfItem<'_>> {
+    let mut items = Vec::new();
+    let count = match le_u16(data, 0) {
+        Some(count) => count,
+        None => return items,
+    };
+    let mut offset = 2;
+    for _ in 0..count {
+        let (type_id, length) = match (le_u16(data, offset), le_u16(data, offset + 2)) {
+            (Some(type_id), Some(length)) => (type_id, length as usize),
+            _ => break,
+        };
+        let item = match data.get(offset + 4..offset + 4 + length) {
+            Some(item) => item,
+            None => break,
+        };
+        items.push(CpfItem { type_id: type_id, data: item });
+        offset += 4 + length;
+    }
+    items
+}
+
+impl EnipAttr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            command: None,
+            length: None,
+            session_handle: None,
+            status: None,
+            connection_id: None,
+            encap_sequence: None,
+            cip_sequence: None,
+            cip_service: None,
+            cip_class: None,
+            cip_instance: None,
+            cip_attribute: None,
+            cip_symbol: None,
+            general_status: None,
+            extended_status: None,
+            vendor_id: None,
+            device_type: None,
+            product_code: None,
+            serial_number: None,
+            product_name: None,
+        }
+    }
+
+    // false if the header isn't an encapsulation header (unknown command or options set)
+    pub fn set_enip(&mut self, encap: &EncapsulationPacket) -> bool {
+        match encap.get_command() {
+            CommandValues::Nop
+            | CommandValues::ListServices
+            | CommandValues::ListIdentity
+            | CommandValues::ListInterfaces
+            | CommandValues::RegisterSession
+            | CommandValues::UnRegisterSession
+            | CommandValues::SendRRData
+            | CommandValues::SendUnitData => {}
+            _ => return false,
+        }
+        if encap.get_options() != 0 {
+            return false;
+        }
+        self.command = Some(encap.get_command());
+        self.length = Some(encap.get_length());
+        self.session_handle = Some(encap.get_session_handle());
+        self.status = Some(encap.get_status());
+        let payload = encap.payload();
+        match encap.get_command() {
+            CommandValues::SendRRData | CommandValues::SendUnitData => {
+                /* Interface Handle (4) + Timeout (2) + CPF */
+                if let Some(cpf) = payload.get(6..) {
+                    self.set_cpf(cpf, false);
+                }
+            }
+            CommandValues::ListIdentity => {
+                /* empty on request */
+                self.set_cpf(payload, false);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    // UDP 2222: Sequenced Address item + Connected Data item
+    pub fn set_io(&mut self, payload: &[u8]) -> bool {
+        let items = parse_cpf(payload);
+        if items.is_empty() {
+            return false;
+        }
+        for item in items.iter() {
+            if item.type_id != ItemValues::SequencedAddress && item.type_id != ItemValues::ConnectedData {
+                return false;
+            }
+        }
+        self.set_cpf(payload, true);
+        true
+    }
+
+    fn set_cpf(&mut self, data: &[u8], io: bool) {
+        for item in parse_cpf(data) {
+            match item.type_id {
+                ItemValues::ConnectedAddress => {
+                    self.connection_id = le_u32(item.data, 0);
+                }
+                ItemValues::SequencedAddress => {
+                    self.connection_id = le_u32(item.data, 0);
+                    self.encap_sequence = le_u32(item.data, 4);
+                }
+                ItemValues::ConnectedData => {
+                    /* Sequence Count + CIP message (class 3) or I/O data (class 0/1) */
+                    self.cip_sequence = le_u16(item.data, 0);
+                    if !io {
+                        if let Some(message) = item.data.get(2..) {
+                            self.set_cip(message);
+                        }
+                    }
+                }
+                ItemValues::UnconnectedData => {
+                    self.set_cip(item.data);
+                }
+                ItemValues::ListIdentity => {
+                    self.set_identity(item.data);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_cip(&mut self, message: &[u8]) {
+        let service = match message.get(0) {
+            Some(service) => *service,
+            None => return,
+        };
+        self.cip_service = Some(service);
+        if service & ServiceValues::Reply != 0 {
+            /* Service | Reserved | General Status | Additional Status Size | Additional Status ... */
+            self.general_status = message.get(2).cloned();
+            if let Some(size) = message.get(3) {
+                if *size > 0 {
+                    self.extended_status = le_u16(message, 4);
+                }
+            }
+            return;
+        }
+        /* Service | Path Size (words) | Path ... | Data ... */
+        let path_len = match message.get(1) {
+            Some(size) => *size as usize * 2,
+            None => return,
+        };
+        let path = match message.get(2..2 + path_len) {
+            Some(path) => path,
+            None => return,
+        };
+        self.set_epath(path);
+        if service == ServiceValues::UnconnectedSend && self.cip_class == Some(CONNECTION_MANAGER) {
+            /* Priority/Tick | Timeout Ticks | Message Size | Embedded Message ... */
+            let request = &message[2 + path_len..];
+            if let Some(size) = le_u16(request, 2) {
+                if let Some(embedded) = request.get(4..4 + size as usize) {
+                    self.cip_class = None;
+                    self.cip_instance = None;
+                    self.cip_attribute = None;
+                    self.set_cip(embedded);
+                }
+            }
+        }
+    }
+
+    fn set_epath(&mut self, path: &[u8]) {
+        let mut offset = 0;
+        while offset < path.len() {
+            let segment = path[offset];
+            match segment >> 5 {
+                0 => { /* Port segment */
+                    if segment & 0x10 != 0 {
+                        let size = match path.get(offset + 1) {
+                            Some(size) => *size as usize,
+                            None => return,
+                        };
+                        offset += 2 + size + size % 2;
+                    } else {
+                        offset += 2;
+                    }
+                }
+                1 => { /* Logical segment: 001 | type (3) | format (2) */
+                    if segment == 0x34 {
+                        /* Electronic key: key format 4 | vendor | device type | product code | major | minor revision */
+                        offset += 10;
+                        continue;
+                    }
+                    let value = match segment & 0x03 {
+                        0 => path.get(offset + 1).map(|v| *v as u32),
+                        1 => le_u16(path, offset + 2).map(|v| v as u32),
+                        2 => le_u32(path, offset + 2),
+                        _ => return,
+                    };
+                    match (segment >> 2) & 0x07 {
+                        0 => self.cip_class = value,
+                        1 => self.cip_instance = value,
+                        4 => self.cip_attribute = value,
+                        _ => {}
+                    }
+                    offset += match segment & 0x03 {
+                        0 => 2,
+                        1 => 4,
+                        _ => 6,
+                    };
+                }
+                4 => { /* Data segment (ANSI extended symbol) */
+                    let size = match path.get(offset + 1) {
+                        Some(size) => *size as usize,
+                        None => return,
+                    };
+                    if segment == 0x91 {
+                        if let Some(symbol) = path.get(offset + 2..offset + 2 + size) {
+                            let symbol = String::from_utf8_lossy(symbol);
+                            self.cip_symbol = Some(match self.cip_symbol.take() {
+                                Some(parent) => format!("{}.{}", parent, symbol),
+                                None => symbol.to_string(),
+                            });
+                        }
+                        offset += 2 + size + size % 2;
+                    } else {
+                        offset += 2 + size * 2;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn set_identity(&mut self, item: &[u8]) {
+        /* Protocol Version (2) | Socket Address (16) | Vendor ID | Device Type | Product Code |
+           Revision (2) | Status (2) | Serial Number (4) | Product Name (short string) | State */
+        self.vendor_id = le_u16(item, 18);
+        self.device_type = le_u16(item, 20);
+        self.product_code = le_u16(item, 22);
+        self.serial_number = le_u32(item, 28);
+        if let Some(size) = item.get(32) {
+            if let Some(name) = item.get(33..33 + *size as usize) {
+                self.product_name = Some(String::from_utf8_lossy(name).to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+
+    fn encapsulation(command: u16, options: u32, data: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&command.to_le_bytes());
+        frame.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        frame.extend_from_slice(&0x12345678u32.to_le_bytes());
+        frame.extend_from_slice(&0u32.to_le_bytes());
+        frame.extend_from_slice(&[0; 8]);
+        frame.extend_from_slice(&options.to_le_bytes());
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    fn send_rr_data(message: &[u8]) -> Vec<u8> {
+        let mut data = vec![
+            0x00, 0x00, 0x00, 0x00, // interface handle
+            0x0a, 0x00, // timeout
+            0x02, 0x00, // item count
+            0x00, 0x00, 0x00, 0x00, // null address
+            0xb2, 0x00, // unconnected data
+        ];
+        data.extend_from_slice(&(message.len() as u16).to_le_bytes());
+        data.extend_from_slice(message);
+        encapsulation(CommandValues::SendRRData, 0, &data)
+    }
+
+    fn parse(frame: &[u8]) -> (bool, EnipAttr) {
+        let mut attr = EnipAttr::new(test_addresses(50000, ENIP_PORT));
+        let encap = EncapsulationPacket::new(frame).unwrap();
+        (attr.set_enip(&encap), attr)
+    }
+
+    // Read Tag "Tag1", 1 element
+    const READ_TAG: [u8; 10] = [0x4c, 0x03, 0x91, 0x04, b'T', b'a', b'g', b'1', 0x01, 0x00];
+
+    #[test]
+    fn read_tag_request() {
+        let (ok, attr) = parse(&send_rr_data(&READ_TAG));
+        assert!(ok);
+        assert_eq!(attr.command, Some(CommandValues::SendRRData));
+        assert_eq!(attr.session_handle, Some(0x12345678));
+        assert_eq!(attr.cip_service, Some(ServiceValues::ReadTag));
+        assert_eq!(attr.cip_symbol.as_deref(), Some("Tag1"));
+    }
+
+    #[test]
+    fn unconnected_send_is_unwrapped() {
+        /* Unconnected Send to the Connection Manager (class 6, instance 1) */
+        let mut message = vec![0x52, 0x02, 0x20, 0x06, 0x24, 0x01, 0x0a, 0x0e];
+        message.extend_from_slice(&(READ_TAG.len() as u16).to_le_bytes());
+        message.extend_from_slice(&READ_TAG);
+        message.extend_from_slice(&[0x01, 0x00, 0x01, 0x00]); // route path: backplane, slot 0
+        let (ok, attr) = parse(&send_rr_data(&message));
+        assert!(ok);
+        assert_eq!(attr.cip_service, Some(ServiceValues::ReadTag));
+        assert_eq!(attr.cip_class, None);
+        assert_eq!(attr.cip_symbol.as_deref(), Some("Tag1"));
+    }
+
+    #[test]
+    fn electronic_key_is_skipped() {
+        /* Get Attribute Single: electronic key, class 1, instance 1, attribute 7 */
+        let message = [
+            0x0e, 0x08,
+            0x34, 0x04, 0x01, 0x00, 0x0e, 0x00, 0x36, 0x00, 0x14, 0x0b,
+            0x20, 0x01, 0x24, 0x01, 0x30, 0x07,
+        ];
+        let (ok, attr) = parse(&send_rr_data(&message));
+        assert!(ok);
+        assert_eq!(attr.cip_service, Some(ServiceValues::GetAttributeSingle));
+        assert_eq!(attr.cip_class, Some(1));
+        assert_eq!(attr.cip_instance, Some(1));
+        assert_eq!(attr.cip_attribute, Some(7));
+    }
+
+    #[test]
+    fn reply_status() {
+        let (ok, attr) = parse(&send_rr_data(&[0xcc, 0x00, 0x05, 0x01, 0x04, 0x21]));
+        assert!(ok);
+        assert_eq!(attr.cip_service, Some(ServiceValues::ReadTag | ServiceValues::Reply));
+        assert_eq!(attr.general_status, Some(0x05));
+        assert_eq!(attr.extended_status, Some(0x2104));
+    }
+
+    #[test]
+    fn list_identity_reply() {
+        let name = b"1756-L61/B LOGIX5561";
+        let mut item = vec![0x01, 0x00];
+        item.extend_from_slice(&[0x00, 0x02, 0xaf, 0x12, 192, 168, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        item.extend_from_slice(&[0x01, 0x00, 0x0e, 0x00, 0x36, 0x00, 0x14, 0x0b, 0x60, 0x00]);
+        item.extend_from_slice(&0x00c0ffeeu32.to_le_bytes());
+        item.push(name.len() as u8);
+        item.extend_from_slice(name);
+        item.push(0x03);
+        let mut data = vec![0x01, 0x00, 0x0c, 0x00];
+        data.extend_from_slice(&(item.len() as u16).to_le_bytes());
+        data.extend_from_slice(&item);
+        let (ok, attr) = parse(&encapsulation(CommandValues::ListIdentity, 0, &data));
+        assert!(ok);
+        assert_eq!(attr.vendor_id, Some(1));
+        assert_eq!(attr.device_type, Some(0x0e));
+        assert_eq!(attr.product_code, Some(0x36));
+        assert_eq!(attr.serial_number, Some(0x00c0ffee));
+        assert_eq!(attr.product_name.as_deref(), Some("1756-L61/B LOGIX5561"));
+    }
+
+    #[test]
+    fn rejects_foreign_headers() {
+        assert!(!parse(&encapsulation(0x1234, 0, &[])).0);
+        assert!(!parse(&encapsulation(CommandValues::ListIdentity, 1, &[])).0);
+        assert!(EncapsulationPacket::new(&[0x65, 0x00, 0x04]).is_none());
+    }
+
+    #[test]
+    fn truncated_messages() {
+        /* CPF item longer than the data, and a path longer than the message */
+        let mut frame = send_rr_data(&READ_TAG);
+        frame.truncate(frame.len() - 4);
+        let (ok, attr) = parse(&frame);
+        assert!(ok);
+        assert_eq!(attr.cip_service, None);
+        let (ok, attr) = parse(&send_rr_data(&[0x4c, 0x08, 0x91, 0x04]));
+        assert!(ok);
+        assert_eq!(attr.cip_service, Some(ServiceValues::ReadTag));
+        assert_eq!(attr.cip_symbol, None);
+        let (ok, _) = parse(&encapsulation(CommandValues::SendRRData, 0, &[0x00, 0x00]));
+        assert!(ok);
+    }
+
+    #[test]
+    fn implicit_io() {
+        let payload = [
+            0x02, 0x00,
+            0x02, 0x80, 0x08, 0x00, 0x01, 0x00, 0x02, 0x00, 0x2a, 0x00, 0x00, 0x00,
+            0xb1, 0x00, 0x04, 0x00, 0x07, 0x00, 0xff, 0xff,
+        ];
+        let mut attr = EnipAttr::new(test_addresses(ENIP_IO_PORT, ENIP_IO_PORT));
+        assert!(attr.set_io(&payload));
+        assert_eq!(attr.connection_id, Some(0x00020001));
+        assert_eq!(attr.encap_sequence, Some(42));
+        assert_eq!(attr.cip_sequence, Some(7));
+        assert_eq!(attr.cip_service, None);
+        let mut attr = EnipAttr::new(test_addresses(ENIP_IO_PORT, ENIP_IO_PORT));
+        assert!(!attr.set_io(&[0x01, 0x00, 0xb2, 0x00, 0x00, 0x00]));
+        assert!(!attr.set_io(&[0x01]));
+    }
+}
@@ -0,0 +1,309 @@
+//! Omron FINS (UDP/TCP 9600)
+//!
+//! FINS header
+//! +-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+---------
+//! | ICF | RSV | GCT | DNA | DA1 | DA2 | SNA | SA1 | SA2 | SID | MRC | SRC | Text ...
+//! +-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+---------
+//! responses carry MRES/SRES (end code) in front of the text
+//!
+//! FINS/TCP wraps each frame in
+//! +--------+--------+---------+------------+---------------------------
+//! | "FINS" | Length | Command | Error Code | Node addresses / FINS frame
+//! +--------+--------+---------+------------+---------------------------
+//! |   4    |   4    |    4    |     4      | ...
+
+use super::{Addresses, be_u16, be_u32};
+
+pub const FINS_PORT: u16 = 9600;
+
+const FINS_MAGIC: u32 = 0x46494E53;
+
+// ICF: bit 7 gateway (always set), bit 6 response, bits 5..1 reserved, bit 0 no response
+const ICF_GATEWAY: u8 = 0x80;
+const ICF_RESPONSE: u8 = 0x40;
+const ICF_RESERVED: u8 = 0x3E;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod TcpCommandValues {
+    pub const NodeAddressClient: u32 = 0x00;
+    pub const NodeAddressServer: u32 = 0x01;
+    pub const FrameSend: u32 = 0x02;
+    pub const FrameSendError: u32 = 0x03;
+    pub const ConnectionConfirmation: u32 = 0x06;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod CommandValues {
+    pub const MemoryAreaRead: u16 = 0x0101;
+    pub const MemoryAreaWrite: u16 = 0x0102;
+    pub const MemoryAreaFill: u16 = 0x0103;
+    pub const MultipleMemoryAreaRead: u16 = 0x0104;
+    pub const MemoryAreaTransfer: u16 = 0x0105;
+    pub const Run: u16 = 0x0401;
+    pub const Stop: u16 = 0x0402;
+    pub const ControllerDataRead: u16 = 0x0501;
+    pub const ControllerStatusRead: u16 = 0x0601;
+    pub const ClockRead: u16 = 0x0701;
+    pub const ClockWrite: u16 = 0x0702;
+    pub const ErrorClear: u16 = 0x2101;
+}
+
+#[derive(Debug)]
+pub struct FinsAttr {
+    pub addresses: Addresses,
+    // FINS/TCP
+    pub tcp_command: Option<u32>,
+    pub tcp_error: Option<u32>,
+    pub client_node: Option<u32>,
+    pub server_node: Option<u32>,
+    // FINS header
+    pub icf: Option<u8>,
+    pub gct: Option<u8>,
+    pub dna: Option<u8>,
+    pub da1: Option<u8>,
+    pub da2: Option<u8>,
+    pub sna: Option<u8>,
+    pub sa1: Option<u8>,
+    pub sa2: Option<u8>,
+    pub sid: Option<u8>,
+    pub command_code: Option<u16>,
+    pub end_code: Option<u16>,
+    // memory area commands
+    pub memory_area: Vec<u8>,
+    pub memory_address: Vec<u32>, // word address << 8 | bit
+    pub item_count: Option<u16>,
+}
+
+impl FinsAttr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            tcp_command: None,
+            tcp_error: None,
+            client_node: None,
+            server_node: None,
+            icf: None,
+            gct: None,
+            dna: None,
+            da1: None,
+            da2: None,
+            sna: None,
+            sa1: None,
+            sa2: None,
+            sid: None,
+            command_code: None,
+            end_code: None,
+            memory_area: Vec::new(),
+            memory_address: Vec::new(),
+            item_count: None,
+        }
+    }
+
+    // returns the size of the FINS/TCP frame, or None when it is not one
+    pub fn set_tcp(&mut self, data: &[u8]) -> Option<usize> {
+        if be_u32(data, 0)? != FINS_MAGIC {
+            return None;
+        }
+        /* Length counts the bytes following the length field */
+        let size = 8 + be_u32(data, 4)? as usize;
+        let frame = data.get(..size)?;
+        let command = be_u32(frame, 8)?;
+        self.tcp_command = Some(command);
+        self.tcp_error = be_u32(frame, 12);
+        match command {
+            TcpCommandValues::NodeAddressClient => {
+                self.client_node = be_u32(frame, 16);
+            }
+            TcpCommandValues::NodeAddressServer => {
+                self.client_node = be_u32(frame, 16);
+                self.server_node = be_u32(frame, 20);
+            }
+            TcpCommandValues::FrameSend => {
+                self.set_fins(&frame[16.min(frame.len())..]);
+            }
+            _ => {}
+        }
+        Some(size)
+    }
+
+    // returns false when the data is too short for a FINS header
+    // or the ICF/RSV bytes are not those of one
+    pub fn set_fins(&mut self, data: &[u8]) -> bool {
+        if data.len() < 12 {
+            return false;
+        }
+        let icf = data[0];
+        if icf & ICF_GATEWAY == 0 || icf & ICF_RESERVED != 0 || data[1] != 0 {
+            return false;
+        }
+        self.icf = Some(icf);
+        self.gct = Some(data[2]);
+        self.dna = Some(data[3]);
+        self.da1 = Some(data[4]);
+        self.da2 = Some(data[5]);
+        self.sna = Some(data[6]);
+        self.sa1 = Some(data[7]);
+        self.sa2 = Some(data[8]);
+        self.sid = Some(data[9]);
+        let command_code = be_u16(data, 10).unwrap_or(0);
+        self.command_code = Some(command_code);
+        if icf & ICF_RESPONSE != 0 {
+            self.end_code = be_u16(data, 12);
+            return true;
+        }
+        let text = &data[12..];
+        match command_code {
+            CommandValues::MemoryAreaRead
+            | CommandValues::MemoryAreaWrite
+            | CommandValues::MemoryAreaFill => {
+                /* Area Code | Address (word 2, bit 1) | Number of Items */
+                if self.set_memory(text) {
+                    self.item_count = be_u16(text, 4);
+                }
+            }
+            CommandValues::MultipleMemoryAreaRead => {
+                /* (Area Code | Address) ... */
+                let mut offset = 0;
+                while self.set_memory(&text[offset.min(text.len())..]) {
+                    offset += 4;
+                }
+                self.item_count = Some(self.memory_area.len() as u16);
+            }
+            CommandValues::MemoryAreaTransfer => {
+                /* Source (Area, Address) | Destination (Area, Address) | Number of Items */
+                if self.set_memory(text) && self.set_memory(&text[4.min(text.len())..]) {
+                    self.item_count = be_u16(text, 8);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn set_memory(&mut self, data: &[u8]) -> bool {
+        match data.get(0..4) {
+            Some(b) => {
+                self.memory_area.push(b[0]);
+                self.memory_address.push(u32::from_be_bytes([0, b[1], b[2], b[3]]));
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+
+    // Memory Area Read, DM100, 10 words
+    const READ_DM: [u8; 18] = [
+        0x80, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0xfe, 0x00, 0x19, 0x01, 0x01,
+        0x82, 0x00, 0x64, 0x00, 0x00, 0x0a,
+    ];
+
+    fn fins_attr() -> FinsAttr {
+        FinsAttr::new(test_addresses(FINS_PORT, FINS_PORT))
+    }
+
+    fn fins_tcp(command: u32, data: &[u8]) -> Vec<u8> {
+        let mut frame = b"FINS".to_vec();
+        frame.extend_from_slice(&(8 + data.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&command.to_be_bytes());
+        frame.extend_from_slice(&0u32.to_be_bytes());
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    #[test]
+    fn memory_area_read() {
+        let mut attr = fins_attr();
+        assert!(attr.set_fins(&READ_DM));
+        assert_eq!(attr.icf, Some(0x80));
+        assert_eq!(attr.gct, Some(0x02));
+        assert_eq!(attr.da1, Some(0x01));
+        assert_eq!(attr.sa1, Some(0xfe));
+        assert_eq!(attr.sid, Some(0x19));
+        assert_eq!(attr.command_code, Some(CommandValues::MemoryAreaRead));
+        assert_eq!(attr.memory_area, vec![0x82]);
+        assert_eq!(attr.memory_address, vec![100 << 8]);
+        assert_eq!(attr.item_count, Some(10));
+        assert_eq!(attr.end_code, None);
+    }
+
+    #[test]
+    fn response_end_code() {
+        let mut attr = fins_attr();
+        assert!(attr.set_fins(&[0xc0, 0x00, 0x02, 0x00, 0xfe, 0x00, 0x00, 0x01, 0x00, 0x19, 0x01, 0x01, 0x00, 0x00, 0x12, 0x34]));
+        assert_eq!(attr.end_code, Some(0));
+        assert!(attr.memory_area.is_empty());
+    }
+
+    #[test]
+    fn multiple_memory_area_read() {
+        let mut attr = fins_attr();
+        let data = [
+            0x80, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0xfe, 0x00, 0x1a, 0x01, 0x04,
+            0x82, 0x00, 0x64, 0x00, 0xb0, 0x00, 0x0a, 0x03, 0x82,
+        ];
+        assert!(attr.set_fins(&data));
+        assert_eq!(attr.memory_area, vec![0x82, 0xb0]);
+        assert_eq!(attr.memory_address, vec![100 << 8, 10 << 8 | 3]);
+        assert_eq!(attr.item_count, Some(2));
+    }
+
+    #[test]
+    fn fins_tcp_frames() {
+        let mut attr = fins_attr();
+        assert_eq!(attr.set_tcp(&fins_tcp(TcpCommandValues::NodeAddressClient, &[0, 0, 0, 0])), Some(20));
+        assert_eq!(attr.tcp_command, Some(TcpCommandValues::NodeAddressClient));
+        assert_eq!(attr.client_node, Some(0));
+        let mut attr = fins_attr();
+        assert_eq!(attr.set_tcp(&fins_tcp(TcpCommandValues::NodeAddressServer, &[0, 0, 0, 0x0a, 0, 0, 0, 0x01])), Some(24));
+        assert_eq!(attr.client_node, Some(0x0a));
+        assert_eq!(attr.server_node, Some(0x01));
+        let mut attr = fins_attr();
+        let mut segment = fins_tcp(TcpCommandValues::FrameSend, &READ_DM);
+        segment.extend_from_slice(b"FINS");
+        assert_eq!(attr.set_tcp(&segment), Some(34));
+        assert_eq!(attr.tcp_error, Some(0));
+        assert_eq!(attr.command_code, Some(CommandValues::MemoryAreaRead));
+        assert_eq!(attr.item_count, Some(10));
+    }
+
+    #[test]
+    fn rejects_non_fins_headers() {
+        /* gateway bit clear, reserved ICF bits set, RSV set, too short */
+        let mut data = READ_DM;
+        data[0] = 0x00;
+        assert!(!fins_attr().set_fins(&data));
+        data[0] = 0x82;
+        assert!(!fins_attr().set_fins(&data));
+        data[0] = 0x80;
+        data[1] = 0x01;
+        assert!(!fins_attr().set_fins(&data));
+        assert!(!fins_attr().set_fins(&READ_DM[..11]));
+        /* response-not-required requests are valid */
+        data[0] = 0x81;
+        data[1] = 0x00;
+        assert!(fins_attr().set_fins(&data));
+    }
+
+    #[test]
+    fn malformed_fins_tcp() {
+        assert_eq!(fins_attr().set_tcp(b"FINT\x00\x00\x00\x0c"), None);
+        let frame = fins_tcp(TcpCommandValues::FrameSend, &READ_DM);
+        assert_eq!(fins_attr().set_tcp(&frame[..frame.len() - 1]), None);
+        assert_eq!(fins_attr().set_tcp(b"FIN"), None);
+        /* truncated memory area text */
+        let mut attr = fins_attr();
+        assert!(attr.set_fins(&READ_DM[..14]));
+        assert!(attr.memory_area.is_empty());
+        assert_eq!(attr.item_count, None);
+    }
+}
@@ -0,0 +1,354 @@
+//! Bidirectional flow tracking (IPFIX-like biflows)
+//!
+//! A flow is keyed by VLAN, protocol and 5-tuple; the direction of its first
+//! packet is "forward". Records are exported when a flow ends (TCP FIN from
+//! both sides or RST), when it has been idle for IDLE_TIMEOUT, and every
+//! ACTIVE_TIMEOUT while it stays active (counters restart after each export).
+//! An ended TCP flow lingers for CLOSE_LINGER so the final ACK and stray RSTs
+//! are counted in it instead of opening a new flow. Timeouts are checked as
+//! packets arrive. When the table is full the least recently seen flow is
+//! evicted.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
+
+use super::be_u16;
+
+const ACTIVE_TIMEOUT_SECS: i64 = 60;
+const IDLE_TIMEOUT_SECS: i64 = 15;
+const SWEEP_INTERVAL_MILLIS: i64 = 1000;
+const CLOSE_LINGER_SECS: i64 = 2;
+const MAX_FLOWS: usize = 65536;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod EndReasonValues {
+    pub const IdleTimeout: &str = "IdleTimeout";
+    pub const ActiveTimeout: &str = "ActiveTimeout";
+    pub const Fin: &str = "FIN";
+    pub const Rst: &str = "RST";
+    pub const Evicted: &str = "Evicted";
+}
+
+// (VLAN, protocol, source address, source port, destination address, destination port)
+type FlowKey = (Option<u16>, u8, IpAddr, u16, IpAddr, u16);
+
+#[derive(Debug, Clone)]
+pub struct FlowAttr {
+    pub interface_name: String,
+    pub vlan_id: Option<u16>,
+    pub protocol: u8,
+    pub src_addr: IpAddr,
+    pub src_port: u16,
+    pub dst_addr: IpAddr,
+    pub dst_port: u16,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub packets_fwd: u64,
+    pub packets_rev: u64,
+    pub bytes_fwd: u64,
+    pub bytes_rev: u64,
+    pub tcp_flags_fwd: u16,
+    pub tcp_flags_rev: u16,
+    pub end_reason: &'static str,
+    fin_fwd: bool,
+    fin_rev: bool,
+    // end reason and time of the FIN/RST that closed the flow
+    closed: Option<(&'static str, DateTime<Utc>)>,
+}
+
+impl FlowAttr {
+    fn new(interface_name: &str, key: &FlowKey, utc: DateTime<Utc>) -> Self {
+        Self {
+            interface_name: interface_name.to_string(),
+            vlan_id: key.0,
+            protocol: key.1,
+            src_addr: key.2,
+            src_port: key.3,
+            dst_addr: key.4,
+            dst_port: key.5,
+            first_seen: utc,
+            last_seen: utc,
+            packets_fwd: 0,
+            packets_rev: 0,
+            bytes_fwd: 0,
+            bytes_rev: 0,
+            tcp_flags_fwd: 0,
+            tcp_flags_rev: 0,
+            end_reason: "",
+            fin_fwd: false,
+            fin_rev: false,
+            closed: None,
+        }
+    }
+
+    // nothing seen since the last export
+    fn is_empty(&self) -> bool {
+        self.packets_fwd == 0 && self.packets_rev == 0
+    }
+
+    // exported copy; the live flow restarts its counters
+    fn export(&mut self, end_reason: &'static str, utc: DateTime<Utc>) -> FlowAttr {
+        let mut record = self.clone();
+        record.end_reason = end_reason;
+        self.first_seen = utc;
+        self.packets_fwd = 0;
+        self.packets_rev = 0;
+        self.bytes_fwd = 0;
+        self.bytes_rev = 0;
+        self.tcp_flags_fwd = 0;
+        self.tcp_flags_rev = 0;
+        record
+    }
+}
+
+#[derive(Default)]
+pub struct FlowTracker {
+    flows: HashMap<FlowKey, FlowAttr>,
+    last_sweep: Option<DateTime<Utc>>,
+}
+
+impl FlowTracker {
+    // Accounts one packet and returns the flow records due for export
+    pub fn update(
+        &mut self,
+        interface_name: &str,
+        vlan_id: Option<u16>,
+        source: IpAddr,
+        destination: IpAddr,
+        protocol: IpNextHeaderProtocol,
+        packet: &[u8],
+        utc: DateTime<Utc>,
+    ) -> Vec<FlowAttr> {
+        let mut records = self.sweep(utc);
+
+        let (src_port, dst_port) = match protocol {
+            IpNextHeaderProtocols::Tcp | IpNextHeaderProtocols::Udp => {
+                (be_u16(packet, 0).unwrap_or(0), be_u16(packet, 2).unwrap_or(0))
+            }
+            _ => (0, 0),
+        };
+        let tcp_flags = match protocol {
+            IpNextHeaderProtocols::Tcp => TcpPacket::new(packet).map_or(0, |tcp| tcp.get_flags()),
+            _ => 0,
+        };
+        let forward: FlowKey = (vlan_id, protocol.0, source, src_port, destination, dst_port);
+        let reverse: FlowKey = (vlan_id, protocol.0, destination, dst_port, source, src_port);
+        let (key, is_forward) = if self.flows.contains_key(&forward) {
+            (forward, true)
+        } else if self.flows.contains_key(&reverse) {
+            (reverse, false)
+        } else {
+            (forward, true)
+        };
+        // a new SYN on a closed flow reuses the 5-tuple for a new connection
+        let reopened = tcp_flags & (TcpFlags::SYN | TcpFlags::ACK) == TcpFlags::SYN
+            && self.flows.get(&key).map_or(false, |flow| flow.closed.is_some());
+        if reopened {
+            if let Some(mut flow) = self.flows.remove(&key) {
+                let (end_reason, _) = flow.closed.unwrap();
+                records.push(flow.export(end_reason, utc));
+            }
+        }
+        if !self.flows.contains_key(&key) && self.flows.len() >= MAX_FLOWS {
+            records.extend(self.evict(utc));
+        }
+
+        let flow = self.flows.entry(key).or_insert_with(|| FlowAttr::new(interface_name, &key, utc));
+        flow.last_seen = utc;
+        if is_forward {
+            flow.packets_fwd += 1;
+            flow.bytes_fwd += packet.len() as u64;
+            flow.tcp_flags_fwd |= tcp_flags;
+            flow.fin_fwd |= tcp_flags & TcpFlags::FIN != 0;
+        } else {
+            flow.packets_rev += 1;
+            flow.bytes_rev += packet.len() as u64;
+            flow.tcp_flags_rev |= tcp_flags;
+            flow.fin_rev |= tcp_flags & TcpFlags::FIN != 0;
+        }
+        if flow.closed.is_none() {
+            if tcp_flags & TcpFlags::RST != 0 {
+                flow.closed = Some((EndReasonValues::Rst, utc));
+            } else if flow.fin_fwd && flow.fin_rev {
+                flow.closed = Some((EndReasonValues::Fin, utc));
+            }
+        }
+        records
+    }
+
+    // Removes the least recently seen flow to make room for a new one
+    fn evict(&mut self, utc: DateTime<Utc>) -> Option<FlowAttr> {
+        let key = *self.flows.iter().min_by_key(|(_, flow)| flow.last_seen)?.0;
+        let mut flow = self.flows.remove(&key)?;
+        if flow.is_empty() {
+            return None;
+        }
+        let end_reason = flow.closed.map_or(EndReasonValues::Evicted, |(end_reason, _)| end_reason);
+        Some(flow.export(end_reason, utc))
+    }
+
+    fn sweep(&mut self, utc: DateTime<Utc>) -> Vec<FlowAttr> {
+        let mut records = Vec::new();
+        if let Some(last_sweep) = self.last_sweep {
+            if utc - last_sweep < Duration::milliseconds(SWEEP_INTERVAL_MILLIS) {
+                return records;
+            }
+        }
+        self.last_sweep = Some(utc);
+        let idle_timeout = Duration::seconds(IDLE_TIMEOUT_SECS);
+        let active_timeout = Duration::seconds(ACTIVE_TIMEOUT_SECS);
+        let close_linger = Duration::seconds(CLOSE_LINGER_SECS);
+        self.flows.retain(|_, flow| {
+            if let Some((end_reason, closed_at)) = flow.closed {
+                if utc - closed_at >= close_linger {
+                    records.push(flow.export(end_reason, utc));
+                    return false;
+                }
+                true
+            } else if utc - flow.last_seen >= idle_timeout {
+                // already exported by ActiveTimeout if nothing arrived since
+                if !flow.is_empty() {
+                    records.push(flow.export(EndReasonValues::IdleTimeout, utc));
+                }
+                false
+            } else {
+                if utc - flow.first_seen >= active_timeout && !flow.is_empty() {
+                    records.push(flow.export(EndReasonValues::ActiveTimeout, utc));
+                }
+                true
+            }
+        });
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_tcp_segment;
+
+    const CLIENT: [u8; 4] = [192, 168, 0, 10];
+    const SERVER: [u8; 4] = [192, 168, 0, 1];
+
+    fn tcp(tracker: &mut FlowTracker, from_client: bool, flags: u16) -> Vec<FlowAttr> {
+        let (source, destination, segment) = if from_client {
+            (CLIENT, SERVER, test_tcp_segment(50000, 502, 0, 0, flags, &[]))
+        } else {
+            (SERVER, CLIENT, test_tcp_segment(502, 50000, 0, 0, flags, &[]))
+        };
+        tracker.update("eth0", None, source.into(), destination.into(), IpNextHeaderProtocols::Tcp, &segment, Utc::now())
+    }
+
+    fn udp(tracker: &mut FlowTracker, src_port: u16) -> Vec<FlowAttr> {
+        let mut datagram = src_port.to_be_bytes().to_vec();
+        datagram.extend_from_slice(&[0x00, 0x35, 0x00, 0x08, 0x00, 0x00]);
+        tracker.update("eth0", None, CLIENT.into(), SERVER.into(), IpNextHeaderProtocols::Udp, &datagram, Utc::now())
+    }
+
+    // sweeps regardless of SWEEP_INTERVAL
+    fn sweep(tracker: &mut FlowTracker, utc: DateTime<Utc>) -> Vec<FlowAttr> {
+        tracker.last_sweep = None;
+        tracker.sweep(utc)
+    }
+
+    fn flow(tracker: &FlowTracker) -> &FlowAttr {
+        assert_eq!(tracker.flows.len(), 1);
+        tracker.flows.values().next().unwrap()
+    }
+
+    #[test]
+    fn handshake_is_one_biflow() {
+        let mut tracker = FlowTracker::default();
+        tcp(&mut tracker, true, TcpFlags::SYN);
+        tcp(&mut tracker, false, TcpFlags::SYN | TcpFlags::ACK);
+        tcp(&mut tracker, true, TcpFlags::ACK);
+        let flow = flow(&tracker);
+        assert_eq!(flow.src_addr, IpAddr::from(CLIENT));
+        assert_eq!(flow.dst_port, 502);
+        assert_eq!((flow.packets_fwd, flow.packets_rev), (2, 1));
+        assert_eq!((flow.bytes_fwd, flow.bytes_rev), (40, 20));
+        assert_eq!(flow.tcp_flags_fwd, TcpFlags::SYN | TcpFlags::ACK);
+        assert_eq!(flow.tcp_flags_rev, TcpFlags::SYN | TcpFlags::ACK);
+    }
+
+    #[test]
+    fn final_ack_lingers_in_the_closed_flow() {
+        let mut tracker = FlowTracker::default();
+        tcp(&mut tracker, true, TcpFlags::SYN);
+        tcp(&mut tracker, true, TcpFlags::FIN | TcpFlags::ACK);
+        assert!(flow(&tracker).closed.is_none());
+        tcp(&mut tracker, false, TcpFlags::FIN | TcpFlags::ACK);
+        assert!(tcp(&mut tracker, true, TcpFlags::ACK).is_empty());
+        assert_eq!(flow(&tracker).packets_fwd, 3);
+        let records = sweep(&mut tracker, Utc::now() + Duration::seconds(CLOSE_LINGER_SECS));
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].end_reason, EndReasonValues::Fin);
+        assert_eq!((records[0].packets_fwd, records[0].packets_rev), (3, 1));
+        assert!(tracker.flows.is_empty());
+    }
+
+    #[test]
+    fn syn_reopens_a_closed_flow() {
+        let mut tracker = FlowTracker::default();
+        tcp(&mut tracker, true, TcpFlags::SYN);
+        tcp(&mut tracker, false, TcpFlags::RST | TcpFlags::ACK);
+        /* stray RST during the linger */
+        assert!(tcp(&mut tracker, false, TcpFlags::RST).is_empty());
+        let records = tcp(&mut tracker, true, TcpFlags::SYN);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].end_reason, EndReasonValues::Rst);
+        assert_eq!((records[0].packets_fwd, records[0].packets_rev), (1, 2));
+        let flow = flow(&tracker);
+        assert!(flow.closed.is_none());
+        assert_eq!(flow.packets_fwd, 1);
+    }
+
+    #[test]
+    fn idle_and_active_timeouts() {
+        let mut tracker = FlowTracker::default();
+        udp(&mut tracker, 40000);
+        let now = Utc::now();
+        tracker.flows.values_mut().for_each(|flow| flow.first_seen = now - Duration::seconds(ACTIVE_TIMEOUT_SECS));
+        let records = sweep(&mut tracker, now);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].end_reason, EndReasonValues::ActiveTimeout);
+        assert!(flow(&tracker).is_empty());
+        /* nothing since the export: neither timeout re-exports zero counters */
+        tracker.flows.values_mut().for_each(|flow| flow.first_seen = now - Duration::seconds(ACTIVE_TIMEOUT_SECS));
+        assert!(sweep(&mut tracker, now + Duration::seconds(1)).is_empty());
+        let records = sweep(&mut tracker, now + Duration::seconds(IDLE_TIMEOUT_SECS + 1));
+        assert!(records.is_empty());
+        assert!(tracker.flows.is_empty());
+        udp(&mut tracker, 40000);
+        let records = sweep(&mut tracker, Utc::now() + Duration::seconds(IDLE_TIMEOUT_SECS));
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].end_reason, EndReasonValues::IdleTimeout);
+    }
+
+    #[test]
+    fn full_table_evicts_the_oldest_flow() {
+        let mut tracker = FlowTracker::default();
+        for port in 0..MAX_FLOWS {
+            assert!(udp(&mut tracker, port as u16).is_empty());
+        }
+        let oldest: FlowKey = (None, IpNextHeaderProtocols::Udp.0, CLIENT.into(), 1234, SERVER.into(), 53);
+        let last_seen = tracker.flows[&oldest].last_seen - Duration::seconds(1);
+        tracker.flows.get_mut(&oldest).unwrap().last_seen = last_seen;
+        let mut datagram = vec![0x00, 0x35, 0x00, 0x35, 0x00, 0x08, 0x00, 0x00];
+        let records = tracker.update("eth0", None, SERVER.into(), [10, 0, 0, 1].into(), IpNextHeaderProtocols::Udp, &datagram, Utc::now());
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].src_port, 1234);
+        assert_eq!(records[0].end_reason, EndReasonValues::Evicted);
+        assert_eq!(tracker.flows.len(), MAX_FLOWS);
+        datagram[1] = 0x36;
+        tracker.update("eth0", None, SERVER.into(), [10, 0, 0, 1].into(), IpNextHeaderProtocols::Udp, &datagram, Utc::now());
+        assert_eq!(tracker.flows.len(), MAX_FLOWS);
+    }
+}
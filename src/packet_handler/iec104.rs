@@ -0,0 +1,353 @@
+//! IEC 60870-5-104
+//!
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  Start (0x68) | APDU Length   | Control Field 1 | Control 2   |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! | Control 3     | Control 4     |    Type ID    |  SQ | Number  |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! | T|PN| Cause   |  Originator   |  Common Address (LE)          |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  IOA (3 bytes LE) | Element ...  (ASDU only in I-format)
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+use super::{Addresses, le_u16, le_u32};
+
+pub const IEC104_PORT: u16 = 2404;
+
+const START: u8 = 0x68;
+// CP56Time2a
+const TIME_TAG: usize = 7;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod TypeValues {
+    pub const M_SP_NA_1: u8 = 1;
+    pub const M_DP_NA_1: u8 = 3;
+    pub const M_ST_NA_1: u8 = 5;
+    pub const M_BO_NA_1: u8 = 7;
+    pub const M_ME_NA_1: u8 = 9;
+    pub const M_ME_NB_1: u8 = 11;
+    pub const M_ME_NC_1: u8 = 13;
+    pub const M_IT_NA_1: u8 = 15;
+    pub const M_SP_TB_1: u8 = 30;
+    pub const M_DP_TB_1: u8 = 31;
+    pub const M_ST_TB_1: u8 = 32;
+    pub const M_BO_TB_1: u8 = 33;
+    pub const M_ME_TD_1: u8 = 34;
+    pub const M_ME_TE_1: u8 = 35;
+    pub const M_ME_TF_1: u8 = 36;
+    pub const M_IT_TB_1: u8 = 37;
+    pub const C_SC_NA_1: u8 = 45;
+    pub const C_DC_NA_1: u8 = 46;
+    pub const C_RC_NA_1: u8 = 47;
+    pub const C_SE_NA_1: u8 = 48;
+    pub const C_SE_NB_1: u8 = 49;
+    pub const C_SE_NC_1: u8 = 50;
+    pub const C_BO_NA_1: u8 = 51;
+    pub const C_SC_TA_1: u8 = 58;
+    pub const C_DC_TA_1: u8 = 59;
+    pub const C_RC_TA_1: u8 = 60;
+    pub const C_SE_TA_1: u8 = 61;
+    pub const C_SE_TB_1: u8 = 62;
+    pub const C_SE_TC_1: u8 = 63;
+    pub const C_BO_TA_1: u8 = 64;
+    pub const M_EI_NA_1: u8 = 70;
+    pub const C_IC_NA_1: u8 = 100;
+    pub const C_CI_NA_1: u8 = 101;
+    pub const C_RD_NA_1: u8 = 102;
+    pub const C_CS_NA_1: u8 = 103;
+    pub const C_RP_NA_1: u8 = 105;
+}
+
+#[derive(Debug)]
+pub struct Iec104Attr {
+    pub addresses: Addresses,
+    // APCI
+    pub frame_format: &'static str,
+    pub apdu_length: u8,
+    pub send_seq: Option<u16>,
+    pub recv_seq: Option<u16>,
+    pub u_function: Option<&'static str>,
+    // ASDU (I-format only)
+    pub type_id: Option<u8>,
+    pub sq: Option<bool>,
+    pub num_objects: Option<u8>,
+    pub cot: Option<u8>,
+    pub negative: Option<bool>,
+    pub test: Option<bool>,
+    pub originator: Option<u8>,
+    pub common_address: Option<u16>,
+    // one entry per information element; quality holds SIQ/DIQ/QDS or the command qualifier
+    pub ioa: Vec<u32>,
+    pub value: Vec<Option<f64>>,
+    pub quality: Vec<u8>,
+}
+
+fn ioa(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 3)
+        .map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16)
+}
+
+fn i16_value(data: &[u8]) -> Option<f64> {
+    le_u16(data, 0).map(|v| v as i16 as f64)
+}
+
+// (element size, value, quality/qualifier octet) for the supported type IDs
+fn element(type_id: u8, data: &[u8]) -> Option<(usize, Option<f64>, u8)> {
+    let first = data.get(0).cloned().unwrap_or(0);
+    let element = match type_id {
+        TypeValues::M_SP_NA_1 => (1, Some((first & 0x01) as f64), first & 0xF0),
+        TypeValues::M_SP_TB_1 => (1 + TIME_TAG, Some((first & 0x01) as f64), first & 0xF0),
+        TypeValues::M_DP_NA_1 => (1, Some((first & 0x03) as f64), first & 0xF0),
+        TypeValues::M_DP_TB_1 => (1 + TIME_TAG, Some((first & 0x03) as f64), first & 0xF0),
+        TypeValues::M_ST_NA_1 | TypeValues::M_ST_TB_1 => {
+            /* VTI: 7-bit signed value + transient bit */
+            let value = (((first << 1) as i8) >> 1) as f64;
+            let size = if type_id == TypeValues::M_ST_NA_1 { 2 } else { 2 + TIME_TAG };
+            (size, Some(value), data.get(1).cloned().unwrap_or(0))
+        }
+        TypeValues::M_BO_NA_1 | TypeValues::M_BO_TB_1 => {
+            let size = if type_id == TypeValues::M_BO_NA_1 { 5 } else { 5 + TIME_TAG };
+            (size, le_u32(data, 0).map(|v| v as f64), data.get(4).cloned().unwrap_or(0))
+        }
+        TypeValues::M_ME_NA_1 | TypeValues::M_ME_TD_1 => {
+            let size = if type_id == TypeValues::M_ME_NA_1 { 3 } else { 3 + TIME_TAG };
+            (size, i16_value(data).map(|v| v / 32768.0), data.get(2).cloned().unwrap_or(0))
+        }
+        TypeValues::M_ME_NB_1 | TypeValues::M_ME_TE_1 => {
+            let size = if type_id == TypeValues::M_ME_NB_1 { 3 } else { 3 + TIME_TAG };
+            (size, i16_value(data), data.get(2).cloned().unwrap_or(0))
+        }
+        TypeValues::M_ME_NC_1 | TypeValues::M_ME_TF_1 => {
+            let size = if type_id == TypeValues::M_ME_NC_1 { 5 } else { 5 + TIME_TAG };
+            (size, le_u32(data, 0).map(|v| f32::from_bits(v) as f64), data.get(4).cloned().unwrap_or(0))
+        }
+        TypeValues::M_IT_NA_1 | TypeValues::M_IT_TB_1 => {
+            /* BCR: counter reading + sequence notation */
+            let size = if type_id == TypeValues::M_IT_NA_1 { 5 } else { 5 + TIME_TAG };
+            (size, le_u32(data, 0).map(|v| v as i32 as f64), data.get(4).cloned().unwrap_or(0))
+        }
+        TypeValues::C_SC_NA_1 => (1, Some((first & 0x01) as f64), first),
+        TypeValues::C_SC_TA_1 => (1 + TIME_TAG, Some((first & 0x01) as f64), first),
+        TypeValues::C_DC_NA_1 | TypeValues::C_RC_NA_1 => (1, Some((first & 0x03) as f64), first),
+        TypeValues::C_DC_TA_1 | TypeValues::C_RC_TA_1 => (1 + TIME_TAG, Some((first & 0x03) as f64), first),
+        TypeValues::C_SE_NA_1 | TypeValues::C_SE_TA_1 => {
+            let size = if type_id == TypeValues::C_SE_NA_1 { 3 } else { 3 + TIME_TAG };
+            (size, i16_value(data).map(|v| v / 32768.0), data.get(2).cloned().unwrap_or(0))
+        }
+        TypeValues::C_SE_NB_1 | TypeValues::C_SE_TB_1 => {
+            let size = if type_id == TypeValues::C_SE_NB_1 { 3 } else { 3 + TIME_TAG };
+            (size, i16_value(data), data.get(2).cloned().unwrap_or(0))
+        }
+        TypeValues::C_SE_NC_1 | TypeValues::C_SE_TC_1 => {
+            let size = if type_id == TypeValues::C_SE_NC_1 { 5 } else { 5 + TIME_TAG };
+            (size, le_u32(data, 0).map(|v| f32::from_bits(v) as f64), data.get(4).cloned().unwrap_or(0))
+        }
+        TypeValues::C_BO_NA_1 => (4, le_u32(data, 0).map(|v| v as f64), 0),
+        TypeValues::C_BO_TA_1 => (4 + TIME_TAG, le_u32(data, 0).map(|v| v as f64), 0),
+        TypeValues::M_EI_NA_1 | TypeValues::C_IC_NA_1 | TypeValues::C_CI_NA_1 | TypeValues::C_RP_NA_1 => {
+            /* COI / QOI / QCC / QRP */
+            (1, None, first)
+        }
+        TypeValues::C_RD_NA_1 => (0, None, 0),
+        TypeValues::C_CS_NA_1 => (TIME_TAG, None, 0),
+        _ => return None,
+    };
+    if data.len() < element.0 {
+        return None;
+    }
+    Some(element)
+}
+
+impl Iec104Attr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            frame_format: "",
+            apdu_length: 0,
+            send_seq: None,
+            recv_seq: None,
+            u_function: None,
+            type_id: None,
+            sq: None,
+            num_objects: None,
+            cot: None,
+            negative: None,
+            test: None,
+            originator: None,
+            common_address: None,
+            ioa: Vec::new(),
+            value: Vec::new(),
+            quality: Vec::new(),
+        }
+    }
+
+    // returns the APDU size (start and length octets included), or None if this is not an APDU
+    pub fn set_apdu(&mut self, payload: &[u8]) -> Option<usize> {
+        if payload.get(0) != Some(&START) {
+            return None;
+        }
+        let length = *payload.get(1)?;
+        let apdu = payload.get(2..2 + length as usize)?;
+        if apdu.len() < 4 {
+            return None;
+        }
+        self.apdu_length = length;
+        let control = &apdu[0..4];
+        if control[0] & 0x01 == 0 {
+            self.frame_format = "I";
+            self.send_seq = Some((control[0] as u16 >> 1) | (control[1] as u16) << 7);
+            self.recv_seq = Some((control[2] as u16 >> 1) | (control[3] as u16) << 7);
+            self.set_asdu(&apdu[4..]);
+        } else if control[0] & 0x03 == 0x01 {
+            self.frame_format = "S";
+            self.recv_seq = Some((control[2] as u16 >> 1) | (control[3] as u16) << 7);
+        } else {
+            self.frame_format = "U";
+            self.u_function = Some(match control[0] {
+                0x07 => "STARTDT_ACT",
+                0x0B => "STARTDT_CON",
+                0x13 => "STOPDT_ACT",
+                0x23 => "STOPDT_CON",
+                0x43 => "TESTFR_ACT",
+                0x83 => "TESTFR_CON",
+                _ => "UNKNOWN",
+            });
+        }
+        Some(2 + length as usize)
+    }
+
+    fn set_asdu(&mut self, asdu: &[u8]) {
+        if asdu.len() < 6 {
+            return;
+        }
+        let type_id = asdu[0];
+        let sq = asdu[1] & 0x80 != 0;
+        let count = asdu[1] & 0x7F;
+        self.type_id = Some(type_id);
+        self.sq = Some(sq);
+        self.num_objects = Some(count);
+        self.cot = Some(asdu[2] & 0x3F);
+        self.negative = Some(asdu[2] & 0x40 != 0);
+        self.test = Some(asdu[2] & 0x80 != 0);
+        self.originator = Some(asdu[3]);
+        self.common_address = le_u16(asdu, 4);
+
+        let mut offset = 6;
+        /* SQ=1: a single IOA followed by consecutive elements */
+        let mut address = 0;
+        if sq {
+            address = match ioa(asdu, offset) {
+                Some(address) => address,
+                None => return,
+            };
+            offset += 3;
+        }
+        for i in 0..count as u32 {
+            if !sq {
+                address = match ioa(asdu, offset) {
+                    Some(address) => address,
+                    None => return,
+                };
+                offset += 3;
+            }
+            let (size, value, quality) = match element(type_id, &asdu[offset.min(asdu.len())..]) {
+                Some(element) => element,
+                None => return,
+            };
+            self.ioa.push(if sq { address + i } else { address });
+            self.value.push(value);
+            self.quality.push(quality);
+            offset += size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+
+    fn parse(payload: &[u8]) -> (Option<usize>, Iec104Attr) {
+        let mut attr = Iec104Attr::new(test_addresses(IEC104_PORT, 50000));
+        (attr.set_apdu(payload), attr)
+    }
+
+    #[test]
+    fn u_and_s_frames() {
+        let (size, attr) = parse(&[0x68, 0x04, 0x07, 0x00, 0x00, 0x00]);
+        assert_eq!(size, Some(6));
+        assert_eq!(attr.frame_format, "U");
+        assert_eq!(attr.u_function, Some("STARTDT_ACT"));
+        let (size, attr) = parse(&[0x68, 0x04, 0x01, 0x00, 0x7e, 0x14]);
+        assert_eq!(size, Some(6));
+        assert_eq!(attr.frame_format, "S");
+        assert_eq!(attr.recv_seq, Some(2623));
+        assert_eq!(attr.type_id, None);
+    }
+
+    #[test]
+    fn interrogation_command() {
+        let payload = [
+            0x68, 0x0e, 0x02, 0x00, 0x04, 0x00,
+            0x64, 0x01, 0x06, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x14,
+        ];
+        let (size, attr) = parse(&payload);
+        assert_eq!(size, Some(16));
+        assert_eq!(attr.frame_format, "I");
+        assert_eq!(attr.send_seq, Some(1));
+        assert_eq!(attr.recv_seq, Some(2));
+        assert_eq!(attr.type_id, Some(TypeValues::C_IC_NA_1));
+        assert_eq!(attr.cot, Some(6));
+        assert_eq!(attr.negative, Some(false));
+        assert_eq!(attr.common_address, Some(1));
+        assert_eq!(attr.ioa, vec![0]);
+        assert_eq!(attr.value, vec![None]);
+        assert_eq!(attr.quality, vec![0x14]);
+    }
+
+    #[test]
+    fn short_floats_in_sequence() {
+        let mut payload = vec![0x68, 0x17, 0x00, 0x00, 0x00, 0x00, 0x0d, 0x82, 0x14, 0x00, 0x01, 0x00, 0x10, 0x27, 0x00];
+        payload.extend_from_slice(&1.5f32.to_le_bytes());
+        payload.push(0x00);
+        payload.extend_from_slice(&(-2.0f32).to_le_bytes());
+        payload.push(0x80);
+        let (size, attr) = parse(&payload);
+        assert_eq!(size, Some(25));
+        assert_eq!(attr.type_id, Some(TypeValues::M_ME_NC_1));
+        assert_eq!(attr.sq, Some(true));
+        assert_eq!(attr.num_objects, Some(2));
+        assert_eq!(attr.cot, Some(20));
+        assert_eq!(attr.ioa, vec![10000, 10001]);
+        assert_eq!(attr.value, vec![Some(1.5), Some(-2.0)]);
+        assert_eq!(attr.quality, vec![0x00, 0x80]);
+    }
+
+    #[test]
+    fn apdus_in_one_segment() {
+        let payload = [0x68, 0x04, 0x43, 0x00, 0x00, 0x00, 0x68, 0x04, 0x83, 0x00, 0x00, 0x00];
+        let (size, attr) = parse(&payload);
+        assert_eq!(size, Some(6));
+        assert_eq!(attr.u_function, Some("TESTFR_ACT"));
+        let (_, attr) = parse(&payload[6..]);
+        assert_eq!(attr.u_function, Some("TESTFR_CON"));
+    }
+
+    #[test]
+    fn malformed_apdus() {
+        assert_eq!(parse(&[0x69, 0x04, 0x07, 0x00, 0x00, 0x00]).0, None);
+        assert_eq!(parse(&[0x68, 0x04, 0x07, 0x00]).0, None);
+        assert_eq!(parse(&[0x68, 0x02, 0x07, 0x00]).0, None);
+        assert_eq!(parse(&[0x68]).0, None);
+        /* second single point cut off, unknown type ID */
+        let (size, attr) = parse(&[0x68, 0x0f, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x01, 0x02]);
+        assert_eq!(size, Some(17));
+        assert_eq!(attr.ioa, vec![1]);
+        assert_eq!(attr.value, vec![Some(1.0)]);
+        let (_, attr) = parse(&[0x68, 0x0d, 0x00, 0x00, 0x00, 0x00, 0xff, 0x01, 0x03, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00]);
+        assert_eq!(attr.type_id, Some(0xff));
+        assert!(attr.ioa.is_empty());
+    }
+}
@@ -0,0 +1,582 @@
+//! IEC 61850 GOOSE (ethertype 0x88B8) and Sampled Values (0x88BA)
+//!
+//! +-------+--------+-----------+-----------+--------------------------
+//! | APPID | Length | Reserved1 | Reserved2 | APDU (BER, goosePdu/savPdu)
+//! +-------+--------+-----------+-----------+--------------------------
+//! |   2   |   2    |     2     |     2     | ...
+//!
+//! Reserved1 bit 15 is the simulation flag (Edition 2).
+
+use pnet::datalink::MacAddr;
+use pnet::packet::ethernet::EtherType;
+
+use super::bounded_map::BoundedMap;
+use super::{LinkAddresses, be_u16};
+
+pub const GOOSE_ETHERTYPE: EtherType = EtherType(0x88B8);
+pub const SV_ETHERTYPE: EtherType = EtherType(0x88BA);
+
+const MAX_STREAMS: usize = 1024;
+const MAX_DATA_DEPTH: usize = 8;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod GooseTagValues {
+    pub const GoosePdu: u8 = 0x61;
+    pub const GocbRef: u8 = 0x80;
+    pub const TimeAllowedToLive: u8 = 0x81;
+    pub const DatSet: u8 = 0x82;
+    pub const GoID: u8 = 0x83;
+    pub const T: u8 = 0x84;
+    pub const StNum: u8 = 0x85;
+    pub const SqNum: u8 = 0x86;
+    pub const Simulation: u8 = 0x87;
+    pub const ConfRev: u8 = 0x88;
+    pub const NdsCom: u8 = 0x89;
+    pub const NumDatSetEntries: u8 = 0x8A;
+    pub const AllData: u8 = 0xAB;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod DataTagValues {
+    pub const Array: u8 = 0xA1;
+    pub const Structure: u8 = 0xA2;
+    pub const Boolean: u8 = 0x83;
+    pub const BitString: u8 = 0x84;
+    pub const Integer: u8 = 0x85;
+    pub const Unsigned: u8 = 0x86;
+    pub const FloatingPoint: u8 = 0x87;
+    pub const OctetString: u8 = 0x89;
+    pub const VisibleString: u8 = 0x8A;
+    pub const UtcTime: u8 = 0x91;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod SvTagValues {
+    pub const SavPdu: u8 = 0x60;
+    pub const NoAsdu: u8 = 0x80;
+    pub const SeqAsdu: u8 = 0xA2;
+    pub const Asdu: u8 = 0x30;
+    pub const SvID: u8 = 0x80;
+    pub const DatSet: u8 = 0x81;
+    pub const SmpCnt: u8 = 0x82;
+    pub const ConfRev: u8 = 0x83;
+    pub const RefrTm: u8 = 0x84;
+    pub const SmpSynch: u8 = 0x85;
+    pub const SmpRate: u8 = 0x86;
+    pub const Sample: u8 = 0x87;
+    pub const SmpMod: u8 = 0x88;
+}
+
+#[derive(Debug)]
+pub struct GooseAttr {
+    pub link: LinkAddresses,
+    pub appid: u16,
+    pub length: u16,
+    pub simulation_bit: bool,
+    pub gocb_ref: Option<String>,
+    pub time_allowed_to_live: Option<u32>,
+    pub dat_set: Option<String>,
+    pub go_id: Option<String>,
+    pub t: Option<u64>, // nanoseconds since the epoch
+    pub st_num: Option<u32>,
+    pub sq_num: Option<u32>,
+    pub simulation: Option<bool>, // goosePdu simulation (test in Edition 1)
+    pub conf_rev: Option<u32>,
+    pub nds_com: Option<bool>,
+    pub num_dat_set_entries: Option<u32>,
+    pub values: Vec<Option<f64>>,
+    pub anomaly: Option<&'static str>,
+}
+
+#[derive(Debug)]
+pub struct SvAttr {
+    pub link: LinkAddresses,
+    pub appid: u16,
+    pub length: u16,
+    pub simulation_bit: bool,
+    pub no_asdu: u8,
+    pub sv_id: Option<String>,
+    pub dat_set: Option<String>,
+    pub smp_cnt: Option<u16>,
+    pub conf_rev: Option<u32>,
+    pub smp_synch: Option<u8>,
+    pub smp_rate: Option<u16>,
+    pub values: Vec<i32>,
+    pub quality: Vec<u32>,
+}
+
+// BER TLV: returns (tag, value start, value end)
+fn ber(data: &[u8], offset: usize) -> Option<(u8, usize, usize)> {
+    let tag = *data.get(offset)?;
+    let first = *data.get(offset + 1)?;
+    let (length, header) = match first {
+        0x00..=0x7F => (first as usize, 2),
+        0x81 => (*data.get(offset + 2)? as usize, 3),
+        0x82 => (be_u16(data, offset + 2)? as usize, 4),
+        _ => return None,
+    };
+    let end = offset + header + length;
+    if end > data.len() {
+        return None;
+    }
+    Some((tag, offset + header, end))
+}
+
+fn ber_unsigned(value: &[u8]) -> u64 {
+    value.iter().take(8).fold(0, |n, b| n << 8 | *b as u64)
+}
+
+fn ber_integer(value: &[u8]) -> i64 {
+    let sign = if value.first().map_or(false, |b| b & 0x80 != 0) { -1i64 } else { 0 };
+    value.iter().take(8).fold(sign, |n, b| n << 8 | *b as i64)
+}
+
+fn ber_string(value: &[u8]) -> String {
+    String::from_utf8_lossy(value).into_owned()
+}
+
+// Data sequence flattened depth-first: arrays and structures contribute
+// their members, strings and times are kept as None placeholders
+fn data_values(data: &[u8], depth: usize, values: &mut Vec<Option<f64>>) {
+    let mut offset = 0;
+    while let Some((tag, start, end)) = ber(data, offset) {
+        match tag {
+            DataTagValues::Array | DataTagValues::Structure => {
+                if depth < MAX_DATA_DEPTH {
+                    data_values(&data[start..end], depth + 1, values);
+                }
+            }
+            _ => values.push(data_value(tag, &data[start..end])),
+        }
+        offset = end;
+    }
+}
+
+// Data CHOICE as a number (strings and times have none)
+fn data_value(tag: u8, value: &[u8]) -> Option<f64> {
+    match tag {
+        DataTagValues::Boolean => Some(if value.first().map_or(false, |b| *b != 0) { 1.0 } else { 0.0 }),
+        DataTagValues::BitString => {
+            /* leading octet is the number of unused bits */
+            let unused = *value.first()? as u32;
+            Some((ber_unsigned(&value[1..]) >> unused.min(63)) as f64)
+        }
+        DataTagValues::Integer => Some(ber_integer(value) as f64),
+        DataTagValues::Unsigned => Some(ber_unsigned(value) as f64),
+        DataTagValues::FloatingPoint => {
+            /* exponent width | IEEE 754 single or double */
+            match value.len() {
+                5 => Some(f32::from_be_bytes([value[1], value[2], value[3], value[4]]) as f64),
+                9 => {
+                    let mut bytes = [0u8; 8];
+                    bytes.copy_from_slice(&value[1..9]);
+                    Some(f64::from_be_bytes(bytes))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+// APPID | Length | Reserved1 | Reserved2 (the APDU follows at offset 8)
+fn set_header(payload: &[u8]) -> Option<(u16, u16, bool)> {
+    let appid = be_u16(payload, 0)?;
+    let length = be_u16(payload, 2)?;
+    let reserved1 = be_u16(payload, 4)?;
+    be_u16(payload, 6)?;
+    Some((appid, length, reserved1 & 0x8000 != 0))
+}
+
+impl GooseAttr {
+    pub fn new(link: LinkAddresses) -> Self {
+        Self {
+            link: link,
+            appid: 0,
+            length: 0,
+            simulation_bit: false,
+            gocb_ref: None,
+            time_allowed_to_live: None,
+            dat_set: None,
+            go_id: None,
+            t: None,
+            st_num: None,
+            sq_num: None,
+            simulation: None,
+            conf_rev: None,
+            nds_com: None,
+            num_dat_set_entries: None,
+            values: Vec::new(),
+            anomaly: None,
+        }
+    }
+
+    // returns false when the frame does not carry a goosePdu
+    pub fn set_goose(&mut self, payload: &[u8]) -> bool {
+        let (appid, length, simulation_bit) = match set_header(payload) {
+            Some(header) => header,
+            None => return false,
+        };
+        self.appid = appid;
+        self.length = length;
+        self.simulation_bit = simulation_bit;
+        let (start, end) = match ber(payload, 8) {
+            Some((GooseTagValues::GoosePdu, start, end)) => (start, end),
+            _ => return false,
+        };
+        let pdu = &payload[..end];
+        let mut offset = start;
+        while let Some((tag, start, end)) = ber(pdu, offset) {
+            let value = &pdu[start..end];
+            match tag {
+                GooseTagValues::GocbRef => self.gocb_ref = Some(ber_string(value)),
+                GooseTagValues::TimeAllowedToLive => self.time_allowed_to_live = Some(ber_unsigned(value) as u32),
+                GooseTagValues::DatSet => self.dat_set = Some(ber_string(value)),
+                GooseTagValues::GoID => self.go_id = Some(ber_string(value)),
+                GooseTagValues::T => {
+                    /* SecondSinceEpoch (4) | FractionOfSecond (3) | TimeQuality (1) */
+                    if value.len() == 8 {
+                        let seconds = ber_unsigned(&value[0..4]);
+                        let fraction = ber_unsigned(&value[4..7]);
+                        self.t = Some(seconds * 1_000_000_000 + (fraction * 1_000_000_000 >> 24));
+                    }
+                }
+                GooseTagValues::StNum => self.st_num = Some(ber_unsigned(value) as u32),
+                GooseTagValues::SqNum => self.sq_num = Some(ber_unsigned(value) as u32),
+                GooseTagValues::Simulation => self.simulation = Some(value.first().map_or(false, |b| *b != 0)),
+                GooseTagValues::ConfRev => self.conf_rev = Some(ber_unsigned(value) as u32),
+                GooseTagValues::NdsCom => self.nds_com = Some(value.first().map_or(false, |b| *b != 0)),
+                GooseTagValues::NumDatSetEntries => self.num_dat_set_entries = Some(ber_unsigned(value) as u32),
+                GooseTagValues::AllData => data_values(value, 0, &mut self.values),
+                _ => {}
+            }
+            offset = end;
+        }
+        true
+    }
+}
+
+impl SvAttr {
+    pub fn new(link: LinkAddresses) -> Self {
+        Self {
+            link: link,
+            appid: 0,
+            length: 0,
+            simulation_bit: false,
+            no_asdu: 0,
+            sv_id: None,
+            dat_set: None,
+            smp_cnt: None,
+            conf_rev: None,
+            smp_synch: None,
+            smp_rate: None,
+            values: Vec::new(),
+            quality: Vec::new(),
+        }
+    }
+
+    fn set_asdu(&mut self, asdu: &[u8]) {
+        let mut offset = 0;
+        while let Some((tag, start, end)) = ber(asdu, offset) {
+            let value = &asdu[start..end];
+            match tag {
+                SvTagValues::SvID => self.sv_id = Some(ber_string(value)),
+                SvTagValues::DatSet => self.dat_set = Some(ber_string(value)),
+                SvTagValues::SmpCnt => self.smp_cnt = Some(ber_unsigned(value) as u16),
+                SvTagValues::ConfRev => self.conf_rev = Some(ber_unsigned(value) as u32),
+                SvTagValues::SmpSynch => self.smp_synch = value.first().cloned(),
+                SvTagValues::SmpRate => self.smp_rate = Some(ber_unsigned(value) as u16),
+                SvTagValues::Sample => {
+                    /* (Value Int32 | Quality) ... as in IEC 61850-9-2 LE */
+                    for chunk in value.chunks_exact(8) {
+                        self.values.push(i32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+                        self.quality.push(u32::from_be_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]));
+                    }
+                }
+                _ => {}
+            }
+            offset = end;
+        }
+    }
+}
+
+// one record per ASDU of a savPdu
+pub fn sv_records(link: &LinkAddresses, payload: &[u8]) -> Vec<SvAttr> {
+    let mut records = Vec::new();
+    let (appid, length, simulation_bit) = match set_header(payload) {
+        Some(header) => header,
+        None => return records,
+    };
+    let (start, end) = match ber(payload, 8) {
+        Some((SvTagValues::SavPdu, start, end)) => (start, end),
+        _ => return records,
+    };
+    let pdu = &payload[..end];
+    let mut no_asdu = 0;
+    let mut offset = start;
+    while let Some((tag, start, end)) = ber(pdu, offset) {
+        match tag {
+            SvTagValues::NoAsdu => no_asdu = ber_unsigned(&pdu[start..end]) as u8,
+            SvTagValues::SeqAsdu => {
+                let seq_asdu = &pdu[..end];
+                let mut offset = start;
+                while let Some((SvTagValues::Asdu, start, end)) = ber(seq_asdu, offset) {
+                    let mut sv_attr = SvAttr::new(link.clone());
+                    sv_attr.appid = appid;
+                    sv_attr.length = length;
+                    sv_attr.simulation_bit = simulation_bit;
+                    sv_attr.no_asdu = no_asdu;
+                    sv_attr.set_asdu(&seq_asdu[start..end]);
+                    records.push(sv_attr);
+                    offset = end;
+                }
+            }
+            _ => {}
+        }
+        offset = end;
+    }
+    records
+}
+
+// Last (stNum, sqNum) per publisher and control block; the least recently
+// seen control blocks make room once MAX_STREAMS are tracked
+#[derive(Default)]
+pub struct GooseTracker {
+    last: BoundedMap<(MacAddr, String), (u32, u32), MAX_STREAMS>,
+}
+
+impl GooseTracker {
+    // stNum increments (with sqNum reset to 0) on a state change,
+    // sqNum increments on each retransmission of the same state and rolls
+    // over after u32::MAX (to 0, or to 1 in Edition 2)
+    pub fn check(&mut self, goose_attr: &mut GooseAttr) {
+        let (gocb_ref, st_num, sq_num) = match (&goose_attr.gocb_ref, goose_attr.st_num, goose_attr.sq_num) {
+            (Some(gocb_ref), Some(st_num), Some(sq_num)) => (gocb_ref.clone(), st_num, sq_num),
+            _ => return,
+        };
+        let key = (goose_attr.link.src_mac, gocb_ref);
+        if let Some((last_st_num, last_sq_num)) = self.last.insert(key, (st_num, sq_num)) {
+            goose_attr.anomaly = if st_num == last_st_num {
+                if sq_num == last_sq_num.wrapping_add(1) || (last_sq_num == u32::MAX && sq_num == 1) {
+                    None
+                } else if sq_num <= last_sq_num {
+                    Some("SqNumReplay")
+                } else {
+                    Some("SqNumGap")
+                }
+            } else if st_num == last_st_num.wrapping_add(1) {
+                if sq_num == 0 { None } else { Some("SqNumNotReset") }
+            } else if st_num < last_st_num {
+                Some("StNumDecrease")
+            } else {
+                Some("StNumGap")
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_link;
+
+    fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+        let mut data = vec![tag];
+        if value.len() < 0x80 {
+            data.push(value.len() as u8);
+        } else {
+            data.push(0x81);
+            data.push(value.len() as u8);
+        }
+        data.extend_from_slice(value);
+        data
+    }
+
+    // APPID 0x0001 | Length | Reserved1 | Reserved2 | APDU
+    fn frame(reserved1: u16, apdu: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x00, 0x01];
+        data.extend_from_slice(&(8 + apdu.len() as u16).to_be_bytes());
+        data.extend_from_slice(&reserved1.to_be_bytes());
+        data.extend_from_slice(&[0x00, 0x00]);
+        data.extend_from_slice(apdu);
+        data
+    }
+
+    fn goose_pdu(st_num: u8, sq_num: u8, all_data: &[u8]) -> Vec<u8> {
+        let mut pdu = tlv(GooseTagValues::GocbRef, b"IED1LD0/LLN0$GO$gcb01");
+        pdu.extend(tlv(GooseTagValues::TimeAllowedToLive, &[0x07, 0xd0]));
+        pdu.extend(tlv(GooseTagValues::DatSet, b"IED1LD0/LLN0$ds01"));
+        pdu.extend(tlv(GooseTagValues::GoID, b"gcb01"));
+        pdu.extend(tlv(GooseTagValues::T, &[0x5f, 0x5e, 0x10, 0x00, 0x80, 0x00, 0x00, 0x0a]));
+        pdu.extend(tlv(GooseTagValues::StNum, &[st_num]));
+        pdu.extend(tlv(GooseTagValues::SqNum, &[sq_num]));
+        pdu.extend(tlv(GooseTagValues::Simulation, &[0x00]));
+        pdu.extend(tlv(GooseTagValues::ConfRev, &[0x01]));
+        pdu.extend(tlv(GooseTagValues::NdsCom, &[0x00]));
+        pdu.extend(tlv(GooseTagValues::NumDatSetEntries, &[0x04]));
+        pdu.extend(tlv(GooseTagValues::AllData, all_data));
+        tlv(GooseTagValues::GoosePdu, &pdu)
+    }
+
+    fn goose(st_num: u8, sq_num: u8) -> GooseAttr {
+        let mut attr = GooseAttr::new(test_link());
+        assert!(attr.set_goose(&frame(0, &goose_pdu(st_num, sq_num, &[0x83, 0x01, 0x01]))));
+        attr
+    }
+
+    #[test]
+    fn goose_header_fields() {
+        let attr = goose(3, 7);
+        assert_eq!(attr.appid, 1);
+        assert!(!attr.simulation_bit);
+        assert_eq!(attr.gocb_ref.as_deref(), Some("IED1LD0/LLN0$GO$gcb01"));
+        assert_eq!(attr.time_allowed_to_live, Some(2000));
+        assert_eq!(attr.dat_set.as_deref(), Some("IED1LD0/LLN0$ds01"));
+        assert_eq!(attr.go_id.as_deref(), Some("gcb01"));
+        assert_eq!(attr.t, Some(1_600_000_000_500_000_000));
+        assert_eq!(attr.st_num, Some(3));
+        assert_eq!(attr.sq_num, Some(7));
+        assert_eq!(attr.simulation, Some(false));
+        assert_eq!(attr.num_dat_set_entries, Some(4));
+        assert_eq!(attr.values, vec![Some(1.0)]);
+    }
+
+    #[test]
+    fn nested_data_is_flattened() {
+        /* boolean, {quality, float}, [integer, visible-string] */
+        let mut all_data = vec![0x83, 0x01, 0x01];
+        let mut structure = vec![0x84, 0x03, 0x03, 0x00, 0x08];
+        structure.extend_from_slice(&[0x87, 0x05, 0x08, 0x42, 0xc8, 0x00, 0x00]);
+        all_data.extend(tlv(DataTagValues::Structure, &structure));
+        let array = [0x85, 0x01, 0xfe, 0x8a, 0x03, b'a', b'b', b'c'];
+        all_data.extend(tlv(DataTagValues::Array, &array));
+        let mut attr = GooseAttr::new(test_link());
+        assert!(attr.set_goose(&frame(0x8000, &goose_pdu(1, 0, &all_data))));
+        assert!(attr.simulation_bit);
+        assert_eq!(attr.values, vec![Some(1.0), Some(1.0), Some(100.0), Some(-2.0), None]);
+    }
+
+    #[test]
+    fn nesting_depth_is_bounded() {
+        let mut data = vec![0x85, 0x01, 0x05];
+        for _ in 0..MAX_DATA_DEPTH + 2 {
+            data = tlv(DataTagValues::Structure, &data);
+        }
+        let mut values = Vec::new();
+        data_values(&data, 0, &mut values);
+        assert!(values.is_empty());
+        let mut values = Vec::new();
+        data_values(&data[4..], 0, &mut values);
+        assert_eq!(values, vec![Some(5.0)]);
+    }
+
+    #[test]
+    fn malformed_goose() {
+        let mut attr = GooseAttr::new(test_link());
+        assert!(!attr.set_goose(&[0x00, 0x01, 0x00, 0x08, 0x00, 0x00]));
+        assert!(!attr.set_goose(&frame(0, &tlv(SvTagValues::SavPdu, &[0x80, 0x01, 0x01]))));
+        let mut data = frame(0, &goose_pdu(1, 0, &[0x83, 0x01, 0x01]));
+        data.truncate(40);
+        assert!(!attr.set_goose(&data));
+        /* inner element length past the PDU: decoding stops there */
+        let mut attr = GooseAttr::new(test_link());
+        assert!(attr.set_goose(&frame(0, &tlv(GooseTagValues::GoosePdu, &[0x85, 0x01, 0x02, 0x86, 0x09, 0x00]))));
+        assert_eq!(attr.st_num, Some(2));
+        assert_eq!(attr.sq_num, None);
+    }
+
+    #[test]
+    fn goose_sequence_anomalies() {
+        let mut tracker = GooseTracker::default();
+        let mut check = |st_num, sq_num| {
+            let mut attr = goose(st_num, sq_num);
+            tracker.check(&mut attr);
+            attr.anomaly
+        };
+        assert_eq!(check(1, 0), None);
+        assert_eq!(check(1, 1), None);
+        assert_eq!(check(1, 1), Some("SqNumReplay"));
+        assert_eq!(check(1, 5), Some("SqNumGap"));
+        assert_eq!(check(2, 0), None);
+        assert_eq!(check(3, 2), Some("SqNumNotReset"));
+        assert_eq!(check(1, 0), Some("StNumDecrease"));
+        assert_eq!(check(9, 0), Some("StNumGap"));
+    }
+
+    #[test]
+    fn sq_num_rollover() {
+        let mut tracker = GooseTracker::default();
+        let mut check = |sq_num| {
+            let mut attr = goose(1, 0);
+            attr.sq_num = Some(sq_num);
+            tracker.check(&mut attr);
+            attr.anomaly
+        };
+        check(u32::MAX - 1);
+        assert_eq!(check(u32::MAX), None);
+        assert_eq!(check(0), None);
+        check(u32::MAX);
+        assert_eq!(check(1), None);
+        check(u32::MAX);
+        assert_eq!(check(u32::MAX - 3), Some("SqNumReplay"));
+    }
+
+    #[test]
+    fn tracker_is_bounded() {
+        let mut tracker = GooseTracker::default();
+        for i in 0..MAX_STREAMS + 10 {
+            let mut attr = goose(1, 0);
+            attr.gocb_ref = Some(format!("IED1LD0/LLN0$GO$gcb{}", i));
+            tracker.check(&mut attr);
+            assert!(tracker.last.len() <= MAX_STREAMS);
+        }
+        /* an active control block keeps its sequence state */
+        let mut tracker = GooseTracker::default();
+        let mut sq_num = 0;
+        for i in 0..MAX_STREAMS + 10 {
+            if i % 64 == 0 {
+                let mut attr = goose(1, sq_num);
+                tracker.check(&mut attr);
+                assert_eq!(attr.anomaly, None);
+                sq_num += 1;
+            }
+            let mut attr = goose(1, 0);
+            attr.gocb_ref = Some(format!("IED2LD0/LLN0$GO$gcb{}", i));
+            tracker.check(&mut attr);
+        }
+        let mut attr = goose(1, 0);
+        tracker.check(&mut attr);
+        assert_eq!(attr.anomaly, Some("SqNumReplay"));
+    }
+
+    #[test]
+    fn sampled_values() {
+        let mut asdu = tlv(SvTagValues::SvID, b"MU01");
+        asdu.extend(tlv(SvTagValues::SmpCnt, &[0x00, 0x2a]));
+        asdu.extend(tlv(SvTagValues::ConfRev, &[0x00, 0x00, 0x00, 0x01]));
+        asdu.extend(tlv(SvTagValues::SmpSynch, &[0x02]));
+        asdu.extend(tlv(SvTagValues::Sample, &[0x00, 0x00, 0x03, 0xe8, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xfc, 0x18, 0x00, 0x00, 0x20, 0x00]));
+        let mut seq_asdu = tlv(SvTagValues::Asdu, &asdu);
+        seq_asdu.extend(tlv(SvTagValues::Asdu, &asdu));
+        let mut pdu = tlv(SvTagValues::NoAsdu, &[0x02]);
+        pdu.extend(tlv(SvTagValues::SeqAsdu, &seq_asdu));
+        let records = sv_records(&test_link(), &frame(0, &tlv(SvTagValues::SavPdu, &pdu)));
+        assert_eq!(records.len(), 2);
+        let attr = &records[0];
+        assert_eq!(attr.no_asdu, 2);
+        assert_eq!(attr.sv_id.as_deref(), Some("MU01"));
+        assert_eq!(attr.smp_cnt, Some(42));
+        assert_eq!(attr.conf_rev, Some(1));
+        assert_eq!(attr.smp_synch, Some(2));
+        assert_eq!(attr.values, vec![1000, -1000]);
+        assert_eq!(attr.quality, vec![0, 0x2000]);
+        assert!(sv_records(&test_link(), &frame(0, &tlv(GooseTagValues::GoosePdu, &pdu))).is_empty());
+        assert!(sv_records(&test_link(), &[0x40, 0x00]).is_empty());
+    }
+}
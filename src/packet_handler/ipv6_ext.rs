@@ -0,0 +1,144 @@
+//! IPv6 extension header chain
+//!
+//! Hop-by-Hop / Routing / Destination Options / Mobility
+//! +-------------+-------------+-----------------------------+
+//! | Next Header | Hdr Ext Len | ... ((Hdr Ext Len + 1) * 8) |
+//! +-------------+-------------+-----------------------------+
+//! Routing: Next Header | Hdr Ext Len | Routing Type | Segments Left | ...
+//! Fragment: Next Header | Reserved | Fragment Offset (13) | Res | M | Identification (8 bytes)
+//! AH: Next Header | Payload Len ((Payload Len + 2) * 4) | ...
+
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+
+use super::be_u16;
+
+// guards against crafted chains
+const MAX_EXTENSION_HEADERS: usize = 16;
+
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionHeaders {
+    // next-header values in chain order
+    pub headers: Vec<u8>,
+    // deprecated source routing (RFC 5095)
+    pub routing_type0: bool,
+    // fragment with a non-zero offset (no upper-layer header)
+    pub later_fragment: bool,
+}
+
+// Returns the upper-layer protocol and its data
+pub fn walk(next_header: IpNextHeaderProtocol, payload: &[u8]) -> (IpNextHeaderProtocol, &[u8], ExtensionHeaders) {
+    let mut extensions = ExtensionHeaders::default();
+    let mut protocol = next_header;
+    let mut data = payload;
+    while extensions.headers.len() < MAX_EXTENSION_HEADERS {
+        let length = match protocol {
+            IpNextHeaderProtocols::Hopopt
+            | IpNextHeaderProtocols::Ipv6Route
+            | IpNextHeaderProtocols::Ipv6Opts
+            | IpNextHeaderProtocols::MobilityHeader => match data.get(1) {
+                Some(length) => (*length as usize + 1) * 8,
+                None => break,
+            },
+            IpNextHeaderProtocols::Ipv6Frag => 8,
+            IpNextHeaderProtocols::Ah => match data.get(1) {
+                Some(length) => (*length as usize + 2) * 4,
+                None => break,
+            },
+            _ => break,
+        };
+        if data.len() < length {
+            break;
+        }
+        match protocol {
+            IpNextHeaderProtocols::Ipv6Route => {
+                if data[2] == 0 {
+                    extensions.routing_type0 = true;
+                }
+            }
+            IpNextHeaderProtocols::Ipv6Frag => {
+                if be_u16(data, 2).unwrap_or(0) & 0xFFF8 != 0 {
+                    extensions.later_fragment = true;
+                }
+            }
+            _ => {}
+        }
+        extensions.headers.push(protocol.0);
+        protocol = IpNextHeaderProtocol::new(data[0]);
+        data = &data[length..];
+        if extensions.later_fragment {
+            break;
+        }
+    }
+    (protocol, data, extensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hop-by-Hop (router alert) -> Destination Options (PadN) -> TCP
+    #[test]
+    fn chain_to_upper_layer() {
+        let mut payload = vec![60, 0, 0x05, 0x02, 0x00, 0x00, 0x01, 0x00];
+        payload.extend_from_slice(&[6, 1, 0x01, 0x0C, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        payload.extend_from_slice(&[0xC3, 0x50, 0x01, 0xF6]);
+        let (protocol, data, extensions) = walk(IpNextHeaderProtocols::Hopopt, &payload);
+        assert_eq!(protocol, IpNextHeaderProtocols::Tcp);
+        assert_eq!(data, &[0xC3, 0x50, 0x01, 0xF6]);
+        assert_eq!(extensions.headers, vec![0, 60]);
+        assert!(!extensions.routing_type0);
+        assert!(!extensions.later_fragment);
+    }
+
+    #[test]
+    fn routing_and_fragments() {
+        /* Routing type 0 -> first fragment (offset 0, M set) -> UDP */
+        let mut payload = vec![44, 0, 0, 1, 0, 0, 0, 0];
+        payload.extend_from_slice(&[17, 0, 0x00, 0x01, 0x12, 0x34, 0x56, 0x78]);
+        payload.extend_from_slice(&[0x00, 0x35]);
+        let (protocol, data, extensions) = walk(IpNextHeaderProtocols::Ipv6Route, &payload);
+        assert_eq!(protocol, IpNextHeaderProtocols::Udp);
+        assert_eq!(data, &[0x00, 0x35]);
+        assert_eq!(extensions.headers, vec![43, 44]);
+        assert!(extensions.routing_type0);
+        assert!(!extensions.later_fragment);
+        /* later fragment (offset 185 * 8) stops the walk */
+        let payload = [17, 0, 0x05, 0xC8, 0x12, 0x34, 0x56, 0x78, 0xAA, 0xBB];
+        let (protocol, data, extensions) = walk(IpNextHeaderProtocols::Ipv6Frag, &payload);
+        assert_eq!(protocol, IpNextHeaderProtocols::Udp);
+        assert_eq!(data, &[0xAA, 0xBB]);
+        assert!(extensions.later_fragment);
+    }
+
+    #[test]
+    fn authentication_header() {
+        /* AH with a 96-bit ICV: Payload Len 4 -> 24 bytes */
+        let mut payload = vec![58, 4, 0, 0];
+        payload.extend_from_slice(&[0u8; 20]);
+        payload.extend_from_slice(&[0x80, 0x00]);
+        let (protocol, data, extensions) = walk(IpNextHeaderProtocols::Ah, &payload);
+        assert_eq!(protocol, IpNextHeaderProtocols::Icmpv6);
+        assert_eq!(data, &[0x80, 0x00]);
+        assert_eq!(extensions.headers, vec![51]);
+    }
+
+    #[test]
+    fn truncated_and_crafted_chains() {
+        /* header longer than the data: stays at the extension header */
+        let payload = [6, 1, 0, 0, 0, 0, 0, 0];
+        let (protocol, data, extensions) = walk(IpNextHeaderProtocols::Ipv6Opts, &payload);
+        assert_eq!(protocol, IpNextHeaderProtocols::Ipv6Opts);
+        assert_eq!(data.len(), 8);
+        assert!(extensions.headers.is_empty());
+        let (protocol, _, _) = walk(IpNextHeaderProtocols::Hopopt, &[6]);
+        assert_eq!(protocol, IpNextHeaderProtocols::Hopopt);
+        let (protocol, _, _) = walk(IpNextHeaderProtocols::Ipv6Frag, &[17, 0, 0, 0]);
+        assert_eq!(protocol, IpNextHeaderProtocols::Ipv6Frag);
+        /* endless Destination Options chain is cut off */
+        let payload: Vec<u8> = [60u8, 0, 1, 4, 0, 0, 0, 0].repeat(MAX_EXTENSION_HEADERS + 4);
+        let (protocol, data, extensions) = walk(IpNextHeaderProtocols::Ipv6Opts, &payload);
+        assert_eq!(protocol, IpNextHeaderProtocols::Ipv6Opts);
+        assert_eq!(extensions.headers.len(), MAX_EXTENSION_HEADERS);
+        assert_eq!(data.len(), 4 * 8);
+    }
+}
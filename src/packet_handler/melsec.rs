@@ -0,0 +1,332 @@
+//! MELSEC communication protocol (SLMP / MC protocol 3E and 4E binary frames)
+//!
+//! 3E request
+//! +--------+--------+--------+---------+---------+--------+-------+---------+------------+------
+//! | Sub    | Network| PC No. | I/O No. | Station | Data   | Timer | Command | Subcommand | Data
+//! | header |  No.   |        |  (le)   |   No.   | Length |       |         |            |
+//! +--------+--------+--------+---------+---------+--------+-------+---------+------------+------
+//! |  0x5000|   1    |   1    |    2    |    1    |   2    |   2   |    2    |     2      | ...
+//!
+//! 3E response
+//! |  0xD000|   1    |   1    |    2    |    1    |   2    | End Code (2) | Data ...
+//!
+//! 4E frames insert a serial number (2) and a reserved word (2) after the subheader
+//! (0x5400 request, 0xD400 response).
+
+use super::{Addresses, le_u16, le_u32};
+
+// MC protocol ports are set per module; the default range covers the usual
+// GX Works settings and can be changed with e.g. ARROWS_MELSEC_PORTS=1025-1030
+pub const MELSEC_PORTS_ENV: &str = "ARROWS_MELSEC_PORTS";
+pub const MELSEC_PORT_MIN: u16 = 5000;
+pub const MELSEC_PORT_MAX: u16 = 5010;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod SubheaderValues {
+    // read little-endian, i.e. wire bytes 50 00 -> 0x0050
+    pub const Request3E: u16 = 0x0050;
+    pub const Response3E: u16 = 0x00D0;
+    pub const Request4E: u16 = 0x0054;
+    pub const Response4E: u16 = 0x00D4;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod CommandValues {
+    pub const BatchRead: u16 = 0x0401;
+    pub const BatchWrite: u16 = 0x1401;
+    pub const RandomRead: u16 = 0x0403;
+    pub const RandomWrite: u16 = 0x1402;
+    pub const BlockRead: u16 = 0x0406;
+    pub const BlockWrite: u16 = 0x1406;
+    pub const MonitorRegister: u16 = 0x0801;
+    pub const Monitor: u16 = 0x0802;
+    pub const ReadTypeName: u16 = 0x0101;
+    pub const RemoteRun: u16 = 0x1001;
+    pub const RemoteStop: u16 = 0x1002;
+    pub const RemotePause: u16 = 0x1003;
+    pub const RemoteLatchClear: u16 = 0x1005;
+    pub const RemoteReset: u16 = 0x1006;
+}
+
+#[derive(Debug)]
+pub struct MelsecAttr {
+    pub addresses: Addresses,
+    pub subheader: u16,
+    pub serial_number: Option<u16>,
+    pub network_number: u8,
+    pub station_number: u8,
+    pub module_io: u16,
+    pub module_station: u8,
+    pub data_length: u16,
+    // request
+    pub monitoring_timer: Option<u16>,
+    pub command: Option<u16>,
+    pub subcommand: Option<u16>,
+    pub device_code: Vec<u16>,
+    pub device_number: Vec<u32>,
+    pub points: Option<u16>,
+    // response
+    pub end_code: Option<u16>,
+}
+
+impl MelsecAttr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            subheader: 0,
+            serial_number: None,
+            network_number: 0,
+            station_number: 0,
+            module_io: 0,
+            module_station: 0,
+            data_length: 0,
+            monitoring_timer: None,
+            command: None,
+            subcommand: None,
+            device_code: Vec::new(),
+            device_number: Vec::new(),
+            points: None,
+            end_code: None,
+        }
+    }
+
+    // returns the frame size, or None when the data is not a binary 3E/4E frame
+    pub fn set_frame(&mut self, data: &[u8]) -> Option<usize> {
+        let subheader = le_u16(data, 0)?;
+        let offset = match subheader {
+            SubheaderValues::Request3E | SubheaderValues::Response3E => 2,
+            SubheaderValues::Request4E | SubheaderValues::Response4E => {
+                /* Serial No. | 0x0000 */
+                if le_u16(data, 4)? != 0 {
+                    return None;
+                }
+                self.serial_number = le_u16(data, 2);
+                6
+            }
+            _ => return None,
+        };
+        self.subheader = subheader;
+        self.network_number = *data.get(offset)?;
+        self.station_number = *data.get(offset + 1)?;
+        self.module_io = le_u16(data, offset + 2)?;
+        self.module_station = *data.get(offset + 4)?;
+        self.data_length = le_u16(data, offset + 5)?;
+        let size = offset + 7 + self.data_length as usize;
+        let body = data.get(offset + 7..size)?;
+        /* a request carries at least Timer, Command and Subcommand, a response its End Code */
+        let min_body = match subheader {
+            SubheaderValues::Request3E | SubheaderValues::Request4E => 6,
+            _ => 2,
+        };
+        if body.len() < min_body {
+            return None;
+        }
+        match subheader {
+            SubheaderValues::Request3E | SubheaderValues::Request4E => self.set_request(body),
+            _ => {
+                self.end_code = le_u16(body, 0);
+            }
+        }
+        Some(size)
+    }
+
+    fn set_request(&mut self, body: &[u8]) {
+        /* Monitoring Timer | Command | Subcommand | Request Data */
+        self.monitoring_timer = le_u16(body, 0);
+        self.command = le_u16(body, 2);
+        self.subcommand = le_u16(body, 4);
+        let (command, subcommand) = match (self.command, self.subcommand) {
+            (Some(command), Some(subcommand)) => (command, subcommand),
+            _ => return,
+        };
+        /* subcommand 0x0002/0x0003 (iQ-R) widens device No. to 4 bytes and device code to 2 */
+        let wide = subcommand & 0x0002 != 0;
+        let device_size = if wide { 6 } else { 4 };
+        let data = &body[6.min(body.len())..];
+        match command {
+            CommandValues::BatchRead | CommandValues::BatchWrite => {
+                /* Head Device No. | Device Code | Number of Points | (Write Data) */
+                if self.set_device(data, wide) {
+                    self.points = le_u16(data, device_size);
+                }
+            }
+            CommandValues::RandomRead | CommandValues::MonitorRegister => {
+                /* Word Points | Dword Points | Device ... */
+                if data.len() < 2 {
+                    return;
+                }
+                let total = data[0] as usize + data[1] as usize;
+                self.points = Some(total as u16);
+                for i in 0..total {
+                    if !self.set_device(&data[(2 + i * device_size).min(data.len())..], wide) {
+                        break;
+                    }
+                }
+            }
+            CommandValues::RandomWrite => {
+                /* Word Points | Dword Points | (Device, Write Data) ... */
+                if data.len() < 2 {
+                    return;
+                }
+                let words = data[0] as usize;
+                let dwords = data[1] as usize;
+                self.points = Some((words + dwords) as u16);
+                /* bit devices (subcommand 0x0001/0x0003) are written point by point */
+                let bit = subcommand & 0x0001 != 0;
+                let word_size = if bit { device_size + 1 } else { device_size + 2 };
+                let mut offset = 2;
+                for i in 0..words + dwords {
+                    if !self.set_device(&data[offset.min(data.len())..], wide) {
+                        break;
+                    }
+                    offset += if i < words { word_size } else { device_size + 4 };
+                }
+            }
+            CommandValues::BlockRead | CommandValues::BlockWrite => {
+                /* Word Blocks | Bit Blocks | (Device, Points) ... */
+                if data.len() < 2 {
+                    return;
+                }
+                let blocks = data[0] as usize + data[1] as usize;
+                let mut offset = 2;
+                let mut points: u16 = 0;
+                for _ in 0..blocks {
+                    if !self.set_device(&data[offset.min(data.len())..], wide) {
+                        break;
+                    }
+                    let block_points = le_u16(data, offset + device_size).unwrap_or(0);
+                    points = points.saturating_add(block_points);
+                    offset += device_size + 2;
+                    if command == CommandValues::BlockWrite {
+                        offset += block_points as usize * 2;
+                    }
+                }
+                self.points = Some(points);
+            }
+            _ => {}
+        }
+    }
+
+    // Device No. (3 or 4 bytes) | Device Code (1 or 2 bytes)
+    fn set_device(&mut self, data: &[u8], wide: bool) -> bool {
+        let (number, code) = if wide {
+            match (le_u32(data, 0), le_u16(data, 4)) {
+                (Some(number), Some(code)) => (number, code),
+                _ => return false,
+            }
+        } else {
+            match data.get(0..4) {
+                Some(b) => (u32::from_le_bytes([b[0], b[1], b[2], 0]), b[3] as u16),
+                None => return false,
+            }
+        };
+        self.device_number.push(number);
+        self.device_code.push(code);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::{melsec_records, test_addresses, Record};
+
+    const BATCH_READ_3E: [u8; 21] = [
+        0x50, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x0c, 0x00, 0x10, 0x00,
+        0x01, 0x04, 0x00, 0x00, 0x64, 0x00, 0x00, 0xa8, 0x0a, 0x00,
+    ];
+
+    fn parse(data: &[u8]) -> (Option<usize>, MelsecAttr) {
+        let mut attr = MelsecAttr::new(test_addresses(50000, MELSEC_PORT_MIN));
+        (attr.set_frame(data), attr)
+    }
+
+    #[test]
+    fn batch_read_3e() {
+        let (size, attr) = parse(&BATCH_READ_3E);
+        assert_eq!(size, Some(21));
+        assert_eq!(attr.subheader, SubheaderValues::Request3E);
+        assert_eq!(attr.network_number, 0);
+        assert_eq!(attr.station_number, 0xff);
+        assert_eq!(attr.module_io, 0x03ff);
+        assert_eq!(attr.data_length, 12);
+        assert_eq!(attr.monitoring_timer, Some(0x10));
+        assert_eq!(attr.command, Some(CommandValues::BatchRead));
+        assert_eq!(attr.subcommand, Some(0));
+        assert_eq!(attr.device_number, vec![100]);
+        assert_eq!(attr.device_code, vec![0xa8]);
+        assert_eq!(attr.points, Some(10));
+    }
+
+    #[test]
+    fn response_and_4e_frames() {
+        let (size, attr) = parse(&[0xd0, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x06, 0x00, 0x00, 0x00, 0x34, 0x12, 0x78, 0x56]);
+        assert_eq!(size, Some(15));
+        assert_eq!(attr.end_code, Some(0));
+        assert_eq!(attr.command, None);
+        let mut frame = vec![0x54, 0x00, 0x34, 0x12, 0x00, 0x00];
+        frame.extend_from_slice(&BATCH_READ_3E[2..]);
+        let (size, attr) = parse(&frame);
+        assert_eq!(size, Some(25));
+        assert_eq!(attr.serial_number, Some(0x1234));
+        assert_eq!(attr.device_number, vec![100]);
+    }
+
+    #[test]
+    fn random_read_and_wide_devices() {
+        let (_, attr) = parse(&[
+            0x50, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x10, 0x00, 0x10, 0x00,
+            0x03, 0x04, 0x00, 0x00, 0x02, 0x00, 0x64, 0x00, 0x00, 0xa8, 0xc8, 0x00, 0x00, 0xa8,
+        ]);
+        assert_eq!(attr.command, Some(CommandValues::RandomRead));
+        assert_eq!(attr.points, Some(2));
+        assert_eq!(attr.device_number, vec![100, 200]);
+        /* iQ-R subcommand 0x0002: 4-byte device No., 2-byte device code */
+        let (_, attr) = parse(&[
+            0x50, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x0e, 0x00, 0x10, 0x00,
+            0x01, 0x04, 0x02, 0x00, 0x64, 0x00, 0x00, 0x00, 0xa8, 0x00, 0x0a, 0x00,
+        ]);
+        assert_eq!(attr.device_number, vec![100]);
+        assert_eq!(attr.device_code, vec![0xa8]);
+        assert_eq!(attr.points, Some(10));
+    }
+
+    #[test]
+    fn remote_stop() {
+        let (_, attr) = parse(&[0x50, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x08, 0x00, 0x10, 0x00, 0x02, 0x10, 0x00, 0x00, 0x01, 0x00]);
+        assert_eq!(attr.command, Some(CommandValues::RemoteStop));
+        assert!(attr.device_number.is_empty());
+    }
+
+    #[test]
+    fn malformed_frames() {
+        /* ASCII frame, data length past the end, 4E reserved word set, request without a command */
+        assert_eq!(parse(b"500000FF03FF00").0, None);
+        assert_eq!(parse(&BATCH_READ_3E[..20]).0, None);
+        assert_eq!(parse(&[0x54, 0x00, 0x34, 0x12, 0x01, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x02, 0x00, 0x00, 0x00]).0, None);
+        assert_eq!(parse(&[0x50, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x02, 0x00, 0x10, 0x00]).0, None);
+        assert_eq!(parse(&[0x50]).0, None);
+        /* device list shorter than the point counts */
+        let (size, attr) = parse(&[
+            0x50, 0x00, 0x00, 0xff, 0xff, 0x03, 0x00, 0x0c, 0x00, 0x10, 0x00,
+            0x03, 0x04, 0x00, 0x00, 0x05, 0x00, 0x64, 0x00, 0x00, 0xa8,
+        ]);
+        assert_eq!(size, Some(21));
+        assert_eq!(attr.points, Some(5));
+        assert_eq!(attr.device_number, vec![100]);
+    }
+
+    #[test]
+    fn frames_in_one_segment() {
+        let mut payload = BATCH_READ_3E.to_vec();
+        payload.extend_from_slice(&BATCH_READ_3E);
+        payload.extend_from_slice(&[0x50, 0x00]);
+        let records = melsec_records(&test_addresses(50000, MELSEC_PORT_MIN), &payload);
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|record| matches!(record, Record::Melsec(attr) if attr.points == Some(10))));
+    }
+}
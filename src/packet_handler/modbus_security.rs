@@ -0,0 +1,243 @@
+//! Modbus/TCP Security (TLS on TCP 802)
+//!
+//! The client's role is carried in its X.509 certificate as an extension
+//! (OID 1.3.6.1.4.1.50316.802.1) holding a UTF8String. Certificates are
+//! exchanged in clear up to TLS 1.2 (mutual authentication is mandatory).
+//!
+//! Unit IDs travel inside encrypted application data, so they are taken from
+//! the plaintext Modbus/TCP requests (port 502) between the same client and
+//! server. A session record maps the client's role to the server (device) it
+//! authenticated against and the unit IDs seen so far; it is exported again
+//! whenever a new unit ID shows up for a host pair with a known role.
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+
+use super::bounded_map::BoundedMap;
+use super::Addresses;
+use super::tls::{HandshakeValues, TlsAttr};
+
+pub const MODBUS_SECURITY_PORT: u16 = 802;
+pub const ROLE_OID: &str = "1.3.6.1.4.1.50316.802.1";
+
+const MAX_SESSIONS: usize = 4096;
+
+// (client address, client port, server address)
+type SessionKey = (IpAddr, u16, IpAddr);
+// (client address, server address)
+type HostKey = (IpAddr, IpAddr);
+
+#[derive(Default)]
+struct ServerHandshake {
+    version: Option<u16>,
+    cipher_suite: Option<u16>,
+    subject: Option<String>,
+    not_after: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModbusSessionAttr {
+    pub addresses: Addresses,
+    pub role: Option<String>,
+    pub client_subject: Option<String>,
+    pub client_not_after: Option<i64>,
+    pub expired: bool,
+    pub server_subject: Option<String>,
+    pub server_not_after: Option<i64>,
+    pub version: Option<u16>,
+    pub cipher_suite: Option<u16>,
+    // plaintext Modbus unit IDs seen from the client to the server (sorted)
+    pub unit_ids: Vec<u8>,
+}
+
+// Server side of each handshake until the client presents its certificate,
+// the last session per host pair and the unit IDs accessed between them
+// (MAX_SESSIONS each, least recently used first out)
+#[derive(Default)]
+pub struct ModbusSecuritySessions {
+    servers: BoundedMap<SessionKey, ServerHandshake, MAX_SESSIONS>,
+    sessions: BoundedMap<HostKey, ModbusSessionAttr, MAX_SESSIONS>,
+    units: BoundedMap<HostKey, Vec<u8>, MAX_SESSIONS>,
+}
+
+impl ModbusSecuritySessions {
+    pub fn update(&mut self, tls_attr: &TlsAttr, utc: DateTime<Utc>) -> Option<ModbusSessionAttr> {
+        let addresses = &tls_attr.addresses;
+        let has_certificate = tls_attr.handshake_types.contains(&HandshakeValues::Certificate);
+        if addresses.src_port == MODBUS_SECURITY_PORT {
+            let server = self.servers
+                .get_or_insert_with((addresses.dst_addr, addresses.dst_port, addresses.src_addr), ServerHandshake::default);
+            if tls_attr.handshake_types.contains(&HandshakeValues::ServerHello) {
+                server.version = tls_attr.version;
+                server.cipher_suite = tls_attr.cipher_suites.first().copied();
+            }
+            if has_certificate {
+                server.subject = tls_attr.cert_subject.clone();
+                server.not_after = tls_attr.cert_not_after;
+            }
+            return None;
+        }
+        if !has_certificate {
+            return None;
+        }
+        let server = self.servers
+            .remove(&(addresses.src_addr, addresses.src_port, addresses.dst_addr))
+            .unwrap_or_default();
+        let expired = tls_attr.cert_not_after.map_or(false, |not_after| not_after < utc.timestamp_nanos());
+        if expired {
+            log::warn!(
+                "Modbus/TCP Security: expired client certificate {} ({} > {})",
+                tls_attr.cert_subject.as_deref().unwrap_or(""),
+                addresses.src_addr,
+                addresses.dst_addr
+            );
+        }
+        let host_key = (addresses.src_addr, addresses.dst_addr);
+        let session_attr = ModbusSessionAttr {
+            addresses: addresses.clone(),
+            role: tls_attr.cert_role.clone(),
+            client_subject: tls_attr.cert_subject.clone(),
+            client_not_after: tls_attr.cert_not_after,
+            expired: expired,
+            server_subject: server.subject,
+            server_not_after: server.not_after,
+            version: server.version,
+            cipher_suite: server.cipher_suite,
+            unit_ids: self.units.get(&host_key).cloned().unwrap_or_default(),
+        };
+        self.sessions.insert(host_key, session_attr.clone());
+        Some(session_attr)
+    }
+
+    // Records a unit ID from a plaintext Modbus request; returns the updated
+    // session when the unit is new for a host pair with a known role
+    pub fn observe_unit(&mut self, client: IpAddr, server: IpAddr, unit_id: u8) -> Option<ModbusSessionAttr> {
+        let host_key = (client, server);
+        let units = self.units.get_or_insert_with(host_key, Vec::new);
+        match units.binary_search(&unit_id) {
+            Ok(_) => return None,
+            Err(index) => units.insert(index, unit_id),
+        }
+        let session_attr = self.sessions.get_mut(&host_key)?;
+        session_attr.unit_ids = units.clone();
+        Some(session_attr.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+    use crate::packet_handler::x509::test_certificate;
+
+    // 1.3.6.1.4.1.50316.802.1 UTF8String "Operator"
+    const ROLE_EXTENSION: ([u8; 11], [u8; 10]) = (
+        [0x2B, 0x06, 0x01, 0x04, 0x01, 0x83, 0x89, 0x0C, 0x86, 0x22, 0x01],
+        [0x0C, 0x08, b'O', b'p', b'e', b'r', b'a', b't', b'o', b'r'],
+    );
+
+    // TLS record carrying a single-certificate Certificate handshake
+    fn certificate_record(certificate: &[u8]) -> Vec<u8> {
+        let u24 = |n: usize| (n as u32).to_be_bytes()[1..].to_vec();
+        let mut handshake = vec![HandshakeValues::Certificate];
+        handshake.extend(u24(certificate.len() + 6));
+        handshake.extend(u24(certificate.len() + 3));
+        handshake.extend(u24(certificate.len()));
+        handshake.extend_from_slice(certificate);
+        let mut data = vec![0x16, 0x03, 0x03];
+        data.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+        data.extend(handshake);
+        data
+    }
+
+    fn client_attr(certificate: &[u8]) -> TlsAttr {
+        let mut attr = TlsAttr::new(test_addresses(50123, MODBUS_SECURITY_PORT));
+        attr.set_record(&certificate_record(certificate)).unwrap();
+        attr
+    }
+
+    fn server_attr() -> TlsAttr {
+        let mut addresses = test_addresses(MODBUS_SECURITY_PORT, 50123);
+        std::mem::swap(&mut addresses.src_addr, &mut addresses.dst_addr);
+        let mut attr = TlsAttr::new(addresses);
+        attr.set_record(&certificate_record(&test_certificate("plc-01", &[]))).unwrap();
+        attr.handshake_types.push(HandshakeValues::ServerHello);
+        attr.version = Some(0x0303);
+        attr.cipher_suites = vec![0xC02B];
+        attr
+    }
+
+    #[test]
+    fn client_role_session() {
+        let mut sessions = ModbusSecuritySessions::default();
+        assert!(sessions.update(&server_attr(), Utc::now()).is_none());
+        let certificate = test_certificate("hmi-01", &[(&ROLE_EXTENSION.0, &ROLE_EXTENSION.1)]);
+        let session_attr = sessions.update(&client_attr(&certificate), Utc::now()).unwrap();
+        assert_eq!(session_attr.role.as_deref(), Some("Operator"));
+        assert_eq!(session_attr.client_subject.as_deref(), Some("CN=hmi-01, O=Plant"));
+        assert_eq!(session_attr.server_subject.as_deref(), Some("CN=plc-01, O=Plant"));
+        assert!(session_attr.server_not_after.is_some());
+        assert_eq!(session_attr.version, Some(0x0303));
+        assert_eq!(session_attr.cipher_suite, Some(0xC02B));
+        assert!(!session_attr.expired);
+        assert!(session_attr.unit_ids.is_empty());
+        /* server handshake state is consumed */
+        assert!(sessions.servers.is_empty());
+    }
+
+    #[test]
+    fn client_without_server_handshake() {
+        let mut sessions = ModbusSecuritySessions::default();
+        let session_attr = sessions.update(&client_attr(&test_certificate("hmi-01", &[])), Utc::now()).unwrap();
+        assert_eq!(session_attr.role, None);
+        assert_eq!(session_attr.server_subject, None);
+        assert_eq!(session_attr.version, None);
+        /* ClientHello only: nothing to record */
+        let attr = TlsAttr::new(test_addresses(50124, MODBUS_SECURITY_PORT));
+        assert!(sessions.update(&attr, Utc::now()).is_none());
+    }
+
+    #[test]
+    fn expired_certificate() {
+        let mut sessions = ModbusSecuritySessions::default();
+        let mut attr = client_attr(&test_certificate("hmi-01", &[]));
+        attr.cert_not_after = Some(Utc::now().timestamp_nanos() - 1);
+        assert!(sessions.update(&attr, Utc::now()).unwrap().expired);
+    }
+
+    #[test]
+    fn unit_ids_follow_role() {
+        let mut sessions = ModbusSecuritySessions::default();
+        let (client, server) = (IpAddr::from([192, 168, 0, 10]), IpAddr::from([192, 168, 0, 1]));
+        /* units seen before the handshake are carried into the session */
+        assert!(sessions.observe_unit(client, server, 5).is_none());
+        let certificate = test_certificate("hmi-01", &[(&ROLE_EXTENSION.0, &ROLE_EXTENSION.1)]);
+        assert_eq!(sessions.update(&client_attr(&certificate), Utc::now()).unwrap().unit_ids, vec![5]);
+        let session_attr = sessions.observe_unit(client, server, 1).unwrap();
+        assert_eq!(session_attr.unit_ids, vec![1, 5]);
+        assert_eq!(session_attr.role.as_deref(), Some("Operator"));
+        /* repeated unit, other host pair */
+        assert!(sessions.observe_unit(client, server, 5).is_none());
+        assert!(sessions.observe_unit(IpAddr::from([192, 168, 0, 11]), server, 1).is_none());
+    }
+
+    #[test]
+    fn sessions_bounded() {
+        let mut sessions = ModbusSecuritySessions::default();
+        let server = IpAddr::from([192, 168, 0, 1]);
+        let client = IpAddr::from([192, 168, 0, 10]);
+        sessions.update(&client_attr(&test_certificate("hmi-01", &[(&ROLE_EXTENSION.0, &ROLE_EXTENSION.1)])), Utc::now());
+        for host in 0..MAX_SESSIONS as u32 + 1 {
+            sessions.observe_unit(IpAddr::from((10u32 << 24 | host).to_be_bytes()), server, 1);
+            if host % 1024 == 0 {
+                sessions.observe_unit(client, server, 1 + (host / 1024) as u8);
+            }
+        }
+        assert!(sessions.units.len() < MAX_SESSIONS);
+        assert!(!sessions.units.contains_key(&(IpAddr::from([10, 0, 0, 0]), server)));
+        /* the active host pair keeps its units and role */
+        let session_attr = sessions.observe_unit(client, server, 9).unwrap();
+        assert_eq!(session_attr.unit_ids, vec![1, 2, 3, 4, 5, 9]);
+    }
+}
@@ -0,0 +1,429 @@
+//! MQTT 3.1/3.1.1/5.0 (TCP 1883)
+//!
+//! +--------------------------+----------------------------+-----------------+---------
+//! | Packet Type | Flags      | Remaining Length (varint)  | Variable Header | Payload
+//! +--------------------------+----------------------------+-----------------+---------
+//! |   4 bits    |  4 bits    |          1..4              |       ...       |   ...
+
+use std::net::IpAddr;
+
+use super::bounded_map::BoundedMap;
+use super::tcp_stream::MAX_BUFFER;
+use super::{Addresses, be_u16};
+
+pub const MQTT_PORT: u16 = 1883;
+
+const MAX_SESSIONS: usize = 4096;
+
+// bytes of the PUBLISH payload kept as a preview (0 disables it)
+const PAYLOAD_PREVIEW_LEN: usize = 32;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod PacketTypeValues {
+    pub const Connect: u8 = 1;
+    pub const Connack: u8 = 2;
+    pub const Publish: u8 = 3;
+    pub const Puback: u8 = 4;
+    pub const Pubrec: u8 = 5;
+    pub const Pubrel: u8 = 6;
+    pub const Pubcomp: u8 = 7;
+    pub const Subscribe: u8 = 8;
+    pub const Suback: u8 = 9;
+    pub const Unsubscribe: u8 = 10;
+    pub const Unsuback: u8 = 11;
+    pub const Pingreq: u8 = 12;
+    pub const Pingresp: u8 = 13;
+    pub const Disconnect: u8 = 14;
+    pub const Auth: u8 = 15;
+}
+
+#[derive(Debug)]
+pub struct MqttAttr {
+    pub addresses: Addresses,
+    pub packet_type: u8,
+    pub flags: u8,
+    pub remaining_length: u32,
+    // CONNECT
+    pub protocol_name: Option<String>,
+    pub protocol_level: Option<u8>,
+    pub connect_flags: Option<u8>,
+    pub keepalive: Option<u16>,
+    pub client_id: Option<String>,
+    pub has_username: Option<bool>,
+    pub has_password: Option<bool>,
+    // CONNACK
+    pub return_code: Option<u8>,
+    // PUBLISH
+    pub packet_id: Option<u16>,
+    pub topic: Option<String>,
+    pub qos: Option<u8>,
+    pub retain: Option<bool>,
+    pub dup: Option<bool>,
+    pub payload_size: Option<u32>,
+    pub payload_preview: Option<String>,
+    // SUBSCRIBE/UNSUBSCRIBE
+    pub topic_filters: Vec<String>,
+    pub requested_qos: Vec<u8>,
+}
+
+// Variable Byte Integer: returns (value, size)
+fn varint(data: &[u8], offset: usize) -> Option<(u32, usize)> {
+    let mut value: u32 = 0;
+    for i in 0..4 {
+        let b = *data.get(offset + i)?;
+        value |= ((b & 0x7F) as u32) << (7 * i);
+        if b & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+// UTF-8 string: u16 length | bytes
+fn utf8(data: &[u8], offset: usize) -> Option<(String, usize)> {
+    let length = be_u16(data, offset)? as usize;
+    let bytes = data.get(offset + 2..offset + 2 + length)?;
+    Some((String::from_utf8_lossy(bytes).into_owned(), offset + 2 + length))
+}
+
+// Properties (MQTT 5): returns the offset following them
+fn skip_properties(data: &[u8], offset: usize) -> Option<usize> {
+    let (length, size) = varint(data, offset)?;
+    Some(offset + size + length as usize)
+}
+
+// (client address, client port, broker address, broker port)
+type SessionKey = (IpAddr, u16, IpAddr, u16);
+
+// Protocol level negotiated by CONNECT, per connection
+#[derive(Default)]
+pub struct MqttSessions {
+    levels: BoundedMap<SessionKey, u8, MAX_SESSIONS>,
+}
+
+impl MqttSessions {
+    fn key(addresses: &Addresses) -> SessionKey {
+        if addresses.dst_port == MQTT_PORT {
+            (addresses.src_addr, addresses.src_port, addresses.dst_addr, addresses.dst_port)
+        } else {
+            (addresses.dst_addr, addresses.dst_port, addresses.src_addr, addresses.src_port)
+        }
+    }
+
+    fn level(&self, addresses: &Addresses) -> u8 {
+        *self.levels.get(&Self::key(addresses)).unwrap_or(&4)
+    }
+
+    fn set_level(&mut self, addresses: &Addresses, level: u8) {
+        self.levels.insert(Self::key(addresses), level);
+    }
+
+    pub fn close(&mut self, addresses: &Addresses) {
+        self.levels.remove(&Self::key(addresses));
+    }
+}
+
+impl MqttAttr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            packet_type: 0,
+            flags: 0,
+            remaining_length: 0,
+            protocol_name: None,
+            protocol_level: None,
+            connect_flags: None,
+            keepalive: None,
+            client_id: None,
+            has_username: None,
+            has_password: None,
+            return_code: None,
+            packet_id: None,
+            topic: None,
+            qos: None,
+            retain: None,
+            dup: None,
+            payload_size: None,
+            payload_preview: None,
+            topic_filters: Vec::new(),
+            requested_qos: Vec::new(),
+        }
+    }
+
+    // Decodes one control packet from the reassembled stream.
+    // Err(()) when the data cannot be MQTT, Ok(None) when more data is needed.
+    // A packet too large to reassemble keeps only its fixed header; its full
+    // size is returned so the stream skips it.
+    pub fn set_mqtt(&mut self, data: &[u8], sessions: &mut MqttSessions) -> Result<Option<usize>, ()> {
+        let first = match data.get(0) {
+            Some(first) => *first,
+            None => return Ok(None),
+        };
+        let packet_type = first >> 4;
+        let flags = first & 0x0F;
+        let valid = match packet_type {
+            PacketTypeValues::Publish => flags & 0x06 != 0x06,
+            PacketTypeValues::Pubrel | PacketTypeValues::Subscribe | PacketTypeValues::Unsubscribe => flags == 0x02,
+            0 => false,
+            _ => flags == 0,
+        };
+        if !valid {
+            return Err(());
+        }
+        let (remaining_length, size) = match varint(data, 1) {
+            Some(varint) => varint,
+            None if data.len() < 5 => return Ok(None),
+            None => return Err(()),
+        };
+        let end = 1 + size + remaining_length as usize;
+        self.packet_type = packet_type;
+        self.flags = flags;
+        self.remaining_length = remaining_length;
+        let body = match data.get(1 + size..end) {
+            Some(body) => body,
+            None if end > MAX_BUFFER => return Ok(Some(end)),
+            None => return Ok(None),
+        };
+        let level = sessions.level(&self.addresses);
+        match packet_type {
+            PacketTypeValues::Connect => {
+                self.set_connect(body);
+                if let Some(level) = self.protocol_level {
+                    sessions.set_level(&self.addresses, level);
+                }
+            }
+            PacketTypeValues::Connack => {
+                /* Acknowledge Flags | Return Code (Reason Code in MQTT 5) */
+                self.return_code = body.get(1).cloned();
+            }
+            PacketTypeValues::Publish => self.set_publish(body, level),
+            PacketTypeValues::Puback
+            | PacketTypeValues::Pubrec
+            | PacketTypeValues::Pubrel
+            | PacketTypeValues::Pubcomp
+            | PacketTypeValues::Suback
+            | PacketTypeValues::Unsuback => {
+                self.packet_id = be_u16(body, 0);
+            }
+            PacketTypeValues::Subscribe | PacketTypeValues::Unsubscribe => self.set_subscribe(body, level),
+            PacketTypeValues::Disconnect => sessions.close(&self.addresses),
+            _ => {}
+        }
+        Ok(Some(end))
+    }
+
+    fn set_connect(&mut self, body: &[u8]) {
+        /* Protocol Name | Protocol Level | Connect Flags | Keep Alive | (Properties) | Client ID */
+        let (protocol_name, offset) = match utf8(body, 0) {
+            Some(name) => name,
+            None => return,
+        };
+        self.protocol_name = Some(protocol_name);
+        let (level, flags) = match (body.get(offset), body.get(offset + 1)) {
+            (Some(level), Some(flags)) => (*level, *flags),
+            _ => return,
+        };
+        self.protocol_level = Some(level);
+        self.connect_flags = Some(flags);
+        self.has_username = Some(flags & 0x80 != 0);
+        self.has_password = Some(flags & 0x40 != 0);
+        self.keepalive = be_u16(body, offset + 2);
+        let mut offset = offset + 4;
+        if level >= 5 {
+            offset = match skip_properties(body, offset) {
+                Some(offset) => offset,
+                None => return,
+            };
+        }
+        self.client_id = utf8(body, offset).map(|(client_id, _)| client_id);
+    }
+
+    fn set_publish(&mut self, body: &[u8], level: u8) {
+        /* Topic Name | (Packet Identifier) | (Properties) | Payload */
+        let qos = (self.flags >> 1) & 0x03;
+        self.qos = Some(qos);
+        self.retain = Some(self.flags & 0x01 != 0);
+        self.dup = Some(self.flags & 0x08 != 0);
+        let (topic, mut offset) = match utf8(body, 0) {
+            Some(topic) => topic,
+            None => return,
+        };
+        self.topic = Some(topic);
+        if qos > 0 {
+            self.packet_id = be_u16(body, offset);
+            offset += 2;
+        }
+        if level >= 5 {
+            offset = match skip_properties(body, offset) {
+                Some(offset) => offset,
+                None => return,
+            };
+        }
+        if let Some(payload) = body.get(offset..) {
+            self.payload_size = Some(payload.len() as u32);
+            if PAYLOAD_PREVIEW_LEN > 0 {
+                let preview = &payload[..payload.len().min(PAYLOAD_PREVIEW_LEN)];
+                self.payload_preview = Some(String::from_utf8_lossy(preview).into_owned());
+            }
+        }
+    }
+
+    fn set_subscribe(&mut self, body: &[u8], level: u8) {
+        /* Packet Identifier | (Properties) | (Topic Filter | Options) ... */
+        self.packet_id = be_u16(body, 0);
+        let mut offset = 2;
+        if level >= 5 {
+            offset = match skip_properties(body, offset) {
+                Some(offset) => offset,
+                None => return,
+            };
+        }
+        while let Some((topic_filter, next)) = utf8(body, offset) {
+            self.topic_filters.push(topic_filter);
+            offset = next;
+            if self.packet_type == PacketTypeValues::Subscribe {
+                match body.get(offset) {
+                    Some(options) => self.requested_qos.push(options & 0x03),
+                    None => break,
+                }
+                offset += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+
+    fn parse(data: &[u8], sessions: &mut MqttSessions) -> (Result<Option<usize>, ()>, MqttAttr) {
+        let mut attr = MqttAttr::new(test_addresses(50000, MQTT_PORT));
+        (attr.set_mqtt(data, sessions), attr)
+    }
+
+    fn packet(first: u8, body: &[u8]) -> Vec<u8> {
+        let mut data = vec![first, body.len() as u8];
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        let mut data = (value.len() as u16).to_be_bytes().to_vec();
+        data.extend_from_slice(value.as_bytes());
+        data
+    }
+
+    #[test]
+    fn variable_byte_integer() {
+        assert_eq!(varint(&[0x00], 0), Some((0, 1)));
+        assert_eq!(varint(&[0xc1, 0x02], 0), Some((321, 2)));
+        assert_eq!(varint(&[0xff, 0xff, 0xff, 0x7f], 0), Some((268_435_455, 4)));
+        assert_eq!(varint(&[0xff, 0xff, 0xff, 0xff, 0x01], 0), None);
+        assert_eq!(varint(&[0x80], 0), None);
+    }
+
+    #[test]
+    fn connect_v311() {
+        let mut body = string("MQTT");
+        body.extend_from_slice(&[0x04, 0xc2, 0x00, 0x3c]);
+        body.extend(string("sensor-0001"));
+        body.extend(string("user"));
+        body.extend(string("secret"));
+        let mut sessions = MqttSessions::default();
+        let data = packet(0x10, &body);
+        let (result, attr) = parse(&data, &mut sessions);
+        assert_eq!(result, Ok(Some(data.len())));
+        assert_eq!(attr.packet_type, PacketTypeValues::Connect);
+        assert_eq!(attr.protocol_name.as_deref(), Some("MQTT"));
+        assert_eq!(attr.protocol_level, Some(4));
+        assert_eq!(attr.keepalive, Some(60));
+        assert_eq!(attr.client_id.as_deref(), Some("sensor-0001"));
+        assert_eq!(attr.has_username, Some(true));
+        assert_eq!(attr.has_password, Some(true));
+    }
+
+    #[test]
+    fn v5_session_properties() {
+        let mut sessions = MqttSessions::default();
+        let mut body = string("MQTT");
+        body.extend_from_slice(&[0x05, 0x02, 0x00, 0x3c, 0x05, 0x11, 0x00, 0x00, 0x00, 0x78]);
+        body.extend(string("c01"));
+        let (_, attr) = parse(&packet(0x10, &body), &mut sessions);
+        assert_eq!(attr.client_id.as_deref(), Some("c01"));
+        /* PUBLISH with a Content Type property */
+        let mut body = string("plant/temp");
+        body.extend_from_slice(&[0x00, 0x07, 0x04, 0x03, 0x00, 0x01, b't']);
+        body.extend_from_slice(b"21.5");
+        let (_, attr) = parse(&packet(0x32, &body), &mut sessions);
+        assert_eq!(attr.packet_id, Some(7));
+        assert_eq!(attr.payload_preview.as_deref(), Some("21.5"));
+        /* after DISCONNECT the connection falls back to 3.1.1 framing */
+        assert_eq!(parse(&[0xe0, 0x00], &mut sessions).0, Ok(Some(2)));
+        let (_, attr) = parse(&packet(0x32, &body), &mut sessions);
+        assert_eq!(attr.payload_size, Some(9));
+    }
+
+    #[test]
+    fn publish_v311() {
+        let mut body = string("plant/line1/temp");
+        body.extend_from_slice(&[0x00, 0x07]);
+        body.extend_from_slice(b"{\"value\":21.5,\"unit\":\"C\",\"sensor\":\"t1\"}");
+        let (_, attr) = parse(&packet(0x3b, &body), &mut MqttSessions::default());
+        assert_eq!(attr.topic.as_deref(), Some("plant/line1/temp"));
+        assert_eq!(attr.qos, Some(1));
+        assert_eq!(attr.retain, Some(true));
+        assert_eq!(attr.dup, Some(true));
+        assert_eq!(attr.packet_id, Some(7));
+        assert_eq!(attr.payload_size, Some(39));
+        assert_eq!(attr.payload_preview.as_ref().map(|p| p.len()), Some(PAYLOAD_PREVIEW_LEN));
+    }
+
+    #[test]
+    fn subscribe() {
+        let mut body = vec![0x00, 0x01];
+        body.extend(string("a/b/#"));
+        body.push(0x01);
+        body.extend(string("c/+"));
+        body.push(0x02);
+        let (_, attr) = parse(&packet(0x82, &body), &mut MqttSessions::default());
+        assert_eq!(attr.packet_id, Some(1));
+        assert_eq!(attr.topic_filters, vec!["a/b/#".to_string(), "c/+".to_string()]);
+        assert_eq!(attr.requested_qos, vec![1, 2]);
+        let (_, attr) = parse(&packet(0xa2, &[0x00, 0x02, 0x00, 0x01, b'x']), &mut MqttSessions::default());
+        assert_eq!(attr.topic_filters, vec!["x".to_string()]);
+    }
+
+    #[test]
+    fn incomplete_and_invalid() {
+        let mut sessions = MqttSessions::default();
+        assert_eq!(parse(&[], &mut sessions).0, Ok(None));
+        assert_eq!(parse(&[0x30], &mut sessions).0, Ok(None));
+        assert_eq!(parse(&[0x30, 0x0a, 0x00, 0x03], &mut sessions).0, Ok(None));
+        /* QoS 3, reserved type 0, wrong SUBSCRIBE flags, 5-byte remaining length */
+        assert_eq!(parse(&[0x36, 0x00], &mut sessions).0, Err(()));
+        assert_eq!(parse(&[0x00, 0x00], &mut sessions).0, Err(()));
+        assert_eq!(parse(&[0x80, 0x00], &mut sessions).0, Err(()));
+        assert_eq!(parse(&[0x30, 0xff, 0xff, 0xff, 0xff, 0x01], &mut sessions).0, Err(()));
+        assert_eq!(parse(b"GET / HTTP/1.1", &mut sessions).0, Err(()));
+        /* pipelined packets are consumed one at a time */
+        assert_eq!(parse(&[0xc0, 0x00, 0xd0, 0x00], &mut sessions).0, Ok(Some(2)));
+        /* topic length past the body */
+        let (result, attr) = parse(&[0x30, 0x03, 0x00, 0x09, b'a'], &mut sessions);
+        assert_eq!(result, Ok(Some(5)));
+        assert_eq!(attr.topic, None);
+    }
+
+    #[test]
+    fn oversized_packet_is_skipped() {
+        let mut sessions = MqttSessions::default();
+        /* PUBLISH with a 1 MiB remaining length */
+        let (result, attr) = parse(&[0x30, 0x80, 0x80, 0x40, 0x00, 0x03, b'a'], &mut sessions);
+        assert_eq!(result, Ok(Some(4 + 0x100000)));
+        assert_eq!(attr.packet_type, PacketTypeValues::Publish);
+        assert_eq!(attr.remaining_length, 0x100000);
+        assert_eq!(attr.topic, None);
+    }
+}
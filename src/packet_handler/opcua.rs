@@ -0,0 +1,557 @@
+//! OPC UA Binary (UA TCP, port 4840)
+//!
+//! +--------------+------------+--------------+---------------------------------------
+//! | Message Type | Chunk Type | Message Size | HEL/ACK/ERR fields, or
+//! |  "HEL" ...   | 'F'/'C'/'A'|    (le)      | SecureChannelId | Security Header |
+//! +--------------+------------+--------------+   Sequence Header | Body (TypeId ...)
+//! |      3       |     1      |      4       |
+//!
+//! OPN carries the asymmetric security header (policy URI, certificates),
+//! MSG/CLO the symmetric one (token ID). Bodies are only decoded when they
+//! are not encrypted, i.e. OPN with SecurityPolicy#None and MSG/CLO whose
+//! TypeId decodes to a known service.
+
+use super::{Addresses, le_u16, le_u32};
+
+pub const OPCUA_PORT: u16 = 4840;
+
+const SECURITY_POLICY_NONE: &str = "http://opcfoundation.org/UA/SecurityPolicy#None";
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod ServiceValues {
+    // DefaultBinary encoding IDs (namespace 0)
+    pub const ServiceFault: u32 = 397;
+    pub const FindServersRequest: u32 = 422;
+    pub const FindServersResponse: u32 = 425;
+    pub const GetEndpointsRequest: u32 = 428;
+    pub const GetEndpointsResponse: u32 = 431;
+    pub const OpenSecureChannelRequest: u32 = 446;
+    pub const OpenSecureChannelResponse: u32 = 449;
+    pub const CloseSecureChannelRequest: u32 = 452;
+    pub const CreateSessionRequest: u32 = 461;
+    pub const CreateSessionResponse: u32 = 464;
+    pub const ActivateSessionRequest: u32 = 467;
+    pub const ActivateSessionResponse: u32 = 470;
+    pub const CloseSessionRequest: u32 = 473;
+    pub const CloseSessionResponse: u32 = 476;
+    pub const BrowseRequest: u32 = 527;
+    pub const BrowseResponse: u32 = 530;
+    pub const BrowseNextRequest: u32 = 533;
+    pub const BrowseNextResponse: u32 = 536;
+    pub const TranslateBrowsePathsToNodeIdsRequest: u32 = 554;
+    pub const TranslateBrowsePathsToNodeIdsResponse: u32 = 557;
+    pub const ReadRequest: u32 = 631;
+    pub const ReadResponse: u32 = 634;
+    pub const HistoryReadRequest: u32 = 664;
+    pub const HistoryReadResponse: u32 = 667;
+    pub const WriteRequest: u32 = 673;
+    pub const WriteResponse: u32 = 676;
+    pub const CallRequest: u32 = 712;
+    pub const CallResponse: u32 = 715;
+    pub const CreateMonitoredItemsRequest: u32 = 751;
+    pub const CreateMonitoredItemsResponse: u32 = 754;
+    pub const CreateSubscriptionRequest: u32 = 787;
+    pub const CreateSubscriptionResponse: u32 = 790;
+    pub const PublishRequest: u32 = 826;
+    pub const PublishResponse: u32 = 829;
+    pub const DeleteSubscriptionsRequest: u32 = 847;
+    pub const DeleteSubscriptionsResponse: u32 = 850;
+}
+
+fn service_name(type_id: u32) -> Option<&'static str> {
+    let name = match type_id {
+        ServiceValues::ServiceFault => "ServiceFault",
+        ServiceValues::FindServersRequest => "FindServersRequest",
+        ServiceValues::FindServersResponse => "FindServersResponse",
+        ServiceValues::GetEndpointsRequest => "GetEndpointsRequest",
+        ServiceValues::GetEndpointsResponse => "GetEndpointsResponse",
+        ServiceValues::OpenSecureChannelRequest => "OpenSecureChannelRequest",
+        ServiceValues::OpenSecureChannelResponse => "OpenSecureChannelResponse",
+        ServiceValues::CloseSecureChannelRequest => "CloseSecureChannelRequest",
+        ServiceValues::CreateSessionRequest => "CreateSessionRequest",
+        ServiceValues::CreateSessionResponse => "CreateSessionResponse",
+        ServiceValues::ActivateSessionRequest => "ActivateSessionRequest",
+        ServiceValues::ActivateSessionResponse => "ActivateSessionResponse",
+        ServiceValues::CloseSessionRequest => "CloseSessionRequest",
+        ServiceValues::CloseSessionResponse => "CloseSessionResponse",
+        ServiceValues::BrowseRequest => "BrowseRequest",
+        ServiceValues::BrowseResponse => "BrowseResponse",
+        ServiceValues::BrowseNextRequest => "BrowseNextRequest",
+        ServiceValues::BrowseNextResponse => "BrowseNextResponse",
+        ServiceValues::TranslateBrowsePathsToNodeIdsRequest => "TranslateBrowsePathsToNodeIdsRequest",
+        ServiceValues::TranslateBrowsePathsToNodeIdsResponse => "TranslateBrowsePathsToNodeIdsResponse",
+        ServiceValues::ReadRequest => "ReadRequest",
+        ServiceValues::ReadResponse => "ReadResponse",
+        ServiceValues::HistoryReadRequest => "HistoryReadRequest",
+        ServiceValues::HistoryReadResponse => "HistoryReadResponse",
+        ServiceValues::WriteRequest => "WriteRequest",
+        ServiceValues::WriteResponse => "WriteResponse",
+        ServiceValues::CallRequest => "CallRequest",
+        ServiceValues::CallResponse => "CallResponse",
+        ServiceValues::CreateMonitoredItemsRequest => "CreateMonitoredItemsRequest",
+        ServiceValues::CreateMonitoredItemsResponse => "CreateMonitoredItemsResponse",
+        ServiceValues::CreateSubscriptionRequest => "CreateSubscriptionRequest",
+        ServiceValues::CreateSubscriptionResponse => "CreateSubscriptionResponse",
+        ServiceValues::PublishRequest => "PublishRequest",
+        ServiceValues::PublishResponse => "PublishResponse",
+        ServiceValues::DeleteSubscriptionsRequest => "DeleteSubscriptionsRequest",
+        ServiceValues::DeleteSubscriptionsResponse => "DeleteSubscriptionsResponse",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[derive(Debug)]
+pub struct OpcuaAttr {
+    pub addresses: Addresses,
+    pub message_type: String,
+    pub chunk_type: String,
+    pub message_size: u32,
+    // HEL/ACK
+    pub protocol_version: Option<u32>,
+    pub receive_buffer_size: Option<u32>,
+    pub send_buffer_size: Option<u32>,
+    pub max_message_size: Option<u32>,
+    pub max_chunk_count: Option<u32>,
+    pub endpoint_url: Option<String>,
+    // ERR
+    pub error: Option<u32>,
+    pub reason: Option<String>,
+    // OPN/MSG/CLO security and sequence headers
+    pub secure_channel_id: Option<u32>,
+    pub security_policy_uri: Option<String>,
+    pub sender_certificate_size: Option<u32>,
+    pub token_id: Option<u32>,
+    pub sequence_number: Option<u32>,
+    pub request_id: Option<u32>,
+    // body (unencrypted only)
+    pub service_type_id: Option<u32>,
+    pub service: Option<&'static str>,
+    pub request_handle: Option<u32>,
+    pub service_result: Option<u32>,
+    pub request_type: Option<u32>,
+    pub security_mode: Option<u32>,
+    pub revised_lifetime: Option<u32>,
+}
+
+// UA String/ByteString: Int32 length (-1 = null) | bytes
+fn ua_bytes(data: &[u8], offset: usize) -> Option<(Option<&[u8]>, usize)> {
+    let length = le_u32(data, offset)? as i32;
+    if length < 0 {
+        return Some((None, offset + 4));
+    }
+    let end = offset + 4 + length as usize;
+    Some((Some(data.get(offset + 4..end)?), end))
+}
+
+fn ua_string(data: &[u8], offset: usize) -> Option<(Option<String>, usize)> {
+    let (bytes, next) = ua_bytes(data, offset)?;
+    Some((bytes.map(|b| String::from_utf8_lossy(b).into_owned()), next))
+}
+
+// NodeId: returns the numeric identifier in namespace 0 (if any) and the next offset
+fn node_id(data: &[u8], offset: usize) -> Option<(Option<u32>, usize)> {
+    match *data.get(offset)? & 0x3F {
+        0x00 => Some((Some(*data.get(offset + 1)? as u32), offset + 2)),
+        0x01 => {
+            let namespace = *data.get(offset + 1)?;
+            let id = le_u16(data, offset + 2)? as u32;
+            Some((if namespace == 0 { Some(id) } else { None }, offset + 4))
+        }
+        0x02 => {
+            let namespace = le_u16(data, offset + 1)?;
+            let id = le_u32(data, offset + 3)?;
+            Some((if namespace == 0 { Some(id) } else { None }, offset + 7))
+        }
+        0x03 | 0x05 => {
+            let (_, next) = ua_bytes(data, offset + 3)?;
+            Some((None, next))
+        }
+        0x04 => Some((None, offset + 19)),
+        _ => None,
+    }
+}
+
+// ExtensionObject: TypeId | Encoding | (Body)
+fn skip_extension_object(data: &[u8], offset: usize) -> Option<usize> {
+    let (_, next) = node_id(data, offset)?;
+    match *data.get(next)? {
+        0x00 => Some(next + 1),
+        0x01 | 0x02 => Some(ua_bytes(data, next + 1)?.1),
+        _ => None,
+    }
+}
+
+impl OpcuaAttr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            message_type: String::new(),
+            chunk_type: String::new(),
+            message_size: 0,
+            protocol_version: None,
+            receive_buffer_size: None,
+            send_buffer_size: None,
+            max_message_size: None,
+            max_chunk_count: None,
+            endpoint_url: None,
+            error: None,
+            reason: None,
+            secure_channel_id: None,
+            security_policy_uri: None,
+            sender_certificate_size: None,
+            token_id: None,
+            sequence_number: None,
+            request_id: None,
+            service_type_id: None,
+            service: None,
+            request_handle: None,
+            service_result: None,
+            request_type: None,
+            security_mode: None,
+            revised_lifetime: None,
+        }
+    }
+
+    // returns the number of bytes consumed, or None when the data is not a UA TCP message
+    pub fn set_message(&mut self, data: &[u8]) -> Option<usize> {
+        let header = data.get(0..4)?;
+        let message_type = std::str::from_utf8(&header[0..3]).ok()?;
+        match message_type {
+            "HEL" | "ACK" | "ERR" | "RHE" | "OPN" | "MSG" | "CLO" => {}
+            _ => return None,
+        }
+        match header[3] {
+            b'F' | b'C' | b'A' => {}
+            _ => return None,
+        }
+        let message_size = le_u32(data, 4)?;
+        if message_size < 8 {
+            return None;
+        }
+        self.message_type = message_type.to_string();
+        self.chunk_type = (header[3] as char).to_string();
+        self.message_size = message_size;
+        /* a message may continue in the next segment */
+        let size = (message_size as usize).min(data.len());
+        let message = &data[..size];
+        match message_type {
+            "HEL" => {
+                self.set_buffer_sizes(message);
+                self.endpoint_url = ua_string(message, 28).and_then(|(url, _)| url);
+            }
+            "ACK" => {
+                self.set_buffer_sizes(message);
+            }
+            "ERR" => {
+                self.error = le_u32(message, 8);
+                self.reason = ua_string(message, 12).and_then(|(reason, _)| reason);
+            }
+            "OPN" => {
+                self.set_open(message);
+            }
+            "MSG" | "CLO" => {
+                /* SecureChannelId | TokenId | SequenceNumber | RequestId | Body */
+                self.secure_channel_id = le_u32(message, 8);
+                self.token_id = le_u32(message, 12);
+                self.sequence_number = le_u32(message, 16);
+                self.request_id = le_u32(message, 20);
+                if header[3] != b'A' {
+                    self.set_body(message, 24);
+                }
+            }
+            _ => {}
+        }
+        Some(size)
+    }
+
+    fn set_buffer_sizes(&mut self, message: &[u8]) {
+        self.protocol_version = le_u32(message, 8);
+        self.receive_buffer_size = le_u32(message, 12);
+        self.send_buffer_size = le_u32(message, 16);
+        self.max_message_size = le_u32(message, 20);
+        self.max_chunk_count = le_u32(message, 24);
+    }
+
+    fn set_open(&mut self, message: &[u8]) {
+        /* SecureChannelId | SecurityPolicyUri | SenderCertificate | ReceiverCertificateThumbprint */
+        self.secure_channel_id = le_u32(message, 8);
+        let (policy, offset) = match ua_string(message, 12) {
+            Some(policy) => policy,
+            None => return,
+        };
+        let (certificate, offset) = match ua_bytes(message, offset) {
+            Some(certificate) => certificate,
+            None => return,
+        };
+        self.sender_certificate_size = certificate.map(|c| c.len() as u32);
+        let offset = match ua_bytes(message, offset) {
+            Some((_, offset)) => offset,
+            None => return,
+        };
+        let encrypted = policy.as_deref() != Some(SECURITY_POLICY_NONE);
+        self.security_policy_uri = policy;
+        /* SequenceNumber | RequestId (encrypted along with the body) */
+        if encrypted {
+            return;
+        }
+        self.sequence_number = le_u32(message, offset);
+        self.request_id = le_u32(message, offset + 4);
+        self.set_body(message, offset + 8);
+    }
+
+    fn set_body(&mut self, message: &[u8], offset: usize) {
+        let (type_id, offset) = match node_id(message, offset) {
+            Some((Some(type_id), offset)) => (type_id, offset),
+            _ => return,
+        };
+        /* an unknown TypeId means the body is encrypted (or not the first chunk) */
+        let service = match service_name(type_id) {
+            Some(service) => service,
+            None => return,
+        };
+        self.service_type_id = Some(type_id);
+        self.service = Some(service);
+        if service.ends_with("Request") {
+            self.set_request(message, offset, type_id);
+        } else {
+            self.set_response(message, offset, type_id);
+        }
+    }
+
+    fn set_request(&mut self, message: &[u8], offset: usize, type_id: u32) {
+        /* RequestHeader: AuthenticationToken | Timestamp | RequestHandle | ReturnDiagnostics |
+           AuditEntryId | TimeoutHint | AdditionalHeader */
+        let offset = match node_id(message, offset) {
+            Some((_, offset)) => offset,
+            None => return,
+        };
+        self.request_handle = le_u32(message, offset + 8);
+        let offset = match ua_bytes(message, offset + 16) {
+            Some((_, offset)) => offset + 4,
+            None => return,
+        };
+        let offset = match skip_extension_object(message, offset) {
+            Some(offset) => offset,
+            None => return,
+        };
+        if type_id == ServiceValues::OpenSecureChannelRequest {
+            /* ClientProtocolVersion | RequestType | SecurityMode | ClientNonce | RequestedLifetime */
+            self.request_type = le_u32(message, offset + 4);
+            self.security_mode = le_u32(message, offset + 8);
+        }
+    }
+
+    fn set_response(&mut self, message: &[u8], offset: usize, type_id: u32) {
+        /* ResponseHeader: Timestamp | RequestHandle | ServiceResult | ServiceDiagnostics |
+           StringTable | AdditionalHeader */
+        self.request_handle = le_u32(message, offset + 8);
+        self.service_result = le_u32(message, offset + 12);
+        if type_id != ServiceValues::OpenSecureChannelResponse {
+            return;
+        }
+        /* only an empty DiagnosticInfo is skipped */
+        if message.get(offset + 16) != Some(&0) {
+            return;
+        }
+        let mut offset = offset + 17;
+        let strings = match le_u32(message, offset) {
+            Some(strings) => strings as i32,
+            None => return,
+        };
+        offset += 4;
+        for _ in 0..strings.max(0) {
+            offset = match ua_bytes(message, offset) {
+                Some((_, offset)) => offset,
+                None => return,
+            };
+        }
+        let offset = match skip_extension_object(message, offset) {
+            Some(offset) => offset,
+            None => return,
+        };
+        /* ServerProtocolVersion | ChannelId | TokenId | CreatedAt | RevisedLifetime */
+        self.token_id = le_u32(message, offset + 8);
+        self.revised_lifetime = le_u32(message, offset + 20);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+
+    fn message(message_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = message_type.to_vec();
+        data.extend_from_slice(&(8 + body.len() as u32).to_le_bytes());
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn string(value: &str) -> Vec<u8> {
+        let mut data = (value.len() as u32).to_le_bytes().to_vec();
+        data.extend_from_slice(value.as_bytes());
+        data
+    }
+
+    // AuthenticationToken (null) | Timestamp | RequestHandle | ReturnDiagnostics |
+    // AuditEntryId (null) | TimeoutHint | AdditionalHeader (empty)
+    fn request_header(handle: u32) -> Vec<u8> {
+        let mut data = vec![0x00, 0x00];
+        data.extend_from_slice(&[0x80, 0x3e, 0x58, 0x6b, 0x1c, 0x4f, 0xd9, 0x01]);
+        data.extend_from_slice(&handle.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&[0xff; 4]);
+        data.extend_from_slice(&10000u32.to_le_bytes());
+        data.extend_from_slice(&[0x00, 0x00, 0x00]);
+        data
+    }
+
+    fn type_id(id: u16) -> Vec<u8> {
+        let mut data = vec![0x01, 0x00];
+        data.extend_from_slice(&id.to_le_bytes());
+        data
+    }
+
+    fn parse(data: &[u8]) -> (Option<usize>, OpcuaAttr) {
+        let mut attr = OpcuaAttr::new(test_addresses(50000, OPCUA_PORT));
+        (attr.set_message(data), attr)
+    }
+
+    #[test]
+    fn hello() {
+        let mut body = Vec::new();
+        for value in [0u32, 65536, 65536, 0, 0].iter() {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        body.extend_from_slice(&string("opc.tcp://plc01:4840"));
+        let data = message(b"HELF", &body);
+        let (size, attr) = parse(&data);
+        assert_eq!(size, Some(data.len()));
+        assert_eq!(attr.message_type, "HEL");
+        assert_eq!(attr.chunk_type, "F");
+        assert_eq!(attr.protocol_version, Some(0));
+        assert_eq!(attr.receive_buffer_size, Some(65536));
+        assert_eq!(attr.max_chunk_count, Some(0));
+        assert_eq!(attr.endpoint_url.as_deref(), Some("opc.tcp://plc01:4840"));
+    }
+
+    #[test]
+    fn error_message() {
+        let mut body = 0x807f0000u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&string("Bad_TcpEndpointUrlInvalid"));
+        let (_, attr) = parse(&message(b"ERRF", &body));
+        assert_eq!(attr.error, Some(0x807f0000));
+        assert_eq!(attr.reason.as_deref(), Some("Bad_TcpEndpointUrlInvalid"));
+    }
+
+    #[test]
+    fn open_secure_channel_without_security() {
+        let mut body = 0u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&string(SECURITY_POLICY_NONE));
+        body.extend_from_slice(&[0xff; 8]);
+        body.extend_from_slice(&51u32.to_le_bytes());
+        body.extend_from_slice(&1u32.to_le_bytes());
+        body.extend_from_slice(&type_id(ServiceValues::OpenSecureChannelRequest as u16));
+        body.extend_from_slice(&request_header(7));
+        for value in [0u32, 0, 1].iter() {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        body.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+        body.extend_from_slice(&600000u32.to_le_bytes());
+        let (_, attr) = parse(&message(b"OPNF", &body));
+        assert_eq!(attr.secure_channel_id, Some(0));
+        assert_eq!(attr.security_policy_uri.as_deref(), Some(SECURITY_POLICY_NONE));
+        assert_eq!(attr.sender_certificate_size, None);
+        assert_eq!(attr.sequence_number, Some(51));
+        assert_eq!(attr.request_id, Some(1));
+        assert_eq!(attr.service, Some("OpenSecureChannelRequest"));
+        assert_eq!(attr.request_handle, Some(7));
+        assert_eq!(attr.request_type, Some(0));
+        assert_eq!(attr.security_mode, Some(1));
+    }
+
+    #[test]
+    fn open_secure_channel_response() {
+        let mut body = 5u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&string(SECURITY_POLICY_NONE));
+        body.extend_from_slice(&[0xff; 8]);
+        body.extend_from_slice(&52u32.to_le_bytes());
+        body.extend_from_slice(&1u32.to_le_bytes());
+        body.extend_from_slice(&type_id(ServiceValues::OpenSecureChannelResponse as u16));
+        body.extend_from_slice(&[0; 8]);
+        body.extend_from_slice(&7u32.to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        for value in [0u32, 5, 9].iter() {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        body.extend_from_slice(&[0; 8]);
+        body.extend_from_slice(&600000u32.to_le_bytes());
+        let (_, attr) = parse(&message(b"OPNF", &body));
+        assert_eq!(attr.service, Some("OpenSecureChannelResponse"));
+        assert_eq!(attr.request_handle, Some(7));
+        assert_eq!(attr.service_result, Some(0));
+        assert_eq!(attr.token_id, Some(9));
+        assert_eq!(attr.revised_lifetime, Some(600000));
+    }
+
+    #[test]
+    fn read_request_and_encrypted_message() {
+        let mut body = Vec::new();
+        for value in [5u32, 9, 53, 3].iter() {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut read = body.clone();
+        read.extend_from_slice(&type_id(ServiceValues::ReadRequest as u16));
+        read.extend_from_slice(&request_header(11));
+        let (_, attr) = parse(&message(b"MSGF", &read));
+        assert_eq!(attr.secure_channel_id, Some(5));
+        assert_eq!(attr.token_id, Some(9));
+        assert_eq!(attr.sequence_number, Some(53));
+        assert_eq!(attr.request_id, Some(3));
+        assert_eq!(attr.service_type_id, Some(ServiceValues::ReadRequest));
+        assert_eq!(attr.request_handle, Some(11));
+        /* signed and encrypted: the TypeId is ciphertext */
+        body.extend_from_slice(&[0x01, 0x00, 0x13, 0x37, 0xde, 0xad, 0xbe, 0xef]);
+        let (_, attr) = parse(&message(b"MSGF", &body));
+        assert_eq!(attr.sequence_number, Some(53));
+        assert_eq!(attr.service, None);
+    }
+
+    #[test]
+    fn encrypted_open_secure_channel() {
+        let mut body = 0u32.to_le_bytes().to_vec();
+        body.extend_from_slice(&string("http://opcfoundation.org/UA/SecurityPolicy#Basic256Sha256"));
+        body.extend_from_slice(&3u32.to_le_bytes());
+        body.extend_from_slice(&[0x30, 0x82, 0x01]);
+        body.extend_from_slice(&[0xff; 4]);
+        body.extend_from_slice(&[0x5a; 32]);
+        let (_, attr) = parse(&message(b"OPNF", &body));
+        assert_eq!(attr.sender_certificate_size, Some(3));
+        assert_eq!(attr.sequence_number, None);
+        assert_eq!(attr.service, None);
+    }
+
+    #[test]
+    fn malformed_messages() {
+        assert_eq!(parse(b"GET / HTTP/1.1\r\n").0, None);
+        assert_eq!(parse(b"HELX\x08\x00\x00\x00").0, None);
+        assert_eq!(parse(b"HELF\x04\x00\x00\x00").0, None);
+        assert_eq!(parse(b"HELF\x20").0, None);
+        /* chunk split across segments: the available bytes are consumed */
+        let mut body = vec![0; 20];
+        body.extend_from_slice(&string("opc.tcp://plc01:4840"));
+        let data = message(b"HELF", &body);
+        let (size, attr) = parse(&data[..30]);
+        assert_eq!(size, Some(30));
+        assert_eq!(attr.message_size as usize, data.len());
+        assert_eq!(attr.endpoint_url, None);
+        /* string length past the end of the message */
+        let (_, attr) = parse(&message(b"ERRF", &[0, 0, 0, 0, 0xff, 0xff, 0xff, 0x7f, b'x']));
+        assert_eq!(attr.error, Some(0));
+        assert_eq!(attr.reason, None);
+    }
+}
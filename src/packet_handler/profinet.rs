@@ -0,0 +1,373 @@
+//! PROFINET (ethertype 0x8892)
+//!
+//! RT cyclic frame
+//! +---------+-------------------+---------------+-------------+-----------------+
+//! | FrameID | C_SDU (I/O data)  | CycleCounter  | DataStatus  | TransferStatus  |
+//! +---------+-------------------+---------------+-------------+-----------------+
+//! |    2    |      40..1440     |       2       |      1      |        1        |
+//!
+//! DCP frame
+//! +---------+-----------+-------------+-----+---------------------+------------+--------
+//! | FrameID | ServiceID | ServiceType | Xid | ResponseDelay/Rsvd  | DataLength | Blocks
+//! +---------+-----------+-------------+-----+---------------------+------------+--------
+//! |    2    |     1     |      1      |  4  |          2          |     2      | ...
+//!
+//! Block: Option | Suboption | DCPBlockLength | (BlockQualifier or BlockInfo) | Data | (padding)
+
+use std::net::Ipv4Addr;
+
+use pnet::packet::ethernet::EtherType;
+
+use super::{LinkAddresses, be_u16, be_u32};
+
+pub const PROFINET_ETHERTYPE: EtherType = EtherType(0x8892);
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod FrameIdValues {
+    pub const AlarmHigh: u16 = 0xFC01;
+    pub const AlarmLow: u16 = 0xFE01;
+    pub const DcpHello: u16 = 0xFEFC;
+    pub const DcpGetSet: u16 = 0xFEFD;
+    pub const DcpIdentifyRequest: u16 = 0xFEFE;
+    pub const DcpIdentifyResponse: u16 = 0xFEFF;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod ServiceValues {
+    pub const Get: u8 = 3;
+    pub const Set: u8 = 4;
+    pub const Identify: u8 = 5;
+    pub const Hello: u8 = 6;
+    // ServiceType
+    pub const Request: u8 = 0;
+    pub const ResponseSuccess: u8 = 1;
+    pub const ResponseUnsupported: u8 = 5;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod OptionValues {
+    // (Option, Suboption)
+    pub const IpMacAddress: (u8, u8) = (1, 1);
+    pub const IpParameter: (u8, u8) = (1, 2);
+    pub const TypeOfStation: (u8, u8) = (2, 1);
+    pub const NameOfStation: (u8, u8) = (2, 2);
+    pub const DeviceId: (u8, u8) = (2, 3);
+    pub const DeviceRole: (u8, u8) = (2, 4);
+    pub const DeviceOptions: (u8, u8) = (2, 5);
+    pub const AliasName: (u8, u8) = (2, 6);
+    pub const Control: u8 = 5;
+    pub const ControlResponse: (u8, u8) = (5, 4);
+    pub const All: u8 = 0xFF;
+}
+
+#[derive(Debug)]
+pub struct DcpAttr {
+    pub link: LinkAddresses,
+    pub frame_id: u16,
+    pub service_id: u8,
+    pub service_type: u8,
+    pub xid: u32,
+    pub response_delay: u16,
+    pub data_length: u16,
+    pub options: Vec<u16>, // Option << 8 | Suboption of each block
+    pub block_qualifier: Option<u16>,
+    pub name_of_station: Option<String>,
+    pub type_of_station: Option<String>,
+    pub alias_name: Option<String>,
+    pub ip_address: Option<String>,
+    pub subnet_mask: Option<String>,
+    pub gateway: Option<String>,
+    pub vendor_id: Option<u16>,
+    pub device_id: Option<u16>,
+    pub device_role: Option<u8>,
+    pub control: Option<u8>,
+    pub response_error: Option<u8>,
+}
+
+#[derive(Debug)]
+pub struct RtAttr {
+    pub link: LinkAddresses,
+    pub frame_id: u16,
+    pub rt_class: &'static str,
+    pub data_length: u16,
+    pub cycle_counter: u16,
+    pub data_status: u8,
+    pub transfer_status: u8,
+}
+
+fn rt_class(frame_id: u16) -> Option<&'static str> {
+    match frame_id {
+        0x0100..=0x7FFF => Some("RT_CLASS_3"),
+        0x8000..=0xBFFF => Some("RT_CLASS_2"),
+        0xC000..=0xF7FF => Some("RT_CLASS_1"),
+        0xF800..=0xFBFF => Some("RT_CLASS_UDP"),
+        _ => None,
+    }
+}
+
+impl DcpAttr {
+    pub fn new(link: LinkAddresses) -> Self {
+        Self {
+            link: link,
+            frame_id: 0,
+            service_id: 0,
+            service_type: 0,
+            xid: 0,
+            response_delay: 0,
+            data_length: 0,
+            options: Vec::new(),
+            block_qualifier: None,
+            name_of_station: None,
+            type_of_station: None,
+            alias_name: None,
+            ip_address: None,
+            subnet_mask: None,
+            gateway: None,
+            vendor_id: None,
+            device_id: None,
+            device_role: None,
+            control: None,
+            response_error: None,
+        }
+    }
+
+    // returns false when the frame is not a DCP PDU
+    pub fn set_dcp(&mut self, payload: &[u8]) -> bool {
+        let frame_id = match be_u16(payload, 0) {
+            Some(frame_id) => frame_id,
+            None => return false,
+        };
+        match frame_id {
+            FrameIdValues::DcpHello
+            | FrameIdValues::DcpGetSet
+            | FrameIdValues::DcpIdentifyRequest
+            | FrameIdValues::DcpIdentifyResponse => {}
+            _ => return false,
+        }
+        if payload.len() < 12 {
+            return false;
+        }
+        self.frame_id = frame_id;
+        self.service_id = payload[2];
+        self.service_type = payload[3];
+        self.xid = be_u32(payload, 4).unwrap_or(0);
+        self.response_delay = be_u16(payload, 8).unwrap_or(0);
+        self.data_length = be_u16(payload, 10).unwrap_or(0);
+        let end = (12 + self.data_length as usize).min(payload.len());
+        let blocks = &payload[12..end];
+        /* Get requests only list the options asked for */
+        if self.service_id == ServiceValues::Get && self.service_type == ServiceValues::Request {
+            for option in blocks.chunks_exact(2) {
+                self.options.push(u16::from_be_bytes([option[0], option[1]]));
+            }
+            return true;
+        }
+        /* Set requests carry a BlockQualifier, responses a BlockInfo, Identify filters neither */
+        let qualified = !(self.service_id == ServiceValues::Identify && self.service_type == ServiceValues::Request);
+        let mut offset = 0;
+        while offset + 4 <= blocks.len() {
+            let option = (blocks[offset], blocks[offset + 1]);
+            let length = be_u16(blocks, offset + 2).unwrap_or(0) as usize;
+            let block = match blocks.get(offset + 4..offset + 4 + length) {
+                Some(block) => block,
+                None => break,
+            };
+            self.options.push((option.0 as u16) << 8 | option.1 as u16);
+            let data = if qualified && option != OptionValues::ControlResponse && block.len() >= 2 {
+                if self.service_id == ServiceValues::Set && self.service_type == ServiceValues::Request {
+                    self.block_qualifier = be_u16(block, 0);
+                }
+                &block[2..]
+            } else {
+                block
+            };
+            self.set_block(option, data);
+            /* blocks are padded to an even length */
+            offset += 4 + length + (length & 1);
+        }
+        true
+    }
+
+    fn set_block(&mut self, option: (u8, u8), data: &[u8]) {
+        match option {
+            OptionValues::IpParameter => {
+                /* IPAddress | Subnetmask | StandardGateway */
+                if data.len() >= 12 {
+                    let ip = |i: usize| Ipv4Addr::new(data[i], data[i + 1], data[i + 2], data[i + 3]).to_string();
+                    self.ip_address = Some(ip(0));
+                    self.subnet_mask = Some(ip(4));
+                    self.gateway = Some(ip(8));
+                }
+            }
+            OptionValues::TypeOfStation => {
+                self.type_of_station = Some(String::from_utf8_lossy(data).into_owned());
+            }
+            OptionValues::NameOfStation => {
+                self.name_of_station = Some(String::from_utf8_lossy(data).into_owned());
+            }
+            OptionValues::AliasName => {
+                self.alias_name = Some(String::from_utf8_lossy(data).into_owned());
+            }
+            OptionValues::DeviceId => {
+                self.vendor_id = be_u16(data, 0);
+                self.device_id = be_u16(data, 2);
+            }
+            OptionValues::DeviceRole => {
+                self.device_role = data.first().cloned();
+            }
+            OptionValues::ControlResponse => {
+                /* Option | Suboption (of the Set block) | BlockError */
+                self.response_error = data.get(2).cloned();
+            }
+            (OptionValues::Control, suboption) => {
+                self.control = Some(suboption);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl RtAttr {
+    pub fn new(link: LinkAddresses) -> Self {
+        Self {
+            link: link,
+            frame_id: 0,
+            rt_class: "",
+            data_length: 0,
+            cycle_counter: 0,
+            data_status: 0,
+            transfer_status: 0,
+        }
+    }
+
+    // returns false when the frame is not a cyclic RT frame
+    pub fn set_rt(&mut self, payload: &[u8]) -> bool {
+        let frame_id = match be_u16(payload, 0) {
+            Some(frame_id) => frame_id,
+            None => return false,
+        };
+        let rt_class = match rt_class(frame_id) {
+            Some(rt_class) => rt_class,
+            None => return false,
+        };
+        /* APDU status trails the I/O data */
+        if payload.len() < 6 {
+            return false;
+        }
+        let status = payload.len() - 4;
+        self.frame_id = frame_id;
+        self.rt_class = rt_class;
+        self.data_length = (status - 2) as u16;
+        self.cycle_counter = be_u16(payload, status).unwrap_or(0);
+        self.data_status = payload[status + 2];
+        self.transfer_status = payload[status + 3];
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_link;
+
+    fn dcp(frame_id: u16, service_id: u8, service_type: u8, blocks: &[u8]) -> Vec<u8> {
+        let mut data = frame_id.to_be_bytes().to_vec();
+        data.extend_from_slice(&[service_id, service_type, 0x00, 0x00, 0x10, 0x01, 0x00, 0x80]);
+        data.extend_from_slice(&(blocks.len() as u16).to_be_bytes());
+        data.extend_from_slice(blocks);
+        data
+    }
+
+    fn parse(payload: &[u8]) -> (bool, DcpAttr) {
+        let mut attr = DcpAttr::new(test_link());
+        (attr.set_dcp(payload), attr)
+    }
+
+    #[test]
+    fn identify_all_request() {
+        let (ok, attr) = parse(&dcp(FrameIdValues::DcpIdentifyRequest, ServiceValues::Identify, ServiceValues::Request, &[0xff, 0xff, 0x00, 0x00]));
+        assert!(ok);
+        assert_eq!(attr.xid, 0x1001);
+        assert_eq!(attr.response_delay, 0x80);
+        assert_eq!(attr.options, vec![0xffff]);
+    }
+
+    #[test]
+    fn identify_response() {
+        let mut blocks = vec![0x02, 0x02, 0x00, 0x0b, 0x00, 0x00];
+        blocks.extend_from_slice(b"plc-01xxx");
+        blocks.push(0x00); // padding
+        blocks.extend_from_slice(&[0x02, 0x03, 0x00, 0x06, 0x00, 0x00, 0x00, 0x2a, 0x01, 0x0d]);
+        blocks.extend_from_slice(&[0x01, 0x02, 0x00, 0x0e, 0x00, 0x01, 192, 168, 0, 1, 255, 255, 255, 0, 192, 168, 0, 254]);
+        blocks.extend_from_slice(&[0x02, 0x04, 0x00, 0x04, 0x00, 0x00, 0x02, 0x00]);
+        let (ok, attr) = parse(&dcp(FrameIdValues::DcpIdentifyResponse, ServiceValues::Identify, ServiceValues::ResponseSuccess, &blocks));
+        assert!(ok);
+        assert_eq!(attr.options, vec![0x0202, 0x0203, 0x0102, 0x0204]);
+        assert_eq!(attr.name_of_station.as_deref(), Some("plc-01xxx"));
+        assert_eq!(attr.vendor_id, Some(0x2a));
+        assert_eq!(attr.device_id, Some(0x010d));
+        assert_eq!(attr.ip_address.as_deref(), Some("192.168.0.1"));
+        assert_eq!(attr.subnet_mask.as_deref(), Some("255.255.255.0"));
+        assert_eq!(attr.gateway.as_deref(), Some("192.168.0.254"));
+        assert_eq!(attr.device_role, Some(0x02));
+        assert_eq!(attr.block_qualifier, None);
+    }
+
+    #[test]
+    fn set_and_get() {
+        let mut blocks = vec![0x02, 0x02, 0x00, 0x08, 0x00, 0x01];
+        blocks.extend_from_slice(b"io-dev");
+        blocks.extend_from_slice(&[0x05, 0x03, 0x00, 0x02, 0x00, 0x01]);
+        let (_, attr) = parse(&dcp(FrameIdValues::DcpGetSet, ServiceValues::Set, ServiceValues::Request, &blocks));
+        assert_eq!(attr.block_qualifier, Some(1));
+        assert_eq!(attr.name_of_station.as_deref(), Some("io-dev"));
+        assert_eq!(attr.control, Some(3));
+        let (_, attr) = parse(&dcp(FrameIdValues::DcpGetSet, ServiceValues::Set, ServiceValues::ResponseSuccess, &[0x05, 0x04, 0x00, 0x03, 0x02, 0x02, 0x03, 0x00]));
+        assert_eq!(attr.options, vec![0x0504]);
+        assert_eq!(attr.response_error, Some(3));
+        let (_, attr) = parse(&dcp(FrameIdValues::DcpGetSet, ServiceValues::Get, ServiceValues::Request, &[0x02, 0x02, 0x01, 0x02, 0x07]));
+        assert_eq!(attr.options, vec![0x0202, 0x0102]);
+    }
+
+    #[test]
+    fn malformed_dcp() {
+        assert!(!parse(&[0xfe, 0xfe, 0x05, 0x00, 0x00]).0);
+        assert!(!parse(&dcp(0xc000, ServiceValues::Identify, ServiceValues::Request, &[])).0);
+        /* block length past the data, DataLength past the frame */
+        let (ok, attr) = parse(&dcp(FrameIdValues::DcpIdentifyResponse, ServiceValues::Identify, ServiceValues::ResponseSuccess, &[0x02, 0x02, 0x00, 0x40, 0x00, 0x00, b'p']));
+        assert!(ok);
+        assert!(attr.options.is_empty());
+        let mut data = dcp(FrameIdValues::DcpIdentifyResponse, ServiceValues::Identify, ServiceValues::ResponseSuccess, &[0x02, 0x04, 0x00, 0x04, 0x00, 0x00, 0x01, 0x00]);
+        data[11] = 0xff;
+        let (_, attr) = parse(&data);
+        assert_eq!(attr.device_role, Some(0x01));
+        /* IP block too short for three addresses */
+        let (_, attr) = parse(&dcp(FrameIdValues::DcpIdentifyResponse, ServiceValues::Identify, ServiceValues::ResponseSuccess, &[0x01, 0x02, 0x00, 0x06, 0x00, 0x00, 192, 168, 0, 1]));
+        assert_eq!(attr.ip_address, None);
+    }
+
+    #[test]
+    fn rt_cyclic_frame() {
+        let mut payload = vec![0x80, 0x00];
+        payload.extend_from_slice(&[0x00; 40]);
+        payload.extend_from_slice(&[0x12, 0x34, 0x35, 0x00]);
+        let mut attr = RtAttr::new(test_link());
+        assert!(attr.set_rt(&payload));
+        assert_eq!(attr.frame_id, 0x8000);
+        assert_eq!(attr.rt_class, "RT_CLASS_2");
+        assert_eq!(attr.data_length, 40);
+        assert_eq!(attr.cycle_counter, 0x1234);
+        assert_eq!(attr.data_status, 0x35);
+        assert_eq!(attr.transfer_status, 0x00);
+        let mut attr = RtAttr::new(test_link());
+        assert!(!attr.set_rt(&[0xfe, 0xfe, 0x00, 0x00, 0x00, 0x00]));
+        assert!(!attr.set_rt(&[0xc0, 0x00, 0x12, 0x34, 0x35]));
+        assert!(!attr.set_rt(&[0xc0]));
+    }
+}
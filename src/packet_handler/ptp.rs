@@ -0,0 +1,339 @@
+//! IEEE 1588 PTPv2 (ethertype 0x88F7, UDP 319 event / 320 general)
+//!
+//! Common header
+//! +------------------------+---------+--------+--------+--------+-------+------------+----------+
+//! | transportSpecific|Type | Version | Length | Domain | Rsvd   | Flags | Correction | Reserved |
+//! +------------------------+---------+--------+--------+--------+-------+------------+----------+
+//! |           1            |    1    |   2    |   1    |   1    |   2   |     8      |    4     |
+//! +--------------------------+------------+---------+-------------+
+//! | SourcePortIdentity       | SequenceID | Control | LogInterval |
+//! | (ClockIdentity | Port)   |            |         |             |
+//! +--------------------------+------------+---------+-------------+
+//! |         8 + 2            |     2      |    1    |      1      |
+//!
+//! Sync / Delay_Req / Follow_Up / Delay_Resp / Announce start with a timestamp
+//! (seconds (6) | nanoseconds (4)).
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+
+use pnet::packet::ethernet::EtherType;
+
+use super::bounded_map::BoundedMap;
+use super::{LinkAddresses, be_u16, be_u32};
+
+pub const PTP_ETHERTYPE: EtherType = EtherType(0x88F7);
+pub const PTP_EVENT_PORT: u16 = 319;
+pub const PTP_GENERAL_PORT: u16 = 320;
+
+const HEADER_LEN: usize = 34;
+const MAX_CLOCKS: usize = 1024;
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod MessageTypeValues {
+    pub const Sync: u8 = 0x0;
+    pub const DelayReq: u8 = 0x1;
+    pub const PdelayReq: u8 = 0x2;
+    pub const PdelayResp: u8 = 0x3;
+    pub const FollowUp: u8 = 0x8;
+    pub const DelayResp: u8 = 0x9;
+    pub const PdelayRespFollowUp: u8 = 0xA;
+    pub const Announce: u8 = 0xB;
+    pub const Signaling: u8 = 0xC;
+    pub const Management: u8 = 0xD;
+}
+
+#[derive(Debug)]
+pub struct PtpAttr {
+    pub link: LinkAddresses,
+    pub src_addr: Option<IpAddr>,
+    pub dst_addr: Option<IpAddr>,
+    pub message_type: u8,
+    pub version: u8,
+    pub message_length: u16,
+    pub domain: u8,
+    pub flags: u16,
+    // nanoseconds * 2^16
+    pub correction: i64,
+    pub clock_identity: String,
+    pub port_number: u16,
+    pub sequence_id: u16,
+    pub log_message_interval: i8,
+    // origin / precise origin / receive timestamp in nanoseconds
+    pub timestamp: Option<i64>,
+    // Announce
+    pub grandmaster_identity: Option<String>,
+    pub priority1: Option<u8>,
+    pub priority2: Option<u8>,
+    pub clock_class: Option<u8>,
+    pub clock_accuracy: Option<u8>,
+    pub steps_removed: Option<u16>,
+    pub utc_offset: Option<i16>,
+    pub time_source: Option<u8>,
+    // analysis
+    pub grandmaster_changed: bool,
+    pub sync_interval: Option<i64>,
+    pub sync_jitter: Option<i64>,
+}
+
+fn clock_identity(data: &[u8], offset: usize) -> Option<String> {
+    let identity = data.get(offset..offset + 8)?;
+    Some(identity.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(":"))
+}
+
+// seconds (48 bits) | nanoseconds (32 bits)
+fn timestamp(data: &[u8], offset: usize) -> Option<i64> {
+    let high = be_u16(data, offset)? as i64;
+    let low = be_u32(data, offset + 2)? as i64;
+    let nanoseconds = be_u32(data, offset + 6)? as i64;
+    ((high << 32 | low).checked_mul(1_000_000_000))?.checked_add(nanoseconds)
+}
+
+impl PtpAttr {
+    pub fn new(link: LinkAddresses, source: Option<IpAddr>, destination: Option<IpAddr>) -> Self {
+        Self {
+            link: link,
+            src_addr: source,
+            dst_addr: destination,
+            message_type: 0,
+            version: 0,
+            message_length: 0,
+            domain: 0,
+            flags: 0,
+            correction: 0,
+            clock_identity: String::new(),
+            port_number: 0,
+            sequence_id: 0,
+            log_message_interval: 0,
+            timestamp: None,
+            grandmaster_identity: None,
+            priority1: None,
+            priority2: None,
+            clock_class: None,
+            clock_accuracy: None,
+            steps_removed: None,
+            utc_offset: None,
+            time_source: None,
+            grandmaster_changed: false,
+            sync_interval: None,
+            sync_jitter: None,
+        }
+    }
+
+    pub fn set_ptp(&mut self, data: &[u8]) -> bool {
+        if data.len() < HEADER_LEN || data[1] & 0x0F != 2 {
+            return false;
+        }
+        self.message_type = data[0] & 0x0F;
+        self.version = data[1] & 0x0F;
+        self.message_length = be_u16(data, 2).unwrap_or(0);
+        self.domain = data[4];
+        self.flags = be_u16(data, 6).unwrap_or(0);
+        self.correction = ((be_u32(data, 8).unwrap_or(0) as u64) << 32 | be_u32(data, 12).unwrap_or(0) as u64) as i64;
+        self.clock_identity = clock_identity(data, 20).unwrap_or_default();
+        self.port_number = be_u16(data, 28).unwrap_or(0);
+        self.sequence_id = be_u16(data, 30).unwrap_or(0);
+        self.log_message_interval = data[33] as i8;
+        match self.message_type {
+            MessageTypeValues::Sync
+            | MessageTypeValues::DelayReq
+            | MessageTypeValues::FollowUp
+            | MessageTypeValues::DelayResp => {
+                self.timestamp = timestamp(data, HEADER_LEN);
+            }
+            MessageTypeValues::Announce => {
+                /* originTimestamp | currentUtcOffset | reserved | priority1 | clockQuality
+                   | priority2 | grandmasterIdentity | stepsRemoved | timeSource */
+                self.timestamp = timestamp(data, 34);
+                self.utc_offset = be_u16(data, 44).map(|offset| offset as i16);
+                self.priority1 = data.get(47).copied();
+                self.clock_class = data.get(48).copied();
+                self.clock_accuracy = data.get(49).copied();
+                self.priority2 = data.get(52).copied();
+                self.grandmaster_identity = clock_identity(data, 53);
+                self.steps_removed = be_u16(data, 61);
+                self.time_source = data.get(63).copied();
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+// Grandmaster per domain and Sync arrivals per master port (the MAX_CLOCKS
+// most recently heard)
+#[derive(Default)]
+pub struct PtpTracker {
+    grandmasters: HashMap<u8, String>,
+    syncs: BoundedMap<(u8, String, u16), DateTime<Utc>, MAX_CLOCKS>,
+}
+
+impl PtpTracker {
+    pub fn check(&mut self, attr: &mut PtpAttr, utc: DateTime<Utc>) {
+        match attr.message_type {
+            MessageTypeValues::Announce => {
+                let grandmaster = match &attr.grandmaster_identity {
+                    Some(grandmaster) => grandmaster.clone(),
+                    None => return,
+                };
+                if let Some(previous) = self.grandmasters.insert(attr.domain, grandmaster.clone()) {
+                    if previous != grandmaster {
+                        attr.grandmaster_changed = true;
+                        log::warn!("PTP domain {}: grandmaster changed {} -> {}", attr.domain, previous, grandmaster);
+                    }
+                }
+            }
+            MessageTypeValues::Sync => {
+                let key = (attr.domain, attr.clock_identity.clone(), attr.port_number);
+                if let Some(last) = self.syncs.insert(key, utc) {
+                    attr.sync_interval = (utc - last).num_nanoseconds();
+                    /* expected interval is 2^logMessageInterval seconds (0x7F: unspecified) */
+                    if attr.log_message_interval != 0x7F {
+                        let expected = (2f64.powi(attr.log_message_interval as i32) * 1e9) as i64;
+                        attr.sync_jitter = attr.sync_interval.map(|interval| interval - expected);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_link;
+
+    const CLOCK: [u8; 8] = [0x00, 0x1b, 0x1b, 0xff, 0xfe, 0x11, 0x22, 0x33];
+
+    fn message(message_type: u8, sequence_id: u16, body: &[u8]) -> Vec<u8> {
+        let mut data = vec![0u8; HEADER_LEN];
+        data[0] = message_type;
+        data[1] = 0x02;
+        data[2..4].copy_from_slice(&((HEADER_LEN + body.len()) as u16).to_be_bytes());
+        data[6..8].copy_from_slice(&[0x02, 0x00]);
+        /* correction field: 1.5 ns */
+        data[8..16].copy_from_slice(&0x18000i64.to_be_bytes());
+        data[20..28].copy_from_slice(&CLOCK);
+        data[28..30].copy_from_slice(&1u16.to_be_bytes());
+        data[30..32].copy_from_slice(&sequence_id.to_be_bytes());
+        data[33] = 0xFD;
+        data.extend_from_slice(body);
+        data
+    }
+
+    // 1700000000 s 250000000 ns
+    fn origin() -> Vec<u8> {
+        [&[0x00, 0x00][..], &1_700_000_000u32.to_be_bytes(), &250_000_000u32.to_be_bytes()].concat()
+    }
+
+    fn announce(grandmaster: [u8; 8]) -> Vec<u8> {
+        let mut body = origin();
+        body.extend_from_slice(&[0x00, 0x25, 0x00, 128, 6, 0x21, 0x4E, 0x5D, 128]);
+        body.extend_from_slice(&grandmaster);
+        body.extend_from_slice(&[0x00, 0x01, 0x20]);
+        message(MessageTypeValues::Announce, 7, &body)
+    }
+
+    fn parse(data: &[u8]) -> (bool, PtpAttr) {
+        let mut attr = PtpAttr::new(test_link(), None, None);
+        (attr.set_ptp(data), attr)
+    }
+
+    #[test]
+    fn sync_header() {
+        let (ok, attr) = parse(&message(MessageTypeValues::Sync, 42, &origin()));
+        assert!(ok);
+        assert_eq!(attr.message_type, MessageTypeValues::Sync);
+        assert_eq!(attr.version, 2);
+        assert_eq!(attr.message_length, 44);
+        assert_eq!(attr.flags, 0x0200);
+        assert_eq!(attr.correction, 0x18000);
+        assert_eq!(attr.clock_identity, "00:1b:1b:ff:fe:11:22:33");
+        assert_eq!(attr.port_number, 1);
+        assert_eq!(attr.sequence_id, 42);
+        assert_eq!(attr.log_message_interval, -3);
+        assert_eq!(attr.timestamp, Some(1_700_000_000_250_000_000));
+    }
+
+    #[test]
+    fn announce_fields() {
+        let (ok, attr) = parse(&announce(CLOCK));
+        assert!(ok);
+        assert_eq!(attr.timestamp, Some(1_700_000_000_250_000_000));
+        assert_eq!(attr.utc_offset, Some(37));
+        assert_eq!(attr.priority1, Some(128));
+        assert_eq!(attr.clock_class, Some(6));
+        assert_eq!(attr.clock_accuracy, Some(0x21));
+        assert_eq!(attr.priority2, Some(128));
+        assert_eq!(attr.grandmaster_identity.as_deref(), Some("00:1b:1b:ff:fe:11:22:33"));
+        assert_eq!(attr.steps_removed, Some(1));
+        assert_eq!(attr.time_source, Some(0x20));
+    }
+
+    #[test]
+    fn truncated_messages() {
+        /* short header, PTPv1 */
+        assert!(!parse(&message(MessageTypeValues::Sync, 1, &[])[..HEADER_LEN - 1]).0);
+        let mut data = message(MessageTypeValues::Sync, 1, &origin());
+        data[1] = 0x01;
+        assert!(!parse(&data).0);
+        /* timestamp and Announce body cut short */
+        let (ok, attr) = parse(&message(MessageTypeValues::Sync, 1, &origin()[..9]));
+        assert!(ok);
+        assert_eq!(attr.timestamp, None);
+        let data = announce(CLOCK);
+        let (ok, attr) = parse(&data[..55]);
+        assert!(ok);
+        assert_eq!(attr.priority2, Some(128));
+        assert_eq!(attr.grandmaster_identity, None);
+        assert_eq!(attr.steps_removed, None);
+        assert_eq!(timestamp(&[0xFF; 10], 0), None);
+    }
+
+    #[test]
+    fn grandmaster_change() {
+        let mut tracker = PtpTracker::default();
+        let (_, mut attr) = parse(&announce(CLOCK));
+        tracker.check(&mut attr, Utc::now());
+        assert!(!attr.grandmaster_changed);
+        let (_, mut attr) = parse(&announce(CLOCK));
+        tracker.check(&mut attr, Utc::now());
+        assert!(!attr.grandmaster_changed);
+        let (_, mut attr) = parse(&announce([0x00, 0x0e, 0x8c, 0xff, 0xfe, 0x00, 0x00, 0x01]));
+        tracker.check(&mut attr, Utc::now());
+        assert!(attr.grandmaster_changed);
+        /* other domain keeps its own grandmaster */
+        let mut data = announce(CLOCK);
+        data[4] = 24;
+        let (_, mut attr) = parse(&data);
+        tracker.check(&mut attr, Utc::now());
+        assert!(!attr.grandmaster_changed);
+    }
+
+    #[test]
+    fn sync_interval() {
+        let mut tracker = PtpTracker::default();
+        let utc = Utc::now();
+        let (_, mut attr) = parse(&message(MessageTypeValues::Sync, 1, &origin()));
+        tracker.check(&mut attr, utc);
+        assert_eq!(attr.sync_interval, None);
+        /* 130 ms after the previous Sync, 125 ms announced by logMessageInterval -3 */
+        let utc = utc + chrono::Duration::milliseconds(130);
+        let (_, mut attr) = parse(&message(MessageTypeValues::Sync, 2, &origin()));
+        tracker.check(&mut attr, utc);
+        assert_eq!(attr.sync_interval, Some(130_000_000));
+        assert_eq!(attr.sync_jitter, Some(5_000_000));
+        /* unspecified interval: no jitter */
+        let mut data = message(MessageTypeValues::Sync, 3, &origin());
+        data[33] = 0x7F;
+        let (_, mut attr) = parse(&data);
+        tracker.check(&mut attr, utc + chrono::Duration::milliseconds(125));
+        assert_eq!(attr.sync_interval, Some(125_000_000));
+        assert_eq!(attr.sync_jitter, None);
+    }
+}
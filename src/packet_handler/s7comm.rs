@@ -0,0 +1,445 @@
+//! Siemens S7comm / S7comm-plus over ISO-on-TCP (RFC 1006)
+//!
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  TPKT Version |   Reserved    |          TPKT Length          |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  COTP Length  |   PDU Type    |   COTP (DT: TPDU-NR/EOT) ...
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  Protocol ID  |    ROSCTR     |           Reserved            |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |         PDU Reference         |       Parameter Length        |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |          Data Length          |  Error Class  |  Error Code   |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |   Parameter ... | Data ...      (error fields only on ROSCTR 2/3)
+
+use pnet_macros::packet;
+use pnet_macros_support::types::*;
+use pnet::packet::Packet;
+
+use super::{Addresses, be_u16};
+
+// ISO-TSAP
+pub const S7COMM_PORT: u16 = 102;
+
+#[packet]
+pub struct Tpkt {
+    pub version: u8,
+    pub reserved: u8,
+    pub length: u16be,
+    #[payload]
+    pub payload: Vec<u8>,
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod CotpValues {
+    pub const ConnectionRequest: u8 = 0xE0;
+    pub const ConnectionConfirm: u8 = 0xD0;
+    pub const DisconnectRequest: u8 = 0x80;
+    pub const Data: u8 = 0xF0;
+    // parameter codes
+    pub const CallingTsap: u8 = 0xC1;
+    pub const CalledTsap: u8 = 0xC2;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod ProtocolValues {
+    pub const S7comm: u8 = 0x32;
+    pub const S7commPlus: u8 = 0x72;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod RosctrValues {
+    pub const Job: u8 = 0x01;
+    pub const Ack: u8 = 0x02;
+    pub const AckData: u8 = 0x03;
+    pub const Userdata: u8 = 0x07;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod FunctionValues {
+    pub const CpuServices: u8 = 0x00;
+    pub const ReadVar: u8 = 0x04;
+    pub const WriteVar: u8 = 0x05;
+    pub const RequestDownload: u8 = 0x1A;
+    pub const DownloadBlock: u8 = 0x1B;
+    pub const DownloadEnded: u8 = 0x1C;
+    pub const StartUpload: u8 = 0x1D;
+    pub const Upload: u8 = 0x1E;
+    pub const EndUpload: u8 = 0x1F;
+    pub const PiService: u8 = 0x28;
+    pub const PlcStop: u8 = 0x29;
+    pub const SetupCommunication: u8 = 0xF0;
+}
+
+#[derive(Debug)]
+pub struct S7Attr {
+    pub addresses: Addresses,
+    pub cotp_pdu_type: u8,
+    // COTP connection request/confirm
+    pub src_tsap: Option<u16>,
+    pub dst_tsap: Option<u16>,
+    // S7 header
+    pub protocol_id: Option<u8>,
+    pub rosctr: Option<u8>,
+    pub pdu_ref: Option<u16>,
+    pub error_class: Option<u8>,
+    pub error_code: Option<u8>,
+    pub function: Option<u8>,
+    // read/write var items (job) and return codes (ack-data)
+    pub item_count: Option<u8>,
+    pub item_transport_size: Vec<u8>,
+    pub item_length: Vec<u16>,
+    pub item_db_number: Vec<u16>,
+    pub item_area: Vec<u8>,
+    pub item_address: Vec<u32>,
+    pub item_return_code: Vec<u8>,
+    // setup communication
+    pub max_amq_calling: Option<u16>,
+    pub max_amq_called: Option<u16>,
+    pub pdu_length: Option<u16>,
+    // block upload/download file name (e.g. "_0A00001P") and PI service (e.g. "P_PROGRAM")
+    pub block: Option<String>,
+    pub pi_service: Option<String>,
+    // S7comm-plus
+    pub plus_version: Option<u8>,
+    pub plus_opcode: Option<u8>,
+    pub plus_function: Option<u16>,
+    pub plus_sequence: Option<u16>,
+}
+
+fn ascii(data: &[u8], offset: usize) -> Option<String> {
+    /* length byte followed by the characters */
+    let size = *data.get(offset)? as usize;
+    data.get(offset + 1..offset + 1 + size)
+        .map(|s| String::from_utf8_lossy(s).to_string())
+}
+
+impl S7Attr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            cotp_pdu_type: 0,
+            src_tsap: None,
+            dst_tsap: None,
+            protocol_id: None,
+            rosctr: None,
+            pdu_ref: None,
+            error_class: None,
+            error_code: None,
+            function: None,
+            item_count: None,
+            item_transport_size: Vec::new(),
+            item_length: Vec::new(),
+            item_db_number: Vec::new(),
+            item_area: Vec::new(),
+            item_address: Vec::new(),
+            item_return_code: Vec::new(),
+            max_amq_calling: None,
+            max_amq_called: None,
+            pdu_length: None,
+            block: None,
+            pi_service: None,
+            plus_version: None,
+            plus_opcode: None,
+            plus_function: None,
+            plus_sequence: None,
+        }
+    }
+
+    // returns false when the TPKT payload is not COTP
+    pub fn set_s7(&mut self, tpkt: &TpktPacket) -> bool {
+        let cotp = tpkt.payload();
+        let (header_len, pdu_type) = match (cotp.get(0), cotp.get(1)) {
+            (Some(len), Some(pdu_type)) => (*len as usize + 1, pdu_type & 0xF0),
+            _ => return false,
+        };
+        if tpkt.get_version() != 3 || cotp.len() < header_len {
+            return false;
+        }
+        self.cotp_pdu_type = pdu_type;
+        match pdu_type {
+            CotpValues::ConnectionRequest | CotpValues::ConnectionConfirm => {
+                /* DST-REF (2) | SRC-REF (2) | Class (1) | Parameters ... */
+                let mut offset = 7;
+                while offset + 2 <= header_len {
+                    let code = cotp[offset];
+                    let size = cotp[offset + 1] as usize;
+                    match code {
+                        CotpValues::CallingTsap => self.src_tsap = be_u16(cotp, offset + 2),
+                        CotpValues::CalledTsap => self.dst_tsap = be_u16(cotp, offset + 2),
+                        _ => {}
+                    }
+                    offset += 2 + size;
+                }
+            }
+            CotpValues::Data => {
+                self.set_pdu(&cotp[header_len..]);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn set_pdu(&mut self, pdu: &[u8]) {
+        self.protocol_id = pdu.get(0).cloned();
+        match self.protocol_id {
+            Some(ProtocolValues::S7comm) => self.set_s7comm(pdu),
+            Some(ProtocolValues::S7commPlus) => self.set_s7comm_plus(pdu),
+            _ => {}
+        }
+    }
+
+    fn set_s7comm(&mut self, pdu: &[u8]) {
+        let rosctr = match pdu.get(1) {
+            Some(rosctr) => *rosctr,
+            None => return,
+        };
+        self.rosctr = Some(rosctr);
+        self.pdu_ref = be_u16(pdu, 4);
+        let (param_len, data_len) = match (be_u16(pdu, 6), be_u16(pdu, 8)) {
+            (Some(param_len), Some(data_len)) => (param_len as usize, data_len as usize),
+            _ => return,
+        };
+        let header_len = match rosctr {
+            RosctrValues::Ack | RosctrValues::AckData => {
+                self.error_class = pdu.get(10).cloned();
+                self.error_code = pdu.get(11).cloned();
+                12
+            }
+            _ => 10,
+        };
+        let params = match pdu.get(header_len..header_len + param_len) {
+            Some(params) => params,
+            None => return,
+        };
+        let data = pdu.get(header_len + param_len..header_len + param_len + data_len).unwrap_or(&[]);
+        if rosctr == RosctrValues::Userdata {
+            return;
+        }
+        self.function = params.get(0).cloned();
+        match (rosctr, self.function) {
+            (RosctrValues::Job, Some(FunctionValues::ReadVar))
+            | (RosctrValues::Job, Some(FunctionValues::WriteVar)) => {
+                self.item_count = params.get(1).cloned();
+                self.set_var_items(params);
+            }
+            (RosctrValues::AckData, Some(FunctionValues::ReadVar)) => {
+                self.item_count = params.get(1).cloned();
+                self.set_read_data(data);
+            }
+            (RosctrValues::AckData, Some(FunctionValues::WriteVar)) => {
+                /* one return code per item */
+                self.item_count = params.get(1).cloned();
+                let count = self.item_count.unwrap_or(0) as usize;
+                self.item_return_code = data.iter().take(count).cloned().collect();
+            }
+            (_, Some(FunctionValues::SetupCommunication)) => {
+                /* Function | Reserved | Max AmQ calling | Max AmQ called | PDU length */
+                self.max_amq_calling = be_u16(params, 2);
+                self.max_amq_called = be_u16(params, 4);
+                self.pdu_length = be_u16(params, 6);
+            }
+            (RosctrValues::Job, Some(FunctionValues::RequestDownload))
+            | (RosctrValues::Job, Some(FunctionValues::DownloadBlock))
+            | (RosctrValues::Job, Some(FunctionValues::DownloadEnded))
+            | (RosctrValues::Job, Some(FunctionValues::StartUpload)) => {
+                /* Function | Status | Unknown (2) | Upload ID / Unknown (4) | File name */
+                self.block = ascii(params, 8);
+            }
+            (RosctrValues::Job, Some(FunctionValues::PiService)) => {
+                /* Function | Unknown (7) | Parameter block length | Parameter block | Service name */
+                if let Some(block_len) = be_u16(params, 8) {
+                    self.pi_service = ascii(params, 10 + block_len as usize);
+                }
+            }
+            (RosctrValues::Job, Some(FunctionValues::PlcStop)) => {
+                /* Function | Unknown (5) | Service name */
+                self.pi_service = ascii(params, 6);
+            }
+            _ => {}
+        }
+    }
+
+    fn set_var_items(&mut self, params: &[u8]) {
+        /* Variable specification (0x12) | Length | Syntax ID | Transport size | Length |
+           DB number | Area | Address (3) */
+        let mut offset = 2;
+        for _ in 0..self.item_count.unwrap_or(0) {
+            let size = match params.get(offset + 1) {
+                Some(size) => *size as usize,
+                None => return,
+            };
+            let item = match params.get(offset + 2..offset + 2 + size) {
+                Some(item) => item,
+                None => return,
+            };
+            /* S7ANY addressing only */
+            if item.len() >= 10 && item[0] == 0x10 {
+                self.item_transport_size.push(item[1]);
+                self.item_length.push(be_u16(item, 2).unwrap_or(0));
+                self.item_db_number.push(be_u16(item, 4).unwrap_or(0));
+                self.item_area.push(item[6]);
+                self.item_address.push((item[7] as u32) << 16 | (item[8] as u32) << 8 | item[9] as u32);
+            }
+            offset += 2 + size;
+        }
+    }
+
+    fn set_read_data(&mut self, data: &[u8]) {
+        /* Return code | Transport size | Length | Data (padded to even except the last item) */
+        let mut offset = 0;
+        for _ in 0..self.item_count.unwrap_or(0) {
+            let (return_code, transport_size, length) = match (data.get(offset), data.get(offset + 1), be_u16(data, offset + 2)) {
+                (Some(return_code), Some(transport_size), Some(length)) => (*return_code, *transport_size, length as usize),
+                _ => return,
+            };
+            self.item_return_code.push(return_code);
+            let size = match transport_size {
+                /* BIT, BYTE/WORD/DWORD and INTEGER give the length in bits */
+                0x03 | 0x04 | 0x05 => (length + 7) / 8,
+                _ => length,
+            };
+            offset += 4 + size + size % 2;
+        }
+    }
+
+    fn set_s7comm_plus(&mut self, pdu: &[u8]) {
+        /* Protocol ID | Version | Data length | [Integrity part (V3)] | Opcode | Reserved (2) |
+           Function (2) | Reserved (2) | Sequence number (2) */
+        self.plus_version = pdu.get(1).cloned();
+        let mut offset = 4;
+        if self.plus_version == Some(0x03) {
+            match pdu.get(4) {
+                Some(digest_len) => offset += 1 + *digest_len as usize,
+                None => return,
+            }
+        }
+        self.plus_opcode = pdu.get(offset).cloned();
+        self.plus_function = be_u16(pdu, offset + 3);
+        self.plus_sequence = be_u16(pdu, offset + 7);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+
+    fn parse(frame: &[u8]) -> (bool, S7Attr) {
+        let mut attr = S7Attr::new(test_addresses(50000, S7COMM_PORT));
+        let tpkt = TpktPacket::new(frame).unwrap();
+        (attr.set_s7(&tpkt), attr)
+    }
+
+    #[test]
+    fn connection_request_tsaps() {
+        let frame = [
+            0x03, 0x00, 0x00, 0x16, 0x11, 0xe0, 0x00, 0x00, 0x00, 0x01, 0x00,
+            0xc1, 0x02, 0x01, 0x00, 0xc2, 0x02, 0x01, 0x02, 0xc0, 0x01, 0x0a,
+        ];
+        let (ok, attr) = parse(&frame);
+        assert!(ok);
+        assert_eq!(attr.cotp_pdu_type, CotpValues::ConnectionRequest);
+        assert_eq!(attr.src_tsap, Some(0x0100));
+        assert_eq!(attr.dst_tsap, Some(0x0102));
+        assert_eq!(attr.protocol_id, None);
+    }
+
+    #[test]
+    fn read_var_job() {
+        let frame = [
+            0x03, 0x00, 0x00, 0x1f, 0x02, 0xf0, 0x80,
+            0x32, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0e, 0x00, 0x00,
+            0x04, 0x01, 0x12, 0x0a, 0x10, 0x02, 0x00, 0x10, 0x00, 0x01, 0x84, 0x00, 0x00, 0x28,
+        ];
+        let (ok, attr) = parse(&frame);
+        assert!(ok);
+        assert_eq!(attr.protocol_id, Some(ProtocolValues::S7comm));
+        assert_eq!(attr.rosctr, Some(RosctrValues::Job));
+        assert_eq!(attr.pdu_ref, Some(1));
+        assert_eq!(attr.function, Some(FunctionValues::ReadVar));
+        assert_eq!(attr.item_count, Some(1));
+        assert_eq!(attr.item_transport_size, vec![0x02]);
+        assert_eq!(attr.item_length, vec![16]);
+        assert_eq!(attr.item_db_number, vec![1]);
+        assert_eq!(attr.item_area, vec![0x84]);
+        assert_eq!(attr.item_address, vec![0x28]);
+    }
+
+    #[test]
+    fn read_var_ack_data() {
+        let mut frame = vec![
+            0x03, 0x00, 0x00, 0x29, 0x02, 0xf0, 0x80,
+            0x32, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x14, 0x00, 0x00,
+            0x04, 0x01, 0xff, 0x04, 0x00, 0x80,
+        ];
+        frame.extend_from_slice(&[0x55; 16]);
+        let (ok, attr) = parse(&frame);
+        assert!(ok);
+        assert_eq!(attr.rosctr, Some(RosctrValues::AckData));
+        assert_eq!(attr.error_class, Some(0));
+        assert_eq!(attr.error_code, Some(0));
+        assert_eq!(attr.item_return_code, vec![0xff]);
+    }
+
+    #[test]
+    fn setup_communication() {
+        let frame = [
+            0x03, 0x00, 0x00, 0x19, 0x02, 0xf0, 0x80,
+            0x32, 0x01, 0x00, 0x00, 0xff, 0xff, 0x00, 0x08, 0x00, 0x00,
+            0xf0, 0x00, 0x00, 0x01, 0x00, 0x01, 0x01, 0xe0,
+        ];
+        let (_, attr) = parse(&frame);
+        assert_eq!(attr.function, Some(FunctionValues::SetupCommunication));
+        assert_eq!(attr.max_amq_calling, Some(1));
+        assert_eq!(attr.max_amq_called, Some(1));
+        assert_eq!(attr.pdu_length, Some(480));
+    }
+
+    #[test]
+    fn s7comm_plus_header() {
+        let frame = [
+            0x03, 0x00, 0x00, 0x1a, 0x02, 0xf0, 0x80,
+            0x72, 0x01, 0x00, 0x0b, 0x31, 0x00, 0x00, 0x04, 0xca, 0x00, 0x00, 0x00, 0x02, 0x00,
+        ];
+        let (ok, attr) = parse(&frame);
+        assert!(ok);
+        assert_eq!(attr.protocol_id, Some(ProtocolValues::S7commPlus));
+        assert_eq!(attr.plus_version, Some(1));
+        assert_eq!(attr.plus_opcode, Some(0x31));
+        assert_eq!(attr.plus_function, Some(0x04ca));
+        assert_eq!(attr.plus_sequence, Some(2));
+    }
+
+    #[test]
+    fn malformed_frames() {
+        /* TPKT version other than 3, COTP header longer than the payload */
+        assert!(!parse(&[0x02, 0x00, 0x00, 0x07, 0x02, 0xf0, 0x80]).0);
+        assert!(!parse(&[0x03, 0x00, 0x00, 0x07, 0x11, 0xe0, 0x00]).0);
+        assert!(!parse(&[0x03, 0x00, 0x00, 0x04]).0);
+        /* item count larger than the items present */
+        let frame = [
+            0x03, 0x00, 0x00, 0x1b, 0x02, 0xf0, 0x80,
+            0x32, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0e, 0x00, 0x00,
+            0x04, 0x05, 0x12, 0x0a, 0x10, 0x02, 0x00, 0x10,
+        ];
+        let (ok, attr) = parse(&frame);
+        assert!(ok);
+        assert_eq!(attr.function, None);
+        assert!(attr.item_length.is_empty());
+        /* S7comm-plus V3 with the integrity part cut off */
+        let (ok, attr) = parse(&[0x03, 0x00, 0x00, 0x0c, 0x02, 0xf0, 0x80, 0x72, 0x03, 0x00, 0x20, 0x20]);
+        assert!(ok);
+        assert_eq!(attr.plus_version, Some(3));
+        assert_eq!(attr.plus_opcode, None);
+    }
+}
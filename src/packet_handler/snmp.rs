@@ -0,0 +1,397 @@
+//! SNMP v1/v2c (UDP 161 agent / 162 trap receiver)
+//!
+//! Message ::= SEQUENCE { version INTEGER, community OCTET STRING, data PDU }
+//! PDU ::= [n] { request-id, error-status, error-index, VarBindList }
+//! Trap-PDU (v1) ::= [4] { enterprise, agent-addr, generic-trap, specific-trap, time-stamp, VarBindList }
+//! VarBind ::= SEQUENCE { name OBJECT IDENTIFIER, value }
+//!
+//! The community string is kept as an HMAC-SHA256 digest only. The key is set
+//! per deployment (ARROWS_SNMP_COMMUNITY_KEY) so that digests of common
+//! communities like "public" can't be looked up in a dictionary; without it a
+//! random key is used and digests only compare within one run.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::net::Ipv4Addr;
+
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use super::Addresses;
+use super::x509::{der, oid};
+
+pub const SNMP_PORT: u16 = 161;
+pub const SNMP_TRAP_PORT: u16 = 162;
+pub const COMMUNITY_KEY_ENV: &str = "ARROWS_SNMP_COMMUNITY_KEY";
+
+// HMAC key for community strings (never logged)
+#[derive(Clone)]
+pub struct CommunityKey(Vec<u8>);
+
+impl CommunityKey {
+    pub fn from_env() -> Self {
+        match std::env::var(COMMUNITY_KEY_ENV) {
+            Ok(key) if !key.is_empty() => CommunityKey(key.into_bytes()),
+            _ => {
+                log::warn!("{} is not set, SNMP community digests use a random key", COMMUNITY_KEY_ENV);
+                CommunityKey::random()
+            }
+        }
+    }
+
+    pub fn random() -> Self {
+        let mut key = vec![0u8; 32];
+        let read = File::open("/dev/urandom").and_then(|mut urandom| urandom.read_exact(&mut key));
+        if read.is_err() {
+            /* SipHash keys of RandomState are seeded from the OS */
+            for chunk in key.chunks_mut(8) {
+                let mut hasher = RandomState::new().build_hasher();
+                hasher.write_usize(chunk.as_ptr() as usize);
+                chunk.copy_from_slice(&hasher.finish().to_be_bytes());
+            }
+        }
+        CommunityKey(key)
+    }
+
+    fn digest(&self, community: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC accepts any key length");
+        mac.update(community);
+        mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl Default for CommunityKey {
+    fn default() -> Self {
+        CommunityKey::random()
+    }
+}
+
+impl fmt::Debug for CommunityKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CommunityKey(..)")
+    }
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod PduValues {
+    pub const GetRequest: u8 = 0xA0;
+    pub const GetNextRequest: u8 = 0xA1;
+    pub const Response: u8 = 0xA2;
+    pub const SetRequest: u8 = 0xA3;
+    pub const Trap: u8 = 0xA4;
+    pub const GetBulkRequest: u8 = 0xA5;
+    pub const InformRequest: u8 = 0xA6;
+    pub const SNMPv2Trap: u8 = 0xA7;
+    pub const Report: u8 = 0xA8;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod TagValues {
+    pub const Integer: u8 = 0x02;
+    pub const OctetString: u8 = 0x04;
+    pub const Null: u8 = 0x05;
+    pub const ObjectIdentifier: u8 = 0x06;
+    pub const Sequence: u8 = 0x30;
+    pub const IpAddress: u8 = 0x40;
+    pub const Counter32: u8 = 0x41;
+    pub const Gauge32: u8 = 0x42;
+    pub const TimeTicks: u8 = 0x43;
+    pub const Counter64: u8 = 0x46;
+    pub const NoSuchObject: u8 = 0x80;
+    pub const NoSuchInstance: u8 = 0x81;
+    pub const EndOfMibView: u8 = 0x82;
+}
+
+#[derive(Debug)]
+pub struct SnmpAttr {
+    pub addresses: Addresses,
+    pub version: u8,
+    pub community_hash: String,
+    pub pdu_type: u8,
+    pub request_id: Option<i32>,
+    pub error_status: Option<i32>,
+    pub error_index: Option<i32>,
+    // v1 Trap
+    pub enterprise: Option<String>,
+    pub agent_addr: Option<String>,
+    pub generic_trap: Option<i32>,
+    pub specific_trap: Option<i32>,
+    pub varbind_oids: Vec<String>,
+    pub varbind_types: Vec<u8>,
+    pub varbind_values: Vec<String>,
+    // SetRequest, traps and informs
+    pub high_interest: bool,
+}
+
+fn integer(value: &[u8]) -> i64 {
+    let sign = if value.first().map_or(false, |b| b & 0x80 != 0) { -1i64 } else { 0 };
+    value.iter().take(8).fold(sign, |n, b| n << 8 | *b as i64)
+}
+
+fn unsigned(value: &[u8]) -> u64 {
+    value.iter().take(9).fold(0, |n, b| n << 8 | *b as u64)
+}
+
+fn value_text(tag: u8, value: &[u8]) -> String {
+    match tag {
+        TagValues::Integer => integer(value).to_string(),
+        TagValues::Counter32 | TagValues::Gauge32 | TagValues::TimeTicks | TagValues::Counter64 => unsigned(value).to_string(),
+        TagValues::ObjectIdentifier => oid(value),
+        TagValues::IpAddress if value.len() == 4 => Ipv4Addr::new(value[0], value[1], value[2], value[3]).to_string(),
+        TagValues::OctetString => match std::str::from_utf8(value) {
+            Ok(text) if text.chars().all(|c| !c.is_control() || c.is_whitespace()) => text.to_string(),
+            _ => value.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(":"),
+        },
+        TagValues::Null => String::new(),
+        TagValues::NoSuchObject => "noSuchObject".to_string(),
+        TagValues::NoSuchInstance => "noSuchInstance".to_string(),
+        TagValues::EndOfMibView => "endOfMibView".to_string(),
+        _ => value.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(""),
+    }
+}
+
+impl SnmpAttr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            version: 0,
+            community_hash: String::new(),
+            pdu_type: 0,
+            request_id: None,
+            error_status: None,
+            error_index: None,
+            enterprise: None,
+            agent_addr: None,
+            generic_trap: None,
+            specific_trap: None,
+            varbind_oids: Vec::new(),
+            varbind_types: Vec::new(),
+            varbind_values: Vec::new(),
+            high_interest: false,
+        }
+    }
+
+    pub fn set_snmp(&mut self, data: &[u8], key: &CommunityKey) -> bool {
+        self.set_message(data, key).is_some()
+    }
+
+    fn set_message(&mut self, data: &[u8], key: &CommunityKey) -> Option<()> {
+        let (tag, start, end) = der(data, 0)?;
+        if tag != TagValues::Sequence {
+            return None;
+        }
+        let message = &data[..end];
+        let (tag, version_start, version_end) = der(message, start)?;
+        let version = integer(&message[version_start..version_end]);
+        /* v1 = 0, v2c = 1 (v3 has no community) */
+        if tag != TagValues::Integer || !(version == 0 || version == 1) {
+            return None;
+        }
+        let (tag, community_start, community_end) = der(message, version_end)?;
+        if tag != TagValues::OctetString {
+            return None;
+        }
+        let (pdu_type, pdu_start, pdu_end) = der(message, community_end)?;
+        if !(PduValues::GetRequest..=PduValues::Report).contains(&pdu_type) {
+            return None;
+        }
+        self.version = version as u8;
+        self.community_hash = key.digest(&message[community_start..community_end]);
+        self.pdu_type = pdu_type;
+        self.high_interest = match pdu_type {
+            PduValues::SetRequest | PduValues::Trap | PduValues::SNMPv2Trap | PduValues::InformRequest => true,
+            _ => false,
+        };
+        let pdu = &message[..pdu_end];
+        let mut fields = Vec::new();
+        let mut offset = pdu_start;
+        while let Some((tag, start, end)) = der(pdu, offset) {
+            fields.push((tag, start, end));
+            offset = end;
+        }
+        let varbinds = if pdu_type == PduValues::Trap {
+            /* enterprise | agent-addr | generic-trap | specific-trap | time-stamp | varbinds */
+            if fields.len() < 6 {
+                return None;
+            }
+            let (_, start, end) = fields[0];
+            self.enterprise = Some(oid(&pdu[start..end]));
+            let (tag, start, end) = fields[1];
+            self.agent_addr = Some(value_text(tag, &pdu[start..end]));
+            self.generic_trap = Some(integer(&pdu[fields[2].1..fields[2].2]) as i32);
+            self.specific_trap = Some(integer(&pdu[fields[3].1..fields[3].2]) as i32);
+            fields[5]
+        } else {
+            /* request-id | error-status (non-repeaters) | error-index (max-repetitions) | varbinds */
+            if fields.len() < 4 {
+                return None;
+            }
+            self.request_id = Some(integer(&pdu[fields[0].1..fields[0].2]) as i32);
+            self.error_status = Some(integer(&pdu[fields[1].1..fields[1].2]) as i32);
+            self.error_index = Some(integer(&pdu[fields[2].1..fields[2].2]) as i32);
+            fields[3]
+        };
+        self.set_varbinds(&pdu[..varbinds.2], varbinds.1);
+        Some(())
+    }
+
+    fn set_varbinds(&mut self, data: &[u8], start: usize) {
+        let mut offset = start;
+        while let Some((_, varbind_start, varbind_end)) = der(data, offset) {
+            let varbind = &data[..varbind_end];
+            if let Some((TagValues::ObjectIdentifier, oid_start, oid_end)) = der(varbind, varbind_start) {
+                if let Some((tag, value_start, value_end)) = der(varbind, oid_end) {
+                    self.varbind_oids.push(oid(&varbind[oid_start..oid_end]));
+                    self.varbind_types.push(tag);
+                    self.varbind_values.push(value_text(tag, &varbind[value_start..value_end]));
+                }
+            }
+            offset = varbind_end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+
+    // 1.3.6.1.2.1.1.1.0 (sysDescr.0), 1.3.6.1.2.1.1.3.0 (sysUpTime.0)
+    const SYS_DESCR: [u8; 8] = [0x2B, 0x06, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00];
+    const SYS_UPTIME: [u8; 8] = [0x2B, 0x06, 0x01, 0x02, 0x01, 0x01, 0x03, 0x00];
+
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag, content.len() as u8];
+        element.extend_from_slice(content);
+        element
+    }
+
+    fn varbind(name: &[u8], tag: u8, value: &[u8]) -> Vec<u8> {
+        tlv(TagValues::Sequence, &[tlv(TagValues::ObjectIdentifier, name), tlv(tag, value)].concat())
+    }
+
+    fn message(version: u8, community: &[u8], pdu_type: u8, fields: &[Vec<u8>], varbinds: &[Vec<u8>]) -> Vec<u8> {
+        let mut pdu = fields.concat();
+        pdu.extend(tlv(TagValues::Sequence, &varbinds.concat()));
+        let body = [
+            tlv(TagValues::Integer, &[version]),
+            tlv(TagValues::OctetString, community),
+            tlv(pdu_type, &pdu),
+        ];
+        tlv(TagValues::Sequence, &body.concat())
+    }
+
+    // request-id 0x1234, error-status, error-index
+    fn request_fields(error_status: u8) -> Vec<Vec<u8>> {
+        vec![
+            tlv(TagValues::Integer, &[0x12, 0x34]),
+            tlv(TagValues::Integer, &[error_status]),
+            tlv(TagValues::Integer, &[0]),
+        ]
+    }
+
+    fn key() -> CommunityKey {
+        CommunityKey(b"site-key".to_vec())
+    }
+
+    fn parse(data: &[u8]) -> (bool, SnmpAttr) {
+        let mut attr = SnmpAttr::new(test_addresses(50123, SNMP_PORT));
+        (attr.set_snmp(data, &key()), attr)
+    }
+
+    #[test]
+    fn get_request() {
+        let data = message(1, b"public", PduValues::GetRequest, &request_fields(0), &[varbind(&SYS_DESCR, TagValues::Null, &[])]);
+        let (ok, attr) = parse(&data);
+        assert!(ok);
+        assert_eq!(attr.version, 1);
+        assert_eq!(attr.pdu_type, PduValues::GetRequest);
+        assert_eq!(attr.community_hash, "4afa031f49e5de008e57b50b58d732a40979ae1c7f3e5e3fc8b15bdd538b1a80");
+        assert_eq!(attr.request_id, Some(0x1234));
+        assert_eq!(attr.error_status, Some(0));
+        assert_eq!(attr.error_index, Some(0));
+        assert_eq!(attr.varbind_oids, vec!["1.3.6.1.2.1.1.1.0".to_string()]);
+        assert_eq!(attr.varbind_types, vec![TagValues::Null]);
+        assert_eq!(attr.varbind_values, vec![String::new()]);
+        assert!(!attr.high_interest);
+    }
+
+    #[test]
+    fn response_values() {
+        let varbinds = [
+            varbind(&SYS_DESCR, TagValues::OctetString, b"SIMATIC S7-1500"),
+            varbind(&SYS_UPTIME, TagValues::TimeTicks, &[0x00, 0x9A, 0x7E, 0xC8]),
+            varbind(&SYS_UPTIME, TagValues::IpAddress, &[192, 168, 0, 1]),
+            varbind(&SYS_UPTIME, TagValues::OctetString, &[0x00, 0x1b, 0x1b]),
+            varbind(&SYS_UPTIME, TagValues::NoSuchInstance, &[]),
+        ];
+        let (ok, attr) = parse(&message(0, b"public", PduValues::Response, &request_fields(2), &varbinds));
+        assert!(ok);
+        assert_eq!(attr.version, 0);
+        assert_eq!(attr.error_status, Some(2));
+        assert_eq!(attr.varbind_values, vec!["SIMATIC S7-1500", "10125000", "192.168.0.1", "00:1b:1b", "noSuchInstance"]);
+    }
+
+    #[test]
+    fn set_request_and_trap() {
+        let data = message(1, b"private", PduValues::SetRequest, &request_fields(0), &[varbind(&SYS_DESCR, TagValues::Integer, &[0xFF])]);
+        let (ok, attr) = parse(&data);
+        assert!(ok);
+        assert!(attr.high_interest);
+        assert_eq!(attr.community_hash, "0a79090451a1c92a8eca9f8cf6efb7425a04fa935818832e66dec72672777835");
+        assert_eq!(attr.varbind_values, vec!["-1"]);
+        /* v1 Trap: enterprise | agent-addr | linkDown | 0 | time-stamp */
+        let fields = vec![
+            tlv(TagValues::ObjectIdentifier, &[0x2B, 0x06, 0x01, 0x04, 0x01, 0x81, 0xB0, 0x2A]),
+            tlv(TagValues::IpAddress, &[192, 168, 0, 10]),
+            tlv(TagValues::Integer, &[2]),
+            tlv(TagValues::Integer, &[0]),
+            tlv(TagValues::TimeTicks, &[0x01, 0x00]),
+        ];
+        let (ok, attr) = parse(&message(0, b"public", PduValues::Trap, &fields, &[varbind(&SYS_UPTIME, TagValues::Integer, &[3])]));
+        assert!(ok);
+        assert!(attr.high_interest);
+        assert_eq!(attr.enterprise.as_deref(), Some("1.3.6.1.4.1.22570"));
+        assert_eq!(attr.agent_addr.as_deref(), Some("192.168.0.10"));
+        assert_eq!(attr.generic_trap, Some(2));
+        assert_eq!(attr.specific_trap, Some(0));
+        assert_eq!(attr.request_id, None);
+        assert_eq!(attr.varbind_oids, vec!["1.3.6.1.2.1.1.3.0".to_string()]);
+    }
+
+    #[test]
+    fn rejected_messages() {
+        let varbinds = [varbind(&SYS_DESCR, TagValues::Null, &[])];
+        /* SNMPv3 and unknown versions carry no community */
+        assert!(!parse(&message(3, b"public", PduValues::GetRequest, &request_fields(0), &varbinds)).0);
+        assert!(!parse(&message(2, b"public", PduValues::GetRequest, &request_fields(0), &varbinds)).0);
+        assert!(!parse(&message(1, b"public", 0xA9, &request_fields(0), &varbinds)).0);
+        /* missing varbind list, short Trap-PDU */
+        assert!(!parse(&message(1, b"public", PduValues::GetRequest, &request_fields(0)[..2], &varbinds)).0);
+        assert!(!parse(&message(0, b"public", PduValues::Trap, &request_fields(0), &[])).0);
+        let data = message(1, b"public", PduValues::GetRequest, &request_fields(0), &varbinds);
+        for length in [0, 1, 5, 12, data.len() - 1].iter() {
+            assert!(!parse(&data[..*length]).0);
+        }
+        assert!(!parse(&[0x04, 0x02, 0x00, 0x00]).0);
+    }
+
+    #[test]
+    fn community_digest() {
+        let first = key().digest(b"public");
+        assert_eq!(first.len(), 64);
+        assert_eq!(first, key().digest(b"public"));
+        assert_ne!(first, CommunityKey(b"other-key".to_vec()).digest(b"public"));
+        assert_ne!(CommunityKey::random().0, CommunityKey::random().0);
+        assert_eq!(format!("{:?}", key()), "CommunityKey(..)");
+        assert_eq!(integer(&[0xFF, 0x7F]), -129);
+        assert_eq!(unsigned(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF]), 0xFFFF_FFFF);
+    }
+}
@@ -0,0 +1,276 @@
+//! Per-connection TCP state for anomaly flags on Modbus records
+//!
+//! Anomalies seen on a connection are kept until the next Modbus record of
+//! that connection, which then carries them:
+//! - retransmission: a segment whose data was already seen in that direction
+//! - zero window: a segment advertising a zero receive window
+//! - RST storm: more than RST_STORM_COUNT resets between the same hosts within RST_STORM_WINDOW
+//! - half-open: a SYN left unanswered for HALF_OPEN_TIMEOUT, or data sent after the peer reset
+//!
+//! Up to MAX_CONNECTIONS connections (and host pairs for RST counting) are
+//! tracked; the least recently seen ones make room for new ones.
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Duration, Utc};
+
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
+use pnet::packet::Packet;
+
+use super::bounded_map::BoundedMap;
+use super::PacketAttr;
+
+const MAX_CONNECTIONS: usize = 16384;
+const HALF_OPEN_TIMEOUT_SECS: i64 = 3;
+const RST_STORM_WINDOW_MILLIS: i64 = 1000;
+const RST_STORM_COUNT: u32 = 10;
+
+// endpoints ordered so that both directions share a key
+type ConnectionKey = (IpAddr, u16, IpAddr, u16);
+type HostKey = (IpAddr, IpAddr);
+
+fn connection_key(source: IpAddr, src_port: u16, destination: IpAddr, dst_port: u16) -> (ConnectionKey, usize) {
+    if (source, src_port) <= (destination, dst_port) {
+        ((source, src_port, destination, dst_port), 0)
+    } else {
+        ((destination, dst_port, source, src_port), 1)
+    }
+}
+
+fn host_key(source: IpAddr, destination: IpAddr) -> HostKey {
+    if source <= destination { (source, destination) } else { (destination, source) }
+}
+
+#[derive(Default)]
+struct Connection {
+    next_seq: [Option<u32>; 2], // per direction (index from connection_key)
+    syn_sent: Option<DateTime<Utc>>,
+    // direction of the SYN, i.e. the client side
+    client: Option<usize>,
+    established: bool,
+    reset_by: Option<usize>,
+    retransmission: bool,
+    zero_window: bool,
+    half_open: bool,
+}
+
+struct RstCounter {
+    window_start: DateTime<Utc>,
+    count: u32,
+}
+
+#[derive(Default)]
+pub struct TcpTracker {
+    connections: BoundedMap<ConnectionKey, Connection, MAX_CONNECTIONS>,
+    resets: BoundedMap<HostKey, RstCounter, MAX_CONNECTIONS>,
+}
+
+impl TcpTracker {
+    pub fn update(&mut self, source: IpAddr, destination: IpAddr, tcp: &TcpPacket, utc: DateTime<Utc>) {
+        let (key, direction) = connection_key(source, tcp.get_source(), destination, tcp.get_destination());
+        let connection = self.connections.get_or_insert_with(key, Connection::default);
+
+        let flags = tcp.get_flags();
+        let seq = tcp.get_sequence();
+        let payload_len = tcp.payload().len() as u32;
+        let syn_ack = TcpFlags::SYN | TcpFlags::ACK;
+        if flags & syn_ack == TcpFlags::SYN && (connection.syn_sent.is_none() || connection.established) {
+            /* new connection (possibly on a reused port pair) */
+            *connection = Connection::default();
+            connection.syn_sent = Some(utc);
+            connection.client = Some(direction);
+        } else if let Some(syn_sent) = connection.syn_sent {
+            if !connection.established
+                && flags & syn_ack != syn_ack
+                && utc - syn_sent >= Duration::seconds(HALF_OPEN_TIMEOUT_SECS) {
+                connection.half_open = true;
+            }
+        }
+        if flags & TcpFlags::SYN != 0 {
+            if flags & TcpFlags::ACK != 0 {
+                connection.established = true;
+                connection.client.get_or_insert(1 - direction);
+            }
+            connection.next_seq[direction] = Some(seq.wrapping_add(1));
+        }
+        if payload_len > 0 {
+            let end = seq.wrapping_add(payload_len);
+            match connection.next_seq[direction] {
+                Some(next_seq) if (end.wrapping_sub(next_seq) as i32) <= 0 => {
+                    connection.retransmission = true;
+                }
+                Some(next_seq) if (seq.wrapping_sub(next_seq) as i32) < 0 => {
+                    /* partly new data */
+                    connection.next_seq[direction] = Some(end);
+                }
+                _ => connection.next_seq[direction] = Some(end),
+            }
+            if connection.reset_by == Some(1 - direction) {
+                connection.half_open = true;
+            }
+        }
+        if tcp.get_window() == 0 && flags & (TcpFlags::RST | TcpFlags::SYN | TcpFlags::FIN) == 0 {
+            connection.zero_window = true;
+        }
+        if flags & TcpFlags::RST != 0 {
+            connection.reset_by = Some(direction);
+            let counter = self.resets.get_or_insert_with(host_key(source, destination), || RstCounter {
+                window_start: utc,
+                count: 0,
+            });
+            if utc - counter.window_start >= Duration::milliseconds(RST_STORM_WINDOW_MILLIS) {
+                counter.window_start = utc;
+                counter.count = 0;
+            }
+            counter.count += 1;
+        }
+    }
+
+    // port of the listening side, known once the handshake has been seen
+    pub fn server_port(&self, source: IpAddr, destination: IpAddr, tcp: &TcpPacket) -> Option<u16> {
+        let (key, direction) = connection_key(source, tcp.get_source(), destination, tcp.get_destination());
+        let client = self.connections.get(&key)?.client?;
+        if client == direction {
+            Some(tcp.get_destination())
+        } else {
+            Some(tcp.get_source())
+        }
+    }
+
+    // moves the pending anomalies of the record's connection onto the record
+    pub fn take_anomalies(&mut self, packet_attr: &mut PacketAttr, utc: DateTime<Utc>) {
+        let (key, _) = connection_key(packet_attr.src_addr, packet_attr.src_port, packet_attr.dst_addr, packet_attr.dst_port);
+        if let Some(connection) = self.connections.get_mut(&key) {
+            packet_attr.retransmission = connection.retransmission;
+            packet_attr.zero_window = connection.zero_window;
+            packet_attr.half_open = connection.half_open;
+            connection.retransmission = false;
+            connection.zero_window = false;
+            connection.half_open = false;
+        }
+        if let Some(counter) = self.resets.get(&host_key(packet_attr.src_addr, packet_attr.dst_addr)) {
+            packet_attr.rst_storm = counter.count > RST_STORM_COUNT
+                && utc - counter.window_start < Duration::milliseconds(RST_STORM_WINDOW_MILLIS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_tcp_segment;
+
+    const CLIENT: [u8; 4] = [192, 168, 0, 10];
+    const SERVER: [u8; 4] = [192, 168, 0, 1];
+
+    fn segment(from_client: bool, seq: u32, flags: u16, payload: &[u8]) -> (IpAddr, IpAddr, Vec<u8>) {
+        if from_client {
+            (CLIENT.into(), SERVER.into(), test_tcp_segment(50000, 502, seq, 0, flags, payload))
+        } else {
+            (SERVER.into(), CLIENT.into(), test_tcp_segment(502, 50000, seq, 0, flags, payload))
+        }
+    }
+
+    fn update(tracker: &mut TcpTracker, from_client: bool, seq: u32, flags: u16, payload: &[u8]) {
+        let (source, destination, data) = segment(from_client, seq, flags, payload);
+        tracker.update(source, destination, &TcpPacket::new(&data).unwrap(), Utc::now());
+    }
+
+    fn server_port(tracker: &TcpTracker, from_client: bool) -> Option<u16> {
+        let (source, destination, data) = segment(from_client, 0, TcpFlags::ACK, &[]);
+        tracker.server_port(source, destination, &TcpPacket::new(&data).unwrap())
+    }
+
+    fn anomalies(tracker: &mut TcpTracker) -> (bool, bool, bool, bool) {
+        let mut attr = PacketAttr::new("eth0".to_string(), Default::default(), Default::default(), CLIENT.into(), SERVER.into(), 50000, 502, 0);
+        tracker.take_anomalies(&mut attr, Utc::now());
+        (attr.retransmission, attr.zero_window, attr.rst_storm, attr.half_open)
+    }
+
+    #[test]
+    fn server_side_from_the_handshake() {
+        let mut tracker = TcpTracker::default();
+        update(&mut tracker, false, 7000, TcpFlags::ACK, &[]);
+        assert_eq!(server_port(&tracker, true), None);
+        update(&mut tracker, true, 1000, TcpFlags::SYN, &[]);
+        assert_eq!(server_port(&tracker, true), Some(502));
+        assert_eq!(server_port(&tracker, false), Some(502));
+        /* capture started after the SYN */
+        let mut tracker = TcpTracker::default();
+        update(&mut tracker, false, 7000, TcpFlags::SYN | TcpFlags::ACK, &[]);
+        assert_eq!(server_port(&tracker, true), Some(502));
+    }
+
+    #[test]
+    fn retransmission_is_flagged_once() {
+        let mut tracker = TcpTracker::default();
+        update(&mut tracker, true, 1000, TcpFlags::SYN, &[]);
+        update(&mut tracker, false, 7000, TcpFlags::SYN | TcpFlags::ACK, &[]);
+        update(&mut tracker, true, 1001, TcpFlags::ACK | TcpFlags::PSH, &[0; 12]);
+        assert_eq!(anomalies(&mut tracker), (false, false, false, false));
+        update(&mut tracker, true, 1001, TcpFlags::ACK | TcpFlags::PSH, &[0; 12]);
+        assert_eq!(anomalies(&mut tracker), (true, false, false, false));
+        assert_eq!(anomalies(&mut tracker), (false, false, false, false));
+        /* overlapping segment that extends the stream is not a retransmission */
+        update(&mut tracker, true, 1007, TcpFlags::ACK | TcpFlags::PSH, &[0; 12]);
+        assert!(!anomalies(&mut tracker).0);
+    }
+
+    #[test]
+    fn zero_window() {
+        let mut tracker = TcpTracker::default();
+        let (source, destination, mut data) = segment(false, 7001, TcpFlags::ACK, &[]);
+        data[14] = 0;
+        data[15] = 0;
+        tracker.update(source, destination, &TcpPacket::new(&data).unwrap(), Utc::now());
+        assert!(anomalies(&mut tracker).1);
+    }
+
+    #[test]
+    fn rst_storm() {
+        let mut tracker = TcpTracker::default();
+        for _ in 0..RST_STORM_COUNT {
+            update(&mut tracker, false, 7001, TcpFlags::RST, &[]);
+        }
+        assert!(!anomalies(&mut tracker).2);
+        update(&mut tracker, false, 7001, TcpFlags::RST, &[]);
+        assert!(anomalies(&mut tracker).2);
+    }
+
+    #[test]
+    fn half_open() {
+        /* data sent after the peer reset */
+        let mut tracker = TcpTracker::default();
+        update(&mut tracker, false, 7001, TcpFlags::RST, &[]);
+        update(&mut tracker, true, 1001, TcpFlags::ACK | TcpFlags::PSH, &[0; 12]);
+        assert!(anomalies(&mut tracker).3);
+        /* SYN left unanswered */
+        let mut tracker = TcpTracker::default();
+        update(&mut tracker, true, 1000, TcpFlags::SYN, &[]);
+        update(&mut tracker, true, 1001, TcpFlags::ACK, &[]);
+        assert!(!anomalies(&mut tracker).3);
+        let (key, _) = connection_key(CLIENT.into(), 50000, SERVER.into(), 502);
+        tracker.connections.get_mut(&key).unwrap().syn_sent = Some(Utc::now() - Duration::seconds(HALF_OPEN_TIMEOUT_SECS));
+        update(&mut tracker, true, 1001, TcpFlags::ACK, &[]);
+        assert!(anomalies(&mut tracker).3);
+    }
+
+    #[test]
+    fn full_table_keeps_recent_connections() {
+        let mut tracker = TcpTracker::default();
+        update(&mut tracker, true, 1001, TcpFlags::ACK | TcpFlags::PSH, &[0; 12]);
+        update(&mut tracker, true, 1001, TcpFlags::ACK | TcpFlags::PSH, &[0; 12]);
+        let scanner = IpAddr::from([10, 0, 0, 1]);
+        for port in 0..MAX_CONNECTIONS as u16 {
+            let data = test_tcp_segment(port, 80, 1000, 0, TcpFlags::SYN, &[]);
+            tracker.update(scanner, SERVER.into(), &TcpPacket::new(&data).unwrap(), Utc::now());
+            if port % 1024 == 0 {
+                update(&mut tracker, false, 7001, TcpFlags::ACK, &[]);
+            }
+        }
+        /* the oldest connections made room; the active one kept its pending retransmission */
+        assert!(tracker.connections.len() < MAX_CONNECTIONS);
+        assert!(!tracker.connections.contains_key(&connection_key(scanner, 0, SERVER.into(), 80).0));
+        assert!(anomalies(&mut tracker).0);
+    }
+}
@@ -0,0 +1,169 @@
+//! Per-direction TCP stream reassembly for dissectors whose PDUs span segments
+//!
+//! Only in-order data is buffered: retransmitted bytes are trimmed and a
+//! sequence gap (lost or out-of-order segment) restarts the buffer at the
+//! new segment. A PDU too large to buffer is skipped: the dissector consumes
+//! its full length and the bytes still to come are dropped as they arrive.
+//! When MAX_STREAMS streams are open the least recently used ones are dropped.
+
+use std::net::IpAddr;
+
+use pnet::packet::tcp::{TcpFlags, TcpPacket};
+use pnet::packet::Packet;
+
+use super::bounded_map::BoundedMap;
+
+// (source address, source port, destination address, destination port)
+pub type StreamKey = (IpAddr, u16, IpAddr, u16);
+
+pub const MAX_BUFFER: usize = 256 * 1024;
+
+const MAX_STREAMS: usize = 4096;
+
+struct Stream {
+    next_seq: u32,
+    buffer: Vec<u8>,
+    // bytes of a skipped PDU not received yet
+    skip: usize,
+}
+
+#[derive(Default)]
+pub struct TcpReassembler {
+    streams: BoundedMap<StreamKey, Stream, MAX_STREAMS>,
+}
+
+impl TcpReassembler {
+    // Appends the segment to its stream and hands the buffered bytes to `parse`
+    // until it returns None (incomplete PDU). `parse` returns the number of
+    // bytes it consumed, which may run past the buffered bytes to skip a PDU
+    // larger than MAX_BUFFER.
+    pub fn reassemble<F>(&mut self, key: StreamKey, tcp: &TcpPacket, mut parse: F)
+    where
+        F: FnMut(&[u8]) -> Option<usize>,
+    {
+        let flags = tcp.get_flags();
+        let seq = tcp.get_sequence();
+        let payload = tcp.payload();
+        let stream = self.streams.get_or_insert_with(key, || Stream {
+            next_seq: seq,
+            buffer: Vec::new(),
+            skip: 0,
+        });
+        if flags & TcpFlags::SYN != 0 {
+            stream.next_seq = seq.wrapping_add(1);
+            stream.buffer.clear();
+            stream.skip = 0;
+        } else {
+            let ahead = seq.wrapping_sub(stream.next_seq) as i32;
+            if ahead > 0 {
+                /* gap: resynchronise on this segment */
+                stream.buffer.clear();
+                stream.buffer.extend_from_slice(payload);
+                stream.next_seq = seq.wrapping_add(payload.len() as u32);
+                stream.skip = 0;
+            } else {
+                /* in order, or a retransmission that may carry new bytes */
+                let behind = stream.next_seq.wrapping_sub(seq) as usize;
+                if behind < payload.len() {
+                    let new = &payload[behind..];
+                    let skipped = stream.skip.min(new.len());
+                    stream.skip -= skipped;
+                    stream.buffer.extend_from_slice(&new[skipped..]);
+                    stream.next_seq = stream.next_seq.wrapping_add(new.len() as u32);
+                }
+            }
+        }
+        let mut consumed = 0;
+        while consumed < stream.buffer.len() {
+            match parse(&stream.buffer[consumed..]) {
+                Some(size) if size > 0 => consumed += size,
+                _ => break,
+            }
+        }
+        if consumed > stream.buffer.len() {
+            stream.skip = consumed - stream.buffer.len();
+        }
+        stream.buffer.drain(..consumed.min(stream.buffer.len()));
+        if stream.buffer.len() > MAX_BUFFER {
+            stream.buffer.clear();
+        }
+        if flags & (TcpFlags::FIN | TcpFlags::RST) != 0 {
+            self.streams.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_tcp_segment;
+
+    fn key() -> StreamKey {
+        (IpAddr::from([192, 168, 0, 10]), 50000, IpAddr::from([192, 168, 0, 1]), 1883)
+    }
+
+    // feeds one segment and returns the PDUs (length-prefixed by one byte) parsed from it
+    fn feed(reassembler: &mut TcpReassembler, seq: u32, flags: u16, payload: &[u8]) -> Vec<Vec<u8>> {
+        let segment = test_tcp_segment(50000, 1883, seq, 0, flags, payload);
+        let mut pdus = Vec::new();
+        reassembler.reassemble(key(), &TcpPacket::new(&segment).unwrap(), |data| {
+            let size = 1 + *data.first()? as usize;
+            pdus.push(data.get(1..size)?.to_vec());
+            Some(size)
+        });
+        pdus
+    }
+
+    #[test]
+    fn pdu_spanning_segments() {
+        let mut reassembler = TcpReassembler::default();
+        assert!(feed(&mut reassembler, 1000, TcpFlags::SYN, &[]).is_empty());
+        assert!(feed(&mut reassembler, 1001, TcpFlags::ACK, &[4, b'a', b'b']).is_empty());
+        assert_eq!(feed(&mut reassembler, 1004, TcpFlags::ACK, &[b'c', b'd', 1, b'x']), vec![b"abcd".to_vec(), b"x".to_vec()]);
+    }
+
+    #[test]
+    fn retransmission_is_trimmed() {
+        let mut reassembler = TcpReassembler::default();
+        feed(&mut reassembler, 1000, TcpFlags::SYN, &[]);
+        assert!(feed(&mut reassembler, 1001, TcpFlags::ACK, &[3, b'a']).is_empty());
+        /* the first two bytes again, plus two new ones */
+        assert_eq!(feed(&mut reassembler, 1001, TcpFlags::ACK, &[3, b'a', b'b', b'c']), vec![b"abc".to_vec()]);
+        assert!(feed(&mut reassembler, 1001, TcpFlags::ACK, &[3, b'a', b'b', b'c']).is_empty());
+    }
+
+    #[test]
+    fn gap_resynchronises() {
+        let mut reassembler = TcpReassembler::default();
+        feed(&mut reassembler, 1000, TcpFlags::SYN, &[]);
+        assert!(feed(&mut reassembler, 1001, TcpFlags::ACK, &[9, b'a']).is_empty());
+        assert_eq!(feed(&mut reassembler, 2000, TcpFlags::ACK, &[1, b'z']), vec![b"z".to_vec()]);
+        assert_eq!(feed(&mut reassembler, 2002, TcpFlags::ACK, &[1, b'y']), vec![b"y".to_vec()]);
+    }
+
+    #[test]
+    fn oversized_pdu_is_skipped() {
+        let mut reassembler = TcpReassembler::default();
+        feed(&mut reassembler, 1000, TcpFlags::SYN, &[]);
+        /* PDU header announcing 6 more bytes than buffered; the parser consumes them all */
+        let segment = test_tcp_segment(50000, 1883, 1001, 0, TcpFlags::ACK, &[0xff, b'a', b'b']);
+        reassembler.reassemble(key(), &TcpPacket::new(&segment).unwrap(), |data| {
+            assert_eq!(data, [0xff, b'a', b'b']);
+            Some(9)
+        });
+        assert!(feed(&mut reassembler, 1004, TcpFlags::ACK, &[b'c', b'd', b'e', b'f']).is_empty());
+        assert_eq!(feed(&mut reassembler, 1008, TcpFlags::ACK, &[b'g', b'h', 1, b'x']), vec![b"x".to_vec()]);
+        assert_eq!(feed(&mut reassembler, 1012, TcpFlags::ACK, &[1, b'y']), vec![b"y".to_vec()]);
+    }
+
+    #[test]
+    fn fin_and_rst_drop_the_stream() {
+        let mut reassembler = TcpReassembler::default();
+        feed(&mut reassembler, 1000, TcpFlags::SYN, &[]);
+        feed(&mut reassembler, 1001, TcpFlags::ACK, &[5, b'a']);
+        feed(&mut reassembler, 1003, TcpFlags::FIN | TcpFlags::ACK, &[]);
+        assert!(reassembler.streams.is_empty());
+        feed(&mut reassembler, 1000, TcpFlags::RST, &[]);
+        assert!(reassembler.streams.is_empty());
+    }
+}
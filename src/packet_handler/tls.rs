@@ -0,0 +1,512 @@
+//! TLS handshake metadata (ClientHello / ServerHello / Certificate)
+//!
+//! Record
+//! +-------------+---------+--------+----------
+//! | ContentType | Version | Length | Fragment
+//! +-------------+---------+--------+----------
+//! |      1      |    2    |   2    | ...
+//!
+//! Handshake: Type (1) | Length (3) | Body
+//!
+//! ClientHello body: Version (2) | Random (32) | SessionID (1+n) | CipherSuites (2+n)
+//!                   | CompressionMethods (1+n) | Extensions (2+n)
+//! ServerHello body: Version (2) | Random (32) | SessionID (1+n) | CipherSuite (2)
+//!                   | CompressionMethod (1) | Extensions (2+n)
+//!
+//! Certificates are only visible up to TLS 1.2 (encrypted in 1.3). Handshake
+//! messages fragmented over several records are not reassembled.
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use super::{Addresses, be_u16};
+use super::modbus_security;
+use super::x509::Certificate;
+
+// TLS is recognised on these ports (HTTPS, OPC UA over TLS, MQTT over TLS)
+pub const TLS_PORTS: [u16; 4] = [443, 4843, 8443, 8883];
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod ContentTypeValues {
+    pub const ChangeCipherSpec: u8 = 20;
+    pub const Alert: u8 = 21;
+    pub const Handshake: u8 = 22;
+    pub const ApplicationData: u8 = 23;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod HandshakeValues {
+    pub const ClientHello: u8 = 1;
+    pub const ServerHello: u8 = 2;
+    pub const Certificate: u8 = 11;
+}
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+pub mod ExtensionValues {
+    pub const ServerName: u16 = 0x0000;
+    pub const SupportedGroups: u16 = 0x000A;
+    pub const EcPointFormats: u16 = 0x000B;
+    pub const SignatureAlgorithms: u16 = 0x000D;
+    pub const Alpn: u16 = 0x0010;
+    pub const SupportedVersions: u16 = 0x002B;
+}
+
+#[derive(Debug)]
+pub struct TlsAttr {
+    pub addresses: Addresses,
+    pub record_version: u16,
+    pub handshake_types: Vec<u8>,
+    pub version: Option<u16>,
+    pub supported_versions: Vec<u16>,
+    pub cipher_suites: Vec<u16>,
+    pub extensions: Vec<u16>,
+    pub supported_groups: Vec<u16>,
+    pub ec_point_formats: Vec<u8>,
+    pub signature_algorithms: Vec<u16>,
+    pub sni: Option<String>,
+    pub alpn: Vec<String>,
+    // leaf certificate
+    pub cert_subject: Option<String>,
+    pub cert_issuer: Option<String>,
+    pub cert_not_before: Option<i64>,
+    pub cert_not_after: Option<i64>,
+    pub cert_role: Option<String>,
+    // ClientHello: JA3 and JA4, ServerHello: JA3S
+    pub ja3: Option<String>,
+    pub ja3_hash: Option<String>,
+    pub ja4: Option<String>,
+}
+
+// RFC 8701 GREASE values (0x?A?A) are ignored by the fingerprints
+fn grease(value: u16) -> bool {
+    value & 0x0F0F == 0x0A0A && value >> 8 == value & 0xFF
+}
+
+fn u24(data: &[u8], offset: usize) -> Option<usize> {
+    data.get(offset..offset + 3).map(|b| (b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize)
+}
+
+// 1-byte or 2-byte length prefixed vector: (contents, offset past it)
+fn vector(data: &[u8], offset: usize, size: usize) -> Option<(&[u8], usize)> {
+    let length = match size {
+        1 => *data.get(offset)? as usize,
+        _ => be_u16(data, offset)? as usize,
+    };
+    let start = offset + size;
+    Some((data.get(start..start + length)?, start + length))
+}
+
+fn u16_list(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]])).collect()
+}
+
+fn dashed<T: ToString>(values: impl Iterator<Item = T>) -> String {
+    values.map(|value| value.to_string()).collect::<Vec<String>>().join("-")
+}
+
+fn truncated_sha256(text: &str) -> String {
+    if text.is_empty() {
+        return "000000000000".to_string();
+    }
+    format!("{:x}", Sha256::digest(text.as_bytes()))[..12].to_string()
+}
+
+impl TlsAttr {
+    pub fn new(addresses: Addresses) -> Self {
+        Self {
+            addresses: addresses,
+            record_version: 0,
+            handshake_types: Vec::new(),
+            version: None,
+            supported_versions: Vec::new(),
+            cipher_suites: Vec::new(),
+            extensions: Vec::new(),
+            supported_groups: Vec::new(),
+            ec_point_formats: Vec::new(),
+            signature_algorithms: Vec::new(),
+            sni: None,
+            alpn: Vec::new(),
+            cert_subject: None,
+            cert_issuer: None,
+            cert_not_before: None,
+            cert_not_after: None,
+            cert_role: None,
+            ja3: None,
+            ja3_hash: None,
+            ja4: None,
+        }
+    }
+
+    // Returns the record size (Err when the stream is not TLS). Ok(None) means
+    // the record is incomplete; `decoded` is set when a handshake was read.
+    pub fn set_record(&mut self, data: &[u8]) -> Result<Option<(usize, bool)>, ()> {
+        if data.len() < 5 {
+            return Ok(None);
+        }
+        let content_type = data[0];
+        let version = be_u16(data, 1).unwrap_or(0);
+        if !(ContentTypeValues::ChangeCipherSpec..=ContentTypeValues::ApplicationData).contains(&content_type)
+            || version >> 8 != 3
+        {
+            return Err(());
+        }
+        let length = be_u16(data, 3).unwrap_or(0) as usize;
+        let fragment = match data.get(5..5 + length) {
+            Some(fragment) => fragment,
+            None => return Ok(None),
+        };
+        self.record_version = version;
+        let decoded = content_type == ContentTypeValues::Handshake && self.set_handshakes(fragment);
+        Ok(Some((5 + length, decoded)))
+    }
+
+    fn set_handshakes(&mut self, fragment: &[u8]) -> bool {
+        let mut offset = 0;
+        while let (Some(handshake_type), Some(length)) = (fragment.get(offset).copied(), u24(fragment, offset + 1)) {
+            /* encrypted handshake messages (Finished) do not frame cleanly */
+            let body = match fragment.get(offset + 4..offset + 4 + length) {
+                Some(body) => body,
+                None => break,
+            };
+            let decoded = match handshake_type {
+                HandshakeValues::ClientHello => self.set_client_hello(body).is_some(),
+                HandshakeValues::ServerHello => self.set_server_hello(body).is_some(),
+                HandshakeValues::Certificate => self.set_certificates(body).is_some(),
+                _ => false,
+            };
+            if decoded {
+                self.handshake_types.push(handshake_type);
+            }
+            offset += 4 + length;
+        }
+        !self.handshake_types.is_empty()
+    }
+
+    fn set_client_hello(&mut self, body: &[u8]) -> Option<()> {
+        let version = be_u16(body, 0)?;
+        let (_, offset) = vector(body, 34, 1)?;
+        let (ciphers, offset) = vector(body, offset, 2)?;
+        let (_, offset) = vector(body, offset, 1)?;
+        self.version = Some(version);
+        self.cipher_suites = u16_list(ciphers);
+        if let Some((extensions, _)) = vector(body, offset, 2) {
+            self.set_extensions(extensions, true);
+        }
+        self.set_ja3(version);
+        self.set_ja4(version);
+        Some(())
+    }
+
+    fn set_server_hello(&mut self, body: &[u8]) -> Option<()> {
+        let version = be_u16(body, 0)?;
+        let (_, offset) = vector(body, 34, 1)?;
+        let cipher = be_u16(body, offset)?;
+        self.version = Some(version);
+        self.cipher_suites = vec![cipher];
+        if let Some((extensions, _)) = vector(body, offset + 3, 2) {
+            self.set_extensions(extensions, false);
+        }
+        /* JA3S: SSLVersion,Cipher,Extensions */
+        let ja3 = format!("{},{},{}", version, cipher, dashed(self.extensions.iter()));
+        self.ja3_hash = Some(format!("{:x}", Md5::digest(ja3.as_bytes())));
+        self.ja3 = Some(ja3);
+        Some(())
+    }
+
+    // Certificate list: (3-byte length | DER) ...
+    fn set_certificates(&mut self, body: &[u8]) -> Option<()> {
+        let total = u24(body, 0)?;
+        let list = body.get(3..3 + total)?;
+        /* the sender's own certificate comes first */
+        let length = u24(list, 0)?;
+        let leaf = Certificate::parse(list.get(3..3 + length)?)?;
+        self.cert_role = leaf.string_extension(modbus_security::ROLE_OID);
+        self.cert_subject = Some(leaf.subject);
+        self.cert_issuer = Some(leaf.issuer);
+        self.cert_not_before = leaf.not_before.map(|utc| utc.timestamp_nanos());
+        self.cert_not_after = leaf.not_after.map(|utc| utc.timestamp_nanos());
+        Some(())
+    }
+
+    fn set_extensions(&mut self, data: &[u8], client: bool) {
+        let mut offset = 0;
+        while let (Some(extension), Some((value, end))) = (be_u16(data, offset), vector(data, offset + 2, 2)) {
+            self.extensions.push(extension);
+            match extension {
+                ExtensionValues::ServerName => {
+                    /* ServerNameList: (NameType | HostName) ..., first host_name only */
+                    if let Some((list, _)) = vector(value, 0, 2) {
+                        if list.get(0) == Some(&0) {
+                            if let Some((host, _)) = vector(list, 1, 2) {
+                                self.sni = Some(String::from_utf8_lossy(host).into_owned());
+                            }
+                        }
+                    }
+                }
+                ExtensionValues::SupportedGroups => {
+                    if let Some((groups, _)) = vector(value, 0, 2) {
+                        self.supported_groups = u16_list(groups);
+                    }
+                }
+                ExtensionValues::EcPointFormats => {
+                    if let Some((formats, _)) = vector(value, 0, 1) {
+                        self.ec_point_formats = formats.to_vec();
+                    }
+                }
+                ExtensionValues::SignatureAlgorithms => {
+                    if let Some((algorithms, _)) = vector(value, 0, 2) {
+                        self.signature_algorithms = u16_list(algorithms);
+                    }
+                }
+                ExtensionValues::Alpn => {
+                    if let Some((list, _)) = vector(value, 0, 2) {
+                        let mut position = 0;
+                        while let Some((protocol, next)) = vector(list, position, 1) {
+                            self.alpn.push(String::from_utf8_lossy(protocol).into_owned());
+                            position = next;
+                        }
+                    }
+                }
+                ExtensionValues::SupportedVersions => {
+                    /* client: list, server: the selected version */
+                    if client {
+                        if let Some((versions, _)) = vector(value, 0, 1) {
+                            self.supported_versions = u16_list(versions);
+                        }
+                    } else if let Some(selected) = be_u16(value, 0) {
+                        self.supported_versions = vec![selected];
+                        self.version = Some(selected);
+                    }
+                }
+                _ => {}
+            }
+            offset = end;
+        }
+    }
+
+    // SSLVersion,Ciphers,Extensions,EllipticCurves,EllipticCurvePointFormats
+    fn set_ja3(&mut self, version: u16) {
+        let ja3 = format!(
+            "{},{},{},{},{}",
+            version,
+            dashed(self.cipher_suites.iter().filter(|v| !grease(**v))),
+            dashed(self.extensions.iter().filter(|v| !grease(**v))),
+            dashed(self.supported_groups.iter().filter(|v| !grease(**v))),
+            dashed(self.ec_point_formats.iter()),
+        );
+        self.ja3_hash = Some(format!("{:x}", Md5::digest(ja3.as_bytes())));
+        self.ja3 = Some(ja3);
+    }
+
+    // t<version><d|i><ciphers><extensions><alpn>_<sha256(ciphers)>_<sha256(extensions_signatures)>
+    fn set_ja4(&mut self, version: u16) {
+        let highest = self.supported_versions.iter().copied().filter(|v| !grease(*v)).max().unwrap_or(version);
+        let version = match highest {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            0x0300 => "s3",
+            _ => "00",
+        };
+        let mut ciphers: Vec<u16> = self.cipher_suites.iter().copied().filter(|v| !grease(*v)).collect();
+        let mut extensions: Vec<u16> = self.extensions.iter().copied().filter(|v| !grease(*v)).collect();
+        let alpn = match self.alpn.first().map(|alpn| alpn.as_bytes()) {
+            Some(bytes) if !bytes.is_empty() => {
+                let (first, last) = (bytes[0], bytes[bytes.len() - 1]);
+                if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() {
+                    format!("{}{}", first as char, last as char)
+                } else {
+                    let hex = format!("{:02x}{:02x}", first, last);
+                    format!("{}{}", &hex[0..1], &hex[3..4])
+                }
+            }
+            _ => "00".to_string(),
+        };
+        let prefix = format!(
+            "t{}{}{:02}{:02}{}",
+            version,
+            if self.sni.is_some() { "d" } else { "i" },
+            ciphers.len().min(99),
+            extensions.len().min(99),
+            alpn
+        );
+        ciphers.sort();
+        extensions.retain(|v| *v != ExtensionValues::ServerName && *v != ExtensionValues::Alpn);
+        extensions.sort();
+        let hex = |values: &[u16]| values.iter().map(|v| format!("{:04x}", v)).collect::<Vec<String>>().join(",");
+        let mut extension_text = hex(&extensions);
+        if !self.signature_algorithms.is_empty() {
+            extension_text = format!("{}_{}", extension_text, hex(&self.signature_algorithms));
+        }
+        self.ja4 = Some(format!(
+            "{}_{}_{}",
+            prefix,
+            truncated_sha256(&hex(&ciphers)),
+            if extensions.is_empty() { truncated_sha256("") } else { truncated_sha256(&extension_text) }
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::test_addresses;
+    use crate::packet_handler::x509::test_certificate;
+
+    fn extension(extension_type: u16, value: &[u8]) -> Vec<u8> {
+        let mut data = extension_type.to_be_bytes().to_vec();
+        data.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        data.extend_from_slice(value);
+        data
+    }
+
+    fn prefixed(size: usize, value: &[u8]) -> Vec<u8> {
+        let mut data = match size {
+            1 => vec![value.len() as u8],
+            2 => (value.len() as u16).to_be_bytes().to_vec(),
+            _ => (value.len() as u32).to_be_bytes()[1..].to_vec(),
+        };
+        data.extend_from_slice(value);
+        data
+    }
+
+    fn u16_bytes(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes().to_vec()).collect()
+    }
+
+    fn record(handshakes: &[(u8, Vec<u8>)]) -> Vec<u8> {
+        let mut fragment = Vec::new();
+        for (handshake_type, body) in handshakes {
+            fragment.push(*handshake_type);
+            fragment.extend(prefixed(3, body));
+        }
+        let mut data = vec![ContentTypeValues::Handshake, 0x03, 0x01];
+        data.extend(prefixed(2, &fragment));
+        data
+    }
+
+    fn hello(version: u16, ciphers: &[u8], tail: &[u8]) -> Vec<u8> {
+        let mut body = version.to_be_bytes().to_vec();
+        body.extend_from_slice(&[0x5A; 32]);
+        body.extend(prefixed(1, &[0x11; 32]));
+        body.extend_from_slice(ciphers);
+        body.extend_from_slice(tail);
+        body
+    }
+
+    // Chrome-style ClientHello with GREASE values
+    fn client_hello() -> Vec<u8> {
+        let mut extensions = extension(0x1A1A, &[]);
+        let mut server_name = vec![0];
+        server_name.extend(prefixed(2, b"plc.example"));
+        extensions.extend(extension(ExtensionValues::ServerName, &prefixed(2, &server_name)));
+        extensions.extend(extension(ExtensionValues::SupportedGroups, &prefixed(2, &u16_bytes(&[0x2A2A, 0x001D, 0x0017]))));
+        extensions.extend(extension(ExtensionValues::EcPointFormats, &prefixed(1, &[0])));
+        extensions.extend(extension(ExtensionValues::SignatureAlgorithms, &prefixed(2, &u16_bytes(&[0x0403, 0x0804]))));
+        let alpn = [prefixed(1, b"h2"), prefixed(1, b"http/1.1")].concat();
+        extensions.extend(extension(ExtensionValues::Alpn, &prefixed(2, &alpn)));
+        extensions.extend(extension(ExtensionValues::SupportedVersions, &prefixed(1, &u16_bytes(&[0x3A3A, 0x0304, 0x0303]))));
+        let ciphers = prefixed(2, &u16_bytes(&[0x0A0A, 0x1301, 0x1302, 0xC02B]));
+        let tail = [prefixed(1, &[0]), prefixed(2, &extensions)].concat();
+        hello(0x0303, &ciphers, &tail)
+    }
+
+    fn parse(data: &[u8]) -> (Result<Option<(usize, bool)>, ()>, TlsAttr) {
+        let mut attr = TlsAttr::new(test_addresses(50123, 443));
+        (attr.set_record(data), attr)
+    }
+
+    #[test]
+    fn client_hello_fingerprints() {
+        let data = record(&[(HandshakeValues::ClientHello, client_hello())]);
+        let (result, attr) = parse(&data);
+        assert_eq!(result, Ok(Some((data.len(), true))));
+        assert_eq!(attr.record_version, 0x0301);
+        assert_eq!(attr.handshake_types, vec![HandshakeValues::ClientHello]);
+        assert_eq!(attr.version, Some(0x0303));
+        assert_eq!(attr.supported_versions, vec![0x3A3A, 0x0304, 0x0303]);
+        assert_eq!(attr.cipher_suites, vec![0x0A0A, 0x1301, 0x1302, 0xC02B]);
+        assert_eq!(attr.extensions, vec![0x1A1A, 0, 10, 11, 13, 16, 43]);
+        assert_eq!(attr.supported_groups, vec![0x2A2A, 0x001D, 0x0017]);
+        assert_eq!(attr.ec_point_formats, vec![0]);
+        assert_eq!(attr.signature_algorithms, vec![0x0403, 0x0804]);
+        assert_eq!(attr.sni.as_deref(), Some("plc.example"));
+        assert_eq!(attr.alpn, vec!["h2".to_string(), "http/1.1".to_string()]);
+        assert_eq!(attr.ja3.as_deref(), Some("771,4865-4866-49195,0-10-11-13-16-43,29-23,0"));
+        assert_eq!(attr.ja3_hash.as_deref(), Some("11138d9933242c3a03b6aad35a296476"));
+        assert_eq!(attr.ja4.as_deref(), Some("t13d0306h2_5559582ccdc4_fb71836bce29"));
+    }
+
+    #[test]
+    fn client_hello_without_extensions() {
+        let ciphers = prefixed(2, &u16_bytes(&[0x002F, 0x0035]));
+        let body = hello(0x0301, &ciphers, &prefixed(1, &[0]));
+        let (result, attr) = parse(&record(&[(HandshakeValues::ClientHello, body)]));
+        assert!(matches!(result, Ok(Some((_, true)))));
+        assert_eq!(attr.sni, None);
+        assert_eq!(attr.ja3.as_deref(), Some("769,47-53,,,"));
+        assert_eq!(attr.ja4.as_deref(), Some("t10i020000_f54dd463d39b_000000000000"));
+    }
+
+    #[test]
+    fn server_hello_and_certificate() {
+        let mut tail = vec![0];
+        tail.extend(prefixed(2, &extension(ExtensionValues::SupportedVersions, &[0x03, 0x04])));
+        let server_hello = hello(0x0303, &u16_bytes(&[0x1301]), &tail);
+        let certificate = test_certificate("plc-01", &[]);
+        let chain = prefixed(3, &[prefixed(3, &certificate), prefixed(3, &test_certificate("Plant CA", &[]))].concat());
+        let data = record(&[(HandshakeValues::ServerHello, server_hello), (HandshakeValues::Certificate, chain)]);
+        let (result, attr) = parse(&data);
+        assert_eq!(result, Ok(Some((data.len(), true))));
+        assert_eq!(attr.handshake_types, vec![HandshakeValues::ServerHello, HandshakeValues::Certificate]);
+        assert_eq!(attr.version, Some(0x0304));
+        assert_eq!(attr.cipher_suites, vec![0x1301]);
+        assert_eq!(attr.ja3.as_deref(), Some("771,4865,43"));
+        assert_eq!(attr.ja3_hash.as_deref(), Some("cce84e7a8b742462e40afb585a3e3ccc"));
+        assert_eq!(attr.ja4, None);
+        /* leaf certificate only */
+        assert_eq!(attr.cert_subject.as_deref(), Some("CN=plc-01, O=Plant"));
+        assert_eq!(attr.cert_issuer.as_deref(), Some("CN=Plant CA, O=Plant"));
+        assert!(attr.cert_not_before.unwrap() < attr.cert_not_after.unwrap());
+        assert_eq!(attr.cert_role, None);
+    }
+
+    #[test]
+    fn record_framing() {
+        let data = record(&[(HandshakeValues::ClientHello, client_hello())]);
+        assert_eq!(parse(&data[..4]).0, Ok(None));
+        assert_eq!(parse(&data[..data.len() - 1]).0, Ok(None));
+        assert_eq!(parse(b"GET / HTTP/1.1\r\n").0, Err(()));
+        assert_eq!(parse(&[ContentTypeValues::Handshake, 0x02, 0x00, 0x00, 0x00]).0, Err(()));
+        let (result, attr) = parse(&[ContentTypeValues::ApplicationData, 0x03, 0x03, 0x00, 0x02, 0xAB, 0xCD, 0x17]);
+        assert_eq!(result, Ok(Some((7, false))));
+        assert_eq!(attr.record_version, 0x0303);
+    }
+
+    #[test]
+    fn malformed_handshakes() {
+        /* handshake length past the record, then a ClientHello cut inside the cipher suites */
+        let mut body = client_hello();
+        let mut data = record(&[(HandshakeValues::ClientHello, body.clone())]);
+        data[8] += 1;
+        assert_eq!(parse(&data).0, Ok(Some((data.len(), false))));
+        body.truncate(70);
+        let (result, attr) = parse(&record(&[(HandshakeValues::ClientHello, body)]));
+        assert_eq!(result, Ok(Some((74 + 5, false))));
+        assert_eq!(attr.ja3, None);
+        /* unparseable certificate */
+        let chain = prefixed(3, &prefixed(3, &[0x30, 0x03, 0x02, 0x01, 0x01]));
+        let (result, attr) = parse(&record(&[(HandshakeValues::Certificate, chain)]));
+        assert_eq!(result, Ok(Some((20, false))));
+        assert_eq!(attr.cert_subject, None);
+        assert!(!grease(0x0A1A));
+        assert!(grease(0xFAFA));
+    }
+}
@@ -0,0 +1,183 @@
+//! Generic per-packet L2-L4 records ("all traffic" table)
+//!
+//! One record per decoded IPv4/IPv6 packet, regardless of the application
+//! protocol. Ports are set for TCP/UDP, ICMP fields for ICMP/ICMPv6, and the
+//! extension header chain for IPv6.
+
+use std::net::IpAddr;
+
+use pnet::packet::ip::{IpNextHeaderProtocol, IpNextHeaderProtocols};
+use pnet::packet::tcp::TcpPacket;
+use pnet::packet::udp::UdpPacket;
+
+use super::{LinkAddresses, be_u16};
+use super::ipv6_ext::ExtensionHeaders;
+
+// set to 1 to export the all-traffic table (off by default, one record per
+// IP packet competes with the protocol records for the log channel)
+pub const LOG_ALL_TRAFFIC_ENV: &str = "ARROWS_LOG_ALL_TRAFFIC";
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+mod EchoValues {
+    pub const IcmpEchoReply: u8 = 0;
+    pub const IcmpEchoRequest: u8 = 8;
+    pub const Icmpv6EchoRequest: u8 = 128;
+    pub const Icmpv6EchoReply: u8 = 129;
+}
+
+#[derive(Debug)]
+pub struct TrafficAttr {
+    pub link: LinkAddresses,
+    pub src_addr: IpAddr,
+    pub dst_addr: IpAddr,
+    pub protocol: u8,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+    // TCP
+    pub tcp_flags: Option<u16>,
+    // UDP
+    pub udp_length: Option<u16>,
+    // ICMP / ICMPv6
+    pub icmp_type: Option<u8>,
+    pub icmp_code: Option<u8>,
+    pub echo_id: Option<u16>,
+    pub echo_seq: Option<u16>,
+    // IPv6 extension headers (next-header values in chain order)
+    pub extension_headers: Vec<u8>,
+    pub routing_type0: bool,
+}
+
+impl TrafficAttr {
+    pub fn new(link: LinkAddresses, source: IpAddr, destination: IpAddr, protocol: IpNextHeaderProtocol) -> Self {
+        Self {
+            link: link,
+            src_addr: source,
+            dst_addr: destination,
+            protocol: protocol.0,
+            src_port: None,
+            dst_port: None,
+            tcp_flags: None,
+            udp_length: None,
+            icmp_type: None,
+            icmp_code: None,
+            echo_id: None,
+            echo_seq: None,
+            extension_headers: Vec::new(),
+            routing_type0: false,
+        }
+    }
+
+    pub fn set_extensions(&mut self, extensions: &ExtensionHeaders) {
+        self.extension_headers = extensions.headers.clone();
+        self.routing_type0 = extensions.routing_type0;
+    }
+
+    pub fn set_transport(&mut self, protocol: IpNextHeaderProtocol, packet: &[u8]) {
+        match protocol {
+            IpNextHeaderProtocols::Tcp => {
+                if let Some(tcp) = TcpPacket::new(packet) {
+                    self.src_port = Some(tcp.get_source());
+                    self.dst_port = Some(tcp.get_destination());
+                    self.tcp_flags = Some(tcp.get_flags());
+                }
+            }
+            IpNextHeaderProtocols::Udp => {
+                if let Some(udp) = UdpPacket::new(packet) {
+                    self.src_port = Some(udp.get_source());
+                    self.dst_port = Some(udp.get_destination());
+                    self.udp_length = Some(udp.get_length());
+                }
+            }
+            IpNextHeaderProtocols::Icmp | IpNextHeaderProtocols::Icmpv6 => {
+                /* Type | Code | Checksum | (Identifier | Sequence Number) */
+                if packet.len() < 4 {
+                    return;
+                }
+                self.icmp_type = Some(packet[0]);
+                self.icmp_code = Some(packet[1]);
+                let echo = match (protocol, packet[0]) {
+                    (IpNextHeaderProtocols::Icmp, EchoValues::IcmpEchoReply)
+                    | (IpNextHeaderProtocols::Icmp, EchoValues::IcmpEchoRequest)
+                    | (IpNextHeaderProtocols::Icmpv6, EchoValues::Icmpv6EchoRequest)
+                    | (IpNextHeaderProtocols::Icmpv6, EchoValues::Icmpv6EchoReply) => true,
+                    _ => false,
+                };
+                if echo {
+                    self.echo_id = be_u16(packet, 4);
+                    self.echo_seq = be_u16(packet, 6);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packet_handler::{env_flag, test_link, test_tcp_segment};
+    use pnet::packet::tcp::TcpFlags;
+
+    fn transport(protocol: IpNextHeaderProtocol, packet: &[u8]) -> TrafficAttr {
+        let mut attr = TrafficAttr::new(test_link(), IpAddr::from([192, 168, 0, 10]), IpAddr::from([192, 168, 0, 1]), protocol);
+        attr.set_transport(protocol, packet);
+        attr
+    }
+
+    #[test]
+    fn tcp_and_udp() {
+        let attr = transport(IpNextHeaderProtocols::Tcp, &test_tcp_segment(50000, 502, 1, 0, TcpFlags::SYN, &[]));
+        assert_eq!(attr.protocol, 6);
+        assert_eq!((attr.src_port, attr.dst_port), (Some(50000), Some(502)));
+        assert_eq!(attr.tcp_flags, Some(TcpFlags::SYN));
+        let attr = transport(IpNextHeaderProtocols::Udp, &[0xba, 0xc0, 0xba, 0xc0, 0x00, 0x0c, 0x00, 0x00, 0x81, 0x0b, 0x00, 0x04]);
+        assert_eq!((attr.src_port, attr.dst_port), (Some(47808), Some(47808)));
+        assert_eq!(attr.udp_length, Some(12));
+        assert_eq!(attr.tcp_flags, None);
+    }
+
+    #[test]
+    fn icmp_echo() {
+        let attr = transport(IpNextHeaderProtocols::Icmp, &[0x08, 0x00, 0xf7, 0xfe, 0x00, 0x01, 0x00, 0x2a]);
+        assert_eq!((attr.icmp_type, attr.icmp_code), (Some(8), Some(0)));
+        assert_eq!((attr.echo_id, attr.echo_seq), (Some(1), Some(42)));
+        /* neighbour solicitation carries no identifier */
+        let attr = transport(IpNextHeaderProtocols::Icmpv6, &[0x87, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(attr.icmp_type, Some(135));
+        assert_eq!(attr.echo_id, None);
+        /* ICMPv4 type 128 is not an echo */
+        let attr = transport(IpNextHeaderProtocols::Icmp, &[0x80, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01]);
+        assert_eq!(attr.echo_id, None);
+    }
+
+    #[test]
+    fn truncated_headers() {
+        let attr = transport(IpNextHeaderProtocols::Tcp, &[0xc3, 0x50, 0x01, 0xf6, 0x00]);
+        assert_eq!(attr.src_port, None);
+        let attr = transport(IpNextHeaderProtocols::Udp, &[0xba, 0xc0]);
+        assert_eq!(attr.src_port, None);
+        let attr = transport(IpNextHeaderProtocols::Icmp, &[0x08, 0x00, 0xf7]);
+        assert_eq!(attr.icmp_type, None);
+        let attr = transport(IpNextHeaderProtocols::Icmp, &[0x08, 0x00, 0xf7, 0xfe, 0x00]);
+        assert_eq!(attr.icmp_type, Some(8));
+        assert_eq!(attr.echo_seq, None);
+    }
+
+    #[test]
+    fn log_all_traffic_flag() {
+        let name = "ARROWS_TEST_LOG_ALL_TRAFFIC";
+        std::env::remove_var(name);
+        assert!(!env_flag(name));
+        for value in ["1", "true", "YES", "on"].iter() {
+            std::env::set_var(name, value);
+            assert!(env_flag(name), "{}", value);
+        }
+        for value in ["0", "false", "", "enabled"].iter() {
+            std::env::set_var(name, value);
+            assert!(!env_flag(name), "{}", value);
+        }
+        std::env::remove_var(name);
+    }
+}
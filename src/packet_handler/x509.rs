@@ -0,0 +1,316 @@
+//! Minimal X.509 (DER) reader for certificates seen in cleartext handshakes
+//!
+//! Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signature }
+//! TBSCertificate ::= SEQUENCE {
+//!     [0] version, serialNumber, signature, issuer, validity, subject,
+//!     subjectPublicKeyInfo, [1] issuerUniqueID, [2] subjectUniqueID, [3] extensions }
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+#[allow(non_snake_case)]
+#[allow(non_upper_case_globals)]
+#[allow(dead_code)]
+mod TagValues {
+    pub const Boolean: u8 = 0x01;
+    pub const OctetString: u8 = 0x04;
+    pub const Utf8String: u8 = 0x0C;
+    pub const PrintableString: u8 = 0x13;
+    pub const Ia5String: u8 = 0x16;
+    pub const ObjectIdentifier: u8 = 0x06;
+    pub const Sequence: u8 = 0x30;
+    pub const UtcTime: u8 = 0x17;
+    pub const GeneralizedTime: u8 = 0x18;
+    pub const Version: u8 = 0xA0;
+    pub const Extensions: u8 = 0xA3;
+}
+
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    pub subject: String,
+    pub issuer: String,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+    // (OID, extnValue contents)
+    pub extensions: Vec<(String, Vec<u8>)>,
+}
+
+// (tag, content start, content end)
+pub fn der(data: &[u8], offset: usize) -> Option<(u8, usize, usize)> {
+    let tag = *data.get(offset)?;
+    let first = *data.get(offset + 1)?;
+    let (length, header) = match first {
+        0x00..=0x7F => (first as usize, 2),
+        0x81..=0x84 => {
+            let size = (first & 0x7F) as usize;
+            let bytes = data.get(offset + 2..offset + 2 + size)?;
+            (bytes.iter().fold(0usize, |n, b| n << 8 | *b as usize), 2 + size)
+        }
+        _ => return None,
+    };
+    let end = offset.checked_add(header)?.checked_add(length)?;
+    if end > data.len() {
+        return None;
+    }
+    Some((tag, offset + header, end))
+}
+
+pub fn oid(value: &[u8]) -> String {
+    let mut arcs: Vec<u64> = Vec::new();
+    let mut arc: u64 = 0;
+    for b in value {
+        arc = arc << 7 | (b & 0x7F) as u64;
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (arc / 40).min(2);
+                arcs.push(first);
+                arcs.push(arc - first * 40);
+            } else {
+                arcs.push(arc);
+            }
+            arc = 0;
+        }
+    }
+    arcs.iter().map(|arc| arc.to_string()).collect::<Vec<String>>().join(".")
+}
+
+fn attribute_name(oid: &str) -> &str {
+    match oid {
+        "2.5.4.3" => "CN",
+        "2.5.4.5" => "serialNumber",
+        "2.5.4.6" => "C",
+        "2.5.4.7" => "L",
+        "2.5.4.8" => "ST",
+        "2.5.4.10" => "O",
+        "2.5.4.11" => "OU",
+        "1.2.840.113549.1.9.1" => "emailAddress",
+        _ => oid,
+    }
+}
+
+// Name ::= SEQUENCE OF SET OF SEQUENCE { type OID, value ANY }, as "CN=..., O=..."
+fn name(data: &[u8], start: usize, end: usize) -> String {
+    let mut attributes = Vec::new();
+    let mut offset = start;
+    while let Some((_, set_start, set_end)) = der(&data[..end], offset) {
+        let mut position = set_start;
+        while let Some((_, attr_start, attr_end)) = der(&data[..set_end], position) {
+            if let Some((TagValues::ObjectIdentifier, oid_start, oid_end)) = der(&data[..attr_end], attr_start) {
+                if let Some((_, value_start, value_end)) = der(&data[..attr_end], oid_end) {
+                    let attribute = oid(&data[oid_start..oid_end]);
+                    attributes.push(format!(
+                        "{}={}",
+                        attribute_name(&attribute),
+                        String::from_utf8_lossy(&data[value_start..value_end])
+                    ));
+                }
+            }
+            position = attr_end;
+        }
+        offset = set_end;
+    }
+    attributes.join(", ")
+}
+
+fn time(tag: u8, value: &[u8]) -> Option<DateTime<Utc>> {
+    let text = std::str::from_utf8(value).ok()?.trim_end_matches('Z');
+    let text = match tag {
+        /* YYMMDDHHMMSS: 50-99 -> 19xx */
+        TagValues::UtcTime => format!("{}{}", if text.get(0..2)? >= "50" { "19" } else { "20" }, text),
+        TagValues::GeneralizedTime => text.to_string(),
+        _ => return None,
+    };
+    let naive = NaiveDateTime::parse_from_str(text.get(0..14)?, "%Y%m%d%H%M%S").ok()?;
+    Some(DateTime::<Utc>::from_utc(naive, Utc))
+}
+
+impl Certificate {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let (_, cert_start, cert_end) = der(data, 0)?;
+        let (tag, tbs_start, tbs_end) = der(&data[..cert_end], cert_start)?;
+        if tag != TagValues::Sequence {
+            return None;
+        }
+        let tbs = &data[..tbs_end];
+        let mut offset = tbs_start;
+        let (tag, _, end) = der(tbs, offset)?;
+        if tag == TagValues::Version {
+            offset = end;
+        }
+        /* serialNumber, signature */
+        offset = der(tbs, offset)?.2;
+        offset = der(tbs, offset)?.2;
+        let (_, issuer_start, issuer_end) = der(tbs, offset)?;
+        let (_, validity_start, validity_end) = der(tbs, issuer_end)?;
+        let (_, subject_start, subject_end) = der(tbs, validity_end)?;
+        let (before_tag, before_start, before_end) = der(&tbs[..validity_end], validity_start)?;
+        let (after_tag, after_start, after_end) = der(&tbs[..validity_end], before_end)?;
+        let mut certificate = Self {
+            subject: name(tbs, subject_start, subject_end),
+            issuer: name(tbs, issuer_start, issuer_end),
+            not_before: time(before_tag, &tbs[before_start..before_end]),
+            not_after: time(after_tag, &tbs[after_start..after_end]),
+            extensions: Vec::new(),
+        };
+        /* subjectPublicKeyInfo, then optional [1] [2] [3] */
+        offset = der(tbs, subject_end)?.2;
+        while let Some((tag, start, end)) = der(tbs, offset) {
+            if tag == TagValues::Extensions {
+                certificate.set_extensions(&tbs[..end], start);
+            }
+            offset = end;
+        }
+        Some(certificate)
+    }
+
+    // Extension whose value is a single DER string (e.g. the Modbus/TCP Security role)
+    pub fn string_extension(&self, extension_oid: &str) -> Option<String> {
+        let (_, value) = self.extensions.iter().find(|(oid, _)| oid == extension_oid)?;
+        match der(value, 0)? {
+            (TagValues::Utf8String, start, end) | (TagValues::PrintableString, start, end) | (TagValues::Ia5String, start, end) => {
+                Some(String::from_utf8_lossy(&value[start..end]).into_owned())
+            }
+            _ => None,
+        }
+    }
+
+    // [3] { SEQUENCE OF Extension { extnID OID, critical BOOLEAN DEFAULT FALSE, extnValue OCTET STRING } }
+    fn set_extensions(&mut self, data: &[u8], start: usize) {
+        let (_, list_start, list_end) = match der(data, start) {
+            Some(list) => list,
+            None => return,
+        };
+        let mut offset = list_start;
+        while let Some((_, ext_start, ext_end)) = der(&data[..list_end], offset) {
+            let ext = &data[..ext_end];
+            if let Some((TagValues::ObjectIdentifier, oid_start, oid_end)) = der(ext, ext_start) {
+                let mut position = oid_end;
+                if let Some((TagValues::Boolean, _, end)) = der(ext, position) {
+                    position = end;
+                }
+                if let Some((TagValues::OctetString, value_start, value_end)) = der(ext, position) {
+                    self.extensions.push((oid(&ext[oid_start..oid_end]), ext[value_start..value_end].to_vec()));
+                }
+            }
+            offset = ext_end;
+        }
+    }
+}
+
+// DER element with a definite length, for building test certificates
+#[cfg(test)]
+fn test_der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag];
+    match content.len() {
+        0..=0x7F => element.push(content.len() as u8),
+        0x80..=0xFF => element.extend_from_slice(&[0x81, content.len() as u8]),
+        _ => element.extend_from_slice(&[0x82, (content.len() >> 8) as u8, content.len() as u8]),
+    }
+    element.extend_from_slice(content);
+    element
+}
+
+// v3 certificate (CN/O names, ECDSA key) with the given (OID bytes, extnValue) extensions
+#[cfg(test)]
+pub fn test_certificate(subject_cn: &str, extensions: &[(&[u8], &[u8])]) -> Vec<u8> {
+    let rdn = |oid: &[u8], value: &str| {
+        let attribute = [test_der(TagValues::ObjectIdentifier, oid), test_der(TagValues::Utf8String, value.as_bytes())].concat();
+        test_der(0x31, &test_der(TagValues::Sequence, &attribute))
+    };
+    let name = |cn: &str| test_der(TagValues::Sequence, &[rdn(&[0x55, 0x04, 0x03], cn), rdn(&[0x55, 0x04, 0x0A], "Plant")].concat());
+    let algorithm = test_der(TagValues::Sequence, &test_der(TagValues::ObjectIdentifier, &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x02]));
+    let validity = [test_der(TagValues::UtcTime, b"240101000000Z"), test_der(TagValues::GeneralizedTime, b"20340101120000Z")].concat();
+    let key = [
+        test_der(TagValues::Sequence, &test_der(TagValues::ObjectIdentifier, &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01])),
+        test_der(0x03, &[0x00, 0x04, 0x01, 0x02, 0x03, 0x04]),
+    ]
+    .concat();
+    let mut list = Vec::new();
+    for (oid, value) in extensions {
+        let extension = [test_der(TagValues::ObjectIdentifier, oid), test_der(TagValues::OctetString, value)].concat();
+        list.extend(test_der(TagValues::Sequence, &extension));
+    }
+    let tbs = [
+        test_der(TagValues::Version, &test_der(0x02, &[0x02])),
+        test_der(0x02, &[0x01, 0x23, 0x45]),
+        algorithm.clone(),
+        name("Plant CA"),
+        test_der(TagValues::Sequence, &validity),
+        name(subject_cn),
+        test_der(TagValues::Sequence, &key),
+        test_der(TagValues::Extensions, &test_der(TagValues::Sequence, &list)),
+    ]
+    .concat();
+    let signature = test_der(0x03, &[0x00; 9]);
+    test_der(TagValues::Sequence, &[test_der(TagValues::Sequence, &tbs), algorithm, signature].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1.3.6.1.4.1.50316.802.1 (Modbus/TCP Security role)
+    const ROLE: [u8; 11] = [0x2B, 0x06, 0x01, 0x04, 0x01, 0x83, 0x89, 0x0C, 0x86, 0x22, 0x01];
+
+    fn utc(text: &str) -> Option<DateTime<Utc>> {
+        text.parse().ok()
+    }
+
+    #[test]
+    fn der_lengths() {
+        assert_eq!(der(&[0x04, 0x02, 0xAA, 0xBB], 0), Some((0x04, 2, 4)));
+        let mut long = vec![0x04, 0x81, 0x80];
+        long.extend_from_slice(&[0u8; 0x80]);
+        assert_eq!(der(&long, 0), Some((0x04, 3, 0x83)));
+        /* length past the buffer, indefinite and oversized length forms */
+        assert_eq!(der(&long[..0x82], 0), None);
+        assert_eq!(der(&[0x30, 0x80, 0x00, 0x00], 0), None);
+        assert_eq!(der(&[0x30, 0x85, 1, 0, 0, 0, 0], 0), None);
+        assert_eq!(der(&[0x30, 0x84, 0xFF, 0xFF, 0xFF, 0xFF], 0), None);
+        assert_eq!(der(&[0x30], 0), None);
+    }
+
+    #[test]
+    fn oid_arcs() {
+        assert_eq!(oid(&[0x55, 0x04, 0x03]), "2.5.4.3");
+        assert_eq!(oid(&[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x01]), "1.2.840.113549.1.9.1");
+        assert_eq!(oid(&ROLE), "1.3.6.1.4.1.50316.802.1");
+        assert_eq!(oid(&[]), "");
+    }
+
+    #[test]
+    fn certificate_fields() {
+        let role = test_der(TagValues::Utf8String, b"Operator");
+        let basic = [0x30, 0x03, 0x01, 0x01, 0xFF];
+        let data = test_certificate("plc-01", &[(&[0x55, 0x1D, 0x13], &basic), (&ROLE, &role)]);
+        let certificate = Certificate::parse(&data).unwrap();
+        assert_eq!(certificate.subject, "CN=plc-01, O=Plant");
+        assert_eq!(certificate.issuer, "CN=Plant CA, O=Plant");
+        assert_eq!(certificate.not_before, utc("2024-01-01T00:00:00Z"));
+        assert_eq!(certificate.not_after, utc("2034-01-01T12:00:00Z"));
+        assert_eq!(certificate.extensions.len(), 2);
+        assert_eq!(certificate.extensions[0], ("2.5.29.19".to_string(), basic.to_vec()));
+        assert_eq!(certificate.string_extension("1.3.6.1.4.1.50316.802.1").as_deref(), Some("Operator"));
+        /* not a string */
+        assert_eq!(certificate.string_extension("2.5.29.19"), None);
+        assert_eq!(certificate.string_extension("2.5.29.17"), None);
+    }
+
+    #[test]
+    fn utc_time_century() {
+        assert_eq!(time(TagValues::UtcTime, b"991231235959Z"), utc("1999-12-31T23:59:59Z"));
+        assert_eq!(time(TagValues::UtcTime, b"491231235959Z"), utc("2049-12-31T23:59:59Z"));
+        assert_eq!(time(TagValues::UtcTime, b"4912"), None);
+        assert_eq!(time(TagValues::OctetString, b"20240101000000Z"), None);
+    }
+
+    #[test]
+    fn truncated_certificate() {
+        let data = test_certificate("plc-01", &[]);
+        assert!(Certificate::parse(&data).is_some());
+        assert!(Certificate::parse(&data[..data.len() - 1]).is_none());
+        assert!(Certificate::parse(&data[..40]).is_none());
+        assert!(Certificate::parse(&[]).is_none());
+        /* outer SEQUENCE wrapping something other than a TBSCertificate */
+        assert!(Certificate::parse(&test_der(TagValues::Sequence, &test_der(0x02, &[1]))).is_none());
+    }
+}